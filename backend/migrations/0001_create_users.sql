@@ -0,0 +1,34 @@
+-- Ids are the hex form of MongoDB ObjectIds so both storage backends share one id format.
+CREATE TABLE users (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    email TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    password_history TEXT[] NOT NULL DEFAULT '{}',
+    email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    totp_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    totp_secret TEXT,
+    totp_last_used_step BIGINT,
+    recovery_code_hashes TEXT[] NOT NULL DEFAULT '{}',
+    reset_token_hash TEXT,
+    reset_token_expiry_at BIGINT,
+    role TEXT NOT NULL DEFAULT 'user',
+    suspended BOOLEAN NOT NULL DEFAULT FALSE,
+    suspension_reason TEXT,
+    session_version INTEGER NOT NULL DEFAULT 0,
+    last_seen_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX users_created_at_idx ON users (created_at DESC);
+CREATE INDEX users_reset_token_hash_idx ON users (reset_token_hash) WHERE reset_token_hash IS NOT NULL;
+
+CREATE TABLE user_oidc_identities (
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    issuer TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    linked_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (issuer, subject)
+);
+
+CREATE INDEX user_oidc_identities_user_id_idx ON user_oidc_identities (user_id);
@@ -0,0 +1,24 @@
+CREATE TABLE chats (
+    id TEXT PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+-- `position` keeps participants in the order the chat was created with.
+CREATE TABLE chat_participants (
+    chat_id TEXT NOT NULL REFERENCES chats (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (chat_id, user_id)
+);
+
+CREATE INDEX chat_participants_user_id_idx ON chat_participants (user_id);
+
+CREATE TABLE messages (
+    id TEXT PRIMARY KEY,
+    chat_id TEXT NOT NULL REFERENCES chats (id) ON DELETE CASCADE,
+    sender_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX messages_chat_id_created_at_idx ON messages (chat_id, created_at);
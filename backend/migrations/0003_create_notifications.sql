@@ -0,0 +1,13 @@
+CREATE TABLE notifications (
+    id TEXT PRIMARY KEY,
+    notification_type TEXT NOT NULL,
+    recipient_id TEXT NOT NULL,
+    sender_id TEXT NOT NULL,
+    message TEXT NOT NULL,
+    is_handled BOOLEAN NOT NULL DEFAULT FALSE,
+    is_read BOOLEAN NOT NULL DEFAULT FALSE,
+    read_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX notifications_recipient_id_created_at_idx ON notifications (recipient_id, created_at DESC);
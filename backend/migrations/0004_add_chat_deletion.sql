@@ -0,0 +1,7 @@
+-- A participant's "delete for me" hides the chat from them until a newer message arrives.
+ALTER TABLE chat_participants ADD COLUMN hidden_at TIMESTAMPTZ;
+
+-- Set once every participant has deleted the chat; the purge job removes the row later.
+ALTER TABLE chats ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX chats_deleted_at_idx ON chats (deleted_at) WHERE deleted_at IS NOT NULL;
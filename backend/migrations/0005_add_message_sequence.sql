@@ -0,0 +1,14 @@
+-- Messages are ordered by a per-chat sequence number the server assigns, not by timestamps.
+ALTER TABLE chats ADD COLUMN last_seq BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE messages ADD COLUMN seq BIGINT;
+ALTER TABLE messages ADD COLUMN client_sent_at TIMESTAMPTZ;
+ALTER TABLE chat_participants ADD COLUMN hidden_seq BIGINT NOT NULL DEFAULT 0;
+
+-- Number existing messages in the order they were stored.
+UPDATE messages SET seq = numbered.seq
+FROM (SELECT id, ROW_NUMBER() OVER (PARTITION BY chat_id ORDER BY created_at, id) AS seq FROM messages) AS numbered
+WHERE messages.id = numbered.id;
+UPDATE chats SET last_seq = COALESCE((SELECT MAX(seq) FROM messages WHERE messages.chat_id = chats.id), 0);
+
+ALTER TABLE messages ALTER COLUMN seq SET NOT NULL;
+CREATE UNIQUE INDEX messages_chat_id_seq_idx ON messages (chat_id, seq);
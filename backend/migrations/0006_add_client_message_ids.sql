@@ -0,0 +1,5 @@
+-- The sender's own id for a message, so a retried send is stored once.
+ALTER TABLE messages ADD COLUMN client_msg_id TEXT;
+
+CREATE UNIQUE INDEX messages_sender_id_client_msg_id_idx ON messages (sender_id, client_msg_id)
+    WHERE client_msg_id IS NOT NULL;
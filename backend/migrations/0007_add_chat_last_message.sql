@@ -0,0 +1,20 @@
+-- A copy of each chat's newest message and its last activity, so the chat list is a single query.
+ALTER TABLE chats ADD COLUMN last_message_id TEXT;
+ALTER TABLE chats ADD COLUMN last_message_sender_id TEXT;
+ALTER TABLE chats ADD COLUMN last_message_seq BIGINT;
+ALTER TABLE chats ADD COLUMN last_message_content TEXT;
+ALTER TABLE chats ADD COLUMN last_message_created_at TIMESTAMPTZ;
+ALTER TABLE chats ADD COLUMN last_activity_at TIMESTAMPTZ;
+
+UPDATE chats SET
+    last_message_id = last_message.id,
+    last_message_sender_id = last_message.sender_id,
+    last_message_seq = last_message.seq,
+    last_message_content = last_message.content,
+    last_message_created_at = last_message.created_at
+FROM (SELECT DISTINCT ON (chat_id) * FROM messages ORDER BY chat_id, seq DESC) AS last_message
+WHERE chats.id = last_message.chat_id;
+UPDATE chats SET last_activity_at = GREATEST(created_at, COALESCE(last_message_created_at, created_at));
+
+ALTER TABLE chats ALTER COLUMN last_activity_at SET NOT NULL;
+CREATE INDEX chats_last_activity_at_idx ON chats (last_activity_at DESC);
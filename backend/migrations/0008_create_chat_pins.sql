@@ -0,0 +1,8 @@
+-- Messages pinned to the top of a chat. A deleted message takes its pin with it.
+CREATE TABLE chat_pins (
+    chat_id TEXT NOT NULL REFERENCES chats (id) ON DELETE CASCADE,
+    message_id TEXT NOT NULL REFERENCES messages (id) ON DELETE CASCADE,
+    pinned_by TEXT NOT NULL,
+    pinned_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (chat_id, message_id)
+);
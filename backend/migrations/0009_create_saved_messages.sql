@@ -0,0 +1,14 @@
+-- Personal bookmarks. They go away with the message or the chat they point to.
+CREATE TABLE saved_messages (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    message_id TEXT NOT NULL REFERENCES messages (id) ON DELETE CASCADE,
+    chat_id TEXT NOT NULL REFERENCES chats (id) ON DELETE CASCADE,
+    note TEXT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (user_id, message_id)
+);
+
+CREATE INDEX saved_messages_user_id_created_at_idx ON saved_messages (user_id, created_at DESC);
+CREATE INDEX saved_messages_chat_id_idx ON saved_messages (chat_id);
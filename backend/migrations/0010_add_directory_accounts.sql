@@ -0,0 +1,2 @@
+-- Accounts created from the LDAP directory; only these are kept in sync with it.
+ALTER TABLE users ADD COLUMN directory_account BOOLEAN NOT NULL DEFAULT FALSE;
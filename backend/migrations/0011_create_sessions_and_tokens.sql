@@ -0,0 +1,44 @@
+-- Server-side sessions; the cookie only carries the key, of which only the hash is stored.
+CREATE TABLE sessions (
+    id TEXT PRIMARY KEY,
+    key_hash TEXT NOT NULL UNIQUE,
+    session_id TEXT,
+    user_id TEXT,
+    state JSONB NOT NULL DEFAULT '{}',
+    user_agent TEXT,
+    ip TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    last_seen_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX sessions_user_id_idx ON sessions (user_id);
+CREATE INDEX sessions_expires_at_idx ON sessions (expires_at);
+
+CREATE TABLE api_tokens (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    name TEXT,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    access_token_id TEXT,
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX api_tokens_user_id_idx ON api_tokens (user_id);
+CREATE INDEX api_tokens_expires_at_idx ON api_tokens (expires_at);
+
+-- Failed-attempt counters for login and password reset throttling.
+CREATE TABLE login_throttles (
+    id TEXT PRIMARY KEY,
+    key TEXT NOT NULL UNIQUE,
+    failures INTEGER NOT NULL,
+    last_failure_at TIMESTAMPTZ NOT NULL,
+    locked_until TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX login_throttles_expires_at_idx ON login_throttles (expires_at);
@@ -0,0 +1,20 @@
+CREATE TABLE notification_preferences (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    notification_type TEXT NOT NULL,
+    in_app BOOLEAN NOT NULL,
+    email BOOLEAN NOT NULL,
+    muted BOOLEAN NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (user_id, notification_type)
+);
+
+CREATE TABLE digest_preferences (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL UNIQUE,
+    frequency TEXT NOT NULL,
+    quiet_hours_start INTEGER,
+    quiet_hours_end INTEGER,
+    utc_offset_minutes INTEGER NOT NULL DEFAULT 0,
+    last_sent_at TIMESTAMPTZ
+);
@@ -0,0 +1,33 @@
+-- Reports keep a copy of the reported message, so they are not tied to the message row.
+CREATE TABLE reports (
+    id TEXT PRIMARY KEY,
+    reporter_id TEXT NOT NULL,
+    reported_user_id TEXT NOT NULL,
+    message_id TEXT,
+    chat_id TEXT,
+    message_content TEXT,
+    reason TEXT NOT NULL,
+    details TEXT,
+    status TEXT NOT NULL,
+    handled_by TEXT,
+    handled_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX reports_status_created_at_idx ON reports (status, created_at);
+CREATE INDEX reports_reported_user_id_idx ON reports (reported_user_id);
+CREATE INDEX reports_message_id_idx ON reports (message_id);
+
+-- The moderation trail. Rows are only ever added.
+CREATE TABLE moderation_actions (
+    id TEXT PRIMARY KEY,
+    moderator_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target_user_id TEXT NOT NULL,
+    message_id TEXT,
+    report_id TEXT,
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX moderation_actions_target_user_id_created_at_idx ON moderation_actions (target_user_id, created_at DESC);
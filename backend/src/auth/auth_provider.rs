@@ -0,0 +1,42 @@
+use crate::{
+    auth::{ldap_provider::LdapProvider, local_provider::LocalProvider},
+    config::app_config::AppConfig,
+    models::user_model::User,
+    repositories::user_repository::UserRepository,
+};
+use async_trait::async_trait;
+use std::{error::Error, sync::Arc};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AuthProviderError {
+    #[error("database error: {0}")]
+    Database(String),
+    #[error("directory error: {0}")]
+    Directory(String),
+    #[error("password error: {0}")]
+    Password(String),
+    /// The directory account cannot be mapped onto a local user.
+    #[error("account conflict: {0}")]
+    Conflict(String),
+}
+
+/// Checks a username and password and resolves them to the local user.
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// `Ok(None)` means the credentials were rejected.
+    async fn authenticate(&self, users: &dyn UserRepository, username: &str, password: &str)
+        -> Result<Option<User>, AuthProviderError>;
+}
+
+/// Build the provider selected by `AUTH_BACKEND`.
+pub fn auth_provider_from_config(config: &AppConfig) -> Result<Arc<dyn AuthProvider>, Box<dyn Error>> {
+    let provider: Arc<dyn AuthProvider> = match config.auth_backend.as_str() {
+        "local" => Arc::new(LocalProvider),
+        "ldap" => Arc::new(LdapProvider::new(
+            config.ldap.clone().ok_or("AUTH_BACKEND is ldap but LDAP is not configured")?,
+        )),
+        other => return Err(format!("Unknown AUTH_BACKEND: {}", other).into()),
+    };
+    Ok(provider)
+}
@@ -0,0 +1,229 @@
+use crate::{
+    auth::auth_provider::{AuthProvider, AuthProviderError},
+    config::app_config::LdapConfig,
+    constants::LDAP_TIMEOUT_SECONDS,
+    models::user_model::User,
+    repositories::{repository::RepositoryError, user_repository::UserRepository},
+    utils::{
+        auth_util::{generate_reset_token, hash_password},
+        validation_util::validate_username,
+    },
+};
+use async_trait::async_trait;
+use ldap3::{ldap_escape, Ldap, LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+use std::time::Duration;
+
+/// LDAP result code for a failed bind.
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// A user found in the directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryUser {
+    pub dn: String,
+    pub username: String,
+    pub email: String,
+    pub groups: Vec<String>,
+}
+
+/// Checks passwords by binding to an LDAP directory as the user.
+///
+/// The user's entry is looked up with the service account, then the password is checked with a
+/// bind as that entry. Users that sign in for the first time get a local account with the
+/// directory's username and email.
+pub struct LdapProvider {
+    config: LdapConfig,
+}
+
+fn directory_error(e: ldap3::LdapError) -> AuthProviderError {
+    AuthProviderError::Directory(e.to_string())
+}
+
+/// The search filter for a login name; the name is escaped so it cannot change the filter.
+pub fn user_search_filter(config: &LdapConfig, username: &str) -> String {
+    format!(
+        "(&{}({}={}))",
+        config.user_filter,
+        config.username_attribute,
+        ldap_escape(username)
+    )
+}
+
+/// Whether the user belongs to one of the allowed groups. Group DNs are compared case-insensitively.
+pub fn is_member_of_allowed_group(allowed_groups: &[String], groups: &[String]) -> bool {
+    allowed_groups.is_empty()
+        || groups
+            .iter()
+            .any(|group| allowed_groups.iter().any(|allowed| allowed.eq_ignore_ascii_case(group)))
+}
+
+impl LdapProvider {
+    pub fn new(config: LdapConfig) -> Self {
+        Self { config }
+    }
+
+    async fn connect(&self) -> Result<Ldap, AuthProviderError> {
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(Duration::from_secs(LDAP_TIMEOUT_SECONDS))
+            .set_starttls(self.config.starttls);
+        let (conn, ldap) = LdapConnAsync::with_settings(settings, &self.config.url)
+            .await
+            .map_err(directory_error)?;
+        ldap3::drive!(conn);
+        Ok(ldap)
+    }
+
+    /// Look the user up with the service account. More than one match is treated as no match.
+    async fn find_directory_user(&self, ldap: &mut Ldap, username: &str) -> Result<Option<DirectoryUser>, AuthProviderError> {
+        if let (Some(bind_dn), Some(bind_password)) = (&self.config.bind_dn, &self.config.bind_password) {
+            ldap.simple_bind(bind_dn, bind_password)
+                .await
+                .and_then(|result| result.success())
+                .map_err(directory_error)?;
+        }
+
+        let attributes = [
+            self.config.username_attribute.as_str(),
+            self.config.email_attribute.as_str(),
+            self.config.group_attribute.as_str(),
+        ];
+        let (entries, _) = ldap
+            .search(
+                &self.config.base_dn,
+                Scope::Subtree,
+                &user_search_filter(&self.config, username),
+                attributes.to_vec(),
+            )
+            .await
+            .and_then(|result| result.success())
+            .map_err(directory_error)?;
+        if entries.len() > 1 {
+            log::warn!("LDAP search for {} matched {} entries", username, entries.len());
+            return Ok(None);
+        }
+        let Some(entry) = entries.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let mut entry = SearchEntry::construct(entry);
+        let mut first = |attribute: &str| {
+            entry
+                .attrs
+                .remove(attribute)
+                .and_then(|values| values.into_iter().next())
+        };
+        let directory_username = first(&self.config.username_attribute);
+        let email = first(&self.config.email_attribute);
+        let (Some(directory_username), Some(email)) = (directory_username, email) else {
+            log::warn!("LDAP entry for {} is missing its username or email", username);
+            return Ok(None);
+        };
+        Ok(Some(DirectoryUser {
+            groups: entry.attrs.remove(&self.config.group_attribute).unwrap_or_default(),
+            dn: entry.dn,
+            username: directory_username,
+            email,
+        }))
+    }
+
+    /// Check the password with a bind as the user's own entry.
+    async fn check_password(&self, ldap: &mut Ldap, dn: &str, password: &str) -> Result<bool, AuthProviderError> {
+        let result = ldap.simple_bind(dn, password).await.map_err(directory_error)?;
+        match result.rc {
+            INVALID_CREDENTIALS => Ok(false),
+            _ => result.success().map(|_| true).map_err(directory_error),
+        }
+    }
+
+    /// The local user for a directory account, created on first sign-in.
+    async fn local_user(&self, users: &dyn UserRepository, directory_user: &DirectoryUser) -> Result<User, AuthProviderError> {
+        // The directory owns the email address, so keep the local copy in sync.
+        let existing_user = users
+            .sync_directory_email(&directory_user.username, &directory_user.email)
+            .await
+            .map_err(|e| AuthProviderError::Database(e.to_string()))?;
+        if let Some(user) = existing_user {
+            return Ok(user);
+        }
+
+        // Taking over a local account would hand its chats to whoever holds the name in the directory.
+        let username_taken = users
+            .find_by_username(&directory_user.username)
+            .await
+            .map_err(|e| AuthProviderError::Database(e.to_string()))?
+            .is_some();
+        if username_taken {
+            return Err(AuthProviderError::Conflict(format!(
+                "username {} already belongs to a local account",
+                directory_user.username
+            )));
+        }
+        if !validate_username(&directory_user.username) {
+            return Err(AuthProviderError::Conflict(format!(
+                "directory username {} is not a valid username",
+                directory_user.username
+            )));
+        }
+        let email_taken = users
+            .find_by_email(&directory_user.email)
+            .await
+            .map_err(|e| AuthProviderError::Database(e.to_string()))?
+            .is_some();
+        if email_taken {
+            return Err(AuthProviderError::Conflict(format!(
+                "email {} already belongs to another account",
+                directory_user.email
+            )));
+        }
+
+        // Passwords stay in the directory; the local hash is never used.
+        let password_hash = hash_password(&generate_reset_token())
+            .map_err(|e| AuthProviderError::Password(e.to_string()))?;
+        let mut user = User::new(&directory_user.username, &directory_user.email, &password_hash);
+        user.email_verified = true;
+        user.directory_account = true;
+        let user_id = users.insert(&user).await.map_err(|e| match e {
+            RepositoryError::Duplicate => AuthProviderError::Conflict(format!(
+                "directory account {} was created concurrently",
+                directory_user.username
+            )),
+            e => AuthProviderError::Database(e.to_string()),
+        })?;
+        user.id = Some(user_id);
+        log::info!("Created {} from the LDAP directory", user.username);
+        Ok(user)
+    }
+}
+
+#[async_trait]
+impl AuthProvider for LdapProvider {
+    async fn authenticate(
+        &self,
+        users: &dyn UserRepository,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<User>, AuthProviderError> {
+        // An empty password would be an unauthenticated bind, which most servers accept.
+        if password.is_empty() {
+            return Ok(None);
+        }
+
+        let mut ldap = self.connect().await?;
+        let directory_user = self.find_directory_user(&mut ldap, username).await?;
+        let password_valid = match &directory_user {
+            Some(directory_user) => self.check_password(&mut ldap, &directory_user.dn, password).await?,
+            None => false,
+        };
+        if let Err(e) = ldap.unbind().await {
+            log::warn!("LDAP unbind failed: {}", e);
+        }
+
+        let Some(directory_user) = directory_user.filter(|_| password_valid) else {
+            return Ok(None);
+        };
+        if !is_member_of_allowed_group(&self.config.allowed_groups, &directory_user.groups) {
+            log::warn!("{} is not in an allowed LDAP group", directory_user.username);
+            return Ok(None);
+        }
+        self.local_user(users, &directory_user).await.map(Some)
+    }
+}
@@ -0,0 +1,32 @@
+use crate::{
+    auth::auth_provider::{AuthProvider, AuthProviderError},
+    models::user_model::User,
+    repositories::user_repository::UserRepository,
+    utils::auth_util::verify_password,
+};
+use async_trait::async_trait;
+
+/// Checks passwords against the argon2 hashes stored with each user.
+pub struct LocalProvider;
+
+#[async_trait]
+impl AuthProvider for LocalProvider {
+    async fn authenticate(
+        &self,
+        users: &dyn UserRepository,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<User>, AuthProviderError> {
+        let user = users
+            .find_by_username(username)
+            .await
+            .map_err(|e| AuthProviderError::Database(e.to_string()))?;
+
+        let Some(user) = user else {
+            return Ok(None);
+        };
+        let is_password_valid = verify_password(password, &user.password_hash)
+            .map_err(|e| AuthProviderError::Password(e.to_string()))?;
+        Ok(is_password_valid.then_some(user))
+    }
+}
@@ -0,0 +1,3 @@
+pub mod auth_provider;
+pub mod ldap_provider;
+pub mod local_provider;
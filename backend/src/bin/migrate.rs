@@ -0,0 +1,39 @@
+// src/bin/migrate.rs
+// Applies pending database migrations without starting the server:
+// `migrate` applies them, `migrate status` lists applied and pending migrations of the storage backend.
+use cphere_backend::{
+    config::{app_config::AppConfig, database::connect_storage},
+    migrations::migration_runner::{migration_status, run_migrations},
+};
+use std::error::Error;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::init();
+
+    let config = AppConfig::new()?;
+    let storage = connect_storage(&config).await?;
+
+    match std::env::args().nth(1).as_deref() {
+        None => {
+            let applied = run_migrations(&storage).await?;
+            for migration in &applied {
+                println!("Applied {} ({})", migration.version, migration.name);
+            }
+            if applied.is_empty() {
+                println!("{} is up to date", config.storage_backend);
+            }
+        }
+        Some("status") => {
+            let status = migration_status(&storage).await?;
+            for migration in status.applied {
+                println!("applied  {} ({}) at {}", migration.version, migration.name, migration.applied_at);
+            }
+            for (version, name) in status.pending {
+                println!("pending  {} ({})", version, name);
+            }
+        }
+        Some(other) => return Err(format!("Unknown command {:?}; use no argument or `status`", other).into()),
+    }
+    Ok(())
+}
@@ -1,20 +1,132 @@
 // src/config/app_config.rs
 use crate::constants;
+use crate::utils::password_util::{load_banned_passwords, PasswordPolicy};
 use dotenv::{dotenv, from_filename};
 use serde::Deserialize;
+use std::collections::HashSet;
 use std::env;
 use std::error::Error;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// The banned-password list. `main` builds the config before serving, so the file is read
+/// once at startup and requests only ever see the loaded list.
+static BANNED_PASSWORDS: OnceLock<Arc<HashSet<String>>> = OnceLock::new();
+
+fn banned_passwords() -> Result<Arc<HashSet<String>>, Box<dyn Error>> {
+    if let Some(passwords) = BANNED_PASSWORDS.get() {
+        return Ok(passwords.clone());
+    }
+    let passwords = match env::var("PASSWORD_BANLIST_FILE") {
+        Ok(path) => load_banned_passwords(&path)?,
+        // The bundled list is optional so the server still starts from other working directories.
+        Err(_) if Path::new(constants::DEFAULT_PASSWORD_BANLIST_FILE).exists() => {
+            load_banned_passwords(constants::DEFAULT_PASSWORD_BANLIST_FILE)?
+        }
+        Err(_) => Default::default(),
+    };
+    Ok(BANNED_PASSWORDS.get_or_init(|| passwords).clone())
+}
+
+/// Settings for single sign-on through an OpenID Connect identity provider.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    /// Not needed for public clients, which rely on PKCE alone.
+    pub client_secret: Option<String>,
+    /// Where the identity provider sends the browser back to, i.e. `/auth/oidc/callback`.
+    pub redirect_url: String,
+    /// Issuers whose ID tokens are accepted. Defaults to `issuer`.
+    pub allowed_issuers: Vec<String>,
+    /// Email domains allowed to sign in. Empty allows every domain.
+    pub allowed_domains: Vec<String>,
+}
+
+/// Settings for checking passwords against an LDAP directory.
+#[derive(Debug, Clone)]
+pub struct LdapConfig {
+    /// `ldap://` or `ldaps://` URL of the directory server.
+    pub url: String,
+    /// Upgrade an `ldap://` connection with StartTLS.
+    pub starttls: bool,
+    /// Account used to look users up. Leave out to search anonymously.
+    pub bind_dn: Option<String>,
+    pub bind_password: Option<String>,
+    pub base_dn: String,
+    /// Extra filter every user entry must match, e.g. `(objectClass=person)`.
+    pub user_filter: String,
+    pub username_attribute: String,
+    pub email_attribute: String,
+    /// Attribute listing the groups an entry belongs to, usually `memberOf`.
+    pub group_attribute: String,
+    /// Group DNs allowed to sign in. Empty allows every user the filter matches.
+    pub allowed_groups: Vec<String>,
+}
+
+fn comma_separated(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_owned())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
 
 #[derive(Debug, Deserialize)]
 pub struct AppConfig {
+    /// MongoDB connection; required only when `storage_backend` is `mongodb`.
     pub database_url: String,
     pub database_name: String,
+    /// Where all server state is stored: `mongodb` or `postgres`.
+    pub storage_backend: String,
+    /// Present when `storage_backend` is `postgres`.
+    pub postgres_url: Option<String>,
+    /// Whether pending migrations are applied when the server starts; otherwise run the `migrate` binary.
+    pub migrate_on_startup: bool,
     pub email_address: String,
     pub email_password: String,
-    pub friend_request_notification: &'static str,
-    pub video_call_notification: &'static str,
+    /// Secret used to sign links sent by email.
+    pub app_secret: String,
+    /// Secret the session cookie encryption key is derived from.
+    pub session_secret: String,
+    /// Secrets that were replaced recently; cookies encrypted with them are still accepted and re-issued.
+    pub previous_session_secrets: Vec<String>,
     pub reset_token_length: usize,
     pub reset_token_expiration_minutes: i64,
+    pub digest_interval_minutes: u64,
+    pub chat_purge_interval_minutes: u64,
+    pub frontend_url: String,
+    /// Browser origins allowed to call the API with credentials, to log in or register, and to open WebSockets.
+    pub cors_allowed_origins: Vec<String>,
+    /// Reverse proxies whose `X-Forwarded-For` header is believed when working out a client's IP.
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Mail transport: `smtp`, `file` or `memory`.
+    pub mail_backend: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    /// SMTP encryption: `tls`, `starttls` or `none`.
+    pub smtp_tls: String,
+    pub mail_file_dir: String,
+    pub outbox_retry_interval_seconds: u64,
+    pub email_verification_expiration_hours: i64,
+    /// Whether accounts must verify their email address before they can send messages.
+    pub require_verified_email: bool,
+    #[serde(skip)]
+    pub password_policy: PasswordPolicy,
+    /// Failed logins allowed per account from one client IP before that IP is locked out of it.
+    pub login_max_account_failures: i32,
+    /// Failed attempts allowed per client IP before it is locked out.
+    pub login_max_ip_failures: i32,
+    pub login_lockout_minutes: i64,
+    /// Where passwords are checked: `local` or `ldap`.
+    pub auth_backend: String,
+    /// Present when `auth_backend` is `ldap`.
+    #[serde(skip)]
+    pub ldap: Option<LdapConfig>,
+    /// Present when single sign-on is configured.
+    #[serde(skip)]
+    pub oidc: Option<OidcConfig>,
 }
 
 impl AppConfig {
@@ -26,29 +138,224 @@ impl AppConfig {
             from_filename(".env.prod").ok();
         }
 
-        let database_url = env::var("DATABASE_URL")
-            .map_err(|e| Box::<dyn Error>::from(format!("Missing DATABASE_URL: {}", e)))?;
-        let database_name = env::var("DATABASE_NAME")
-            .map_err(|e| Box::<dyn Error>::from(format!("Missing DATABASE_NAME: {}", e)))?;
+        let storage_backend = env::var("STORAGE_BACKEND").unwrap_or_else(|_| "mongodb".into());
+        // MongoDB is only contacted when it is the storage backend
+        let (database_url, database_name) = match storage_backend.as_str() {
+            "mongodb" => (
+                env::var("DATABASE_URL")
+                    .map_err(|e| Box::<dyn Error>::from(format!("Missing DATABASE_URL: {}", e)))?,
+                env::var("DATABASE_NAME")
+                    .map_err(|e| Box::<dyn Error>::from(format!("Missing DATABASE_NAME: {}", e)))?,
+            ),
+            _ => (
+                env::var("DATABASE_URL").unwrap_or_default(),
+                env::var("DATABASE_NAME").unwrap_or_default(),
+            ),
+        };
+        let postgres_url = match storage_backend.as_str() {
+            "postgres" => Some(
+                env::var("POSTGRES_URL")
+                    .map_err(|e| Box::<dyn Error>::from(format!("Missing POSTGRES_URL: {}", e)))?,
+            ),
+            _ => None,
+        };
+        let migrate_on_startup = env::var("MIGRATE_ON_STARTUP")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(true);
         let email_address = env::var("EMAIL_ADDRESS")
             .map_err(|e| Box::<dyn Error>::from(format!("Missing EMAIL_ADDRESS: {}", e)))?;
         let email_password = env::var("EMAIL_PASSWORD")
             .map_err(|e| Box::<dyn Error>::from(format!("Missing EMAIL_PASSWORD: {}", e)))?;
+        let app_secret = env::var("APP_SECRET")
+            .map_err(|e| Box::<dyn Error>::from(format!("Missing APP_SECRET: {}", e)))?;
+        let session_secret = env::var("SESSION_SECRET")
+            .map_err(|e| Box::<dyn Error>::from(format!("Missing SESSION_SECRET: {}", e)))?;
+        let previous_session_secrets = env::var("SESSION_SECRET_PREVIOUS")
+            .map(|value| comma_separated(&value))
+            .unwrap_or_default();
+        if std::iter::once(&session_secret)
+            .chain(&previous_session_secrets)
+            .any(|secret| secret.len() < constants::SESSION_SECRET_MIN_LENGTH)
+        {
+            return Err(Box::<dyn Error>::from(format!(
+                "SESSION_SECRET must be at least {} bytes long",
+                constants::SESSION_SECRET_MIN_LENGTH
+            )));
+        }
 
-        let friend_request_notification = constants::FRIEND_REQUEST_NOTIFICATION;
-        let video_call_notification = constants::VIDEO_CALL_NOTIFICATION;
         let reset_token_length = constants::RESET_TOKEN_LENGTH;
         let reset_token_expiration_minutes = constants::TOKEN_EXPIRATION_MINUTES;
+        let digest_interval_minutes = env::var("DIGEST_INTERVAL_MINUTES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(constants::DIGEST_INTERVAL_MINUTES);
+        let chat_purge_interval_minutes = env::var("CHAT_PURGE_INTERVAL_MINUTES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(constants::CHAT_PURGE_INTERVAL_MINUTES);
+
+        let frontend_url = env::var("FRONTEND_URL")
+            .unwrap_or_else(|_| constants::DEFAULT_FRONTEND_URL.into());
+        let cors_allowed_origins = comma_separated(
+            &env::var("CORS_ALLOWED_ORIGINS")
+                .unwrap_or_else(|_| constants::DEFAULT_CORS_ALLOWED_ORIGINS.into()),
+        );
+        let trusted_proxies = comma_separated(&env::var("TRUSTED_PROXIES").unwrap_or_default())
+            .iter()
+            .map(|proxy| {
+                proxy
+                    .parse()
+                    .map_err(|e| Box::<dyn Error>::from(format!("Invalid TRUSTED_PROXIES entry {}: {}", proxy, e)))
+            })
+            .collect::<Result<Vec<IpAddr>, _>>()?;
+        let mail_backend = env::var("MAIL_BACKEND").unwrap_or_else(|_| "smtp".into());
+        let smtp_host = env::var("SMTP_HOST")
+            .unwrap_or_else(|_| constants::DEFAULT_SMTP_HOST.into());
+        let smtp_port = match env::var("SMTP_PORT") {
+            Ok(value) => value
+                .parse()
+                .map_err(|e| Box::<dyn Error>::from(format!("Invalid SMTP_PORT: {}", e)))?,
+            Err(_) => constants::DEFAULT_SMTP_PORT,
+        };
+        let smtp_tls = env::var("SMTP_TLS").unwrap_or_else(|_| "tls".into());
+        let mail_file_dir = env::var("MAIL_FILE_DIR")
+            .unwrap_or_else(|_| constants::DEFAULT_MAIL_FILE_DIR.into());
+        let outbox_retry_interval_seconds = env::var("OUTBOX_RETRY_INTERVAL_SECONDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(constants::OUTBOX_RETRY_INTERVAL_SECONDS);
+        let email_verification_expiration_hours = constants::EMAIL_VERIFICATION_EXPIRATION_HOURS;
+        let require_verified_email = env::var("REQUIRE_VERIFIED_EMAIL")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(false);
+
+        let banned_passwords = banned_passwords()?;
+        let password_policy = PasswordPolicy {
+            min_length: env::var("PASSWORD_MIN_LENGTH")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(constants::DEFAULT_PASSWORD_MIN_LENGTH),
+            banned_passwords,
+            history_size: env::var("PASSWORD_HISTORY_SIZE")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(constants::DEFAULT_PASSWORD_HISTORY_SIZE),
+            min_strength_score: env::var("PASSWORD_MIN_STRENGTH")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(constants::DEFAULT_PASSWORD_MIN_STRENGTH),
+        };
+
+        let login_max_account_failures = env::var("LOGIN_MAX_ACCOUNT_FAILURES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(constants::LOGIN_MAX_ACCOUNT_FAILURES);
+        let login_max_ip_failures = env::var("LOGIN_MAX_IP_FAILURES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(constants::LOGIN_MAX_IP_FAILURES);
+        let login_lockout_minutes = env::var("LOGIN_LOCKOUT_MINUTES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(constants::LOGIN_LOCKOUT_MINUTES);
+
+        let auth_backend = env::var("AUTH_BACKEND").unwrap_or_else(|_| "local".into());
+        let ldap = match auth_backend.as_str() {
+            "ldap" => {
+                let url = env::var("LDAP_URL")
+                    .map_err(|e| Box::<dyn Error>::from(format!("Missing LDAP_URL: {}", e)))?;
+                let base_dn = env::var("LDAP_BASE_DN")
+                    .map_err(|e| Box::<dyn Error>::from(format!("Missing LDAP_BASE_DN: {}", e)))?;
+                // Group DNs contain commas, so the list is separated by semicolons.
+                let allowed_groups = env::var("LDAP_ALLOWED_GROUPS")
+                    .map(|value| {
+                        value
+                            .split(';')
+                            .map(|group| group.trim().to_owned())
+                            .filter(|group| !group.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Some(LdapConfig {
+                    url,
+                    starttls: env::var("LDAP_STARTTLS")
+                        .map(|value| value == "true" || value == "1")
+                        .unwrap_or(false),
+                    bind_dn: env::var("LDAP_BIND_DN").ok(),
+                    bind_password: env::var("LDAP_BIND_PASSWORD").ok(),
+                    base_dn,
+                    user_filter: env::var("LDAP_USER_FILTER")
+                        .unwrap_or_else(|_| constants::DEFAULT_LDAP_USER_FILTER.into()),
+                    username_attribute: env::var("LDAP_USERNAME_ATTRIBUTE")
+                        .unwrap_or_else(|_| constants::DEFAULT_LDAP_USERNAME_ATTRIBUTE.into()),
+                    email_attribute: env::var("LDAP_EMAIL_ATTRIBUTE")
+                        .unwrap_or_else(|_| constants::DEFAULT_LDAP_EMAIL_ATTRIBUTE.into()),
+                    group_attribute: env::var("LDAP_GROUP_ATTRIBUTE")
+                        .unwrap_or_else(|_| constants::DEFAULT_LDAP_GROUP_ATTRIBUTE.into()),
+                    allowed_groups,
+                })
+            }
+            _ => None,
+        };
+
+        let oidc = match env::var("OIDC_ISSUER") {
+            Ok(issuer) => {
+                let issuer = issuer.trim_end_matches('/').to_owned();
+                let client_id = env::var("OIDC_CLIENT_ID")
+                    .map_err(|e| Box::<dyn Error>::from(format!("Missing OIDC_CLIENT_ID: {}", e)))?;
+                let redirect_url = env::var("OIDC_REDIRECT_URL")
+                    .map_err(|e| Box::<dyn Error>::from(format!("Missing OIDC_REDIRECT_URL: {}", e)))?;
+                let allowed_issuers = env::var("OIDC_ALLOWED_ISSUERS")
+                    .map(|value| comma_separated(&value))
+                    .unwrap_or_else(|_| vec![issuer.clone()]);
+                let allowed_domains = env::var("OIDC_ALLOWED_DOMAINS")
+                    .map(|value| comma_separated(&value.to_lowercase()))
+                    .unwrap_or_default();
+                Some(OidcConfig {
+                    issuer,
+                    client_id,
+                    client_secret: env::var("OIDC_CLIENT_SECRET").ok(),
+                    redirect_url,
+                    allowed_issuers,
+                    allowed_domains,
+                })
+            }
+            Err(_) => None,
+        };
 
         Ok(AppConfig {
             database_url,
             database_name,
+            storage_backend,
+            postgres_url,
+            migrate_on_startup,
             email_address,
             email_password,
-            friend_request_notification,
-            video_call_notification,
+            app_secret,
+            session_secret,
+            previous_session_secrets,
             reset_token_length,
             reset_token_expiration_minutes,
+            digest_interval_minutes,
+            chat_purge_interval_minutes,
+            frontend_url,
+            cors_allowed_origins,
+            trusted_proxies,
+            mail_backend,
+            smtp_host,
+            smtp_port,
+            smtp_tls,
+            mail_file_dir,
+            outbox_retry_interval_seconds,
+            email_verification_expiration_hours,
+            require_verified_email,
+            password_policy,
+            login_max_account_failures,
+            login_max_ip_failures,
+            login_lockout_minutes,
+            auth_backend,
+            ldap,
+            oidc,
         })
     }
 }
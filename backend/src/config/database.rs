@@ -4,10 +4,17 @@ use mongodb::{
     options::ClientOptions
 };
 use crate::config::app_config::AppConfig;
+use sqlx::{postgres::PgPoolOptions, PgPool};
 use std::error::Error;
 
-pub async fn init_db() -> Result<(Client, Database), Box<dyn Error>> {
-    let config = AppConfig::new()?; // AppConfig::new() must return Result<AppConfig, Box<dyn Error>>
+/// A connection to the storage backend selected by `STORAGE_BACKEND`. Everything the server
+/// stores lives there, so only that one database has to be running.
+pub enum Storage {
+    Mongo { client: Client, db: Database },
+    Postgres(PgPool),
+}
+
+pub async fn init_db(config: &AppConfig) -> Result<(Client, Database), Box<dyn Error>> {
     let client_options = ClientOptions::parse(&config.database_url)
         .await
         .map_err(|e| Box::new(e) as Box<dyn Error>)?;
@@ -16,3 +23,27 @@ pub async fn init_db() -> Result<(Client, Database), Box<dyn Error>> {
     let db = client.database(&config.database_name);
     Ok((client, db))
 }
+
+/// Connects to PostgreSQL. The schema is brought up to date by the migration runner.
+pub async fn init_postgres(postgres_url: &str) -> Result<PgPool, Box<dyn Error>> {
+    let pool = PgPoolOptions::new().connect(postgres_url).await?;
+    Ok(pool)
+}
+
+/// Connects to the storage backend selected in the configuration.
+pub async fn connect_storage(config: &AppConfig) -> Result<Storage, Box<dyn Error>> {
+    match config.storage_backend.as_str() {
+        "mongodb" => {
+            let (client, db) = init_db(config).await?;
+            Ok(Storage::Mongo { client, db })
+        }
+        "postgres" => {
+            let postgres_url = config
+                .postgres_url
+                .as_deref()
+                .ok_or("STORAGE_BACKEND is postgres but POSTGRES_URL is not set")?;
+            Ok(Storage::Postgres(init_postgres(postgres_url).await?))
+        }
+        other => Err(format!("Unknown STORAGE_BACKEND: {}", other).into()),
+    }
+}
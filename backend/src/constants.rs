@@ -1,4 +1,73 @@
 pub const FRIEND_REQUEST_NOTIFICATION:&str = "friend_request";
 pub const VIDEO_CALL_NOTIFICATION:&str = "video_call";
+pub const MISSED_CALL_NOTIFICATION:&str = "missed_call";
+pub const CHAT_MESSAGE_NOTIFICATION:&str = "chat_message";
+pub const SYSTEM_NOTIFICATION:&str = "system";
 pub const RESET_TOKEN_LENGTH: usize = 32;
 pub const TOKEN_EXPIRATION_MINUTES: i64 = 10;
+pub const NOTIFICATION_PAGE_LIMIT: i64 = 50;
+pub const MESSAGE_PAGE_LIMIT: i64 = 100;
+pub const CHAT_PAGE_LIMIT: i64 = 50;
+pub const CLIENT_MSG_ID_MAX_LENGTH: usize = 64;
+pub const MAX_PINNED_MESSAGES: usize = 20;
+pub const SAVED_MESSAGE_PAGE_LIMIT: i64 = 50;
+pub const SAVED_MESSAGE_NOTE_MAX_LENGTH: usize = 500;
+pub const SAVED_MESSAGE_MAX_TAGS: usize = 10;
+pub const SAVED_MESSAGE_TAG_MAX_LENGTH: usize = 32;
+pub const SAVED_MESSAGE_PREVIEW_LENGTH: usize = 200;
+pub const DIGEST_INTERVAL_MINUTES: u64 = 15;
+/// Users loaded per page when sending digests.
+pub const DIGEST_USER_BATCH_SIZE: i64 = 200;
+pub const CHAT_PURGE_INTERVAL_MINUTES: u64 = 60;
+/// Chats deleted by every participant are kept this long before the purge job removes them.
+pub const DELETED_CHAT_RETENTION_HOURS: i64 = 24;
+/// How often expired sessions, tokens, throttles and outbox emails are removed
+/// from backends without TTL indexes.
+pub const EXPIRY_PURGE_INTERVAL_MINUTES: u64 = 10;
+pub const DEFAULT_FRONTEND_URL: &str = "http://localhost";
+pub const DEFAULT_SMTP_HOST: &str = "smtp.gmail.com";
+pub const DEFAULT_SMTP_PORT: u16 = 465;
+pub const DEFAULT_MAIL_FILE_DIR: &str = "mail";
+pub const OUTBOX_RETRY_INTERVAL_SECONDS: u64 = 60;
+pub const OUTBOX_MAX_ATTEMPTS: i32 = 8;
+/// Sent and failed outbox emails are kept this long, with their bodies blanked, before the TTL index removes them.
+pub const OUTBOX_RETENTION_DAYS: i64 = 7;
+pub const EMAIL_VERIFICATION_EXPIRATION_HOURS: i64 = 24;
+pub const EMAIL_VERIFICATION_PURPOSE: &str = "email_verification";
+pub const DEFAULT_PASSWORD_MIN_LENGTH: usize = 8;
+pub const DEFAULT_PASSWORD_HISTORY_SIZE: usize = 5;
+pub const DEFAULT_PASSWORD_MIN_STRENGTH: u8 = 2;
+pub const DEFAULT_PASSWORD_BANLIST_FILE: &str = "data/common_passwords.txt";
+pub const LOGIN_MAX_ACCOUNT_FAILURES: i32 = 5;
+pub const LOGIN_MAX_IP_FAILURES: i32 = 20;
+pub const LOGIN_LOCKOUT_MINUTES: i64 = 15;
+pub const LOGIN_MAX_BACKOFF_SECONDS: i64 = 60;
+pub const PASSWORD_RESET_MAX_IP_REQUESTS: i32 = 5;
+pub const PASSWORD_RESET_LOCKOUT_MINUTES: i64 = 60;
+/// Failures are forgotten once a key has been quiet (and unlocked) this long.
+pub const LOGIN_THROTTLE_RETENTION_HOURS: i64 = 24;
+
+pub const TOTP_ISSUER: &str = "Cphere";
+pub const RECOVERY_CODE_COUNT: usize = 10;
+pub const TWO_FACTOR_LOGIN_TIMEOUT_MINUTES: i64 = 5;
+/// Wrong password or code attempts allowed when turning two-factor authentication off.
+pub const TWO_FACTOR_DISABLE_MAX_FAILURES: i32 = 5;
+pub const TWO_FACTOR_DISABLE_LOCKOUT_MINUTES: i64 = 15;
+pub const SESSION_SECRET_MIN_LENGTH: usize = 32;
+pub const SESSION_TTL_DAYS: i64 = 7;
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+pub const API_TOKEN_LENGTH: usize = 40;
+pub const OIDC_LOGIN_TIMEOUT_MINUTES: i64 = 10;
+pub const DEFAULT_LDAP_USER_FILTER: &str = "(objectClass=person)";
+pub const DEFAULT_LDAP_USERNAME_ATTRIBUTE: &str = "uid";
+pub const DEFAULT_LDAP_EMAIL_ATTRIBUTE: &str = "mail";
+pub const DEFAULT_LDAP_GROUP_ATTRIBUTE: &str = "memberOf";
+pub const LDAP_TIMEOUT_SECONDS: u64 = 5;
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+pub const DEFAULT_CORS_ALLOWED_ORIGINS: &str = "http://localhost";
+pub const ADMIN_USER_PAGE_LIMIT: i64 = 50;
+pub const MODERATION_PAGE_LIMIT: i64 = 50;
+pub const AUDIT_EVENT_PAGE_LIMIT: i64 = 100;
+/// Audit events read per query while exporting the log.
+pub const AUDIT_EXPORT_BATCH_SIZE: i64 = 500;
@@ -0,0 +1,117 @@
+use crate::{
+    services::{
+        admin_service::{
+            delete_chat_for_everyone, force_password_reset, list_users, server_stats, set_user_role, suspend_user,
+            unlock_user, unsuspend_user, AdminChatRequest, AdminUserQuery, AdminUserRequest, SetRoleRequest,
+            SuspendUserRequest,
+        },
+        audit_service::{export_audit_events, list_audit_events, AuditEventQuery},
+        user_service::extract_authenticated_user_id,
+    },
+    states::app_state::AppState,
+    utils::request_util::RequestContext,
+};
+use actix_web::{get, http::header::CONTENT_DISPOSITION, post, web, Error, HttpRequest, HttpResponse};
+
+#[get("/users")]
+pub async fn admin_list_users_handler(
+    state: web::Data<AppState>,
+    query: web::Query<AdminUserQuery>,
+) -> Result<HttpResponse, Error> {
+    let results = list_users(&state, &query).await?;
+    Ok(HttpResponse::Ok().json(results))
+}
+
+#[post("/users/suspend")]
+pub async fn admin_suspend_user_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<SuspendUserRequest>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = extract_authenticated_user_id(&req)?;
+    suspend_user(&state, &RequestContext::from_request(&req), admin_id, &body).await?;
+    Ok(HttpResponse::Ok().json("User suspended"))
+}
+
+#[post("/users/unsuspend")]
+pub async fn admin_unsuspend_user_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<AdminUserRequest>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = extract_authenticated_user_id(&req)?;
+    unsuspend_user(&state, &RequestContext::from_request(&req), admin_id, &body.user_id).await?;
+    Ok(HttpResponse::Ok().json("User unsuspended"))
+}
+
+#[post("/users/role")]
+pub async fn admin_set_role_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<SetRoleRequest>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = extract_authenticated_user_id(&req)?;
+    set_user_role(&state, &RequestContext::from_request(&req), admin_id, &body).await?;
+    Ok(HttpResponse::Ok().json("Role updated"))
+}
+
+#[post("/users/force_password_reset")]
+pub async fn admin_force_password_reset_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<AdminUserRequest>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = extract_authenticated_user_id(&req)?;
+    force_password_reset(&state, &RequestContext::from_request(&req), admin_id, &body.user_id).await?;
+    Ok(HttpResponse::Ok().json("Password reset email sent"))
+}
+
+#[post("/users/unlock")]
+pub async fn admin_unlock_user_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<AdminUserRequest>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = extract_authenticated_user_id(&req)?;
+    unlock_user(&state, &RequestContext::from_request(&req), admin_id, &body.user_id).await?;
+    Ok(HttpResponse::Ok().json("Account unlocked"))
+}
+
+#[post("/chats/delete")]
+pub async fn admin_delete_chat_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<AdminChatRequest>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = extract_authenticated_user_id(&req)?;
+    delete_chat_for_everyone(&state, &RequestContext::from_request(&req), admin_id, &body.chat_id).await?;
+    Ok(HttpResponse::Ok().json("Chat deleted"))
+}
+
+#[get("/stats")]
+pub async fn admin_stats_handler(state: web::Data<AppState>) -> Result<HttpResponse, Error> {
+    let stats = server_stats(&state).await?;
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+#[get("/audit")]
+pub async fn admin_audit_events_handler(
+    state: web::Data<AppState>,
+    query: web::Query<AuditEventQuery>,
+) -> Result<HttpResponse, Error> {
+    let events = list_audit_events(&state, &query).await?;
+    Ok(HttpResponse::Ok().json(events))
+}
+
+/// Matching audit events as JSON lines, streamed so large exports are not held in memory.
+#[get("/audit/export")]
+pub async fn admin_audit_export_handler(
+    state: web::Data<AppState>,
+    query: web::Query<AuditEventQuery>,
+) -> Result<HttpResponse, Error> {
+    let events = export_audit_events(&state, &query).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .insert_header((CONTENT_DISPOSITION, "attachment; filename=\"audit_events.jsonl\""))
+        .streaming(events))
+}
@@ -1,27 +1,43 @@
 use crate::{
     services::auth_service::{
-        authenticate_user, change_password, logout_user, register_user, send_reset_password_email, auth_status,
-        ChangePasswordRequest, LoginRequest, LoginResponse, RegisterRequest, RegisterResponse, ResetPasswordRequest,
+        authenticate_user, change_password, logout_user, register_user, resend_verification_email,
+        send_reset_password_email, auth_status, verify_email, ChangePasswordRequest, LoginRequest,
+        LoginResponse, RegisterRequest, RegisterResponse, ResetPasswordRequest, VerifyEmailRequest,
     },
+    services::two_factor_service::{
+        begin_two_factor_enrollment, complete_two_factor_login, confirm_two_factor_enrollment,
+        disable_two_factor, start_two_factor_login, DisableTwoFactorRequest, TwoFactorChallengeResponse,
+        TwoFactorCodeRequest,
+    },
+    services::token_service::{
+        create_personal_access_token, exchange_token, list_personal_access_tokens,
+        revoke_personal_access_token, CreatePersonalTokenRequest, RevokeTokenRequest, TokenRequest,
+    },
+    services::csrf_service::{csrf_token, CsrfTokenResponse},
+    services::oidc_service::{begin_oidc_login, complete_oidc_login, frontend_redirect, OidcCallbackQuery},
+    services::session_service::{current_session_id, list_sessions, revoke_session, RevokeSessionRequest},
+    services::user_service::{authenticate_session, start_user_session},
     states::app_state::AppState,
+    utils::request_util::{client_ip, RequestContext},
 };
 use actix_session::Session;
-use actix_web::{get, post, web, Error, HttpResponse};
+use actix_web::{get, http::header::LOCATION, post, web, Error, HttpRequest, HttpResponse};
 
 #[post("/register")]
 pub async fn register_handler(
+    http_req: HttpRequest,
     state: web::Data<AppState>,
     req: web::Json<RegisterRequest>,
     session: Session,
 ) -> Result<HttpResponse, Error> {
-    let user = register_user(&state, &req).await?;
+    let user = register_user(&state, &req, &RequestContext::from_request(&http_req)).await?;
 
-    if let Some(user_id) = &user.id {
-        session.insert("user_id", &user_id.to_hex())?;
-        session.renew();
+    if user.id.is_some() {
+        let user_id = start_user_session(&session, &user, &http_req)?;
         let result = RegisterResponse {
             user_id: user_id.to_string(),
-            username: user.username
+            username: user.username,
+            email_verified: user.email_verified,
         };
         let response = serde_json::json!(result);
         
@@ -33,19 +49,28 @@ pub async fn register_handler(
 
 #[post("/login")]
 pub async fn login_handler(
+    http_req: HttpRequest,
     state: web::Data<AppState>,
     req: web::Json<LoginRequest>,
     session: Session,
 ) -> Result<HttpResponse, Error> {
-    let user = authenticate_user(&req, &state).await?;
+    let user = authenticate_user(&req, &state, &RequestContext::from_request(&http_req)).await?;
+
+    // Accounts with 2FA only get a partial session until the code is verified at /login/2fa
+    if let (true, Some(user_id)) = (user.totp_enabled, user.id) {
+        start_two_factor_login(&session, user_id)?;
+        return Ok(HttpResponse::Ok().json(TwoFactorChallengeResponse {
+            two_factor_required: true,
+        }));
+    }
 
     // Store user ID in the session
-    if let Some(user_id) = &user.id {
-        session.insert("user_id", &user_id.to_hex())?;
-        session.renew();
+    if user.id.is_some() {
+        let user_id = start_user_session(&session, &user, &http_req)?;
         let result = LoginResponse{
             user_id: user_id.to_string(),
-            username: user.username
+            username: user.username,
+            email_verified: user.email_verified,
         };
         let response = serde_json::json!(result);
         Ok(HttpResponse::Ok().json(response))
@@ -54,9 +79,159 @@ pub async fn login_handler(
     }
 }
 
+#[post("/login/2fa")]
+pub async fn login_two_factor_handler(
+    http_req: HttpRequest,
+    state: web::Data<AppState>,
+    req: web::Json<TwoFactorCodeRequest>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let user = complete_two_factor_login(&state, &session, &req, &client_ip(&http_req)).await?;
+    let user_id = start_user_session(&session, &user, &http_req)?;
+    let result = LoginResponse {
+        user_id: user_id.to_string(),
+        username: user.username,
+        email_verified: user.email_verified,
+    };
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/oidc/login")]
+pub async fn oidc_login_handler(session: Session) -> Result<HttpResponse, Error> {
+    let authorization_url = begin_oidc_login(&session).await?;
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, authorization_url))
+        .finish())
+}
+
+#[get("/oidc/callback")]
+pub async fn oidc_callback_handler(
+    http_req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<OidcCallbackQuery>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let user = complete_oidc_login(&state, &session, &query).await?;
+
+    // Single sign-on does not skip the account's own second factor
+    let redirect = match (user.totp_enabled, user.id) {
+        (true, Some(user_id)) => {
+            start_two_factor_login(&session, user_id)?;
+            frontend_redirect("/login?two_factor_required=true")?
+        }
+        _ => {
+            start_user_session(&session, &user, &http_req)?;
+            frontend_redirect("/chats")?
+        }
+    };
+    Ok(HttpResponse::Found().insert_header((LOCATION, redirect)).finish())
+}
+
+#[post("/2fa/setup")]
+pub async fn two_factor_setup_handler(
+    state: web::Data<AppState>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let user_id = authenticate_session(&state, &session).await?;
+    let result = begin_two_factor_enrollment(&state, user_id).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/2fa/confirm")]
+pub async fn two_factor_confirm_handler(
+    state: web::Data<AppState>,
+    req: web::Json<TwoFactorCodeRequest>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let user_id = authenticate_session(&state, &session).await?;
+    let result = confirm_two_factor_enrollment(&state, user_id, &req).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/2fa/disable")]
+pub async fn two_factor_disable_handler(
+    state: web::Data<AppState>,
+    req: web::Json<DisableTwoFactorRequest>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let user_id = authenticate_session(&state, &session).await?;
+    let result = disable_two_factor(&state, user_id, &req).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/sessions")]
+pub async fn list_sessions_handler(
+    state: web::Data<AppState>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let user_id = authenticate_session(&state, &session).await?;
+    let result = list_sessions(&state, user_id, current_session_id(&session).as_deref()).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/sessions/revoke")]
+pub async fn revoke_session_handler(
+    state: web::Data<AppState>,
+    req: web::Json<RevokeSessionRequest>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let user_id = authenticate_session(&state, &session).await?;
+    revoke_session(&state, user_id, &req.session_id).await?;
+    if current_session_id(&session).as_deref() == Some(req.session_id.as_str()) {
+        session.purge();
+    }
+    Ok(HttpResponse::Ok().json("Session revoked"))
+}
+
+#[post("/token")]
+pub async fn token_handler(
+    http_req: HttpRequest,
+    state: web::Data<AppState>,
+    req: web::Json<TokenRequest>,
+) -> Result<HttpResponse, Error> {
+    let result = exchange_token(&state, &req, &RequestContext::from_request(&http_req)).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/tokens")]
+pub async fn list_tokens_handler(
+    state: web::Data<AppState>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let user_id = authenticate_session(&state, &session).await?;
+    let result = list_personal_access_tokens(&state, user_id).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/tokens/create")]
+pub async fn create_token_handler(
+    state: web::Data<AppState>,
+    req: web::Json<CreatePersonalTokenRequest>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let user_id = authenticate_session(&state, &session).await?;
+    let result = create_personal_access_token(&state, user_id, &req).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/tokens/revoke")]
+pub async fn revoke_token_handler(
+    state: web::Data<AppState>,
+    req: web::Json<RevokeTokenRequest>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let user_id = authenticate_session(&state, &session).await?;
+    revoke_personal_access_token(&state, user_id, &req.token_id).await?;
+    Ok(HttpResponse::Ok().json("Token revoked"))
+}
+
 #[post("/logout")]
-pub async fn logout_handler(session: Session) -> Result<HttpResponse, Error> {
-    let result = logout_user(&session).await?;
+pub async fn logout_handler(
+    http_req: HttpRequest,
+    state: web::Data<AppState>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let result = logout_user(&state, &session, &RequestContext::from_request(&http_req)).await?;
     Ok(HttpResponse::Ok().json(result))
 }
 
@@ -69,20 +244,48 @@ pub async fn auth_status_handler(
     Ok(HttpResponse::Ok().json(serde_json::json!(result)))
 }
 
+/// The token to send in the `X-CSRF-Token` header with state-changing requests.
+#[get("/csrf")]
+pub async fn csrf_token_handler(session: Session) -> Result<HttpResponse, Error> {
+    let csrf_token = csrf_token(&session)?;
+    Ok(HttpResponse::Ok().json(CsrfTokenResponse { csrf_token }))
+}
+
 #[post("/reset_password")]
 pub async fn reset_password_handler(
+    http_req: HttpRequest,
     state: web::Data<AppState>,
     req: web::Json<ResetPasswordRequest>,
 ) -> Result<HttpResponse, Error> {
-    let result = send_reset_password_email(&state, &req).await?;
+    let result = send_reset_password_email(&state, &req, &RequestContext::from_request(&http_req)).await?;
     Ok(HttpResponse::Ok().json(result))
 }
 
 #[post("/change_password")]
 pub async fn change_password_handler(
+    http_req: HttpRequest,
     state: web::Data<AppState>,
     req: web::Json<ChangePasswordRequest>,
 ) -> Result<HttpResponse, Error> {
-    let result = change_password(&state, &req).await?;
+    let result = change_password(&state, &req, &RequestContext::from_request(&http_req)).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/verify_email")]
+pub async fn verify_email_handler(
+    http_req: HttpRequest,
+    state: web::Data<AppState>,
+    req: web::Json<VerifyEmailRequest>,
+) -> Result<HttpResponse, Error> {
+    let result = verify_email(&state, &req, &RequestContext::from_request(&http_req)).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/resend_verification")]
+pub async fn resend_verification_handler(
+    state: web::Data<AppState>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    let result = resend_verification_email(&state, &session).await?;
     Ok(HttpResponse::Ok().json(result))
 }
@@ -1,14 +1,15 @@
 use crate::{
     services::{
         chat_service::{
-            create_chat, delete_chat, get_chat_messages, get_chat_summary, send_message,
-            CreateChatRoomRequest, DeleteChatRequest, SendMessageRequest,
+            create_chat, delete_chat, get_chat_messages, get_chat_summary, get_pinned_messages, message_json,
+            pin_message, send_message, unpin_message, CreateChatRoomRequest, DeleteChatRequest, MessageQuery,
+            PinMessageRequest, SendMessageRequest,
         },
-        user_service::extract_user_id_from_session,
+        user_service::extract_authenticated_user_id,
     },
     states::app_state::AppState,
+    utils::request_util::RequestContext,
 };
-use actix_session::SessionExt;
 use actix_web::{get, post, web, Error, HttpRequest, HttpResponse};
 use mongodb::bson::oid::ObjectId;
 use std::collections::HashSet;
@@ -19,8 +20,7 @@ pub async fn get_chat_summary_handler(
     state: web::Data<AppState>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, Error> {
-    let session = req.get_session();
-    let user_id = extract_user_id_from_session(&session)?;
+    let user_id = extract_authenticated_user_id(&req)?;
 
     let chat_id_str = path.into_inner();
     let chat_id = ObjectId::parse_str(chat_id_str)
@@ -37,15 +37,14 @@ pub async fn create_new_chat_handler(
     state: web::Data<AppState>,
     body: web::Json<CreateChatRoomRequest>,
 ) -> Result<HttpResponse, Error> {
-    let session = req.get_session();
-    let user_id = extract_user_id_from_session(&session)?;
+    let user_id = extract_authenticated_user_id(&req)?;
 
     let participant_id = ObjectId::parse_str(&body.participant_id)
         .map_err(|_| actix_web::error::ErrorBadRequest("Invalid participant ID format"))?;
 
     let mut participant_ids = HashSet::new();
     participant_ids.insert(participant_id);
-    participant_ids.insert(user_id.clone());
+    participant_ids.insert(user_id);
 
     let new_chat = create_chat(&state, None, participant_ids).await?;
 
@@ -58,12 +57,11 @@ pub async fn delete_chat_handler(
     state: web::Data<AppState>,
     body: web::Json<DeleteChatRequest>,
 ) -> Result<HttpResponse, Error> {
-    let session = req.get_session();
-    let user_id = extract_user_id_from_session(&session)?;
+    let user_id = extract_authenticated_user_id(&req)?;
 
-    let chat_id = body.chat_id.clone();
+    let chat_id = body.chat_id;
 
-    delete_chat(&state, chat_id, user_id).await?;
+    delete_chat(&state, chat_id, user_id, &RequestContext::from_request(&req)).await?;
 
     Ok(HttpResponse::Ok().json("Chat deleted successfully"))
 }
@@ -74,14 +72,13 @@ pub async fn send_message_handler(
     state: web::Data<AppState>,
     body: web::Json<SendMessageRequest>,
 ) -> Result<HttpResponse, Error> {
-    let session = req.get_session();
-    let user_id = extract_user_id_from_session(&session)?;
+    let user_id = extract_authenticated_user_id(&req)?;
 
-    let chat_id = body.chat_id.clone();
+    let chat_id = body.chat_id;
 
-    send_message(&state, chat_id, user_id, &body.content, None).await?;
+    let sent = send_message(&state, chat_id, user_id, &body.content, None, body.client_msg_id.as_deref()).await?;
 
-    Ok(HttpResponse::Ok().json("Message sent successfully"))
+    Ok(HttpResponse::Ok().json(message_json(&sent.message)))
 }
 
 // NOTE SECURITY ISSUE
@@ -90,15 +87,65 @@ pub async fn get_chat_messages_handler(
     req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<String>,
+    query: web::Query<MessageQuery>,
 ) -> Result<HttpResponse, Error> {
-    let session = req.get_session();
-    let user_id = extract_user_id_from_session(&session)?;
+    let user_id = extract_authenticated_user_id(&req)?;
 
     let chat_id_str = path.into_inner();
     let chat_id = ObjectId::parse_str(&chat_id_str)
         .map_err(|_| actix_web::error::ErrorBadRequest("Invalid chat room ID"))?;
 
-    let messages = get_chat_messages(&state, chat_id, user_id).await?;
+    let messages = get_chat_messages(&state, chat_id, user_id, &query).await?;
 
     Ok(HttpResponse::Ok().json(messages))
 }
+
+#[get("/{chat_id}/pins")]
+pub async fn get_pinned_messages_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let chat_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid chat room ID"))?;
+
+    let pins = get_pinned_messages(&state, chat_id, user_id).await?;
+
+    Ok(HttpResponse::Ok().json(pins))
+}
+
+#[post("/{chat_id}/pins/pin")]
+pub async fn pin_message_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<PinMessageRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let chat_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid chat room ID"))?;
+
+    let pin = pin_message(&state, chat_id, user_id, body.message_id).await?;
+
+    Ok(HttpResponse::Ok().json(pin))
+}
+
+#[post("/{chat_id}/pins/unpin")]
+pub async fn unpin_message_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<PinMessageRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let chat_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid chat room ID"))?;
+
+    unpin_message(&state, chat_id, user_id, body.message_id).await?;
+
+    Ok(HttpResponse::Ok().json("Message unpinned"))
+}
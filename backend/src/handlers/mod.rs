@@ -1,5 +1,9 @@
+pub mod admin_handler;
 pub mod auth_handler;
 pub mod chat_handler;
+pub mod moderation_handler;
+pub mod notification_handler;
+pub mod saved_message_handler;
 pub mod user_handler;
 pub mod video_call_handler;
-pub mod ws_handler;
\ No newline at end of file
+pub mod ws_handler;
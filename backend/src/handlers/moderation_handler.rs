@@ -0,0 +1,86 @@
+use crate::{
+    services::{
+        moderation_service::{
+            create_report, dismiss_report, list_moderation_actions, list_reports, moderator_suspend_user,
+            remove_message, warn_user, CreateReportRequest, DismissReportRequest, ModerationActionQuery,
+            ModeratorSuspendRequest, RemoveMessageRequest, ReportQuery, WarnUserRequest,
+        },
+        user_service::extract_authenticated_user_id,
+    },
+    states::app_state::AppState,
+    utils::request_util::RequestContext,
+};
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse};
+
+#[post("/create")]
+pub async fn create_report_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<CreateReportRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+    let report = create_report(&state, user_id, &body).await?;
+    Ok(HttpResponse::Created().json(report))
+}
+
+#[get("/reports")]
+pub async fn moderation_queue_handler(
+    state: web::Data<AppState>,
+    query: web::Query<ReportQuery>,
+) -> Result<HttpResponse, Error> {
+    let reports = list_reports(&state, &query).await?;
+    Ok(HttpResponse::Ok().json(reports))
+}
+
+#[post("/reports/dismiss")]
+pub async fn dismiss_report_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<DismissReportRequest>,
+) -> Result<HttpResponse, Error> {
+    let moderator_id = extract_authenticated_user_id(&req)?;
+    dismiss_report(&state, moderator_id, &body).await?;
+    Ok(HttpResponse::Ok().json("Report dismissed"))
+}
+
+#[post("/remove_message")]
+pub async fn remove_message_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<RemoveMessageRequest>,
+) -> Result<HttpResponse, Error> {
+    let moderator_id = extract_authenticated_user_id(&req)?;
+    remove_message(&state, moderator_id, &body).await?;
+    Ok(HttpResponse::Ok().json("Message removed"))
+}
+
+#[post("/warn_user")]
+pub async fn warn_user_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<WarnUserRequest>,
+) -> Result<HttpResponse, Error> {
+    let moderator_id = extract_authenticated_user_id(&req)?;
+    warn_user(&state, moderator_id, &body).await?;
+    Ok(HttpResponse::Ok().json("User warned"))
+}
+
+#[post("/suspend_user")]
+pub async fn moderator_suspend_user_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<ModeratorSuspendRequest>,
+) -> Result<HttpResponse, Error> {
+    let moderator_id = extract_authenticated_user_id(&req)?;
+    moderator_suspend_user(&state, &RequestContext::from_request(&req), moderator_id, &body).await?;
+    Ok(HttpResponse::Ok().json("User suspended"))
+}
+
+#[get("/actions")]
+pub async fn moderation_actions_handler(
+    state: web::Data<AppState>,
+    query: web::Query<ModerationActionQuery>,
+) -> Result<HttpResponse, Error> {
+    let actions = list_moderation_actions(&state, &query).await?;
+    Ok(HttpResponse::Ok().json(actions))
+}
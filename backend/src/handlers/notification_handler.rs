@@ -0,0 +1,135 @@
+use crate::{
+    services::{
+        digest_service::{
+            get_digest_preference, update_digest_preference, DigestPreferenceSummary,
+            UpdateDigestPreferenceRequest,
+        },
+        notification_service::{
+            count_unread_notifications, create_user_notification, delete_notification,
+            get_notification_preferences, list_notifications, mark_notifications_read,
+            update_notification_preference, CreateNotificationRequest, DismissNotificationRequest,
+            ListNotificationsQuery, MarkReadRequest, UpdatePreferenceRequest,
+        },
+        user_service::extract_authenticated_user_id,
+    },
+    states::app_state::AppState,
+};
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse};
+use mongodb::bson::oid::ObjectId;
+
+#[get("/list")]
+pub async fn list_notifications_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<ListNotificationsQuery>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let results = list_notifications(&state, user_id, &query).await?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+#[get("/unread_count")]
+pub async fn unread_count_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let unread_count = count_unread_notifications(&state, user_id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "unread_count": unread_count })))
+}
+
+#[post("/create")]
+pub async fn create_notification_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<CreateNotificationRequest>,
+) -> Result<HttpResponse, Error> {
+    let sender_id = extract_authenticated_user_id(&req)?;
+
+    let notification = create_user_notification(&state, sender_id, &body).await?;
+
+    Ok(HttpResponse::Ok().json(notification))
+}
+
+#[post("/mark_read")]
+pub async fn mark_read_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<MarkReadRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let updated = mark_notifications_read(&state, user_id, &body).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "updated": updated })))
+}
+
+#[post("/dismiss")]
+pub async fn dismiss_notification_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<DismissNotificationRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let notification_id = ObjectId::parse_str(&body.notification_id)
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid notification ID"))?;
+
+    delete_notification(&state, user_id, notification_id).await?;
+
+    Ok(HttpResponse::Ok().json("Notification dismissed"))
+}
+
+#[get("/preferences")]
+pub async fn get_preferences_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let preferences = get_notification_preferences(&state, user_id).await?;
+
+    Ok(HttpResponse::Ok().json(preferences))
+}
+
+#[post("/preferences")]
+pub async fn update_preference_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<UpdatePreferenceRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let preference = update_notification_preference(&state, user_id, &body).await?;
+
+    Ok(HttpResponse::Ok().json(preference))
+}
+
+#[get("/digest")]
+pub async fn get_digest_preference_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let preference = get_digest_preference(&state, user_id).await?;
+
+    Ok(HttpResponse::Ok().json(DigestPreferenceSummary::from(preference)))
+}
+
+#[post("/digest")]
+pub async fn update_digest_preference_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<UpdateDigestPreferenceRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+
+    let preference = update_digest_preference(&state, user_id, &body).await?;
+
+    Ok(HttpResponse::Ok().json(preference))
+}
@@ -0,0 +1,47 @@
+use crate::{
+    services::{
+        saved_message_service::{
+            list_saved_messages, save_message, unsave_message, SaveMessageRequest, SavedMessageQuery,
+            UnsaveMessageRequest,
+        },
+        user_service::extract_authenticated_user_id,
+    },
+    states::app_state::AppState,
+};
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse};
+
+#[get("")]
+pub async fn list_saved_messages_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<SavedMessageQuery>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+    let results = list_saved_messages(&state, user_id, &query).await?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+#[post("")]
+pub async fn save_message_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<SaveMessageRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+    let saved = save_message(&state, user_id, &body).await?;
+
+    Ok(HttpResponse::Ok().json(saved))
+}
+
+#[post("/remove")]
+pub async fn unsave_message_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<UnsaveMessageRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id = extract_authenticated_user_id(&req)?;
+    unsave_message(&state, user_id, body.message_id).await?;
+
+    Ok(HttpResponse::Ok().json("Saved message removed"))
+}
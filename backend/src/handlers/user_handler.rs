@@ -1,15 +1,14 @@
 use crate::{
     services::{
-        chat_service::get_user_chats,
+        chat_service::{get_user_chats, ChatListQuery},
         notification_service::get_user_notifications,
         user_service::{
-            extract_user_id_from_session, get_user_data, is_user_online, search_users,
+            extract_authenticated_user_id, get_user_data, is_user_online, search_users,
             BatchCheckOnlineRequest, BatchCheckOnlineResponse, UserDetailsRequest
         },
     },
     states::app_state::AppState,
 };
-use actix_session::SessionExt;
 use actix_web::{get, post, web, Error, HttpRequest, HttpResponse};
 use futures;
 use mongodb::bson::oid::ObjectId;
@@ -57,10 +56,10 @@ pub async fn check_batch_online_handler(
 pub async fn get_chats_handler(
     req: HttpRequest,
     state: web::Data<AppState>,
+    query: web::Query<ChatListQuery>,
 ) -> Result<HttpResponse, Error> {
-    let session = req.get_session();
-    let user_id = extract_user_id_from_session(&session)?;
-    let results = get_user_chats(&state, user_id).await?;
+    let user_id = extract_authenticated_user_id(&req)?;
+    let results = get_user_chats(&state, user_id, &query).await?;
 
     Ok(HttpResponse::Ok().json(results))
 }
@@ -70,8 +69,7 @@ pub async fn get_notifications_handler(
     req: HttpRequest,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
-    let session = req.get_session();
-    let user_id = extract_user_id_from_session(&session)?;
+    let user_id = extract_authenticated_user_id(&req)?;
     let results = get_user_notifications(&state, user_id).await?;
 
     Ok(HttpResponse::Ok().json(results))
@@ -96,7 +94,7 @@ pub async fn get_user_details_handler(
     state: web::Data<AppState>,
     body: web::Json<UserDetailsRequest>
 ) -> Result<HttpResponse, Error> {
-    let user_id = body.user_id.clone();
+    let user_id = body.user_id;
 
     let user_json = get_user_data(&state, user_id).await?;
     Ok(HttpResponse::Ok().json(user_json))
@@ -1,12 +1,11 @@
 use crate::{
     services::{
-        user_service::extract_user_id_from_session,
+        user_service::extract_authenticated_user_id,
         video_call_service::{VideoCallRequest, VideoCallResponse,
         initiate_video_call_logic, respond_video_call_logic},
     },
     states::app_state::AppState,
 };
-use actix_session::SessionExt;
 use actix_web::{post, web, Error, HttpRequest, HttpResponse};
 use mongodb::bson::oid::ObjectId;
 
@@ -16,9 +15,8 @@ pub async fn initiate_video_call(
     state: web::Data<AppState>,
     body: web::Json<VideoCallRequest>,
 ) -> Result<HttpResponse, Error> {
-    // Get the caller's user ID from the session
-    let session = req.get_session();
-    let caller_id = extract_user_id_from_session(&session)?;
+    // Get the caller's user ID from the session or bearer token
+    let caller_id = extract_authenticated_user_id(&req)?;
 
     // Parse recipient_id and chat_id
     let recipient_id = ObjectId::parse_str(&body.recipient_id)
@@ -38,9 +36,8 @@ pub async fn respond_video_call(
     state: web::Data<AppState>,
     body: web::Json<VideoCallResponse>,
 ) -> Result<HttpResponse, Error> {
-    // Get the recipient's user ID from the session
-    let session = req.get_session();
-    let recipient_id = extract_user_id_from_session(&session)?;
+    // Get the recipient's user ID from the session or bearer token
+    let recipient_id = extract_authenticated_user_id(&req)?;
 
     // Parse notification_id
     let notification_id = ObjectId::parse_str(&body.notification_id)
@@ -1,5 +1,6 @@
 use crate::{
-    services::user_service::extract_user_id_from_session,
+    services::{csrf_service::verify_websocket_origin, user_service::extract_authenticated_user_id},
+    session::repository_session_store::SESSION_ID,
     states::app_state::AppState,
     websocket::websocket_session::WsSession,
 };
@@ -14,10 +15,12 @@ pub async fn ws_session_start_handler(
     stream: web::Payload,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
-    // Extract the user ID from the session
-    let session = req.get_session();
-    // Parse the user ID as an ObjectId
-    let user_id = extract_user_id_from_session(&session)?;
+    // Browsers do not apply CORS to WebSockets, so check the page's origin here
+    verify_websocket_origin(&req)?;
+    // Extract the user ID from the session or bearer token
+    let user_id = extract_authenticated_user_id(&req)?;
+    // Remember which HTTP session opened the connection so revoking it closes the socket too
+    let auth_session_id = req.get_session().get::<String>(SESSION_ID).unwrap_or(None);
     // Start the WebSocket connection
-    ws::start(WsSession::new(user_id, state.clone()), &req, stream)
+    ws::start(WsSession::new(user_id, auth_session_id, state.clone()), &req, stream)
 }
@@ -1,11 +1,16 @@
 // src/lib.rs
 
 // Re-export modules that should be accessible from tests.
+pub mod auth;
 pub mod handlers;
 pub mod config;
+pub mod mail;
 pub mod middleware;
+pub mod migrations;
 pub mod models;
+pub mod repositories;
 pub mod services;
+pub mod session;
 pub mod utils;
 pub mod websocket;
 pub mod states;
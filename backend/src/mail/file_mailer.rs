@@ -0,0 +1,37 @@
+use crate::mail::mailer::{EmailMessage, MailError, Mailer};
+use async_trait::async_trait;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Writes every email as an `.eml` file into the `new/` folder of a maildir,
+/// for development setups without an SMTP server.
+pub struct FileMailer {
+    dir: PathBuf,
+    from: String,
+}
+
+impl FileMailer {
+    pub fn new(dir: &str, from: &str) -> Self {
+        Self {
+            dir: PathBuf::from(dir),
+            from: from.to_owned(),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &EmailMessage) -> Result<(), MailError> {
+        let message = email.to_lettre_message(&self.from)?;
+
+        let new_dir = self.dir.join("new");
+        tokio::fs::create_dir_all(&new_dir)
+            .await
+            .map_err(|e| MailError::Transport(e.to_string()))?;
+        let path = new_dir.join(format!("{}.eml", Uuid::new_v4()));
+        tokio::fs::write(&path, message.formatted())
+            .await
+            .map_err(|e| MailError::Transport(e.to_string()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use crate::mail::mailer::EmailMessage;
+
+/// A link rendered as a button in HTML and as a bare URL in plain text.
+struct Action<'a> {
+    label: &'a str,
+    url: &'a str,
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Render the shared layout in both text and HTML from the same content.
+fn render(to: &str, subject: &str, paragraphs: &[String], action: Option<Action>) -> EmailMessage {
+    let mut text_body = paragraphs.join("\n\n");
+    let mut html_body = paragraphs
+        .iter()
+        .map(|paragraph| format!("<p>{}</p>", escape_html(paragraph).replace('\n', "<br>")))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if let Some(action) = action {
+        text_body.push_str(&format!("\n\n{}: {}", action.label, action.url));
+        html_body.push_str(&format!(
+            "\n<p><a href=\"{}\" style=\"display:inline-block;padding:10px 16px;background:#4f46e5;color:#ffffff;text-decoration:none;border-radius:6px\">{}</a></p>",
+            escape_html(action.url),
+            escape_html(action.label)
+        ));
+    }
+
+    text_body.push_str("\n\n-- \nCphere");
+    let html_body = format!(
+        "<!DOCTYPE html>\n<html>\n<body style=\"font-family:sans-serif;color:#111827\">\n{}\n<p style=\"color:#6b7280\">Cphere</p>\n</body>\n</html>\n",
+        html_body
+    );
+
+    EmailMessage {
+        to: to.to_owned(),
+        subject: subject.to_owned(),
+        text_body,
+        html_body,
+    }
+}
+
+fn frontend_link(frontend_url: &str, path: &str) -> String {
+    format!("{}/{}", frontend_url.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+pub fn password_reset_email(to: &str, frontend_url: &str, token: &str) -> EmailMessage {
+    let reset_link = frontend_link(frontend_url, &format!("reset-password/{}", token));
+    render(
+        to,
+        "Password Reset",
+        &[
+            "We received a request to reset your Cphere password.".to_string(),
+            "If you did not ask for this, you can ignore this email.".to_string(),
+        ],
+        Some(Action {
+            label: "Reset your password",
+            url: &reset_link,
+        }),
+    )
+}
+
+pub fn email_verification_email(to: &str, frontend_url: &str, username: &str, token: &str) -> EmailMessage {
+    let verify_link = frontend_link(frontend_url, &format!("verify-email/{}", token));
+    render(
+        to,
+        "Verify your email address",
+        &[
+            format!("Welcome to Cphere, {}!", username),
+            "Please confirm that this is your email address.".to_string(),
+        ],
+        Some(Action {
+            label: "Verify email",
+            url: &verify_link,
+        }),
+    )
+}
+
+pub fn account_locked_email(to: &str, frontend_url: &str, username: &str, lockout_minutes: i64) -> EmailMessage {
+    let reset_link = frontend_link(frontend_url, "forgot-password");
+    render(
+        to,
+        "Your account has been locked",
+        &[
+            format!("Hi {},", username),
+            format!(
+                "We locked your Cphere account for {} minutes after several failed sign-in attempts.",
+                lockout_minutes
+            ),
+            "If this was not you, we recommend resetting your password.".to_string(),
+        ],
+        Some(Action {
+            label: "Reset your password",
+            url: &reset_link,
+        }),
+    )
+}
+
+pub fn notification_email(to: &str, frontend_url: &str, kind: &str, message: &str) -> EmailMessage {
+    let app_link = frontend_link(frontend_url, "");
+    render(
+        to,
+        &format!("New {} notification", kind),
+        &[message.to_owned()],
+        Some(Action {
+            label: "Open Cphere",
+            url: &app_link,
+        }),
+    )
+}
+
+pub fn activity_digest_email(
+    to: &str,
+    frontend_url: &str,
+    username: &str,
+    summary_lines: &[String],
+) -> EmailMessage {
+    let app_link = frontend_link(frontend_url, "");
+    render(
+        to,
+        "Your Cphere activity digest",
+        &[
+            format!("Hi {},", username),
+            "Here is what happened while you were away:".to_string(),
+            summary_lines.join("\n"),
+        ],
+        Some(Action {
+            label: "Catch up on Cphere",
+            url: &app_link,
+        }),
+    )
+}
@@ -0,0 +1,67 @@
+use crate::{
+    config::app_config::AppConfig,
+    mail::{file_mailer::FileMailer, memory_mailer::MemoryMailer, smtp_mailer::SmtpMailer},
+};
+use async_trait::async_trait;
+use lettre::message::{Mailbox, MultiPart};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, sync::Arc};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error("invalid address: {0}")]
+    Address(String),
+    #[error("failed to build message: {0}")]
+    Build(String),
+    #[error("failed to deliver message: {0}")]
+    Transport(String),
+}
+
+/// An email ready to be handed to a [`Mailer`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+impl EmailMessage {
+    /// Build a multipart (text + HTML) message sent from `from`.
+    pub fn to_lettre_message(&self, from: &str) -> Result<lettre::Message, MailError> {
+        let from: Mailbox = from
+            .parse()
+            .map_err(|_| MailError::Address(from.to_string()))?;
+        let to: Mailbox = self
+            .to
+            .parse()
+            .map_err(|_| MailError::Address(self.to.clone()))?;
+        lettre::Message::builder()
+            .from(from)
+            .to(to)
+            .subject(&self.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                self.text_body.clone(),
+                self.html_body.clone(),
+            ))
+            .map_err(|e| MailError::Build(e.to_string()))
+    }
+}
+
+/// Delivers emails. Implementations must not block the async runtime.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &EmailMessage) -> Result<(), MailError>;
+}
+
+/// Build the mailer selected by `MAIL_BACKEND`.
+pub fn mailer_from_config(config: &AppConfig) -> Result<Arc<dyn Mailer>, Box<dyn Error>> {
+    let mailer: Arc<dyn Mailer> = match config.mail_backend.as_str() {
+        "smtp" => Arc::new(SmtpMailer::new(config)?),
+        "file" => Arc::new(FileMailer::new(&config.mail_file_dir, &config.email_address)),
+        "memory" => Arc::new(MemoryMailer::default()),
+        other => return Err(format!("Unknown MAIL_BACKEND: {}", other).into()),
+    };
+    Ok(mailer)
+}
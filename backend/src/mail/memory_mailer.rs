@@ -0,0 +1,27 @@
+use crate::mail::mailer::{EmailMessage, MailError, Mailer};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+/// Keeps sent emails in memory so tests can inspect them.
+#[derive(Default, Clone)]
+pub struct MemoryMailer {
+    sent: Arc<Mutex<Vec<EmailMessage>>>,
+}
+
+impl MemoryMailer {
+    /// All emails sent so far, oldest first.
+    pub fn sent(&self) -> Vec<EmailMessage> {
+        self.sent.lock().map(|sent| sent.clone()).unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, email: &EmailMessage) -> Result<(), MailError> {
+        self.sent
+            .lock()
+            .map_err(|e| MailError::Transport(e.to_string()))?
+            .push(email.clone());
+        Ok(())
+    }
+}
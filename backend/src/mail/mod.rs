@@ -0,0 +1,5 @@
+pub mod file_mailer;
+pub mod mail_templates;
+pub mod mailer;
+pub mod memory_mailer;
+pub mod smtp_mailer;
@@ -0,0 +1,55 @@
+use crate::{
+    config::app_config::AppConfig,
+    mail::mailer::{EmailMessage, MailError, Mailer},
+};
+use async_trait::async_trait;
+use lettre::{
+    transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport,
+    Tokio1Executor,
+};
+use std::error::Error;
+
+/// Sends email through an SMTP relay.
+pub struct SmtpMailer {
+    from: String,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &AppConfig) -> Result<Self, Box<dyn Error>> {
+        let builder = match config.smtp_tls.as_str() {
+            // Implicit TLS, usually port 465.
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)?,
+            // Plain connection upgraded with STARTTLS, usually port 587.
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?,
+            // Unencrypted, only meant for local relays and test servers.
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+            other => return Err(format!("Unknown SMTP_TLS mode: {}", other).into()),
+        };
+
+        let mut builder = builder.port(config.smtp_port);
+        if !config.email_password.is_empty() {
+            builder = builder.credentials(Credentials::new(
+                config.email_address.clone(),
+                config.email_password.clone(),
+            ));
+        }
+
+        Ok(Self {
+            from: config.email_address.clone(),
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &EmailMessage) -> Result<(), MailError> {
+        let message = email.to_lettre_message(&self.from)?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| MailError::Transport(e.to_string()))?;
+        Ok(())
+    }
+}
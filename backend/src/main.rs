@@ -1,5 +1,5 @@
-use actix_session::{storage::CookieSessionStore, SessionMiddleware};
-use actix_session::config::PersistentSession;
+use actix_session::SessionMiddleware;
+use actix_session::config::{CookieContentSecurity, PersistentSession, TtlExtensionPolicy};
 use actix_web::{
     cookie::{Key, SameSite},
     middleware::Logger,
@@ -7,26 +7,62 @@ use actix_web::{
 };
 use actix_cors::Cors;
 use cphere_backend::{
-    config::database::init_db,
+    config::{app_config::AppConfig, database::connect_storage},
+    constants::SESSION_TTL_DAYS,
     handlers::{
+        admin_handler::{
+            admin_audit_events_handler, admin_audit_export_handler, admin_delete_chat_handler, admin_force_password_reset_handler, admin_list_users_handler, admin_set_role_handler,
+            admin_stats_handler, admin_suspend_user_handler, admin_unlock_user_handler,
+            admin_unsuspend_user_handler,
+        },
         auth_handler::{
-            change_password_handler, login_handler, logout_handler, auth_status_handler, register_handler,
-            reset_password_handler,
+            change_password_handler, csrf_token_handler, login_handler, logout_handler, auth_status_handler, register_handler,
+            create_token_handler, list_sessions_handler, list_tokens_handler, login_two_factor_handler,
+            oidc_callback_handler, oidc_login_handler,
+            revoke_token_handler, token_handler, resend_verification_handler,
+            reset_password_handler, revoke_session_handler,
+            two_factor_confirm_handler, two_factor_disable_handler, two_factor_setup_handler,
+            verify_email_handler,
         },
         chat_handler::{
-            create_new_chat_handler, get_chat_messages_handler, send_message_handler, delete_chat_handler, get_chat_summary_handler
+            create_new_chat_handler, get_chat_messages_handler, send_message_handler, delete_chat_handler, get_chat_summary_handler,
+            get_pinned_messages_handler, pin_message_handler, unpin_message_handler
+        },
+        moderation_handler::{
+            create_report_handler, dismiss_report_handler, moderation_actions_handler, moderation_queue_handler,
+            moderator_suspend_user_handler, remove_message_handler, warn_user_handler,
+        },
+        saved_message_handler::{list_saved_messages_handler, save_message_handler, unsave_message_handler},
+        notification_handler::{
+            create_notification_handler, dismiss_notification_handler, get_digest_preference_handler,
+            get_preferences_handler, list_notifications_handler, mark_read_handler, unread_count_handler,
+            update_digest_preference_handler, update_preference_handler,
         },
         ws_handler::ws_session_start_handler,
         user_handler::{
-            check_batch_online_handler, check_online_handler, get_chats_handler, get_user_details_handler, get_notifications_handler, search_users_handler
+            check_batch_online_handler, check_online_handler, get_chats_handler, get_user_details_handler, get_notifications_handler, search_users_handler,
         },
         video_call_handler::{initiate_video_call, respond_video_call},
     },
-    middleware::auth_middleware::AuthMiddlewareFactory,
+    middleware::{
+        auth_middleware::AuthMiddlewareFactory, csrf_middleware::CsrfMiddlewareFactory,
+        role_middleware::RoleMiddlewareFactory,
+        session_key_rotation::SessionKeyRotationFactory,
+    },
+    auth::auth_provider::auth_provider_from_config,
+    mail::mailer::mailer_from_config,
+    migrations::migration_runner::{migration_status, run_migrations},
+    services::{
+        chat_service::start_chat_purge_job, digest_service::start_digest_scheduler,
+        expiry_service::start_expiry_job, mail_service::start_outbox_worker,
+    },
+    session::repository_session_store::RepositorySessionStore,
     states::app_state::AppState,
+    utils::request_util::TrustedProxies,
+    models::user_model::UserRole,
+    repositories::repository::repositories_for,
+    types::auth_types::ApiResource,
 };
-use env_logger;
-use mongodb::{Client, Database};
 use time::Duration;
 use tokio::signal;
 
@@ -35,24 +71,77 @@ async fn main() -> std::io::Result<()> {
     // Initialize the logger
     env_logger::init();
 
-    // Initialize the database
-    let (client, db): (Client, Database) = match init_db().await {
-        Ok((client, db)) => (client, db),
+    let config = AppConfig::new().map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // Connect to the storage backend selected in the configuration; nothing else is needed
+    let storage = match connect_storage(&config).await {
+        Ok(storage) => storage,
         Err(e) => {
             eprintln!("Database error: {}", e);
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
+            return Err(std::io::Error::other(
                 "Database initialization failed",
             ));
         }
     };
 
-    // Initialize AppState with the database
-    let app_state = AppState::new(client, db);
+    // Create indexes and apply other pending schema changes before serving requests
+    if config.migrate_on_startup {
+        if let Err(e) = run_migrations(&storage).await {
+            eprintln!("Migration error: {}", e);
+            return Err(std::io::Error::other("Database migration failed"));
+        }
+    } else {
+        // Records written before a pending migration may be missing fields the models require
+        match migration_status(&storage).await {
+            Ok(status) if status.pending.is_empty() => {}
+            Ok(status) => {
+                eprintln!("{} migrations are pending; apply them with the migrate binary first", status.pending.len());
+                return Err(std::io::Error::other("Database migrations pending"));
+            }
+            Err(e) => {
+                eprintln!("Migration error: {}", e);
+                return Err(std::io::Error::other("Database migration check failed"));
+            }
+        }
+    }
+
+    // Initialize the mail transport selected in the configuration
+    let mailer = mailer_from_config(&config).map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // Initialize the password check selected in the configuration
+    let auth_provider = auth_provider_from_config(&config).map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // Users, chats, sessions and the rest of the server state live in the storage backend
+    let repositories = repositories_for(&storage);
+
+    // The cookie only carries an encrypted session key; the session itself lives in the storage backend
+    let session_store = RepositorySessionStore::new(repositories.sessions.clone());
+    let session_key_rotation = SessionKeyRotationFactory {
+        cookie_name: "session_id".to_owned(),
+        current_key: Key::derive_from(config.session_secret.as_bytes()),
+        previous_keys: config
+            .previous_session_secrets
+            .iter()
+            .map(|secret| Key::derive_from(secret.as_bytes()))
+            .collect(),
+    };
+
+    let cors_allowed_origins = config.cors_allowed_origins.clone();
+    let trusted_proxies = web::Data::new(TrustedProxies(config.trusted_proxies.clone()));
+
+    // Initialize AppState with the storage backend
+    let app_state = AppState::new(mailer, auth_provider, repositories);
 
     // Wrap AppState in web::Data to make it shareable
     let app_state_data = web::Data::new(app_state);
 
+    // Start the background jobs that retry queued emails, email activity digests to offline users,
+    // purge chats every participant deleted and remove expired sessions, tokens and throttles
+    start_outbox_worker(app_state_data.clone(), config.outbox_retry_interval_seconds);
+    start_digest_scheduler(app_state_data.clone(), config.digest_interval_minutes);
+    start_chat_purge_job(app_state_data.clone(), config.chat_purge_interval_minutes);
+    start_expiry_job(app_state_data.clone());
+
     // Start the Actix server
     println!("Server running on http://127.0.0.1:8080");
 
@@ -60,72 +149,151 @@ async fn main() -> std::io::Result<()> {
     let server = HttpServer::new(move || {
         App::new()
             .app_data(app_state_data.clone()) // Add AppState to the app's data
+            .app_data(trusted_proxies.clone()) // Proxies whose X-Forwarded-For is believed
             .wrap(Logger::default()) // Enable logging middleware
+            .wrap(CsrfMiddlewareFactory::new(cors_allowed_origins.clone())) // Runs inside the session and CORS middleware so rejections still carry CORS headers
             .wrap(
-                Cors::default()
-                    .allowed_origin("http://localhost")
+                cors_allowed_origins
+                    .iter()
+                    .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
                     .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS"])
                     .allowed_headers(vec![
                         actix_web::http::header::AUTHORIZATION,
                         actix_web::http::header::ACCEPT,
                         actix_web::http::header::CONTENT_TYPE,
+                        actix_web::http::header::HeaderName::from_static("x-csrf-token"),
                     ])
                     .supports_credentials()
                     .max_age(3600),
             )
             .wrap(
                 SessionMiddleware::builder(
-                    CookieSessionStore::default(),
-                    Key::from(&[0; 64]), // Use a 64-byte key
+                    session_store.clone(),
+                    session_key_rotation.current_key.clone(),
                 )
                 .cookie_secure(false) // Set to true in production with HTTPS
-                .cookie_name("session_id".to_owned()) // Optionally set a custom cookie name
+                .cookie_name(session_key_rotation.cookie_name.clone()) // Optionally set a custom cookie name
                 .cookie_same_site(SameSite::Lax) // Set the SameSite policy
                 .cookie_http_only(true) // Prevent JavaScript access (security best practice)
+                .cookie_content_security(CookieContentSecurity::Private) // Encrypt the session key
                 .session_lifecycle(
-                    PersistentSession::default().session_ttl(Duration::days(7)), // Keep session for 7 days
+                    PersistentSession::default()
+                        .session_ttl(Duration::days(SESSION_TTL_DAYS)) // Keep session for 7 days
+                        .session_ttl_extension_policy(TtlExtensionPolicy::OnEveryRequest), // Track last activity
                 )
                 .build(),
             )
+            .wrap(session_key_rotation.clone()) // Must run before the session middleware reads the cookie
             .configure(|cfg| {
                 cfg.service(
                     web::scope("/auth")
                         .service(register_handler)
                         .service(login_handler)
+                        .service(login_two_factor_handler)
+                        .service(oidc_login_handler)
+                        .service(oidc_callback_handler)
                         .service(logout_handler)
                         .service(auth_status_handler)
+                        .service(csrf_token_handler)
                         .service(reset_password_handler)
-                        .service(change_password_handler),
+                        .service(change_password_handler)
+                        .service(verify_email_handler)
+                        .service(resend_verification_handler)
+                        .service(two_factor_setup_handler)
+                        .service(two_factor_confirm_handler)
+                        .service(two_factor_disable_handler)
+                        .service(list_sessions_handler)
+                        .service(revoke_session_handler)
+                        .service(token_handler)
+                        .service(list_tokens_handler)
+                        .service(create_token_handler)
+                        .service(revoke_token_handler),
                 )
                 .service(search_users_handler)
                 .service(
                     web::scope("/websocket")
-                        .wrap(AuthMiddlewareFactory {}) // Instantiate the middleware
+                        .wrap(AuthMiddlewareFactory::new(ApiResource::Websocket)) // Instantiate the middleware
                         .service(ws_session_start_handler),
                 )
+                .service(
+                    // Registered before `/users` so it matches first; saved messages need the chat scopes
+                    web::scope("/users/saved")
+                        .wrap(AuthMiddlewareFactory::new(ApiResource::Chats))
+                        .service(list_saved_messages_handler)
+                        .service(save_message_handler)
+                        .service(unsave_message_handler),
+                )
                 .service(
                     web::scope("/users")
-                        .wrap(AuthMiddlewareFactory {}) // Instantiate the middleware
+                        .wrap(AuthMiddlewareFactory::new(ApiResource::Users)) // Instantiate the middleware
                         .service(get_chats_handler)
                         .service(check_online_handler)
                         .service(check_batch_online_handler)
                         .service(get_notifications_handler)
-                        .service(get_user_details_handler)
+                        .service(get_user_details_handler),
                 )
                 .service(
                     web::scope("/chats")
-                        .wrap(AuthMiddlewareFactory {}) // Instantiate the middleware
+                        .wrap(AuthMiddlewareFactory::new(ApiResource::Chats)) // Instantiate the middleware
                         .service(create_new_chat_handler)
                         .service(get_chat_summary_handler)
                         .service(delete_chat_handler)
                         .service(send_message_handler)
-                        .service(get_chat_messages_handler),
+                        .service(get_chat_messages_handler)
+                        .service(get_pinned_messages_handler)
+                        .service(pin_message_handler)
+                        .service(unpin_message_handler),
+                )
+                .service(
+                    web::scope("/notifications")
+                        .wrap(AuthMiddlewareFactory::new(ApiResource::Notifications)) // Instantiate the middleware
+                        .service(list_notifications_handler)
+                        .service(unread_count_handler)
+                        .service(create_notification_handler)
+                        .service(mark_read_handler)
+                        .service(dismiss_notification_handler)
+                        .service(get_preferences_handler)
+                        .service(update_preference_handler)
+                        .service(get_digest_preference_handler)
+                        .service(update_digest_preference_handler),
                 )
                 .service(
                     web::scope("/video_call")
-                        .wrap(AuthMiddlewareFactory {}) // Instantiate the middleware
+                        .wrap(AuthMiddlewareFactory::new(ApiResource::VideoCall)) // Instantiate the middleware
                         .service(initiate_video_call)
                         .service(respond_video_call),
+                )
+                .service(
+                    web::scope("/reports")
+                        .wrap(AuthMiddlewareFactory::new(ApiResource::Reports))
+                        .service(create_report_handler),
+                )
+                .service(
+                    // Registered before "/admin" so the moderator scope matches first
+                    web::scope("/admin/moderation")
+                        .wrap(RoleMiddlewareFactory::new(UserRole::Moderator))
+                        .wrap(AuthMiddlewareFactory::new(ApiResource::Admin))
+                        .service(moderation_queue_handler)
+                        .service(dismiss_report_handler)
+                        .service(remove_message_handler)
+                        .service(warn_user_handler)
+                        .service(moderator_suspend_user_handler)
+                        .service(moderation_actions_handler),
+                )
+                .service(
+                    web::scope("/admin")
+                        .wrap(RoleMiddlewareFactory::new(UserRole::Admin)) // Runs after the auth middleware below
+                        .wrap(AuthMiddlewareFactory::new(ApiResource::Admin))
+                        .service(admin_list_users_handler)
+                        .service(admin_suspend_user_handler)
+                        .service(admin_unsuspend_user_handler)
+                        .service(admin_set_role_handler)
+                        .service(admin_force_password_reset_handler)
+                        .service(admin_unlock_user_handler)
+                        .service(admin_delete_chat_handler)
+                        .service(admin_stats_handler)
+                        .service(admin_audit_events_handler)
+                        .service(admin_audit_export_handler),
                 );
             })
     })
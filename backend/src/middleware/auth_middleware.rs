@@ -1,14 +1,30 @@
+use crate::{
+    services::{token_service::authenticate_bearer_token, user_service::authenticate_session},
+    states::app_state::AppState,
+    types::auth_types::{ApiResource, AuthenticatedUser},
+    utils::request_util::{bearer_token, query_access_token},
+};
 use actix_service::{Service, Transform};
 use actix_session::SessionExt;
 use actix_web::{
     body::BoxBody,
     dev::{ServiceRequest, ServiceResponse},
-    Error, HttpResponse,
+    web, Error, HttpMessage, HttpResponse,
 };
 use futures::future::{ok, Either, Ready};
 use futures_util::future::LocalBoxFuture;
+use std::rc::Rc;
 
-pub struct AuthMiddlewareFactory;
+/// Requires a signed-in session cookie or a bearer token with a scope covering `resource`.
+pub struct AuthMiddlewareFactory {
+    pub resource: ApiResource,
+}
+
+impl AuthMiddlewareFactory {
+    pub fn new(resource: ApiResource) -> Self {
+        Self { resource }
+    }
+}
 
 impl<S> Transform<S, ServiceRequest> for AuthMiddlewareFactory
 where
@@ -21,12 +37,28 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(AuthMiddleware { service })
+        ok(AuthMiddleware {
+            service: Rc::new(service),
+            resource: self.resource,
+        })
     }
 }
 
 pub struct AuthMiddleware<S> {
-    service: S,
+    service: Rc<S>,
+    resource: ApiResource,
+}
+
+fn unauthorized(req: ServiceRequest) -> ServiceResponse<BoxBody> {
+    let (request, _payload) = req.into_parts();
+    let response = HttpResponse::Unauthorized().finish();
+    ServiceResponse::new(request, response)
+}
+
+fn insufficient_scope(req: ServiceRequest) -> ServiceResponse<BoxBody> {
+    let (request, _payload) = req.into_parts();
+    let response = HttpResponse::Forbidden().json("Token does not have the required scope");
+    ServiceResponse::new(request, response)
 }
 
 impl<S> Service<ServiceRequest> for AuthMiddleware<S>
@@ -48,18 +80,45 @@ where
     }
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let session = req.get_session();
+        let Some(state) = req.app_data::<web::Data<AppState>>().cloned() else {
+            return Either::Left(ok(unauthorized(req)));
+        };
+        let service = self.service.clone();
 
+        // Bearer tokens are checked first; the WebSocket handshake may pass the token in the query string
+        let token = bearer_token(req.request()).or_else(|| match self.resource {
+            ApiResource::Websocket => query_access_token(req.request()),
+            _ => None,
+        });
+        if let Some(token) = token {
+            let required_scope = self.resource.required_scope(req.method());
+            return Either::Right(Box::pin(async move {
+                match authenticate_bearer_token(&state, &token).await {
+                    Ok(user) if user.has_scope(required_scope) => {
+                        req.extensions_mut().insert(user);
+                        service.call(req).await
+                    }
+                    Ok(_) => Ok(insufficient_scope(req)),
+                    Err(_) => Ok(unauthorized(req)),
+                }
+            }));
+        }
+
+        let session = req.get_session();
         if let Ok(Some(_user_id)) = session.get::<String>("user_id") {
-            // User is authenticated; proceed to the next service
-            let fut = self.service.call(req);
-            Either::Right(Box::pin(fut))
+            // User claims to be authenticated; make sure the session has not been revoked
+            Either::Right(Box::pin(async move {
+                match authenticate_session(&state, &session).await {
+                    Ok(user_id) => {
+                        req.extensions_mut().insert(AuthenticatedUser { user_id, scopes: None });
+                        service.call(req).await
+                    }
+                    Err(_) => Ok(unauthorized(req)),
+                }
+            }))
         } else {
             // User is not authenticated; return an Unauthorized response
-            let (request, _payload) = req.into_parts();
-            let response = HttpResponse::Unauthorized().finish();
-            let service_response = ServiceResponse::new(request, response);
-            Either::Left(ok(service_response))
+            Either::Left(ok(unauthorized(req)))
         }
     }
 }
@@ -0,0 +1,134 @@
+use crate::{
+    constants::CSRF_HEADER,
+    services::csrf_service::{is_allowed_request_origin, is_valid_csrf_token},
+    utils::request_util::bearer_token,
+};
+use actix_service::{Service, Transform};
+use actix_session::SessionExt;
+use actix_web::{
+    body::{BoxBody, EitherBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        header::{AsHeaderName, ORIGIN, REFERER},
+        Method,
+    },
+    Error, HttpResponse,
+};
+use futures::future::{ok, Either, Ready};
+use futures_util::future::LocalBoxFuture;
+use std::rc::Rc;
+
+/// Paths that never act on the session cookie, so they need no CSRF token.
+const CSRF_EXEMPT_PATHS: &[&str] = &["/auth/token"];
+
+/// Requires the session's CSRF token in the `X-CSRF-Token` header on state-changing requests
+/// that are authenticated by the session cookie.
+///
+/// Requests without any session state, such as login and registration, have no token to check;
+/// their `Origin` or `Referer` must be one of `allowed_origins` instead. Requests carrying a
+/// bearer token are let through, since a foreign site cannot make the browser attach one.
+/// Must be wrapped inside the session middleware.
+pub struct CsrfMiddlewareFactory {
+    pub allowed_origins: Rc<Vec<String>>,
+}
+
+impl CsrfMiddlewareFactory {
+    pub fn new(allowed_origins: Vec<String>) -> Self {
+        Self {
+            allowed_origins: Rc::new(allowed_origins),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CsrfMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B, BoxBody>>;
+    type Error = Error;
+    type Transform = CsrfMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CsrfMiddleware {
+            service,
+            allowed_origins: self.allowed_origins.clone(),
+        })
+    }
+}
+
+pub struct CsrfMiddleware<S> {
+    service: S,
+    allowed_origins: Rc<Vec<String>>,
+}
+
+/// What a request has to show before it is handled.
+enum CsrfCheck {
+    None,
+    /// The session's token, for requests authenticated by the session cookie.
+    Token,
+    /// An allowed `Origin` or `Referer`, for requests without session state.
+    Origin,
+}
+
+fn csrf_check(req: &ServiceRequest) -> CsrfCheck {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE) {
+        return CsrfCheck::None;
+    }
+    if CSRF_EXEMPT_PATHS.contains(&req.path()) || bearer_token(req.request()).is_some() {
+        return CsrfCheck::None;
+    }
+    if req.get_session().entries().is_empty() {
+        CsrfCheck::Origin
+    } else {
+        CsrfCheck::Token
+    }
+}
+
+fn header_value(req: &ServiceRequest, name: impl AsHeaderName) -> Option<&str> {
+    req.headers().get(name).and_then(|value| value.to_str().ok())
+}
+
+impl<S, B> Service<ServiceRequest> for CsrfMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B, BoxBody>>;
+    type Error = Error;
+    type Future = Either<
+        Ready<Result<Self::Response, Self::Error>>,
+        LocalBoxFuture<'static, Result<Self::Response, Self::Error>>,
+    >;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let rejection = match csrf_check(&req) {
+            CsrfCheck::None => None,
+            CsrfCheck::Token => {
+                let provided = header_value(&req, CSRF_HEADER).unwrap_or_default();
+                (!is_valid_csrf_token(&req.get_session(), provided)).then_some("Invalid CSRF token")
+            }
+            CsrfCheck::Origin => {
+                let origin = header_value(&req, ORIGIN);
+                let referer = header_value(&req, REFERER);
+                (!is_allowed_request_origin(&self.allowed_origins, origin, referer)).then_some("Origin not allowed")
+            }
+        };
+        if let Some(message) = rejection {
+            let (request, _payload) = req.into_parts();
+            let response = HttpResponse::Forbidden().json(message);
+            return Either::Left(ok(ServiceResponse::new(request, response).map_into_right_body()));
+        }
+        let response = self.service.call(req);
+        Either::Right(Box::pin(async move { Ok(response.await?.map_into_left_body()) }))
+    }
+}
@@ -1 +1,4 @@
 pub mod auth_middleware;
+pub mod csrf_middleware;
+pub mod role_middleware;
+pub mod session_key_rotation;
@@ -0,0 +1,90 @@
+use crate::{
+    models::user_model::UserRole,
+    services::user_service::get_user_by_id,
+    states::app_state::AppState,
+    types::auth_types::AuthenticatedUser,
+};
+use actix_service::{Service, Transform};
+use actix_web::{
+    body::BoxBody,
+    dev::{ServiceRequest, ServiceResponse},
+    web, Error, HttpMessage, HttpResponse,
+};
+use futures::future::{ok, Ready};
+use futures_util::future::LocalBoxFuture;
+use std::rc::Rc;
+
+/// Requires the caller to hold at least `role`. Must be wrapped inside the auth middleware.
+pub struct RoleMiddlewareFactory {
+    pub role: UserRole,
+}
+
+impl RoleMiddlewareFactory {
+    pub fn new(role: UserRole) -> Self {
+        Self { role }
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for RoleMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = RoleMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RoleMiddleware {
+            service: Rc::new(service),
+            role: self.role,
+        })
+    }
+}
+
+pub struct RoleMiddleware<S> {
+    service: Rc<S>,
+    role: UserRole,
+}
+
+fn forbidden(req: ServiceRequest) -> ServiceResponse<BoxBody> {
+    let (request, _payload) = req.into_parts();
+    let response = HttpResponse::Forbidden().json("Insufficient role");
+    ServiceResponse::new(request, response)
+}
+
+impl<S> Service<ServiceRequest> for RoleMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let required_role = self.role;
+        let state = req.app_data::<web::Data<AppState>>().cloned();
+        let user_id = req.extensions().get::<AuthenticatedUser>().map(|user| user.user_id);
+
+        Box::pin(async move {
+            let (Some(state), Some(user_id)) = (state, user_id) else {
+                return Ok(forbidden(req));
+            };
+            // The role is read on every request so a demotion takes effect immediately
+            match get_user_by_id(&state, user_id).await {
+                Ok(user) if user.role >= required_role && !user.suspended => service.call(req).await,
+                Ok(_) => Ok(forbidden(req)),
+                Err(e) => Err(e),
+            }
+        })
+    }
+}
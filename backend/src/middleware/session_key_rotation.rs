@@ -0,0 +1,124 @@
+use actix_service::{Service, Transform};
+use actix_web::{
+    cookie::{Cookie, CookieJar, Key},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderValue, COOKIE},
+    Error,
+};
+use futures::future::{ok, Ready};
+use std::rc::Rc;
+
+/// Accepts session cookies encrypted with a previous key while the session key is being rotated.
+///
+/// A cookie that only the previous key can read is re-encrypted with the current key before the
+/// session middleware sees it, so the session survives and the client gets a cookie under the
+/// new key with the response. Must be wrapped outside the session middleware.
+#[derive(Clone)]
+pub struct SessionKeyRotationFactory {
+    pub cookie_name: String,
+    pub current_key: Key,
+    pub previous_keys: Vec<Key>,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for SessionKeyRotationFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = SessionKeyRotation<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(SessionKeyRotation {
+            service,
+            config: Rc::new(self.clone()),
+        })
+    }
+}
+
+pub struct SessionKeyRotation<S> {
+    service: S,
+    config: Rc<SessionKeyRotationFactory>,
+}
+
+impl SessionKeyRotationFactory {
+    /// The cookie header with the session cookie re-encrypted under the current key,
+    /// or `None` if it does not need rewriting.
+    fn rotated_cookie_header(&self, header: &str) -> Option<String> {
+        let mut rotated = false;
+        let cookies = header
+            .split(';')
+            .map(|pair| pair.trim())
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match Cookie::parse_encoded(pair) {
+                // Cookie values arrive percent-encoded, the way the session middleware sent them.
+                Ok(cookie) if cookie.name() == self.cookie_name => match self.reencrypt(cookie.value()) {
+                    Some(value) => {
+                        rotated = true;
+                        Cookie::new(self.cookie_name.clone(), value).encoded().to_string()
+                    }
+                    None => pair.to_owned(),
+                },
+                _ => pair.to_owned(),
+            })
+            .collect::<Vec<_>>();
+        rotated.then(|| cookies.join("; "))
+    }
+
+    fn reencrypt(&self, value: &str) -> Option<String> {
+        let mut jar = CookieJar::new();
+        jar.add_original(Cookie::new(self.cookie_name.clone(), value.to_owned()));
+        if jar.private(&self.current_key).get(&self.cookie_name).is_some() {
+            return None;
+        }
+        let plain = self
+            .previous_keys
+            .iter()
+            .find_map(|key| jar.private(key).get(&self.cookie_name))?;
+
+        let mut rotated_jar = CookieJar::new();
+        rotated_jar
+            .private_mut(&self.current_key)
+            .add(Cookie::new(self.cookie_name.clone(), plain.value().to_owned()));
+        rotated_jar
+            .get(&self.cookie_name)
+            .map(|cookie| cookie.value().to_owned())
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for SessionKeyRotation<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = S::Future;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        if !self.config.previous_keys.is_empty() {
+            let cookie_header = req
+                .headers()
+                .get_all(COOKIE)
+                .filter_map(|header| header.to_str().ok())
+                .collect::<Vec<_>>()
+                .join("; ");
+            if let Some(header) = self.config.rotated_cookie_header(&cookie_header) {
+                if let Ok(header) = HeaderValue::from_str(&header) {
+                    req.headers_mut().insert(COOKIE, header);
+                }
+            }
+        }
+        self.service.call(req)
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{
+    config::database::Storage,
+    migrations::mongo_migrations::{mongo_migrations, Migration, MigrationStep},
+    models::schema_migration_model::SchemaMigration,
+    repositories::repository::RepositoryError,
+};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    Database,
+};
+use sqlx::{migrate::Migrator, PgPool};
+use std::{collections::HashSet, error::Error};
+
+/// The SQL scripts in `migrations/`, embedded at build time.
+static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Applied and pending migrations of the storage backend, oldest first.
+pub struct MigrationStatus {
+    pub applied: Vec<SchemaMigration>,
+    /// Version and name of each migration still to apply.
+    pub pending: Vec<(i64, String)>,
+}
+
+/// The MongoDB migrations recorded as applied, oldest first.
+pub async fn applied_mongo_migrations(db: &Database) -> Result<Vec<SchemaMigration>, Box<dyn Error>> {
+    let mut applied: Vec<SchemaMigration> = db
+        .collection::<SchemaMigration>(SchemaMigration::collection_name())
+        .find(None, None)
+        .await?
+        .try_collect()
+        .await?;
+    applied.sort_by_key(|migration| migration.version);
+    Ok(applied)
+}
+
+/// The MongoDB migrations that have not been applied yet, oldest first.
+pub async fn pending_mongo_migrations(db: &Database) -> Result<Vec<Migration>, Box<dyn Error>> {
+    let applied: HashSet<i64> = applied_mongo_migrations(db)
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+    Ok(mongo_migrations()
+        .into_iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .collect())
+}
+
+async fn apply_step(db: &Database, step: &MigrationStep) -> Result<(), mongodb::error::Error> {
+    match step {
+        MigrationStep::CreateIndexes { collection, indexes } => {
+            db.collection::<Document>(collection)
+                .create_indexes(indexes.clone(), None)
+                .await?;
+        }
+        MigrationStep::ConvertToDate { collection, field } => {
+            db.collection::<Document>(collection)
+                .update_many(
+                    doc! { *field: { "$type": "string" } },
+                    vec![doc! { "$set": { *field: { "$toDate": format!("${}", field) } } }],
+                    None,
+                )
+                .await?;
+        }
+        MigrationStep::Aggregate { collection, pipeline } => {
+            db.collection::<Document>(collection)
+                .aggregate(pipeline.clone(), None)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Apply the pending MongoDB migrations in order and return the ones applied.
+/// A failed migration stops the run and is retried from its first step next time.
+pub async fn run_mongo_migrations(db: &Database) -> Result<Vec<SchemaMigration>, Box<dyn Error>> {
+    let records = db.collection::<SchemaMigration>(SchemaMigration::collection_name());
+    let mut applied = Vec::new();
+    for migration in pending_mongo_migrations(db).await? {
+        for step in &migration.steps {
+            apply_step(db, step).await.map_err(|e| {
+                format!("Migration {} ({}) failed: {}", migration.version, migration.name, e)
+            })?;
+        }
+
+        let record = SchemaMigration::new(migration.version, migration.name);
+        match records.insert_one(&record, None).await.map_err(RepositoryError::from) {
+            // Another instance finished the same migration first; its steps are safe to repeat.
+            Ok(_) | Err(RepositoryError::Duplicate) => {}
+            Err(e) => return Err(e.into()),
+        }
+        log::info!("Applied migration {} ({})", migration.version, migration.name);
+        applied.push(record);
+    }
+    Ok(applied)
+}
+
+/// The PostgreSQL migrations recorded as applied by sqlx, oldest first.
+pub async fn applied_postgres_migrations(pool: &PgPool) -> Result<Vec<SchemaMigration>, Box<dyn Error>> {
+    // sqlx creates its bookkeeping table with the first migration it runs.
+    let has_table: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+    if !has_table {
+        return Ok(Vec::new());
+    }
+    let rows: Vec<(i64, String, chrono::DateTime<chrono::Utc>)> = sqlx::query_as(
+        "SELECT version, description, installed_on FROM _sqlx_migrations WHERE success ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(version, name, applied_at)| SchemaMigration { version, name, applied_at })
+        .collect())
+}
+
+/// Bring the PostgreSQL schema up to date with `migrations/`.
+pub async fn run_postgres_migrations(pool: &PgPool) -> Result<(), Box<dyn Error>> {
+    POSTGRES_MIGRATOR.run(pool).await?;
+    Ok(())
+}
+
+/// The applied and pending migrations of the storage backend.
+pub async fn migration_status(storage: &Storage) -> Result<MigrationStatus, Box<dyn Error>> {
+    match storage {
+        Storage::Mongo { db, .. } => Ok(MigrationStatus {
+            applied: applied_mongo_migrations(db).await?,
+            pending: pending_mongo_migrations(db)
+                .await?
+                .into_iter()
+                .map(|migration| (migration.version, migration.name.to_owned()))
+                .collect(),
+        }),
+        Storage::Postgres(pool) => {
+            let applied = applied_postgres_migrations(pool).await?;
+            let applied_versions: HashSet<i64> = applied.iter().map(|migration| migration.version).collect();
+            let pending = POSTGRES_MIGRATOR
+                .iter()
+                .filter(|migration| !applied_versions.contains(&migration.version))
+                .map(|migration| (migration.version, migration.description.to_string()))
+                .collect();
+            Ok(MigrationStatus { applied, pending })
+        }
+    }
+}
+
+/// Apply the pending migrations of the storage backend and return the ones applied.
+pub async fn run_migrations(storage: &Storage) -> Result<Vec<SchemaMigration>, Box<dyn Error>> {
+    match storage {
+        Storage::Mongo { db, .. } => run_mongo_migrations(db).await,
+        Storage::Postgres(pool) => {
+            let pending = migration_status(storage).await?.pending;
+            run_postgres_migrations(pool).await?;
+            Ok(pending
+                .into_iter()
+                .map(|(version, name)| SchemaMigration::new(version, &name))
+                .collect())
+        }
+    }
+}
@@ -0,0 +1,2 @@
+pub mod migration_runner;
+pub mod mongo_migrations;
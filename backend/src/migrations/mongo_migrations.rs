@@ -0,0 +1,387 @@
+use crate::{
+    constants::OUTBOX_RETENTION_DAYS,
+    models::{
+        api_token_model::ApiToken, audit_event_model::AuditEvent, chat_model::Chat,
+        digest_preference_model::DigestPreference, login_throttle_model::LoginThrottle, message_model::Message,
+        moderation_action_model::ModerationAction, notification_model::Notification,
+        notification_preference_model::NotificationPreference, outbox_model::OutboxEmail, report_model::Report,
+        saved_message_model::SavedMessage, session_model::StoredSession, user_model::User,
+    },
+};
+use mongodb::{
+    bson::{doc, Document},
+    options::IndexOptions,
+    IndexModel,
+};
+use std::time::Duration;
+
+/// One change to a collection. Every step can safely run again.
+pub enum MigrationStep {
+    /// Creating an index that already exists with the same keys and options does nothing.
+    CreateIndexes {
+        collection: &'static str,
+        indexes: Vec<IndexModel>,
+    },
+    /// Rewrite timestamps stored as strings in `field` as BSON dates, which TTL indexes require.
+    ConvertToDate {
+        collection: &'static str,
+        field: &'static str,
+    },
+    /// Run an aggregation for its `$merge` stage, to backfill fields from other documents.
+    Aggregate {
+        collection: &'static str,
+        pipeline: Vec<Document>,
+    },
+}
+
+/// A versioned group of steps. Versions are applied in ascending order and never change once released.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub steps: Vec<MigrationStep>,
+}
+
+fn index(name: &str, keys: Document) -> IndexModel {
+    IndexModel::builder()
+        .keys(keys)
+        .options(IndexOptions::builder().name(name.to_owned()).build())
+        .build()
+}
+
+fn unique_index(name: &str, keys: Document) -> IndexModel {
+    IndexModel::builder()
+        .keys(keys)
+        .options(IndexOptions::builder().name(name.to_owned()).unique(true).build())
+        .build()
+}
+
+/// Removes each document once the date in `field` has passed. Documents without the field are kept.
+fn ttl_index(name: &str, field: &str) -> IndexModel {
+    IndexModel::builder()
+        .keys(doc! { field: 1 })
+        .options(
+            IndexOptions::builder()
+                .name(name.to_owned())
+                .expire_after(Duration::ZERO)
+                .build(),
+        )
+        .build()
+}
+
+/// Every MongoDB migration, oldest first.
+pub fn mongo_migrations() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: 1,
+            name: "create_lookup_indexes",
+            steps: vec![
+                MigrationStep::CreateIndexes {
+                    collection: User::collection_name(),
+                    indexes: vec![
+                        index("created_at", doc! { "created_at": -1 }),
+                        IndexModel::builder()
+                            .keys(doc! { "reset_token_hash": 1 })
+                            .options(
+                                IndexOptions::builder()
+                                    .name("reset_token_hash".to_owned())
+                                    .sparse(true)
+                                    .build(),
+                            )
+                            .build(),
+                    ],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: Chat::collection_name(),
+                    indexes: vec![index("participant_ids", doc! { "participant_ids": 1 })],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: Message::collection_name(),
+                    indexes: vec![index("chat_id_created_at", doc! { "chat_id": 1, "created_at": 1 })],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: Notification::collection_name(),
+                    indexes: vec![index("recipient_id_created_at", doc! { "recipient_id": 1, "created_at": -1 })],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: StoredSession::collection_name(),
+                    indexes: vec![index("user_id_last_seen_at", doc! { "user_id": 1, "last_seen_at": -1 })],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: ApiToken::collection_name(),
+                    indexes: vec![index("user_id_created_at", doc! { "user_id": 1, "created_at": -1 })],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: OutboxEmail::collection_name(),
+                    indexes: vec![index("status_next_attempt_at", doc! { "status": 1, "next_attempt_at": 1 })],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: AuditEvent::collection_name(),
+                    indexes: vec![
+                        index("created_at", doc! { "created_at": -1 }),
+                        index("actor_id_created_at", doc! { "actor_id": 1, "created_at": -1 }),
+                        index("target_id_created_at", doc! { "target_id": 1, "created_at": -1 }),
+                    ],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: Report::collection_name(),
+                    indexes: vec![
+                        index("status_created_at", doc! { "status": 1, "created_at": 1 }),
+                        index("reporter_id_reported_user_id", doc! { "reporter_id": 1, "reported_user_id": 1 }),
+                    ],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: ModerationAction::collection_name(),
+                    indexes: vec![index("created_at", doc! { "created_at": -1 })],
+                },
+            ],
+        },
+        Migration {
+            version: 2,
+            name: "create_unique_indexes",
+            steps: vec![
+                MigrationStep::CreateIndexes {
+                    collection: User::collection_name(),
+                    indexes: vec![
+                        unique_index("username", doc! { "username": 1 }),
+                        unique_index("email", doc! { "email": 1 }),
+                        // Only accounts with a linked identity are indexed, so the rest do not collide on null.
+                        IndexModel::builder()
+                            .keys(doc! { "oidc_identities.issuer": 1, "oidc_identities.subject": 1 })
+                            .options(
+                                IndexOptions::builder()
+                                    .name("oidc_identity".to_owned())
+                                    .unique(true)
+                                    .partial_filter_expression(doc! { "oidc_identities.issuer": { "$exists": true } })
+                                    .build(),
+                            )
+                            .build(),
+                    ],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: StoredSession::collection_name(),
+                    indexes: vec![unique_index("key_hash", doc! { "key_hash": 1 })],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: ApiToken::collection_name(),
+                    indexes: vec![unique_index("token_hash", doc! { "token_hash": 1 })],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: LoginThrottle::collection_name(),
+                    indexes: vec![unique_index("key", doc! { "key": 1 })],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: NotificationPreference::collection_name(),
+                    indexes: vec![unique_index(
+                        "user_id_notification_type",
+                        doc! { "user_id": 1, "notification_type": 1 },
+                    )],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: DigestPreference::collection_name(),
+                    indexes: vec![unique_index("user_id", doc! { "user_id": 1 })],
+                },
+            ],
+        },
+        Migration {
+            version: 3,
+            name: "expire_stale_records",
+            steps: vec![
+                MigrationStep::ConvertToDate {
+                    collection: StoredSession::collection_name(),
+                    field: "expires_at",
+                },
+                MigrationStep::ConvertToDate {
+                    collection: ApiToken::collection_name(),
+                    field: "expires_at",
+                },
+                MigrationStep::CreateIndexes {
+                    collection: StoredSession::collection_name(),
+                    indexes: vec![ttl_index("expires_at", "expires_at")],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: ApiToken::collection_name(),
+                    indexes: vec![ttl_index("expires_at", "expires_at")],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: LoginThrottle::collection_name(),
+                    indexes: vec![ttl_index("expires_at", "expires_at")],
+                },
+            ],
+        },
+        Migration {
+            version: 4,
+            name: "sequence_messages",
+            steps: vec![
+                // Number the messages stored before sequence numbers, per chat in the order they were stored.
+                MigrationStep::Aggregate {
+                    collection: Message::collection_name(),
+                    pipeline: vec![
+                        doc! { "$match": { "seq": { "$exists": false } } },
+                        doc! {
+                            "$setWindowFields": {
+                                "partitionBy": "$chat_id",
+                                "sortBy": { "created_at": 1, "_id": 1 },
+                                "output": { "seq": { "$documentNumber": {} } }
+                            }
+                        },
+                        doc! { "$project": { "seq": { "$toLong": "$seq" } } },
+                        doc! {
+                            "$merge": {
+                                "into": Message::collection_name(),
+                                "on": "_id",
+                                "whenMatched": "merge",
+                                "whenNotMatched": "discard"
+                            }
+                        },
+                    ],
+                },
+                MigrationStep::Aggregate {
+                    collection: Message::collection_name(),
+                    pipeline: vec![
+                        doc! { "$group": { "_id": "$chat_id", "last_seq": { "$max": "$seq" } } },
+                        doc! {
+                            "$merge": {
+                                "into": Chat::collection_name(),
+                                "on": "_id",
+                                "whenMatched": "merge",
+                                "whenNotMatched": "discard"
+                            }
+                        },
+                    ],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: Message::collection_name(),
+                    indexes: vec![unique_index("chat_id_seq", doc! { "chat_id": 1, "seq": 1 })],
+                },
+            ],
+        },
+        Migration {
+            version: 5,
+            name: "deduplicate_client_messages",
+            steps: vec![MigrationStep::CreateIndexes {
+                collection: Message::collection_name(),
+                // Messages sent without a client id are left out, like linked OIDC identities above.
+                indexes: vec![IndexModel::builder()
+                    .keys(doc! { "sender_id": 1, "client_msg_id": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .name("sender_id_client_msg_id".to_owned())
+                            .unique(true)
+                            .partial_filter_expression(doc! { "client_msg_id": { "$exists": true } })
+                            .build(),
+                    )
+                    .build()],
+            }],
+        },
+        Migration {
+            version: 6,
+            name: "denormalize_chat_activity",
+            steps: vec![
+                MigrationStep::Aggregate {
+                    collection: Chat::collection_name(),
+                    pipeline: vec![
+                        doc! { "$match": { "last_activity_at": { "$exists": false } } },
+                        doc! { "$project": { "last_activity_at": { "$toDate": "$created_at" } } },
+                        doc! {
+                            "$merge": {
+                                "into": Chat::collection_name(),
+                                "on": "_id",
+                                "whenMatched": "merge",
+                                "whenNotMatched": "discard"
+                            }
+                        },
+                    ],
+                },
+                // Copy each chat's newest message onto it, and move its activity up to that message.
+                MigrationStep::Aggregate {
+                    collection: Message::collection_name(),
+                    pipeline: vec![
+                        doc! { "$sort": { "chat_id": 1, "seq": -1 } },
+                        doc! { "$group": { "_id": "$chat_id", "last": { "$first": "$$ROOT" } } },
+                        doc! {
+                            "$project": {
+                                "last_message": {
+                                    "message_id": "$last._id",
+                                    "sender_id": "$last.sender_id",
+                                    "seq": "$last.seq",
+                                    "content": "$last.content",
+                                    "created_at": "$last.created_at"
+                                },
+                                "last_activity_at": { "$toDate": "$last.created_at" }
+                            }
+                        },
+                        doc! {
+                            "$merge": {
+                                "into": Chat::collection_name(),
+                                "on": "_id",
+                                "whenMatched": "merge",
+                                "whenNotMatched": "discard"
+                            }
+                        },
+                    ],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: Chat::collection_name(),
+                    indexes: vec![index(
+                        "participant_ids_last_activity_at",
+                        doc! { "participant_ids": 1, "last_activity_at": -1 },
+                    )],
+                },
+            ],
+        },
+        Migration {
+            version: 7,
+            name: "create_saved_message_indexes",
+            steps: vec![MigrationStep::CreateIndexes {
+                collection: SavedMessage::collection_name(),
+                indexes: vec![
+                    unique_index("user_id_message_id", doc! { "user_id": 1, "message_id": 1 }),
+                    index("user_id_created_at", doc! { "user_id": 1, "created_at": -1 }),
+                    index("message_id", doc! { "message_id": 1 }),
+                    index("chat_id", doc! { "chat_id": 1 }),
+                ],
+            }],
+        },
+        Migration {
+            version: 8,
+            name: "expire_mail_outbox",
+            steps: vec![
+                // Emails sent or given up on before bodies were blanked still hold their links.
+                MigrationStep::Aggregate {
+                    collection: OutboxEmail::collection_name(),
+                    pipeline: vec![
+                        doc! {
+                            "$match": {
+                                "status": { "$in": ["sent", "failed"] },
+                                "expires_at": { "$exists": false }
+                            }
+                        },
+                        doc! {
+                            "$project": {
+                                "email": {
+                                    "to": "$email.to",
+                                    "subject": "$email.subject",
+                                    "text_body": { "$literal": "" },
+                                    "html_body": { "$literal": "" }
+                                },
+                                "expires_at": { "$add": ["$$NOW", OUTBOX_RETENTION_DAYS * 24 * 60 * 60 * 1000] }
+                            }
+                        },
+                        doc! {
+                            "$merge": {
+                                "into": OutboxEmail::collection_name(),
+                                "on": "_id",
+                                "whenMatched": "merge",
+                                "whenNotMatched": "discard"
+                            }
+                        },
+                    ],
+                },
+                MigrationStep::CreateIndexes {
+                    collection: OutboxEmail::collection_name(),
+                    indexes: vec![ttl_index("expires_at", "expires_at")],
+                },
+            ],
+        },
+    ]
+}
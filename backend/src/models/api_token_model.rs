@@ -0,0 +1,109 @@
+use crate::types::auth_types::TokenScope;
+use chrono::prelude::*;
+use mongodb::bson::{
+    doc, oid::ObjectId, serde_helpers::chrono_datetime_as_bson_datetime_optional, to_bson,
+    DateTime as BsonDateTime, Document,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenKind {
+    /// Long-lived token created by the user, e.g. for scripts.
+    Personal,
+    /// Short-lived token issued by the token endpoint.
+    Access,
+    /// Single-use token exchanged for a new access/refresh pair.
+    Refresh,
+}
+
+impl ApiTokenKind {
+    /// The prefix of the raw token, which makes leaked tokens easy to recognise.
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Self::Personal => "cph_pat_",
+            Self::Access => "cph_at_",
+            Self::Refresh => "cph_rt_",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub kind: ApiTokenKind,
+    /// SHA-256 of the token; the token itself is only shown once.
+    pub token_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub scopes: Vec<TokenScope>,
+    /// For refresh tokens, the access token issued alongside it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_token_id: Option<ObjectId>,
+    /// A BSON date so the TTL index on it can remove expired tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "chrono_datetime_as_bson_datetime_optional")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiToken {
+    pub fn new(
+        user_id: ObjectId,
+        kind: ApiTokenKind,
+        token_hash: &str,
+        scopes: Vec<TokenScope>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id: None,
+            user_id,
+            kind,
+            token_hash: token_hash.to_owned(),
+            name: None,
+            scopes,
+            access_token_id: None,
+            expires_at,
+            last_used_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn collection_name() -> &'static str {
+        "api_tokens"
+    }
+
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    pub fn to_document(&self) -> Document {
+        let mut doc = doc! {
+            "user_id": &self.user_id,
+            "kind": to_bson(&self.kind).unwrap_or_default(),
+            "token_hash": &self.token_hash,
+            "scopes": to_bson(&self.scopes).unwrap_or_default(),
+            "created_at": BsonDateTime::from_millis(self.created_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
+        };
+        if let Some(ref id) = self.id {
+            doc.insert("_id", id);
+        }
+        if let Some(ref name) = self.name {
+            doc.insert("name", name);
+        }
+        if let Some(ref access_token_id) = self.access_token_id {
+            doc.insert("access_token_id", access_token_id);
+        }
+        if let Some(ref expires_at) = self.expires_at {
+            doc.insert("expires_at", BsonDateTime::from_millis(expires_at.timestamp_millis()));
+        }
+        if let Some(ref last_used_at) = self.last_used_at {
+            doc.insert("last_used_at", BsonDateTime::from_millis(last_used_at.timestamp_millis()));
+        }
+
+        doc
+    }
+}
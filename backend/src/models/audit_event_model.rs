@@ -0,0 +1,79 @@
+use crate::{
+    types::audit_types::{AuditAction, AuditOutcome},
+    utils::request_util::RequestContext,
+};
+use chrono::prelude::*;
+use mongodb::bson::{doc, oid::ObjectId, to_bson, DateTime as BsonDateTime, Document};
+use serde::{Deserialize, Serialize};
+
+/// One entry in the security audit log. Entries are only ever added, never changed or removed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// The signed-in user who acted, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_id: Option<ObjectId>,
+    /// The username given on failed logins, where there may be no matching account.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_name: Option<String>,
+    pub action: AuditAction,
+    /// The user or chat acted on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<ObjectId>,
+    pub ip: String,
+    pub user_agent: String,
+    pub outcome: AuditOutcome,
+    /// Why the action failed, or what changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction, outcome: AuditOutcome, context: &RequestContext) -> Self {
+        Self {
+            id: None,
+            actor_id: None,
+            actor_name: None,
+            action,
+            target_id: None,
+            ip: context.ip.clone(),
+            user_agent: context.user_agent.clone(),
+            outcome,
+            detail: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn collection_name() -> &'static str {
+        "audit_events"
+    }
+
+    pub fn to_document(&self) -> Document {
+        let mut doc = doc! {
+            "action": to_bson(&self.action).unwrap_or_default(),
+            "ip": &self.ip,
+            "user_agent": &self.user_agent,
+            "outcome": to_bson(&self.outcome).unwrap_or_default(),
+            "created_at": BsonDateTime::from_millis(self.created_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
+        };
+        if let Some(ref id) = self.id {
+            doc.insert("_id", id);
+        }
+        if let Some(ref actor_id) = self.actor_id {
+            doc.insert("actor_id", actor_id);
+        }
+        if let Some(ref actor_name) = self.actor_name {
+            doc.insert("actor_name", actor_name);
+        }
+        if let Some(ref target_id) = self.target_id {
+            doc.insert("target_id", target_id);
+        }
+        if let Some(ref detail) = self.detail {
+            doc.insert("detail", detail);
+        }
+
+        doc
+    }
+}
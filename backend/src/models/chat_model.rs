@@ -1,13 +1,72 @@
 use chrono::prelude::*;
-use mongodb::bson::{doc, oid::ObjectId, DateTime as BsonDateTime, Document};
+use crate::models::message_model::Message;
+use mongodb::bson::{
+    doc, oid::ObjectId, serde_helpers::chrono_datetime_as_bson_datetime, DateTime as BsonDateTime, Document,
+};
 use serde::{Deserialize, Serialize};
 
+/// A participant's "delete for me": the chat stays out of their list until a message after `seq` arrives.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChatHide {
+    pub user_id: ObjectId,
+    pub hidden_at: DateTime<Utc>,
+    /// The chat's `last_seq` when it was hidden; messages up to it stay hidden from the user.
+    #[serde(default)]
+    pub seq: i64,
+}
+
+/// A message pinned to the top of the chat, for every participant.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChatPin {
+    pub message_id: ObjectId,
+    pub pinned_by: ObjectId,
+    pub pinned_at: DateTime<Utc>,
+}
+
+/// A copy of the chat's newest message, so the chat list needs no query per chat.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LastMessage {
+    pub message_id: ObjectId,
+    pub sender_id: ObjectId,
+    pub seq: i64,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl LastMessage {
+    pub fn new(message_id: ObjectId, message: &Message) -> Self {
+        Self {
+            message_id,
+            sender_id: message.sender_id,
+            seq: message.seq,
+            content: message.content.clone(),
+            created_at: message.created_at,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Chat {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
     pub participant_ids: Vec<ObjectId>,
-    pub created_at: DateTime<Utc>
+    pub created_at: DateTime<Utc>,
+    /// The sequence number of the newest message, incremented atomically for each message sent.
+    #[serde(default)]
+    pub last_seq: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_message: Option<LastMessage>,
+    /// When the chat was created or last received a message. The chat list is sorted by it.
+    #[serde(with = "chrono_datetime_as_bson_datetime")]
+    pub last_activity_at: DateTime<Utc>,
+    /// Oldest first, at most `MAX_PINNED_MESSAGES`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pins: Vec<ChatPin>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden_by: Vec<ChatHide>,
+    /// Set once every participant has deleted the chat; the purge job removes it for good later.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Chat {
@@ -16,10 +75,17 @@ impl Chat {
         participant_ids: Vec<ObjectId>,
         created_at: Option<DateTime<Utc>>
     ) -> Self {
+        let created_at = created_at.unwrap_or_else(Utc::now);
         Self {
             id,
             participant_ids,
-            created_at: created_at.unwrap_or_else(Utc::now)
+            created_at,
+            last_seq: 0,
+            last_message: None,
+            last_activity_at: created_at,
+            pins: Vec::new(),
+            hidden_by: Vec::new(),
+            deleted_at: None,
         }
     }
 
@@ -27,11 +93,37 @@ impl Chat {
         "chats"
     }
 
+    /// When the user last hid the chat, if they did.
+    pub fn hidden_at(&self, user_id: ObjectId) -> Option<DateTime<Utc>> {
+        self.hide_by(user_id).map(|hide| hide.hidden_at)
+    }
+
+    /// The last sequence number the user hid, if they hid the chat.
+    pub fn hidden_seq(&self, user_id: ObjectId) -> Option<i64> {
+        self.hide_by(user_id).map(|hide| hide.seq)
+    }
+
+    /// Whether every participant hid the chat after its last message.
+    pub fn is_hidden_by_everyone(&self) -> bool {
+        self.participant_ids
+            .iter()
+            .all(|participant_id| self.hidden_seq(*participant_id).is_some_and(|seq| seq >= self.last_seq))
+    }
+
+    pub fn is_pinned(&self, message_id: ObjectId) -> bool {
+        self.pins.iter().any(|pin| pin.message_id == message_id)
+    }
+
+    fn hide_by(&self, user_id: ObjectId) -> Option<&ChatHide> {
+        self.hidden_by.iter().find(|hide| hide.user_id == user_id)
+    }
+
     pub fn to_document(&self) -> Document {
         let mut doc = doc! {
             "participant_ids": &self.participant_ids,
             "created_at": BsonDateTime::from_millis(
                 self.created_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
+            "last_activity_at": BsonDateTime::from_millis(self.last_activity_at.timestamp_millis()),
         };
         if let Some(ref id) = self.id {
             doc.insert("_id", id);
@@ -0,0 +1,89 @@
+use crate::types::notification_types::DigestFrequency;
+use chrono::prelude::*;
+use mongodb::bson::{doc, oid::ObjectId, DateTime as BsonDateTime, Document};
+use serde::{Deserialize, Serialize};
+
+/// A user's settings for the offline activity digest email.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DigestPreference {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub frequency: DigestFrequency,
+    /// Local hour (0-23) at which quiet hours begin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours_start: Option<u32>,
+    /// Local hour (0-23) at which quiet hours end.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours_end: Option<u32>,
+    /// Offset of the user's local time from UTC, used to evaluate quiet hours.
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sent_at: Option<DateTime<Utc>>,
+}
+
+impl DigestPreference {
+    /// The preference used when the user has not configured digests: one email a day.
+    pub fn default_for(user_id: ObjectId) -> Self {
+        Self {
+            id: None,
+            user_id,
+            frequency: DigestFrequency::Daily,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            utc_offset_minutes: 0,
+            last_sent_at: None,
+        }
+    }
+
+    /// Whether `now` falls inside the user's quiet hours.
+    pub fn is_quiet_at(&self, now: DateTime<Utc>) -> bool {
+        let (start, end) = match (self.quiet_hours_start, self.quiet_hours_end) {
+            (Some(start), Some(end)) if start != end => (start, end),
+            _ => return false,
+        };
+        let local_hour = (now + chrono::Duration::minutes(self.utc_offset_minutes as i64)).hour();
+        if start < end {
+            local_hour >= start && local_hour < end
+        } else {
+            // Quiet hours wrap around midnight, e.g. 22 -> 7.
+            local_hour >= start || local_hour < end
+        }
+    }
+
+    /// Whether a digest should go out at `now`: digests are enabled, the last one is at least an
+    /// interval old and the user is outside their quiet hours.
+    pub fn is_due_at(&self, now: DateTime<Utc>) -> bool {
+        let Some(interval) = self.frequency.interval() else {
+            return false;
+        };
+        self.last_sent_at.is_none_or(|last_sent_at| now - last_sent_at >= interval) && !self.is_quiet_at(now)
+    }
+
+    pub fn collection_name() -> &'static str {
+        "digest_preferences"
+    }
+
+    pub fn to_document(&self) -> Document {
+        let mut doc = doc! {
+            "user_id": &self.user_id,
+            "frequency": mongodb::bson::to_bson(&self.frequency).unwrap_or_default(),
+            "utc_offset_minutes": self.utc_offset_minutes,
+        };
+        if let Some(ref id) = self.id {
+            doc.insert("_id", id);
+        }
+        if let Some(quiet_hours_start) = self.quiet_hours_start {
+            doc.insert("quiet_hours_start", quiet_hours_start as i64);
+        }
+        if let Some(quiet_hours_end) = self.quiet_hours_end {
+            doc.insert("quiet_hours_end", quiet_hours_end as i64);
+        }
+        if let Some(ref last_sent_at) = self.last_sent_at {
+            doc.insert("last_sent_at", BsonDateTime::from_millis(last_sent_at.timestamp_millis()));
+        }
+
+        doc
+    }
+}
@@ -0,0 +1,47 @@
+use chrono::prelude::*;
+use mongodb::bson::{
+    doc, oid::ObjectId, serde_helpers::chrono_datetime_as_bson_datetime_optional, DateTime as BsonDateTime,
+    Document,
+};
+use serde::{Deserialize, Serialize};
+
+/// Failed-attempt bookkeeping for one throttled key, e.g. an account or a client IP.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoginThrottle {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// `<scope>:<subject>`, e.g. `login_account:alice` or `login_ip:203.0.113.7`.
+    pub key: String,
+    pub failures: i32,
+    pub last_failure_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_until: Option<DateTime<Utc>>,
+    /// When the TTL index removes the record, forgetting its failures.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "chrono_datetime_as_bson_datetime_optional")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl LoginThrottle {
+    pub fn collection_name() -> &'static str {
+        "login_throttles"
+    }
+
+    pub fn to_document(&self) -> Document {
+        let mut doc = doc! {
+            "key": &self.key,
+            "failures": self.failures,
+            "last_failure_at": BsonDateTime::from_millis(self.last_failure_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
+        };
+        if let Some(ref id) = self.id {
+            doc.insert("_id", id);
+        }
+        if let Some(ref locked_until) = self.locked_until {
+            doc.insert("locked_until", BsonDateTime::from_millis(locked_until.timestamp_millis()));
+        }
+        if let Some(ref expires_at) = self.expires_at {
+            doc.insert("expires_at", BsonDateTime::from_millis(expires_at.timestamp_millis()));
+        }
+
+        doc
+    }
+}
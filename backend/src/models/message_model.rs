@@ -8,21 +8,37 @@ pub struct Message {
     pub id: Option<ObjectId>,
     pub chat_id: ObjectId,
     pub sender_id: ObjectId,
+    /// Position in the chat, taken from `Chat::last_seq`. History is ordered and paged by it.
+    #[serde(default)]
+    pub seq: i64,
     pub content: String,
+    /// When the server stored the message.
     pub created_at: DateTime<Utc>,
+    /// The sender's clock when they sent it, shown to users but never used for ordering.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_sent_at: Option<DateTime<Utc>>,
+    /// The sender's own id for the message, unique per sender, so a retried send is stored once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_msg_id: Option<String>,
 }
 
 impl Message {
-    pub fn new(chat_id: ObjectId, sender_id: ObjectId, content: &str, created_at: Option<DateTime<Utc>>) -> Self {
+    pub fn new(
+        chat_id: ObjectId,
+        sender_id: ObjectId,
+        seq: i64,
+        content: &str,
+        client_sent_at: Option<DateTime<Utc>>,
+    ) -> Self {
         Self {
             id: None,
             chat_id,
             sender_id,
+            seq,
             content: content.to_owned(),
-            created_at: match created_at {
-                Some(dt) => dt,
-                None => Utc::now(),
-            }
+            created_at: Utc::now(),
+            client_sent_at,
+            client_msg_id: None,
         }
     }
 
@@ -34,6 +50,7 @@ impl Message {
         let mut doc = doc! {
             "chat_id": &self.chat_id,
             "sender_id": &self.sender_id,
+            "seq": self.seq,
             "content": &self.content,
             "created_at": BsonDateTime::from_millis(self.created_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
         };
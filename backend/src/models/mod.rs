@@ -1,4 +1,15 @@
+pub mod api_token_model;
+pub mod audit_event_model;
 pub mod chat_model;
+pub mod digest_preference_model;
+pub mod login_throttle_model;
 pub mod message_model;
+pub mod moderation_action_model;
 pub mod notification_model;
+pub mod notification_preference_model;
+pub mod outbox_model;
+pub mod report_model;
+pub mod saved_message_model;
+pub mod schema_migration_model;
+pub mod session_model;
 pub mod user_model;
@@ -0,0 +1,64 @@
+use crate::types::moderation_types::ModerationActionKind;
+use chrono::prelude::*;
+use mongodb::bson::{doc, oid::ObjectId, to_bson, DateTime as BsonDateTime, Document};
+use serde::{Deserialize, Serialize};
+
+/// One entry in the moderation trail. Entries are only ever added.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModerationAction {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub moderator_id: ObjectId,
+    pub action: ModerationActionKind,
+    pub target_user_id: ObjectId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report_id: Option<ObjectId>,
+    /// The moderator's explanation, also shown to warned users.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ModerationAction {
+    pub fn new(moderator_id: ObjectId, action: ModerationActionKind, target_user_id: ObjectId) -> Self {
+        Self {
+            id: None,
+            moderator_id,
+            action,
+            target_user_id,
+            message_id: None,
+            report_id: None,
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn collection_name() -> &'static str {
+        "moderation_actions"
+    }
+
+    pub fn to_document(&self) -> Document {
+        let mut doc = doc! {
+            "moderator_id": &self.moderator_id,
+            "action": to_bson(&self.action).unwrap_or_default(),
+            "target_user_id": &self.target_user_id,
+            "created_at": BsonDateTime::from_millis(self.created_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
+        };
+        if let Some(ref id) = self.id {
+            doc.insert("_id", id);
+        }
+        if let Some(ref message_id) = self.message_id {
+            doc.insert("message_id", message_id);
+        }
+        if let Some(ref report_id) = self.report_id {
+            doc.insert("report_id", report_id);
+        }
+        if let Some(ref note) = self.note {
+            doc.insert("note", note);
+        }
+
+        doc
+    }
+}
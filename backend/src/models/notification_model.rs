@@ -1,3 +1,4 @@
+use crate::types::notification_types::NotificationType;
 use chrono::prelude::*;
 use mongodb::bson::{doc, oid::ObjectId, DateTime as BsonDateTime, Document};
 use serde::{Deserialize, Serialize};
@@ -6,28 +7,34 @@ use serde::{Deserialize, Serialize};
 pub struct Notification {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
-    pub notification_type: String,
+    pub notification_type: NotificationType,
     pub recipient_id: ObjectId,
     pub sender_id: ObjectId,
     pub message: String,
     pub is_handled: bool,
+    #[serde(default)]
+    pub is_read: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
 impl Notification {
     pub fn new(
-        notification_type: &str,
+        notification_type: NotificationType,
         recipient_id: ObjectId,
         sender_id: ObjectId,
         message: &str,
     ) -> Self {
         Self {
             id: None,
-            notification_type: notification_type.to_owned(),
+            notification_type,
             recipient_id,
             sender_id,
             message: message.to_owned(),
             is_handled: false,
+            is_read: false,
+            read_at: None,
             created_at: Utc::now(),
         }
     }
@@ -38,16 +45,20 @@ impl Notification {
 
     pub fn to_document(&self) -> Document {
         let mut doc = doc! {
-            "notification_type": &self.notification_type,
+            "notification_type": self.notification_type.as_str(),
             "recipient_id": &self.recipient_id,
             "sender_id": &self.sender_id,
             "message": &self.message,
             "is_handled": self.is_handled,
+            "is_read": self.is_read,
             "created_at": BsonDateTime::from_millis(self.created_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
         };
         if let Some(ref id) = self.id {
             doc.insert("_id", id);
         }
+        if let Some(ref read_at) = self.read_at {
+            doc.insert("read_at", BsonDateTime::from_millis(read_at.timestamp_millis()));
+        }
 
         doc
     }
@@ -0,0 +1,64 @@
+use crate::types::notification_types::NotificationType;
+use chrono::prelude::*;
+use mongodb::bson::{doc, oid::ObjectId, DateTime as BsonDateTime, Document};
+use serde::{Deserialize, Serialize};
+
+/// How a user wants to receive one type of notification.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationPreference {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub notification_type: NotificationType,
+    pub in_app: bool,
+    pub email: bool,
+    pub muted: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl NotificationPreference {
+    /// The preference used when the user has not configured this type:
+    /// delivered in-app, no email, not muted.
+    pub fn default_for(user_id: ObjectId, notification_type: NotificationType) -> Self {
+        Self {
+            id: None,
+            user_id,
+            notification_type,
+            in_app: true,
+            email: false,
+            muted: false,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Whether a notification of this type is pushed to the recipient's live session.
+    /// Live signals such as calls always are; muting only keeps them out of the unread count.
+    pub fn pushes_live(&self) -> bool {
+        self.notification_type.is_live_signal() || (self.in_app && !self.muted)
+    }
+
+    /// Whether an email copy of a notification of this type is sent.
+    pub fn sends_email(&self) -> bool {
+        self.email && !self.muted
+    }
+
+    pub fn collection_name() -> &'static str {
+        "notification_preferences"
+    }
+
+    pub fn to_document(&self) -> Document {
+        let mut doc = doc! {
+            "user_id": &self.user_id,
+            "notification_type": self.notification_type.as_str(),
+            "in_app": self.in_app,
+            "email": self.email,
+            "muted": self.muted,
+            "updated_at": BsonDateTime::from_millis(self.updated_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
+        };
+        if let Some(ref id) = self.id {
+            doc.insert("_id", id);
+        }
+
+        doc
+    }
+}
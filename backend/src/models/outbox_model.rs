@@ -0,0 +1,84 @@
+use crate::mail::mailer::EmailMessage;
+use chrono::prelude::*;
+use mongodb::bson::{
+    doc, oid::ObjectId, serde_helpers::chrono_datetime_as_bson_datetime_optional, DateTime as BsonDateTime,
+    Document,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+/// An email waiting in, or delivered from, the persisted outbox.
+/// Bodies can hold live tokens, such as reset links, so they are blanked once the email is sent or given up on.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutboxEmail {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub email: EmailMessage,
+    pub status: OutboxStatus,
+    pub attempts: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// Set once the email is sent or has failed for good; the TTL index removes it then.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "chrono_datetime_as_bson_datetime_optional")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl OutboxEmail {
+    pub fn new(email: EmailMessage, next_attempt_at: DateTime<Utc>) -> Self {
+        Self {
+            id: None,
+            email,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            next_attempt_at,
+            sent_at: None,
+            created_at: Utc::now(),
+            expires_at: None,
+        }
+    }
+
+    pub fn collection_name() -> &'static str {
+        "mail_outbox"
+    }
+
+    pub fn to_document(&self) -> Document {
+        let mut doc = doc! {
+            "email": {
+                "to": &self.email.to,
+                "subject": &self.email.subject,
+                "text_body": &self.email.text_body,
+                "html_body": &self.email.html_body,
+            },
+            "status": mongodb::bson::to_bson(&self.status).unwrap_or_default(),
+            "attempts": self.attempts,
+            "next_attempt_at": BsonDateTime::from_millis(self.next_attempt_at.timestamp_millis()),
+            "created_at": BsonDateTime::from_millis(self.created_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
+        };
+        if let Some(ref id) = self.id {
+            doc.insert("_id", id);
+        }
+        if let Some(ref last_error) = self.last_error {
+            doc.insert("last_error", last_error);
+        }
+        if let Some(ref sent_at) = self.sent_at {
+            doc.insert("sent_at", BsonDateTime::from_millis(sent_at.timestamp_millis()));
+        }
+        if let Some(ref expires_at) = self.expires_at {
+            doc.insert("expires_at", BsonDateTime::from_millis(expires_at.timestamp_millis()));
+        }
+
+        doc
+    }
+}
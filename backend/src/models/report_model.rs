@@ -0,0 +1,86 @@
+use crate::types::moderation_types::{ReportReason, ReportStatus};
+use chrono::prelude::*;
+use mongodb::bson::{doc, oid::ObjectId, to_bson, DateTime as BsonDateTime, Document};
+use serde::{Deserialize, Serialize};
+
+/// A user's report of an abusive message or user, waiting in the moderation queue.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Report {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub reporter_id: ObjectId,
+    pub reported_user_id: ObjectId,
+    /// Set when a specific message was reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<ObjectId>,
+    /// Copy of the reported message, so the evidence survives its removal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_content: Option<String>,
+    pub reason: ReportReason,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    pub status: ReportStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handled_by: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Report {
+    pub fn new(reporter_id: ObjectId, reported_user_id: ObjectId, reason: ReportReason, details: Option<String>) -> Self {
+        Self {
+            id: None,
+            reporter_id,
+            reported_user_id,
+            message_id: None,
+            chat_id: None,
+            message_content: None,
+            reason,
+            details,
+            status: ReportStatus::Open,
+            handled_by: None,
+            handled_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn collection_name() -> &'static str {
+        "reports"
+    }
+
+    pub fn to_document(&self) -> Document {
+        let mut doc = doc! {
+            "reporter_id": &self.reporter_id,
+            "reported_user_id": &self.reported_user_id,
+            "reason": to_bson(&self.reason).unwrap_or_default(),
+            "status": to_bson(&self.status).unwrap_or_default(),
+            "created_at": BsonDateTime::from_millis(self.created_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
+        };
+        if let Some(ref id) = self.id {
+            doc.insert("_id", id);
+        }
+        if let Some(ref message_id) = self.message_id {
+            doc.insert("message_id", message_id);
+        }
+        if let Some(ref chat_id) = self.chat_id {
+            doc.insert("chat_id", chat_id);
+        }
+        if let Some(ref message_content) = self.message_content {
+            doc.insert("message_content", message_content);
+        }
+        if let Some(ref details) = self.details {
+            doc.insert("details", details);
+        }
+        if let Some(ref handled_by) = self.handled_by {
+            doc.insert("handled_by", handled_by);
+        }
+        if let Some(ref handled_at) = self.handled_at {
+            doc.insert("handled_at", BsonDateTime::from_millis(handled_at.timestamp_millis()));
+        }
+
+        doc
+    }
+}
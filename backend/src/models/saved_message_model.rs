@@ -0,0 +1,36 @@
+use chrono::prelude::*;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// A message a user bookmarked for themselves. Only the reference is kept, so the preview follows the message.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedMessage {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub message_id: ObjectId,
+    pub chat_id: ObjectId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SavedMessage {
+    pub fn new(user_id: ObjectId, message_id: ObjectId, chat_id: ObjectId, note: Option<String>, tags: Vec<String>) -> Self {
+        Self {
+            id: None,
+            user_id,
+            message_id,
+            chat_id,
+            note,
+            tags,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn collection_name() -> &'static str {
+        "saved_messages"
+    }
+}
@@ -0,0 +1,34 @@
+use chrono::prelude::*;
+use mongodb::bson::{doc, DateTime as BsonDateTime, Document};
+use serde::{Deserialize, Serialize};
+
+/// A MongoDB migration that has been applied. The version doubles as the id, so it is recorded once.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemaMigration {
+    #[serde(rename = "_id")]
+    pub version: i64,
+    pub name: String,
+    pub applied_at: DateTime<Utc>,
+}
+
+impl SchemaMigration {
+    pub fn new(version: i64, name: &str) -> Self {
+        Self {
+            version,
+            name: name.to_owned(),
+            applied_at: Utc::now(),
+        }
+    }
+
+    pub fn collection_name() -> &'static str {
+        "schema_migrations"
+    }
+
+    pub fn to_document(&self) -> Document {
+        doc! {
+            "_id": self.version,
+            "name": &self.name,
+            "applied_at": BsonDateTime::from_millis(self.applied_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use chrono::prelude::*;
+use mongodb::bson::{
+    doc, oid::ObjectId, serde_helpers::chrono_datetime_as_bson_datetime, DateTime as BsonDateTime, Document,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A server-side session. The cookie only carries the session key; only its hash is stored here.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StoredSession {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub key_hash: String,
+    /// Public identifier used to list and revoke the session without exposing its key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<ObjectId>,
+    /// Session entries as stored by `actix-session`, each value JSON encoded.
+    pub state: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    /// A BSON date so the TTL index on it can remove expired sessions.
+    #[serde(with = "chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+}
+
+impl StoredSession {
+    pub fn collection_name() -> &'static str {
+        "sessions"
+    }
+
+    pub fn to_document(&self) -> Document {
+        let mut doc = doc! {
+            "key_hash": &self.key_hash,
+            "created_at": BsonDateTime::from_millis(self.created_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
+            "last_seen_at": BsonDateTime::from_millis(self.last_seen_at.timestamp_millis()),
+            "expires_at": BsonDateTime::from_millis(self.expires_at.timestamp_millis()),
+        };
+        if let Some(ref id) = self.id {
+            doc.insert("_id", id);
+        }
+        if let Some(ref session_id) = self.session_id {
+            doc.insert("session_id", session_id);
+        }
+        if let Some(ref user_id) = self.user_id {
+            doc.insert("user_id", user_id);
+        }
+        let state: Document = self
+            .state
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone().into()))
+            .collect();
+        doc.insert("state", state);
+        if let Some(ref user_agent) = self.user_agent {
+            doc.insert("user_agent", user_agent);
+        }
+        if let Some(ref ip) = self.ip {
+            doc.insert("ip", ip);
+        }
+
+        doc
+    }
+}
@@ -3,6 +3,33 @@ use mongodb::bson::{doc, oid::ObjectId, DateTime as BsonDateTime, Document};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
+/// An account at an OpenID Connect provider that signs in as this user.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OidcIdentity {
+    pub issuer: String,
+    pub subject: String,
+}
+
+/// What a user may administer. Roles are ordered, so an admin can do everything a moderator can.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Moderator => "moderator",
+            Self::Admin => "admin",
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate, Clone)]
 pub struct User {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -12,13 +39,52 @@ pub struct User {
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
     pub password_hash: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub reset_token: Option<String>,
+    /// Hashes of earlier passwords, newest first, used to prevent reuse.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub password_history: Vec<String>,
+    /// Accounts created before email verification existed are treated as verified.
+    #[serde(default = "default_email_verified")]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub totp_enabled: bool,
+    /// Base32 TOTP secret. Set during enrollment and only trusted once `totp_enabled` is true.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_secret: Option<String>,
+    /// Time step of the last accepted code, so a code cannot be used twice.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_last_used_step: Option<i64>,
+    /// Hashes of the unused recovery codes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recovery_code_hashes: Vec<String>,
+    /// SHA-256 of the outstanding password reset token; the token itself is only ever emailed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_token_hash: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reset_token_expiry_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub oidc_identities: Vec<OidcIdentity>,
+    /// Created from the LDAP directory, which owns its password and email.
+    #[serde(default)]
+    pub directory_account: bool,
+    #[serde(default)]
+    pub role: UserRole,
+    /// Suspended accounts cannot sign in.
+    #[serde(default)]
+    pub suspended: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suspension_reason: Option<String>,
+    /// Bumped to sign the user out everywhere; sessions carrying an older value are rejected.
+    #[serde(default)]
+    pub session_version: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
+fn default_email_verified() -> bool {
+    true
+}
+
 impl User {
     pub fn new(username: &str, email: &str, password_hash: &str) -> Self {
         Self {
@@ -26,8 +92,21 @@ impl User {
             username: username.to_owned(),
             email: email.to_owned(),
             password_hash: password_hash.to_owned(),
-            reset_token: None,
+            password_history: Vec::new(),
+            email_verified: false,
+            totp_enabled: false,
+            totp_secret: None,
+            totp_last_used_step: None,
+            recovery_code_hashes: Vec::new(),
+            reset_token_hash: None,
             reset_token_expiry_at: None,
+            oidc_identities: Vec::new(),
+            directory_account: false,
+            role: UserRole::User,
+            suspended: false,
+            suspension_reason: None,
+            session_version: 0,
+            last_seen_at: None,
             created_at: Utc::now(),
         }
     }
@@ -41,17 +120,49 @@ impl User {
             "username": &self.username,
             "email": &self.email,
             "password_hash": &self.password_hash,
+            "email_verified": self.email_verified,
+            "totp_enabled": self.totp_enabled,
+            "directory_account": self.directory_account,
+            "role": self.role.as_str(),
+            "suspended": self.suspended,
+            "session_version": self.session_version,
             "created_at": BsonDateTime::from_millis(self.created_at.timestamp_millis()), // Convert `chrono::DateTime<Utc>` to `bson::DateTime`
         };
         if let Some(ref id) = self.id {
             doc.insert("_id", id);
         }
-        if let Some(ref reset_token) = self.reset_token {
-            doc.insert("reset_token", reset_token);
+        if !self.password_history.is_empty() {
+            doc.insert("password_history", &self.password_history);
+        }
+        if let Some(ref totp_secret) = self.totp_secret {
+            doc.insert("totp_secret", totp_secret);
+        }
+        if let Some(totp_last_used_step) = self.totp_last_used_step {
+            doc.insert("totp_last_used_step", totp_last_used_step);
+        }
+        if !self.recovery_code_hashes.is_empty() {
+            doc.insert("recovery_code_hashes", &self.recovery_code_hashes);
+        }
+        if !self.oidc_identities.is_empty() {
+            let identities: Vec<Document> = self
+                .oidc_identities
+                .iter()
+                .map(|identity| doc! { "issuer": &identity.issuer, "subject": &identity.subject })
+                .collect();
+            doc.insert("oidc_identities", identities);
+        }
+        if let Some(ref suspension_reason) = self.suspension_reason {
+            doc.insert("suspension_reason", suspension_reason);
+        }
+        if let Some(ref reset_token_hash) = self.reset_token_hash {
+            doc.insert("reset_token_hash", reset_token_hash);
         }
         if let Some(ref reset_token_expiry_at) = self.reset_token_expiry_at {
             doc.insert("reset_token_expiry_at", reset_token_expiry_at);
         }
+        if let Some(ref last_seen_at) = self.last_seen_at {
+            doc.insert("last_seen_at", BsonDateTime::from_millis(last_seen_at.timestamp_millis()));
+        }
 
         doc
     }
@@ -0,0 +1,24 @@
+use crate::{models::api_token_model::ApiToken, repositories::repository::RepositoryError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+/// Bearer tokens, looked up by the hash of the raw token.
+#[async_trait]
+pub trait ApiTokenRepository: Send + Sync {
+    async fn insert(&self, token: &ApiToken) -> Result<ObjectId, RepositoryError>;
+    /// Record that an access or personal token was used and return it. Refresh tokens are not matched.
+    async fn record_use(&self, token_hash: &str, at: DateTime<Utc>) -> Result<Option<ApiToken>, RepositoryError>;
+    /// Remove a refresh token and return it, so it can only be exchanged once.
+    async fn take_refresh_token(&self, token_hash: &str) -> Result<Option<ApiToken>, RepositoryError>;
+    /// Returns false when there is no such token.
+    async fn delete(&self, token_id: ObjectId) -> Result<bool, RepositoryError>;
+    /// The user's personal access tokens, newest first.
+    async fn find_personal(&self, user_id: ObjectId) -> Result<Vec<ApiToken>, RepositoryError>;
+    /// Returns false when the user has no such personal access token.
+    async fn delete_personal(&self, user_id: ObjectId, token_id: ObjectId) -> Result<bool, RepositoryError>;
+    /// Removes every token of the user. Returns how many were removed.
+    async fn delete_for_user(&self, user_id: ObjectId) -> Result<u64, RepositoryError>;
+    /// Removes expired tokens. Returns how many were removed.
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError>;
+}
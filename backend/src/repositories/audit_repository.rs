@@ -0,0 +1,51 @@
+use crate::{
+    models::audit_event_model::AuditEvent,
+    repositories::repository::RepositoryError,
+    types::audit_types::{AuditAction, AuditOutcome},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+/// Narrows a listing of audit events; unset fields match everything.
+#[derive(Debug, Default, Clone)]
+pub struct AuditFilter {
+    pub actor_id: Option<ObjectId>,
+    pub actor_name: Option<String>,
+    pub action: Option<AuditAction>,
+    pub target_id: Option<ObjectId>,
+    pub ip: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    /// Inclusive lower bound on `created_at`.
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on `created_at`.
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        self.actor_id.is_none_or(|actor_id| event.actor_id == Some(actor_id))
+            && self.actor_name.as_ref().is_none_or(|name| event.actor_name.as_ref() == Some(name))
+            && self.action.is_none_or(|action| event.action == action)
+            && self.target_id.is_none_or(|target_id| event.target_id == Some(target_id))
+            && self.ip.as_ref().is_none_or(|ip| &event.ip == ip)
+            && self.outcome.is_none_or(|outcome| event.outcome == outcome)
+            && self.from.is_none_or(|from| event.created_at >= from)
+            && self.to.is_none_or(|to| event.created_at < to)
+    }
+}
+
+/// The append-only audit log.
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    async fn insert(&self, event: &AuditEvent) -> Result<ObjectId, RepositoryError>;
+    /// Matching events, newest first unless `oldest_first`.
+    async fn find(
+        &self,
+        filter: &AuditFilter,
+        oldest_first: bool,
+        skip: u64,
+        limit: i64,
+    ) -> Result<Vec<AuditEvent>, RepositoryError>;
+    async fn count(&self, filter: &AuditFilter) -> Result<u64, RepositoryError>;
+}
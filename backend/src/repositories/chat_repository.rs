@@ -0,0 +1,43 @@
+use crate::{
+    models::chat_model::{Chat, ChatPin, LastMessage},
+    repositories::repository::RepositoryError,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+/// Chats marked deleted are only returned by `find_by_id` and `find_deleted_before`.
+#[async_trait]
+pub trait ChatRepository: Send + Sync {
+    async fn insert(&self, chat: &Chat) -> Result<ObjectId, RepositoryError>;
+    async fn find_by_id(&self, chat_id: ObjectId) -> Result<Option<Chat>, RepositoryError>;
+    /// The chat, only if `user_id` takes part in it.
+    async fn find_for_participant(&self, chat_id: ObjectId, user_id: ObjectId) -> Result<Option<Chat>, RepositoryError>;
+    /// The chat between exactly these participants.
+    async fn find_by_participants(&self, participant_ids: &[ObjectId]) -> Result<Option<Chat>, RepositoryError>;
+    async fn find_for_user(&self, user_id: ObjectId) -> Result<Vec<Chat>, RepositoryError>;
+    /// The user's chat list, most recently active first. Chats they hid stay out until a newer message arrives.
+    async fn list_for_user(&self, user_id: ObjectId, skip: u64, limit: Option<i64>) -> Result<Vec<Chat>, RepositoryError>;
+    /// Increment the chat's `last_seq` and return it, as the sequence number of a new message.
+    /// Returns None when there is no such chat.
+    async fn next_seq(&self, chat_id: ObjectId) -> Result<Option<i64>, RepositoryError>;
+    /// Make `last_message` the chat's last message and move `last_activity_at` up to it,
+    /// unless a message with a higher `seq` was already recorded.
+    async fn record_message(&self, chat_id: ObjectId, last_message: &LastMessage) -> Result<(), RepositoryError>;
+    /// Overwrite the last message, after the recorded one was deleted. `last_activity_at` is kept.
+    async fn replace_last_message(&self, chat_id: ObjectId, last_message: Option<&LastMessage>) -> Result<(), RepositoryError>;
+    /// Add the pin unless its message is already pinned or the chat already has `max_pins` pins.
+    /// Returns whether it was added.
+    async fn pin(&self, chat_id: ObjectId, pin: &ChatPin, max_pins: usize) -> Result<bool, RepositoryError>;
+    /// Returns false when the message was not pinned.
+    async fn unpin(&self, chat_id: ObjectId, message_id: ObjectId) -> Result<bool, RepositoryError>;
+    /// Record that `user_id` hid the chat at `at`, up to its current `last_seq`, replacing an earlier hide.
+    /// Once every participant hid it, the same write marks the chat deleted at `at`.
+    /// Returns the updated chat, or None when the user does not take part in it.
+    async fn hide(&self, chat_id: ObjectId, user_id: ObjectId, at: DateTime<Utc>) -> Result<Option<Chat>, RepositoryError>;
+    /// Chats marked deleted at or before `cutoff`.
+    async fn find_deleted_before(&self, cutoff: DateTime<Utc>) -> Result<Vec<Chat>, RepositoryError>;
+    /// Removes the chat and its messages together. Returns false when there is no such chat.
+    async fn delete(&self, chat_id: ObjectId) -> Result<bool, RepositoryError>;
+    async fn count(&self) -> Result<u64, RepositoryError>;
+}
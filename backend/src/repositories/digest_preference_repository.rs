@@ -0,0 +1,15 @@
+use crate::{models::digest_preference_model::DigestPreference, repositories::repository::RepositoryError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+/// Activity digest settings. Users without a stored preference get the defaults.
+#[async_trait]
+pub trait DigestPreferenceRepository: Send + Sync {
+    async fn find(&self, user_id: ObjectId) -> Result<Option<DigestPreference>, RepositoryError>;
+    async fn find_for_users(&self, user_ids: &[ObjectId]) -> Result<Vec<DigestPreference>, RepositoryError>;
+    /// Create or replace the user's settings. `last_sent_at` is left as it is.
+    async fn upsert(&self, preference: &DigestPreference) -> Result<(), RepositoryError>;
+    /// Record when a digest went out, storing the default settings if the user has none.
+    async fn record_sent(&self, user_id: ObjectId, at: DateTime<Utc>) -> Result<(), RepositoryError>;
+}
@@ -0,0 +1,1384 @@
+use crate::{
+    models::{
+        api_token_model::{ApiToken, ApiTokenKind},
+        audit_event_model::AuditEvent,
+        chat_model::{Chat, ChatHide, ChatPin, LastMessage},
+        digest_preference_model::DigestPreference,
+        login_throttle_model::LoginThrottle,
+        message_model::Message,
+        moderation_action_model::ModerationAction,
+        notification_model::Notification,
+        notification_preference_model::NotificationPreference,
+        outbox_model::{OutboxEmail, OutboxStatus},
+        report_model::Report,
+        saved_message_model::SavedMessage,
+        session_model::StoredSession,
+        user_model::{OidcIdentity, User, UserRole},
+    },
+    repositories::{
+        api_token_repository::ApiTokenRepository,
+        audit_repository::{AuditFilter, AuditRepository},
+        chat_repository::ChatRepository,
+        digest_preference_repository::DigestPreferenceRepository,
+        message_repository::{MessageRange, MessageRepository},
+        moderation_action_repository::ModerationActionRepository,
+        notification_preference_repository::NotificationPreferenceRepository,
+        notification_repository::NotificationRepository,
+        outbox_repository::OutboxRepository,
+        report_repository::{ReportFilter, ReportRepository, ReportSelection},
+        repository::RepositoryError,
+        saved_message_repository::SavedMessageRepository,
+        session_repository::SessionRepository,
+        throttle_repository::ThrottleRepository,
+        user_repository::{UserFilter, UserRepository},
+    },
+    types::{moderation_types::ReportStatus, notification_types::NotificationType},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use std::{
+    collections::HashSet,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+/// Documents of one kind in insertion order.
+struct Store<T>(RwLock<Vec<T>>);
+
+impl<T> Default for Store<T> {
+    fn default() -> Self {
+        Self(RwLock::new(Vec::new()))
+    }
+}
+
+impl<T> Store<T> {
+    // A panic while holding the lock cannot leave a half-applied update behind, so poisoning is ignored.
+    fn read(&self) -> RwLockReadGuard<'_, Vec<T>> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Vec<T>> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[derive(Default)]
+pub struct MemoryUserRepository {
+    users: Store<User>,
+}
+
+impl MemoryUserRepository {
+    fn find(&self, predicate: impl Fn(&User) -> bool) -> Option<User> {
+        self.users.read().iter().find(|user| predicate(user)).cloned()
+    }
+
+    /// Apply `update` to the user and return the updated copy.
+    fn update(&self, user_id: ObjectId, update: impl FnOnce(&mut User)) -> Option<User> {
+        let mut users = self.users.write();
+        let user = users.iter_mut().find(|user| user.id == Some(user_id))?;
+        update(user);
+        Some(user.clone())
+    }
+
+    fn matches(user: &User, filter: &UserFilter) -> bool {
+        let query_matches = match filter.query.as_deref().map(str::trim).filter(|query| !query.is_empty()) {
+            Some(query) => contains_ignore_case(&user.username, query) || contains_ignore_case(&user.email, query),
+            None => true,
+        };
+        query_matches
+            && filter.role.is_none_or(|role| user.role == role)
+            && filter.suspended.is_none_or(|suspended| user.suspended == suspended)
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryUserRepository {
+    async fn insert(&self, user: &User) -> Result<ObjectId, RepositoryError> {
+        let mut users = self.users.write();
+        // Mirrors the unique indexes on usernames and emails.
+        if users.iter().any(|existing| existing.username == user.username || existing.email == user.email) {
+            return Err(RepositoryError::Duplicate);
+        }
+        let mut user = user.clone();
+        let user_id = *user.id.get_or_insert_with(ObjectId::new);
+        users.push(user);
+        Ok(user_id)
+    }
+
+    async fn find_by_id(&self, user_id: ObjectId) -> Result<Option<User>, RepositoryError> {
+        Ok(self.find(|user| user.id == Some(user_id)))
+    }
+
+    async fn find_by_ids(&self, user_ids: &[ObjectId]) -> Result<Vec<User>, RepositoryError> {
+        Ok(self
+            .users
+            .read()
+            .iter()
+            .filter(|user| user.id.is_some_and(|id| user_ids.contains(&id)))
+            .cloned()
+            .collect())
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, RepositoryError> {
+        Ok(self.find(|user| user.username == username))
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepositoryError> {
+        Ok(self.find(|user| user.email == email))
+    }
+
+    async fn find_by_username_or_email(&self, username: &str, email: &str) -> Result<Option<User>, RepositoryError> {
+        Ok(self.find(|user| user.username == username || user.email == email))
+    }
+
+    async fn find_by_reset_token_hash(&self, token_hash: &str) -> Result<Option<User>, RepositoryError> {
+        Ok(self.find(|user| user.reset_token_hash.as_deref() == Some(token_hash)))
+    }
+
+    async fn find_by_oidc_identity(&self, identity: &OidcIdentity) -> Result<Option<User>, RepositoryError> {
+        Ok(self.find(|user| user.oidc_identities.contains(identity)))
+    }
+
+    async fn find_users(&self, filter: &UserFilter, skip: u64, limit: Option<i64>) -> Result<Vec<User>, RepositoryError> {
+        let mut users: Vec<User> = self
+            .users
+            .read()
+            .iter()
+            .filter(|user| Self::matches(user, filter))
+            .cloned()
+            .collect();
+        users.sort_by_key(|user| std::cmp::Reverse(user.created_at));
+        let limit = limit.map_or(usize::MAX, |limit| limit.max(0) as usize);
+        Ok(users.into_iter().skip(skip as usize).take(limit).collect())
+    }
+
+    async fn count_users(&self, filter: &UserFilter) -> Result<u64, RepositoryError> {
+        Ok(self.users.read().iter().filter(|user| Self::matches(user, filter)).count() as u64)
+    }
+
+    async fn set_email_verified(&self, user_id: ObjectId) -> Result<bool, RepositoryError> {
+        Ok(self.update(user_id, |user| user.email_verified = true).is_some())
+    }
+
+    async fn set_reset_token(&self, user_id: ObjectId, token_hash: &str, expires_at_millis: i64) -> Result<(), RepositoryError> {
+        self.update(user_id, |user| {
+            user.reset_token_hash = Some(token_hash.to_owned());
+            user.reset_token_expiry_at = Some(expires_at_millis);
+        });
+        Ok(())
+    }
+
+    async fn complete_password_reset(
+        &self,
+        user_id: ObjectId,
+        token_hash: &str,
+        now_millis: i64,
+        password_hash: &str,
+        password_history: &[String],
+    ) -> Result<bool, RepositoryError> {
+        let mut users = self.users.write();
+        let user = users.iter_mut().find(|user| {
+            user.id == Some(user_id)
+                && user.reset_token_hash.as_deref() == Some(token_hash)
+                && user.reset_token_expiry_at.is_some_and(|expiry| expiry >= now_millis)
+        });
+        let Some(user) = user else {
+            return Ok(false);
+        };
+        user.password_hash = password_hash.to_owned();
+        user.password_history = password_history.to_vec();
+        user.reset_token_hash = None;
+        user.reset_token_expiry_at = None;
+        Ok(true)
+    }
+
+    async fn set_password_hash(&self, user_id: ObjectId, password_hash: &str) -> Result<bool, RepositoryError> {
+        Ok(self.update(user_id, |user| user.password_hash = password_hash.to_owned()).is_some())
+    }
+
+    async fn increment_session_version(&self, user_id: ObjectId) -> Result<(), RepositoryError> {
+        self.update(user_id, |user| user.session_version += 1);
+        Ok(())
+    }
+
+    async fn touch_last_seen(&self, user_id: ObjectId, at: DateTime<Utc>) -> Result<(), RepositoryError> {
+        self.update(user_id, |user| user.last_seen_at = Some(at));
+        Ok(())
+    }
+
+    async fn set_totp_secret(&self, user_id: ObjectId, secret: &str) -> Result<(), RepositoryError> {
+        self.update(user_id, |user| user.totp_secret = Some(secret.to_owned()));
+        Ok(())
+    }
+
+    async fn enable_totp(&self, user_id: ObjectId, step: i64, recovery_code_hashes: &[String]) -> Result<(), RepositoryError> {
+        self.update(user_id, |user| {
+            user.totp_enabled = true;
+            user.totp_last_used_step = Some(step);
+            user.recovery_code_hashes = recovery_code_hashes.to_vec();
+        });
+        Ok(())
+    }
+
+    async fn disable_totp(&self, user_id: ObjectId) -> Result<(), RepositoryError> {
+        self.update(user_id, |user| {
+            user.totp_enabled = false;
+            user.totp_secret = None;
+            user.totp_last_used_step = None;
+            user.recovery_code_hashes.clear();
+        });
+        Ok(())
+    }
+
+    async fn consume_totp_step(&self, user_id: ObjectId, step: i64) -> Result<bool, RepositoryError> {
+        let mut users = self.users.write();
+        let user = users.iter_mut().find(|user| user.id == Some(user_id));
+        match user {
+            Some(user) if user.totp_last_used_step.is_none_or(|last_step| last_step < step) => {
+                user.totp_last_used_step = Some(step);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn consume_recovery_code(&self, user_id: ObjectId, code_hash: &str) -> Result<bool, RepositoryError> {
+        let mut users = self.users.write();
+        let Some(user) = users.iter_mut().find(|user| user.id == Some(user_id)) else {
+            return Ok(false);
+        };
+        let before = user.recovery_code_hashes.len();
+        user.recovery_code_hashes.retain(|hash| hash != code_hash);
+        Ok(user.recovery_code_hashes.len() < before)
+    }
+
+    async fn link_oidc_identity_by_email(&self, email: &str, identity: &OidcIdentity) -> Result<Option<User>, RepositoryError> {
+        let mut users = self.users.write();
+        let Some(user) = users.iter_mut().find(|user| user.email == email && user.email_verified) else {
+            return Ok(None);
+        };
+        if !user.oidc_identities.contains(identity) {
+            user.oidc_identities.push(identity.clone());
+        }
+        Ok(Some(user.clone()))
+    }
+
+    async fn sync_directory_email(&self, username: &str, email: &str) -> Result<Option<User>, RepositoryError> {
+        let mut users = self.users.write();
+        let Some(user) = users.iter_mut().find(|user| user.username == username && user.directory_account) else {
+            return Ok(None);
+        };
+        user.email = email.to_owned();
+        user.email_verified = true;
+        Ok(Some(user.clone()))
+    }
+
+    async fn set_role(&self, user_id: ObjectId, role: UserRole) -> Result<bool, RepositoryError> {
+        Ok(self.update(user_id, |user| user.role = role).is_some())
+    }
+
+    async fn set_suspended(&self, user_id: ObjectId, suspended: bool, reason: Option<&str>) -> Result<bool, RepositoryError> {
+        Ok(self
+            .update(user_id, |user| {
+                user.suspended = suspended;
+                user.suspension_reason = reason.filter(|_| suspended).map(str::to_owned);
+            })
+            .is_some())
+    }
+}
+
+pub struct MemoryChatRepository {
+    chats: Store<Chat>,
+    /// Shared with the message repository so deleting a chat takes its messages with it.
+    messages: Arc<MemoryMessageRepository>,
+}
+
+impl MemoryChatRepository {
+    pub fn new(messages: Arc<MemoryMessageRepository>) -> Self {
+        Self {
+            chats: Store::default(),
+            messages,
+        }
+    }
+
+    fn find(&self, predicate: impl Fn(&Chat) -> bool) -> Option<Chat> {
+        self.chats
+            .read()
+            .iter()
+            .find(|chat| chat.deleted_at.is_none() && predicate(chat))
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl ChatRepository for MemoryChatRepository {
+    async fn insert(&self, chat: &Chat) -> Result<ObjectId, RepositoryError> {
+        let mut chat = chat.clone();
+        let chat_id = *chat.id.get_or_insert_with(ObjectId::new);
+        self.chats.write().push(chat);
+        Ok(chat_id)
+    }
+
+    async fn find_by_id(&self, chat_id: ObjectId) -> Result<Option<Chat>, RepositoryError> {
+        Ok(self.chats.read().iter().find(|chat| chat.id == Some(chat_id)).cloned())
+    }
+
+    async fn find_for_participant(&self, chat_id: ObjectId, user_id: ObjectId) -> Result<Option<Chat>, RepositoryError> {
+        Ok(self.find(|chat| chat.id == Some(chat_id) && chat.participant_ids.contains(&user_id)))
+    }
+
+    async fn find_by_participants(&self, participant_ids: &[ObjectId]) -> Result<Option<Chat>, RepositoryError> {
+        let wanted: HashSet<&ObjectId> = participant_ids.iter().collect();
+        Ok(self.find(|chat| {
+            chat.participant_ids.len() == participant_ids.len()
+                && chat.participant_ids.iter().collect::<HashSet<_>>() == wanted
+        }))
+    }
+
+    async fn find_for_user(&self, user_id: ObjectId) -> Result<Vec<Chat>, RepositoryError> {
+        Ok(self
+            .chats
+            .read()
+            .iter()
+            .filter(|chat| chat.deleted_at.is_none() && chat.participant_ids.contains(&user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn list_for_user(&self, user_id: ObjectId, skip: u64, limit: Option<i64>) -> Result<Vec<Chat>, RepositoryError> {
+        let mut chats: Vec<Chat> = self
+            .find_for_user(user_id)
+            .await?
+            .into_iter()
+            .filter(|chat| chat.hidden_seq(user_id).is_none_or(|hidden_seq| hidden_seq < chat.last_seq))
+            .collect();
+        chats.sort_by_key(|chat| std::cmp::Reverse((chat.last_activity_at, chat.id)));
+        let limit = limit.map_or(usize::MAX, |limit| limit.max(0) as usize);
+        Ok(chats.into_iter().skip(skip as usize).take(limit).collect())
+    }
+
+    async fn next_seq(&self, chat_id: ObjectId) -> Result<Option<i64>, RepositoryError> {
+        let mut chats = self.chats.write();
+        let chat = chats
+            .iter_mut()
+            .find(|chat| chat.id == Some(chat_id) && chat.deleted_at.is_none());
+        Ok(chat.map(|chat| {
+            chat.last_seq += 1;
+            chat.last_seq
+        }))
+    }
+
+    async fn record_message(&self, chat_id: ObjectId, last_message: &LastMessage) -> Result<(), RepositoryError> {
+        let mut chats = self.chats.write();
+        let Some(chat) = chats.iter_mut().find(|chat| chat.id == Some(chat_id)) else {
+            return Ok(());
+        };
+        if chat.last_message.as_ref().is_none_or(|recorded| recorded.seq < last_message.seq) {
+            chat.last_message = Some(last_message.clone());
+            chat.last_activity_at = chat.last_activity_at.max(last_message.created_at);
+        }
+        Ok(())
+    }
+
+    async fn replace_last_message(&self, chat_id: ObjectId, last_message: Option<&LastMessage>) -> Result<(), RepositoryError> {
+        let mut chats = self.chats.write();
+        if let Some(chat) = chats.iter_mut().find(|chat| chat.id == Some(chat_id)) {
+            chat.last_message = last_message.cloned();
+        }
+        Ok(())
+    }
+
+    async fn pin(&self, chat_id: ObjectId, pin: &ChatPin, max_pins: usize) -> Result<bool, RepositoryError> {
+        let mut chats = self.chats.write();
+        let Some(chat) = chats
+            .iter_mut()
+            .find(|chat| chat.id == Some(chat_id) && chat.deleted_at.is_none())
+        else {
+            return Ok(false);
+        };
+        if chat.is_pinned(pin.message_id) || chat.pins.len() >= max_pins {
+            return Ok(false);
+        }
+        chat.pins.push(pin.clone());
+        Ok(true)
+    }
+
+    async fn unpin(&self, chat_id: ObjectId, message_id: ObjectId) -> Result<bool, RepositoryError> {
+        let mut chats = self.chats.write();
+        let Some(chat) = chats.iter_mut().find(|chat| chat.id == Some(chat_id)) else {
+            return Ok(false);
+        };
+        let before = chat.pins.len();
+        chat.pins.retain(|pin| pin.message_id != message_id);
+        Ok(chat.pins.len() < before)
+    }
+
+    async fn hide(&self, chat_id: ObjectId, user_id: ObjectId, at: DateTime<Utc>) -> Result<Option<Chat>, RepositoryError> {
+        let mut chats = self.chats.write();
+        let Some(chat) = chats.iter_mut().find(|chat| {
+            chat.id == Some(chat_id) && chat.deleted_at.is_none() && chat.participant_ids.contains(&user_id)
+        }) else {
+            return Ok(None);
+        };
+        chat.hidden_by.retain(|hide| hide.user_id != user_id);
+        chat.hidden_by.push(ChatHide {
+            user_id,
+            hidden_at: at,
+            seq: chat.last_seq,
+        });
+        if chat.is_hidden_by_everyone() {
+            chat.deleted_at = Some(at);
+        }
+        Ok(Some(chat.clone()))
+    }
+
+    async fn find_deleted_before(&self, cutoff: DateTime<Utc>) -> Result<Vec<Chat>, RepositoryError> {
+        Ok(self
+            .chats
+            .read()
+            .iter()
+            .filter(|chat| chat.deleted_at.is_some_and(|deleted_at| deleted_at <= cutoff))
+            .cloned()
+            .collect())
+    }
+
+    async fn delete(&self, chat_id: ObjectId) -> Result<bool, RepositoryError> {
+        // Both locks are held so no one sees the chat without its messages or the other way round.
+        let mut chats = self.chats.write();
+        let mut messages = self.messages.messages.write();
+        let before = chats.len();
+        chats.retain(|chat| chat.id != Some(chat_id));
+        if chats.len() == before {
+            return Ok(false);
+        }
+        messages.retain(|message| message.chat_id != chat_id);
+        Ok(true)
+    }
+
+    async fn count(&self) -> Result<u64, RepositoryError> {
+        Ok(self.chats.read().len() as u64)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryMessageRepository {
+    messages: Store<Message>,
+}
+
+impl MemoryMessageRepository {
+    fn since(&self, chat_ids: &[ObjectId], excluded_sender: ObjectId, since: DateTime<Utc>) -> Vec<Message> {
+        self.messages
+            .read()
+            .iter()
+            .filter(|message| {
+                chat_ids.contains(&message.chat_id)
+                    && message.sender_id != excluded_sender
+                    && message.created_at > since
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[async_trait]
+impl MessageRepository for MemoryMessageRepository {
+    async fn insert(&self, message: &Message) -> Result<ObjectId, RepositoryError> {
+        let mut messages = self.messages.write();
+        if message.client_msg_id.is_some()
+            && messages.iter().any(|existing| {
+                existing.sender_id == message.sender_id && existing.client_msg_id == message.client_msg_id
+            })
+        {
+            return Err(RepositoryError::Duplicate);
+        }
+        let mut message = message.clone();
+        let message_id = *message.id.get_or_insert_with(ObjectId::new);
+        messages.push(message);
+        Ok(message_id)
+    }
+
+    async fn find_by_id(&self, message_id: ObjectId) -> Result<Option<Message>, RepositoryError> {
+        Ok(self
+            .messages
+            .read()
+            .iter()
+            .find(|message| message.id == Some(message_id))
+            .cloned())
+    }
+
+    async fn find_by_ids(&self, message_ids: &[ObjectId]) -> Result<Vec<Message>, RepositoryError> {
+        Ok(self
+            .messages
+            .read()
+            .iter()
+            .filter(|message| message.id.is_some_and(|id| message_ids.contains(&id)))
+            .cloned()
+            .collect())
+    }
+
+    async fn find_by_client_msg_id(&self, sender_id: ObjectId, client_msg_id: &str) -> Result<Option<Message>, RepositoryError> {
+        Ok(self
+            .messages
+            .read()
+            .iter()
+            .find(|message| message.sender_id == sender_id && message.client_msg_id.as_deref() == Some(client_msg_id))
+            .cloned())
+    }
+
+    async fn find_by_chat(&self, chat_id: ObjectId, range: &MessageRange) -> Result<Vec<Message>, RepositoryError> {
+        let mut messages: Vec<Message> = self
+            .messages
+            .read()
+            .iter()
+            .filter(|message| {
+                message.chat_id == chat_id
+                    && range.lower_bound().is_none_or(|lower_bound| message.seq > lower_bound)
+                    && range.before_seq.is_none_or(|before_seq| message.seq < before_seq)
+            })
+            .cloned()
+            .collect();
+        messages.sort_by_key(|message| message.seq);
+        let limit = range.limit.map_or(messages.len(), |limit| limit.max(0) as usize);
+        if range.newest_first() {
+            messages.drain(..messages.len().saturating_sub(limit));
+        } else {
+            messages.truncate(limit);
+        }
+        Ok(messages)
+    }
+
+    async fn find_last(&self, chat_id: ObjectId) -> Result<Option<Message>, RepositoryError> {
+        Ok(self
+            .messages
+            .read()
+            .iter()
+            .filter(|message| message.chat_id == chat_id)
+            .max_by_key(|message| message.seq)
+            .cloned())
+    }
+
+    async fn delete(&self, message_id: ObjectId) -> Result<Option<Message>, RepositoryError> {
+        let mut messages = self.messages.write();
+        let position = messages.iter().position(|message| message.id == Some(message_id));
+        Ok(position.map(|position| messages.remove(position)))
+    }
+
+    async fn count(&self) -> Result<u64, RepositoryError> {
+        Ok(self.messages.read().len() as u64)
+    }
+
+    async fn count_since(
+        &self,
+        chat_ids: &[ObjectId],
+        excluded_sender: ObjectId,
+        since: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        Ok(self.since(chat_ids, excluded_sender, since).len() as u64)
+    }
+
+    async fn senders_since(
+        &self,
+        chat_ids: &[ObjectId],
+        excluded_sender: ObjectId,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<ObjectId>, RepositoryError> {
+        let mut senders = Vec::new();
+        for message in self.since(chat_ids, excluded_sender, since) {
+            if !senders.contains(&message.sender_id) {
+                senders.push(message.sender_id);
+            }
+        }
+        Ok(senders)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryNotificationRepository {
+    notifications: Store<Notification>,
+}
+
+#[async_trait]
+impl NotificationRepository for MemoryNotificationRepository {
+    async fn insert(&self, notification: &Notification) -> Result<ObjectId, RepositoryError> {
+        let mut notification = notification.clone();
+        let notification_id = *notification.id.get_or_insert_with(ObjectId::new);
+        self.notifications.write().push(notification);
+        Ok(notification_id)
+    }
+
+    async fn find_unhandled(&self, recipient_id: ObjectId) -> Result<Vec<Notification>, RepositoryError> {
+        Ok(self
+            .notifications
+            .read()
+            .iter()
+            .filter(|notification| notification.recipient_id == recipient_id && !notification.is_handled)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_recent(
+        &self,
+        recipient_id: ObjectId,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<Notification>, RepositoryError> {
+        let mut notifications: Vec<Notification> = self
+            .notifications
+            .read()
+            .iter()
+            .filter(|notification| notification.recipient_id == recipient_id)
+            .filter(|notification| !unread_only || !notification.is_read)
+            .cloned()
+            .collect();
+        notifications.sort_by_key(|notification| std::cmp::Reverse(notification.created_at));
+        notifications.truncate(limit.max(0) as usize);
+        Ok(notifications)
+    }
+
+    async fn count_unread(&self, recipient_id: ObjectId) -> Result<u64, RepositoryError> {
+        Ok(self
+            .notifications
+            .read()
+            .iter()
+            .filter(|notification| notification.recipient_id == recipient_id && !notification.is_read)
+            .count() as u64)
+    }
+
+    async fn mark_read(
+        &self,
+        recipient_id: ObjectId,
+        ids: Option<&[ObjectId]>,
+        at: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let mut marked = 0;
+        for notification in self.notifications.write().iter_mut() {
+            let selected = ids.is_none_or(|ids| notification.id.is_some_and(|id| ids.contains(&id)));
+            if notification.recipient_id == recipient_id && !notification.is_read && selected {
+                notification.is_read = true;
+                notification.read_at = Some(at);
+                marked += 1;
+            }
+        }
+        Ok(marked)
+    }
+
+    async fn delete(&self, notification_id: ObjectId, recipient_id: ObjectId) -> Result<bool, RepositoryError> {
+        let mut notifications = self.notifications.write();
+        let before = notifications.len();
+        notifications.retain(|notification| {
+            notification.id != Some(notification_id) || notification.recipient_id != recipient_id
+        });
+        Ok(notifications.len() < before)
+    }
+
+    async fn mark_handled(
+        &self,
+        notification_id: ObjectId,
+        recipient_id: ObjectId,
+    ) -> Result<Option<Notification>, RepositoryError> {
+        let mut notifications = self.notifications.write();
+        let notification = notifications.iter_mut().find(|notification| {
+            notification.id == Some(notification_id) && notification.recipient_id == recipient_id
+        });
+        Ok(notification.map(|notification| {
+            let before = notification.clone();
+            notification.is_handled = true;
+            notification.is_read = true;
+            before
+        }))
+    }
+
+    async fn count_unhandled_since(
+        &self,
+        recipient_id: ObjectId,
+        notification_types: &[NotificationType],
+        since: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        Ok(self
+            .notifications
+            .read()
+            .iter()
+            .filter(|notification| {
+                notification.recipient_id == recipient_id
+                    && notification_types.contains(&notification.notification_type)
+                    && !notification.is_handled
+                    && notification.created_at > since
+            })
+            .count() as u64)
+    }
+}
+
+#[derive(Default)]
+pub struct MemorySavedMessageRepository {
+    saved_messages: Store<SavedMessage>,
+}
+
+impl MemorySavedMessageRepository {
+    fn delete_where(&self, predicate: impl Fn(&SavedMessage) -> bool) -> u64 {
+        let mut saved_messages = self.saved_messages.write();
+        let before = saved_messages.len();
+        saved_messages.retain(|saved| !predicate(saved));
+        (before - saved_messages.len()) as u64
+    }
+}
+
+#[async_trait]
+impl SavedMessageRepository for MemorySavedMessageRepository {
+    async fn upsert(&self, saved: &SavedMessage) -> Result<SavedMessage, RepositoryError> {
+        let mut saved_messages = self.saved_messages.write();
+        if let Some(existing) = saved_messages
+            .iter_mut()
+            .find(|existing| existing.user_id == saved.user_id && existing.message_id == saved.message_id)
+        {
+            existing.note = saved.note.clone();
+            existing.tags = saved.tags.clone();
+            return Ok(existing.clone());
+        }
+        let mut saved = saved.clone();
+        saved.id.get_or_insert_with(ObjectId::new);
+        saved_messages.push(saved.clone());
+        Ok(saved)
+    }
+
+    async fn find_for_user(
+        &self,
+        user_id: ObjectId,
+        tag: Option<&str>,
+        skip: u64,
+        limit: Option<i64>,
+    ) -> Result<Vec<SavedMessage>, RepositoryError> {
+        let mut saved_messages: Vec<SavedMessage> = self
+            .saved_messages
+            .read()
+            .iter()
+            .filter(|saved| saved.user_id == user_id && tag.is_none_or(|tag| saved.tags.iter().any(|t| t == tag)))
+            .cloned()
+            .collect();
+        saved_messages.sort_by_key(|saved| std::cmp::Reverse((saved.created_at, saved.id)));
+        let limit = limit.map_or(usize::MAX, |limit| limit.max(0) as usize);
+        Ok(saved_messages.into_iter().skip(skip as usize).take(limit).collect())
+    }
+
+    async fn delete(&self, user_id: ObjectId, message_id: ObjectId) -> Result<bool, RepositoryError> {
+        Ok(self.delete_where(|saved| saved.user_id == user_id && saved.message_id == message_id) > 0)
+    }
+
+    async fn delete_by_message(&self, message_id: ObjectId) -> Result<u64, RepositoryError> {
+        Ok(self.delete_where(|saved| saved.message_id == message_id))
+    }
+
+    async fn delete_by_chat(&self, chat_id: ObjectId, user_id: Option<ObjectId>) -> Result<u64, RepositoryError> {
+        Ok(self.delete_where(|saved| saved.chat_id == chat_id && user_id.is_none_or(|user_id| saved.user_id == user_id)))
+    }
+}
+
+#[derive(Default)]
+pub struct MemorySessionRepository {
+    sessions: Store<StoredSession>,
+}
+
+impl MemorySessionRepository {
+    fn delete_where(&self, predicate: impl Fn(&StoredSession) -> bool) -> u64 {
+        let mut sessions = self.sessions.write();
+        let before = sessions.len();
+        sessions.retain(|session| !predicate(session));
+        (before - sessions.len()) as u64
+    }
+}
+
+#[async_trait]
+impl SessionRepository for MemorySessionRepository {
+    async fn insert(&self, session: &StoredSession) -> Result<(), RepositoryError> {
+        let mut session = session.clone();
+        session.id.get_or_insert_with(ObjectId::new);
+        self.sessions.write().push(session);
+        Ok(())
+    }
+
+    async fn find_by_key_hash(&self, key_hash: &str, now: DateTime<Utc>) -> Result<Option<StoredSession>, RepositoryError> {
+        Ok(self
+            .sessions
+            .read()
+            .iter()
+            .find(|session| session.key_hash == key_hash && session.expires_at > now)
+            .cloned())
+    }
+
+    async fn update_state(&self, session: &StoredSession) -> Result<bool, RepositoryError> {
+        let mut sessions = self.sessions.write();
+        let Some(stored) = sessions.iter_mut().find(|stored| stored.key_hash == session.key_hash) else {
+            return Ok(false);
+        };
+        stored.state = session.state.clone();
+        stored.user_id = session.user_id;
+        stored.session_id = session.session_id.clone();
+        stored.user_agent = session.user_agent.clone();
+        stored.ip = session.ip.clone();
+        stored.last_seen_at = session.last_seen_at;
+        stored.expires_at = session.expires_at;
+        Ok(true)
+    }
+
+    async fn touch(
+        &self,
+        key_hash: &str,
+        last_seen_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        if let Some(session) = self.sessions.write().iter_mut().find(|session| session.key_hash == key_hash) {
+            session.last_seen_at = last_seen_at;
+            session.expires_at = expires_at;
+        }
+        Ok(())
+    }
+
+    async fn delete_by_key_hash(&self, key_hash: &str) -> Result<(), RepositoryError> {
+        self.delete_where(|session| session.key_hash == key_hash);
+        Ok(())
+    }
+
+    async fn find_for_user(&self, user_id: ObjectId, now: DateTime<Utc>) -> Result<Vec<StoredSession>, RepositoryError> {
+        let mut sessions: Vec<StoredSession> = self
+            .sessions
+            .read()
+            .iter()
+            .filter(|session| session.user_id == Some(user_id) && session.expires_at > now)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen_at));
+        Ok(sessions)
+    }
+
+    async fn delete_for_user(&self, user_id: ObjectId, session_id: &str) -> Result<bool, RepositoryError> {
+        let mut sessions = self.sessions.write();
+        let Some(index) = sessions
+            .iter()
+            .position(|session| session.user_id == Some(user_id) && session.session_id.as_deref() == Some(session_id))
+        else {
+            return Ok(false);
+        };
+        sessions.remove(index);
+        Ok(true)
+    }
+
+    async fn delete_all_for_user(&self, user_id: ObjectId) -> Result<u64, RepositoryError> {
+        Ok(self.delete_where(|session| session.user_id == Some(user_id)))
+    }
+
+    async fn count_signed_in(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        Ok(self
+            .sessions
+            .read()
+            .iter()
+            .filter(|session| session.user_id.is_some() && session.expires_at > now)
+            .count() as u64)
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        Ok(self.delete_where(|session| session.expires_at <= now))
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryApiTokenRepository {
+    tokens: Store<ApiToken>,
+}
+
+impl MemoryApiTokenRepository {
+    fn delete_where(&self, predicate: impl Fn(&ApiToken) -> bool) -> u64 {
+        let mut tokens = self.tokens.write();
+        let before = tokens.len();
+        tokens.retain(|token| !predicate(token));
+        (before - tokens.len()) as u64
+    }
+}
+
+#[async_trait]
+impl ApiTokenRepository for MemoryApiTokenRepository {
+    async fn insert(&self, token: &ApiToken) -> Result<ObjectId, RepositoryError> {
+        let mut token = token.clone();
+        let token_id = *token.id.get_or_insert_with(ObjectId::new);
+        self.tokens.write().push(token);
+        Ok(token_id)
+    }
+
+    async fn record_use(&self, token_hash: &str, at: DateTime<Utc>) -> Result<Option<ApiToken>, RepositoryError> {
+        let mut tokens = self.tokens.write();
+        let token = tokens
+            .iter_mut()
+            .find(|token| token.token_hash == token_hash && token.kind != ApiTokenKind::Refresh);
+        Ok(token.map(|token| {
+            token.last_used_at = Some(at);
+            token.clone()
+        }))
+    }
+
+    async fn take_refresh_token(&self, token_hash: &str) -> Result<Option<ApiToken>, RepositoryError> {
+        let mut tokens = self.tokens.write();
+        let index = tokens
+            .iter()
+            .position(|token| token.token_hash == token_hash && token.kind == ApiTokenKind::Refresh);
+        Ok(index.map(|index| tokens.remove(index)))
+    }
+
+    async fn delete(&self, token_id: ObjectId) -> Result<bool, RepositoryError> {
+        Ok(self.delete_where(|token| token.id == Some(token_id)) > 0)
+    }
+
+    async fn find_personal(&self, user_id: ObjectId) -> Result<Vec<ApiToken>, RepositoryError> {
+        let mut tokens: Vec<ApiToken> = self
+            .tokens
+            .read()
+            .iter()
+            .filter(|token| token.user_id == user_id && token.kind == ApiTokenKind::Personal)
+            .cloned()
+            .collect();
+        tokens.sort_by_key(|token| std::cmp::Reverse((token.created_at, token.id)));
+        Ok(tokens)
+    }
+
+    async fn delete_personal(&self, user_id: ObjectId, token_id: ObjectId) -> Result<bool, RepositoryError> {
+        Ok(self.delete_where(|token| {
+            token.id == Some(token_id) && token.user_id == user_id && token.kind == ApiTokenKind::Personal
+        }) > 0)
+    }
+
+    async fn delete_for_user(&self, user_id: ObjectId) -> Result<u64, RepositoryError> {
+        Ok(self.delete_where(|token| token.user_id == user_id))
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        Ok(self.delete_where(|token| token.is_expired_at(now)))
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryThrottleRepository {
+    throttles: Store<LoginThrottle>,
+}
+
+fn throttle_is_live(throttle: &LoginThrottle, now: DateTime<Utc>) -> bool {
+    throttle.expires_at.is_none_or(|expires_at| expires_at > now)
+}
+
+#[async_trait]
+impl ThrottleRepository for MemoryThrottleRepository {
+    async fn find(&self, key: &str, now: DateTime<Utc>) -> Result<Option<LoginThrottle>, RepositoryError> {
+        Ok(self
+            .throttles
+            .read()
+            .iter()
+            .find(|throttle| throttle.key == key && throttle_is_live(throttle, now))
+            .cloned())
+    }
+
+    async fn increment(
+        &self,
+        key: &str,
+        at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<LoginThrottle, RepositoryError> {
+        let mut throttles = self.throttles.write();
+        let throttle = match throttles.iter_mut().position(|throttle| throttle.key == key) {
+            Some(index) => &mut throttles[index],
+            None => {
+                throttles.push(LoginThrottle {
+                    id: Some(ObjectId::new()),
+                    key: key.to_owned(),
+                    failures: 0,
+                    last_failure_at: at,
+                    locked_until: None,
+                    expires_at: None,
+                });
+                throttles.last_mut().expect("throttle was just pushed")
+            }
+        };
+        if !throttle_is_live(throttle, at) {
+            throttle.failures = 0;
+            throttle.locked_until = None;
+        }
+        throttle.failures += 1;
+        throttle.last_failure_at = at;
+        throttle.expires_at = Some(expires_at);
+        Ok(throttle.clone())
+    }
+
+    async fn lock(
+        &self,
+        key: &str,
+        locked_until: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        if let Some(throttle) = self.throttles.write().iter_mut().find(|throttle| throttle.key == key) {
+            throttle.failures = 0;
+            throttle.locked_until = Some(locked_until);
+            throttle.expires_at = Some(expires_at);
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), RepositoryError> {
+        self.throttles.write().retain(|throttle| throttle.key != key);
+        Ok(())
+    }
+
+    async fn delete_with_prefix(&self, prefix: &str) -> Result<(), RepositoryError> {
+        self.throttles.write().retain(|throttle| !throttle.key.starts_with(prefix));
+        Ok(())
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        let mut throttles = self.throttles.write();
+        let before = throttles.len();
+        throttles.retain(|throttle| throttle_is_live(throttle, now));
+        Ok((before - throttles.len()) as u64)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryAuditRepository {
+    events: Store<AuditEvent>,
+}
+
+#[async_trait]
+impl AuditRepository for MemoryAuditRepository {
+    async fn insert(&self, event: &AuditEvent) -> Result<ObjectId, RepositoryError> {
+        let mut event = event.clone();
+        let event_id = *event.id.get_or_insert_with(ObjectId::new);
+        self.events.write().push(event);
+        Ok(event_id)
+    }
+
+    async fn find(
+        &self,
+        filter: &AuditFilter,
+        oldest_first: bool,
+        skip: u64,
+        limit: i64,
+    ) -> Result<Vec<AuditEvent>, RepositoryError> {
+        let mut events: Vec<AuditEvent> = self
+            .events
+            .read()
+            .iter()
+            .filter(|event| filter.matches(event))
+            .cloned()
+            .collect();
+        events.sort_by_key(|event| (event.created_at, event.id));
+        if !oldest_first {
+            events.reverse();
+        }
+        Ok(events.into_iter().skip(skip as usize).take(limit.max(0) as usize).collect())
+    }
+
+    async fn count(&self, filter: &AuditFilter) -> Result<u64, RepositoryError> {
+        Ok(self.events.read().iter().filter(|event| filter.matches(event)).count() as u64)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryOutboxRepository {
+    outbox: Store<OutboxEmail>,
+}
+
+impl MemoryOutboxRepository {
+    fn update(&self, id: ObjectId, update: impl FnOnce(&mut OutboxEmail)) {
+        if let Some(email) = self.outbox.write().iter_mut().find(|email| email.id == Some(id)) {
+            update(email);
+        }
+    }
+}
+
+#[async_trait]
+impl OutboxRepository for MemoryOutboxRepository {
+    async fn insert(&self, email: &OutboxEmail) -> Result<ObjectId, RepositoryError> {
+        let mut email = email.clone();
+        let email_id = *email.id.get_or_insert_with(ObjectId::new);
+        self.outbox.write().push(email);
+        Ok(email_id)
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> Result<Option<OutboxEmail>, RepositoryError> {
+        Ok(self.outbox.read().iter().find(|email| email.id == Some(id)).cloned())
+    }
+
+    async fn find_due(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<OutboxEmail>, RepositoryError> {
+        let mut due: Vec<OutboxEmail> = self
+            .outbox
+            .read()
+            .iter()
+            .filter(|email| email.status == OutboxStatus::Pending && email.next_attempt_at <= now)
+            .cloned()
+            .collect();
+        due.sort_by_key(|email| email.next_attempt_at);
+        Ok(due.into_iter().take(limit.max(0) as usize).collect())
+    }
+
+    async fn record_sent(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        sent_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        self.update(id, |email| {
+            email.status = OutboxStatus::Sent;
+            email.attempts = attempts;
+            email.sent_at = Some(sent_at);
+            email.last_error = None;
+            email.email.text_body.clear();
+            email.email.html_body.clear();
+            email.expires_at = Some(expires_at);
+        });
+        Ok(())
+    }
+
+    async fn record_failure(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        self.update(id, |email| {
+            email.status = OutboxStatus::Pending;
+            email.attempts = attempts;
+            email.last_error = Some(error.to_owned());
+            email.next_attempt_at = next_attempt_at;
+        });
+        Ok(())
+    }
+
+    async fn give_up(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        error: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        self.update(id, |email| {
+            email.status = OutboxStatus::Failed;
+            email.attempts = attempts;
+            email.last_error = Some(error.to_owned());
+            email.email.text_body.clear();
+            email.email.html_body.clear();
+            email.expires_at = Some(expires_at);
+        });
+        Ok(())
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        let mut outbox = self.outbox.write();
+        let before = outbox.len();
+        outbox.retain(|email| email.expires_at.is_none_or(|expires_at| expires_at > now));
+        Ok((before - outbox.len()) as u64)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryNotificationPreferenceRepository {
+    preferences: Store<NotificationPreference>,
+}
+
+#[async_trait]
+impl NotificationPreferenceRepository for MemoryNotificationPreferenceRepository {
+    async fn find(
+        &self,
+        user_id: ObjectId,
+        notification_type: NotificationType,
+    ) -> Result<Option<NotificationPreference>, RepositoryError> {
+        Ok(self
+            .preferences
+            .read()
+            .iter()
+            .find(|preference| preference.user_id == user_id && preference.notification_type == notification_type)
+            .cloned())
+    }
+
+    async fn find_for_user(&self, user_id: ObjectId) -> Result<Vec<NotificationPreference>, RepositoryError> {
+        Ok(self
+            .preferences
+            .read()
+            .iter()
+            .filter(|preference| preference.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn upsert(&self, preference: &NotificationPreference) -> Result<(), RepositoryError> {
+        let mut preferences = self.preferences.write();
+        match preferences.iter_mut().find(|existing| {
+            existing.user_id == preference.user_id && existing.notification_type == preference.notification_type
+        }) {
+            Some(existing) => {
+                let id = existing.id;
+                *existing = preference.clone();
+                existing.id = id;
+            }
+            None => {
+                let mut preference = preference.clone();
+                preference.id.get_or_insert_with(ObjectId::new);
+                preferences.push(preference);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryDigestPreferenceRepository {
+    preferences: Store<DigestPreference>,
+}
+
+#[async_trait]
+impl DigestPreferenceRepository for MemoryDigestPreferenceRepository {
+    async fn find(&self, user_id: ObjectId) -> Result<Option<DigestPreference>, RepositoryError> {
+        Ok(self
+            .preferences
+            .read()
+            .iter()
+            .find(|preference| preference.user_id == user_id)
+            .cloned())
+    }
+
+    async fn find_for_users(&self, user_ids: &[ObjectId]) -> Result<Vec<DigestPreference>, RepositoryError> {
+        Ok(self
+            .preferences
+            .read()
+            .iter()
+            .filter(|preference| user_ids.contains(&preference.user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn upsert(&self, preference: &DigestPreference) -> Result<(), RepositoryError> {
+        let mut preferences = self.preferences.write();
+        match preferences.iter_mut().find(|existing| existing.user_id == preference.user_id) {
+            Some(existing) => {
+                existing.frequency = preference.frequency;
+                existing.quiet_hours_start = preference.quiet_hours_start;
+                existing.quiet_hours_end = preference.quiet_hours_end;
+                existing.utc_offset_minutes = preference.utc_offset_minutes;
+            }
+            None => {
+                let mut preference = preference.clone();
+                preference.id.get_or_insert_with(ObjectId::new);
+                preference.last_sent_at = None;
+                preferences.push(preference);
+            }
+        }
+        Ok(())
+    }
+
+    async fn record_sent(&self, user_id: ObjectId, at: DateTime<Utc>) -> Result<(), RepositoryError> {
+        let mut preferences = self.preferences.write();
+        match preferences.iter_mut().find(|existing| existing.user_id == user_id) {
+            Some(existing) => existing.last_sent_at = Some(at),
+            None => {
+                let mut preference = DigestPreference::default_for(user_id);
+                preference.id = Some(ObjectId::new());
+                preference.last_sent_at = Some(at);
+                preferences.push(preference);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryReportRepository {
+    reports: Store<Report>,
+}
+
+#[async_trait]
+impl ReportRepository for MemoryReportRepository {
+    async fn insert(&self, report: &Report) -> Result<ObjectId, RepositoryError> {
+        let mut report = report.clone();
+        let report_id = *report.id.get_or_insert_with(ObjectId::new);
+        self.reports.write().push(report);
+        Ok(report_id)
+    }
+
+    async fn find_by_id(&self, report_id: ObjectId) -> Result<Option<Report>, RepositoryError> {
+        Ok(self.reports.read().iter().find(|report| report.id == Some(report_id)).cloned())
+    }
+
+    async fn has_open_duplicate(&self, report: &Report) -> Result<bool, RepositoryError> {
+        Ok(self.reports.read().iter().any(|existing| {
+            existing.reporter_id == report.reporter_id
+                && existing.reported_user_id == report.reported_user_id
+                && existing.message_id == report.message_id
+                && existing.status == ReportStatus::Open
+        }))
+    }
+
+    async fn find(&self, filter: &ReportFilter, skip: u64, limit: i64) -> Result<Vec<Report>, RepositoryError> {
+        let mut reports: Vec<Report> = self
+            .reports
+            .read()
+            .iter()
+            .filter(|report| filter.matches(report))
+            .cloned()
+            .collect();
+        reports.sort_by_key(|report| (report.created_at, report.id));
+        Ok(reports.into_iter().skip(skip as usize).take(limit.max(0) as usize).collect())
+    }
+
+    async fn count(&self, filter: &ReportFilter) -> Result<u64, RepositoryError> {
+        Ok(self.reports.read().iter().filter(|report| filter.matches(report)).count() as u64)
+    }
+
+    async fn close(
+        &self,
+        selection: ReportSelection,
+        status: ReportStatus,
+        handled_by: ObjectId,
+        at: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let mut closed = 0;
+        for report in self.reports.write().iter_mut() {
+            if report.status == ReportStatus::Open && selection.matches(report) {
+                report.status = status;
+                report.handled_by = Some(handled_by);
+                report.handled_at = Some(at);
+                closed += 1;
+            }
+        }
+        Ok(closed)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryModerationActionRepository {
+    actions: Store<ModerationAction>,
+}
+
+#[async_trait]
+impl ModerationActionRepository for MemoryModerationActionRepository {
+    async fn insert(&self, action: &ModerationAction) -> Result<ObjectId, RepositoryError> {
+        let mut action = action.clone();
+        let action_id = *action.id.get_or_insert_with(ObjectId::new);
+        self.actions.write().push(action);
+        Ok(action_id)
+    }
+
+    async fn find(
+        &self,
+        target_user_id: Option<ObjectId>,
+        skip: u64,
+        limit: i64,
+    ) -> Result<Vec<ModerationAction>, RepositoryError> {
+        let mut actions: Vec<ModerationAction> = self
+            .actions
+            .read()
+            .iter()
+            .filter(|action| target_user_id.is_none_or(|user_id| action.target_user_id == user_id))
+            .cloned()
+            .collect();
+        actions.sort_by_key(|action| std::cmp::Reverse((action.created_at, action.id)));
+        Ok(actions.into_iter().skip(skip as usize).take(limit.max(0) as usize).collect())
+    }
+}
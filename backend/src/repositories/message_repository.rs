@@ -0,0 +1,59 @@
+use crate::{models::message_model::Message, repositories::repository::RepositoryError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+/// A window of a chat's history by sequence number.
+#[derive(Debug, Clone, Default)]
+pub struct MessageRange {
+    /// Only messages after this one, for catching up after a reconnect.
+    pub after_seq: Option<i64>,
+    /// Only messages before this one, for paging back through history.
+    pub before_seq: Option<i64>,
+    /// Leave out this message and everything before it, e.g. history the user deleted,
+    /// without changing which end of the window is wanted.
+    pub hidden_seq: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl MessageRange {
+    /// Catching up takes the oldest messages of the window; otherwise the newest are wanted.
+    pub fn newest_first(&self) -> bool {
+        self.after_seq.is_none()
+    }
+
+    /// Only messages after this one are in the range.
+    pub fn lower_bound(&self) -> Option<i64> {
+        self.after_seq.max(self.hidden_seq)
+    }
+}
+
+#[async_trait]
+pub trait MessageRepository: Send + Sync {
+    async fn insert(&self, message: &Message) -> Result<ObjectId, RepositoryError>;
+    async fn find_by_id(&self, message_id: ObjectId) -> Result<Option<Message>, RepositoryError>;
+    async fn find_by_ids(&self, message_ids: &[ObjectId]) -> Result<Vec<Message>, RepositoryError>;
+    /// The message the sender stored earlier under this `client_msg_id`.
+    async fn find_by_client_msg_id(&self, sender_id: ObjectId, client_msg_id: &str) -> Result<Option<Message>, RepositoryError>;
+    /// Up to `range.limit` of a chat's messages in the range, in sequence order.
+    async fn find_by_chat(&self, chat_id: ObjectId, range: &MessageRange) -> Result<Vec<Message>, RepositoryError>;
+    /// The message with the highest sequence number.
+    async fn find_last(&self, chat_id: ObjectId) -> Result<Option<Message>, RepositoryError>;
+    /// Returns the deleted message.
+    async fn delete(&self, message_id: ObjectId) -> Result<Option<Message>, RepositoryError>;
+    async fn count(&self) -> Result<u64, RepositoryError>;
+    /// Messages posted in the chats after `since` by anyone but `excluded_sender`.
+    async fn count_since(
+        &self,
+        chat_ids: &[ObjectId],
+        excluded_sender: ObjectId,
+        since: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError>;
+    /// The distinct senders of the messages counted by `count_since`.
+    async fn senders_since(
+        &self,
+        chat_ids: &[ObjectId],
+        excluded_sender: ObjectId,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<ObjectId>, RepositoryError>;
+}
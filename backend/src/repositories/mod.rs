@@ -0,0 +1,18 @@
+pub mod api_token_repository;
+pub mod audit_repository;
+pub mod chat_repository;
+pub mod digest_preference_repository;
+pub mod memory_repository;
+pub mod message_repository;
+pub mod moderation_action_repository;
+pub mod mongo_repository;
+pub mod notification_preference_repository;
+pub mod notification_repository;
+pub mod outbox_repository;
+pub mod postgres_repository;
+pub mod report_repository;
+pub mod repository;
+pub mod saved_message_repository;
+pub mod session_repository;
+pub mod throttle_repository;
+pub mod user_repository;
@@ -0,0 +1,16 @@
+use crate::{models::moderation_action_model::ModerationAction, repositories::repository::RepositoryError};
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+
+/// The append-only moderation trail.
+#[async_trait]
+pub trait ModerationActionRepository: Send + Sync {
+    async fn insert(&self, action: &ModerationAction) -> Result<ObjectId, RepositoryError>;
+    /// Actions newest first, optionally only those against one user.
+    async fn find(
+        &self,
+        target_user_id: Option<ObjectId>,
+        skip: u64,
+        limit: i64,
+    ) -> Result<Vec<ModerationAction>, RepositoryError>;
+}
@@ -0,0 +1,1571 @@
+use crate::{
+    models::{
+        api_token_model::{ApiToken, ApiTokenKind},
+        audit_event_model::AuditEvent,
+        chat_model::{Chat, ChatPin, LastMessage},
+        digest_preference_model::DigestPreference,
+        login_throttle_model::LoginThrottle,
+        message_model::Message,
+        moderation_action_model::ModerationAction,
+        notification_model::Notification,
+        notification_preference_model::NotificationPreference,
+        outbox_model::{OutboxEmail, OutboxStatus},
+        report_model::Report,
+        saved_message_model::SavedMessage,
+        session_model::StoredSession,
+        user_model::{OidcIdentity, User, UserRole},
+    },
+    repositories::{
+        api_token_repository::ApiTokenRepository,
+        audit_repository::{AuditFilter, AuditRepository},
+        chat_repository::ChatRepository,
+        digest_preference_repository::DigestPreferenceRepository,
+        message_repository::{MessageRange, MessageRepository},
+        moderation_action_repository::ModerationActionRepository,
+        notification_preference_repository::NotificationPreferenceRepository,
+        notification_repository::NotificationRepository,
+        outbox_repository::OutboxRepository,
+        report_repository::{ReportFilter, ReportRepository, ReportSelection},
+        repository::RepositoryError,
+        saved_message_repository::SavedMessageRepository,
+        session_repository::SessionRepository,
+        throttle_repository::ThrottleRepository,
+        user_repository::{UserFilter, UserRepository},
+    },
+    types::{moderation_types::ReportStatus, notification_types::NotificationType},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson, to_document, Bson, DateTime as BsonDateTime, Document},
+    options::{FindOneAndUpdateOptions, FindOneOptions, FindOptions, ReturnDocument, UpdateOptions},
+    Client, ClientSession, Collection, Database,
+};
+
+fn inserted_id(id: Bson) -> Result<ObjectId, RepositoryError> {
+    id.as_object_id()
+        .ok_or_else(|| RepositoryError::Database("inserted id is not an ObjectId".to_owned()))
+}
+
+fn object_ids(values: Vec<Bson>) -> Vec<ObjectId> {
+    values.into_iter().filter_map(|value| value.as_object_id()).collect()
+}
+
+pub struct MongoUserRepository {
+    users: Collection<User>,
+}
+
+impl MongoUserRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            users: db.collection::<User>(User::collection_name()),
+        }
+    }
+
+    fn filter(filter: &UserFilter) -> Document {
+        let mut document = Document::new();
+        if let Some(query) = filter.query.as_deref().map(str::trim).filter(|query| !query.is_empty()) {
+            let pattern = regex::escape(query);
+            document.insert(
+                "$or",
+                vec![
+                    doc! { "username": { "$regex": &pattern, "$options": "i" } },
+                    doc! { "email": { "$regex": &pattern, "$options": "i" } },
+                ],
+            );
+        }
+        if let Some(role) = filter.role {
+            // Users created before roles existed have no role field
+            document.insert(
+                "role",
+                match role {
+                    UserRole::User => doc! { "$in": [role.as_str(), null] },
+                    _ => doc! { "$eq": role.as_str() },
+                },
+            );
+        }
+        if let Some(suspended) = filter.suspended {
+            document.insert("suspended", if suspended { doc! { "$eq": true } } else { doc! { "$ne": true } });
+        }
+        document
+    }
+
+    async fn find_one(&self, filter: Document) -> Result<Option<User>, RepositoryError> {
+        Ok(self.users.find_one(filter, None).await?)
+    }
+
+    async fn update(&self, user_id: ObjectId, update: Document) -> Result<bool, RepositoryError> {
+        let result = self.users.update_one(doc! { "_id": &user_id }, update, None).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn find_one_and_update(&self, filter: Document, update: Document) -> Result<Option<User>, RepositoryError> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        Ok(self.users.find_one_and_update(filter, update, options).await?)
+    }
+}
+
+#[async_trait]
+impl UserRepository for MongoUserRepository {
+    async fn insert(&self, user: &User) -> Result<ObjectId, RepositoryError> {
+        inserted_id(self.users.insert_one(user, None).await?.inserted_id)
+    }
+
+    async fn find_by_id(&self, user_id: ObjectId) -> Result<Option<User>, RepositoryError> {
+        self.find_one(doc! { "_id": user_id }).await
+    }
+
+    async fn find_by_ids(&self, user_ids: &[ObjectId]) -> Result<Vec<User>, RepositoryError> {
+        let cursor = self.users.find(doc! { "_id": { "$in": user_ids } }, None).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, RepositoryError> {
+        self.find_one(doc! { "username": username }).await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepositoryError> {
+        self.find_one(doc! { "email": email }).await
+    }
+
+    async fn find_by_username_or_email(&self, username: &str, email: &str) -> Result<Option<User>, RepositoryError> {
+        self.find_one(doc! { "$or": [ { "username": username }, { "email": email } ] })
+            .await
+    }
+
+    async fn find_by_reset_token_hash(&self, token_hash: &str) -> Result<Option<User>, RepositoryError> {
+        self.find_one(doc! { "reset_token_hash": token_hash }).await
+    }
+
+    async fn find_by_oidc_identity(&self, identity: &OidcIdentity) -> Result<Option<User>, RepositoryError> {
+        self.find_one(doc! { "oidc_identities": to_bson(identity)? }).await
+    }
+
+    async fn find_users(&self, filter: &UserFilter, skip: u64, limit: Option<i64>) -> Result<Vec<User>, RepositoryError> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .skip(skip)
+            .limit(limit)
+            .build();
+        let cursor = self.users.find(Self::filter(filter), options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn count_users(&self, filter: &UserFilter) -> Result<u64, RepositoryError> {
+        Ok(self.users.count_documents(Self::filter(filter), None).await?)
+    }
+
+    async fn set_email_verified(&self, user_id: ObjectId) -> Result<bool, RepositoryError> {
+        self.update(user_id, doc! { "$set": { "email_verified": true } }).await
+    }
+
+    async fn set_reset_token(&self, user_id: ObjectId, token_hash: &str, expires_at_millis: i64) -> Result<(), RepositoryError> {
+        self.update(
+            user_id,
+            doc! {
+                "$set": { "reset_token_hash": token_hash, "reset_token_expiry_at": expires_at_millis },
+                "$unset": { "reset_token": "" },
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn complete_password_reset(
+        &self,
+        user_id: ObjectId,
+        token_hash: &str,
+        now_millis: i64,
+        password_hash: &str,
+        password_history: &[String],
+    ) -> Result<bool, RepositoryError> {
+        // Matching on the token hash and expiry makes the token single use even under concurrent requests.
+        let result = self
+            .users
+            .update_one(
+                doc! {
+                    "_id": &user_id,
+                    "reset_token_hash": token_hash,
+                    "reset_token_expiry_at": { "$gte": now_millis },
+                },
+                doc! {
+                    "$set": {
+                        "password_hash": password_hash,
+                        "password_history": password_history,
+                    },
+                    "$unset": { "reset_token_hash": "", "reset_token_expiry_at": "" },
+                },
+                None,
+            )
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn set_password_hash(&self, user_id: ObjectId, password_hash: &str) -> Result<bool, RepositoryError> {
+        self.update(user_id, doc! { "$set": { "password_hash": password_hash } }).await
+    }
+
+    async fn increment_session_version(&self, user_id: ObjectId) -> Result<(), RepositoryError> {
+        self.update(user_id, doc! { "$inc": { "session_version": 1 } }).await?;
+        Ok(())
+    }
+
+    async fn touch_last_seen(&self, user_id: ObjectId, at: DateTime<Utc>) -> Result<(), RepositoryError> {
+        self.update(user_id, doc! { "$set": { "last_seen_at": to_bson(&at)? } }).await?;
+        Ok(())
+    }
+
+    async fn set_totp_secret(&self, user_id: ObjectId, secret: &str) -> Result<(), RepositoryError> {
+        self.update(user_id, doc! { "$set": { "totp_secret": secret } }).await?;
+        Ok(())
+    }
+
+    async fn enable_totp(&self, user_id: ObjectId, step: i64, recovery_code_hashes: &[String]) -> Result<(), RepositoryError> {
+        self.update(
+            user_id,
+            doc! { "$set": {
+                "totp_enabled": true,
+                "totp_last_used_step": step,
+                "recovery_code_hashes": recovery_code_hashes,
+            } },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn disable_totp(&self, user_id: ObjectId) -> Result<(), RepositoryError> {
+        self.update(
+            user_id,
+            doc! {
+                "$set": { "totp_enabled": false },
+                "$unset": { "totp_secret": "", "totp_last_used_step": "", "recovery_code_hashes": "" },
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn consume_totp_step(&self, user_id: ObjectId, step: i64) -> Result<bool, RepositoryError> {
+        // The step filter makes concurrent requests with the same code race for a single use.
+        let result = self
+            .users
+            .update_one(
+                doc! {
+                    "_id": &user_id,
+                    "$or": [
+                        { "totp_last_used_step": { "$exists": false } },
+                        { "totp_last_used_step": { "$lt": step } },
+                    ],
+                },
+                doc! { "$set": { "totp_last_used_step": step } },
+                None,
+            )
+            .await?;
+        Ok(result.modified_count == 1)
+    }
+
+    async fn consume_recovery_code(&self, user_id: ObjectId, code_hash: &str) -> Result<bool, RepositoryError> {
+        let result = self
+            .users
+            .update_one(
+                doc! { "_id": &user_id, "recovery_code_hashes": code_hash },
+                doc! { "$pull": { "recovery_code_hashes": code_hash } },
+                None,
+            )
+            .await?;
+        Ok(result.modified_count == 1)
+    }
+
+    async fn link_oidc_identity_by_email(&self, email: &str, identity: &OidcIdentity) -> Result<Option<User>, RepositoryError> {
+        self.find_one_and_update(
+            doc! { "email": email, "email_verified": true },
+            doc! { "$addToSet": { "oidc_identities": to_bson(identity)? } },
+        )
+        .await
+    }
+
+    async fn sync_directory_email(&self, username: &str, email: &str) -> Result<Option<User>, RepositoryError> {
+        self.find_one_and_update(
+            doc! { "username": username, "directory_account": true },
+            doc! { "$set": { "email": email, "email_verified": true } },
+        )
+        .await
+    }
+
+    async fn set_role(&self, user_id: ObjectId, role: UserRole) -> Result<bool, RepositoryError> {
+        self.update(user_id, doc! { "$set": { "role": to_bson(&role)? } }).await
+    }
+
+    async fn set_suspended(&self, user_id: ObjectId, suspended: bool, reason: Option<&str>) -> Result<bool, RepositoryError> {
+        let update = if suspended {
+            doc! { "$set": { "suspended": true, "suspension_reason": reason } }
+        } else {
+            doc! { "$set": { "suspended": false }, "$unset": { "suspension_reason": "" } }
+        };
+        self.update(user_id, update).await
+    }
+}
+
+pub struct MongoChatRepository {
+    client: Client,
+    chats: Collection<Chat>,
+    messages: Collection<Message>,
+}
+
+impl MongoChatRepository {
+    /// `client` must be the one `db` was opened with; hard deletes run in a transaction on it.
+    pub fn new(client: &Client, db: &Database) -> Self {
+        Self {
+            client: client.clone(),
+            chats: db.collection::<Chat>(Chat::collection_name()),
+            messages: db.collection::<Message>(Message::collection_name()),
+        }
+    }
+
+    async fn delete_in_session(&self, session: &mut ClientSession, chat_id: ObjectId) -> Result<bool, RepositoryError> {
+        self.messages
+            .delete_many_with_session(doc! { "chat_id": &chat_id }, None, session)
+            .await?;
+        let result = self
+            .chats
+            .delete_one_with_session(doc! { "_id": &chat_id }, None, session)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+#[async_trait]
+impl ChatRepository for MongoChatRepository {
+    async fn insert(&self, chat: &Chat) -> Result<ObjectId, RepositoryError> {
+        inserted_id(self.chats.insert_one(chat, None).await?.inserted_id)
+    }
+
+    async fn find_by_id(&self, chat_id: ObjectId) -> Result<Option<Chat>, RepositoryError> {
+        Ok(self.chats.find_one(doc! { "_id": &chat_id }, None).await?)
+    }
+
+    async fn find_for_participant(&self, chat_id: ObjectId, user_id: ObjectId) -> Result<Option<Chat>, RepositoryError> {
+        Ok(self
+            .chats
+            .find_one(
+                doc! { "_id": &chat_id, "participant_ids": &user_id, "deleted_at": null },
+                None,
+            )
+            .await?)
+    }
+
+    async fn find_by_participants(&self, participant_ids: &[ObjectId]) -> Result<Option<Chat>, RepositoryError> {
+        Ok(self
+            .chats
+            .find_one(
+                doc! {
+                    "participant_ids": {
+                        "$all": participant_ids,
+                        "$size": participant_ids.len() as i32
+                    },
+                    "deleted_at": null
+                },
+                None,
+            )
+            .await?)
+    }
+
+    async fn find_for_user(&self, user_id: ObjectId) -> Result<Vec<Chat>, RepositoryError> {
+        let cursor = self
+            .chats
+            .find(doc! { "participant_ids": &user_id, "deleted_at": null }, None)
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn list_for_user(&self, user_id: ObjectId, skip: u64, limit: Option<i64>) -> Result<Vec<Chat>, RepositoryError> {
+        // Leaves out the chat when the user's hide still covers its newest message.
+        let hidden = doc! {
+            "$anyElementTrue": [{
+                "$map": {
+                    "input": { "$ifNull": ["$hidden_by", []] },
+                    "in": {
+                        "$and": [
+                            { "$eq": ["$$this.user_id", &user_id] },
+                            { "$gte": ["$$this.seq", { "$ifNull": ["$last_seq", 0_i64] }] }
+                        ]
+                    }
+                }
+            }]
+        };
+        let options = FindOptions::builder()
+            .sort(doc! { "last_activity_at": -1, "_id": -1 })
+            .skip(skip)
+            .limit(limit)
+            .build();
+        let cursor = self
+            .chats
+            .find(
+                doc! { "participant_ids": &user_id, "deleted_at": null, "$expr": { "$not": [hidden] } },
+                options,
+            )
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn next_seq(&self, chat_id: ObjectId) -> Result<Option<i64>, RepositoryError> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let chat = self
+            .chats
+            .find_one_and_update(
+                doc! { "_id": &chat_id, "deleted_at": null },
+                doc! { "$inc": { "last_seq": 1_i64 } },
+                options,
+            )
+            .await?;
+        Ok(chat.map(|chat| chat.last_seq))
+    }
+
+    async fn record_message(&self, chat_id: ObjectId, last_message: &LastMessage) -> Result<(), RepositoryError> {
+        self.chats
+            .update_one(
+                doc! {
+                    "_id": &chat_id,
+                    "$or": [
+                        { "last_message": null },
+                        { "last_message.seq": { "$lt": last_message.seq } }
+                    ]
+                },
+                doc! {
+                    "$set": { "last_message": to_bson(last_message)? },
+                    "$max": { "last_activity_at": BsonDateTime::from_chrono(last_message.created_at) }
+                },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn replace_last_message(&self, chat_id: ObjectId, last_message: Option<&LastMessage>) -> Result<(), RepositoryError> {
+        let update = match last_message {
+            Some(last_message) => doc! { "$set": { "last_message": to_bson(last_message)? } },
+            None => doc! { "$unset": { "last_message": "" } },
+        };
+        self.chats.update_one(doc! { "_id": &chat_id }, update, None).await?;
+        Ok(())
+    }
+
+    async fn pin(&self, chat_id: ObjectId, pin: &ChatPin, max_pins: usize) -> Result<bool, RepositoryError> {
+        let mut filter = doc! {
+            "_id": &chat_id,
+            "deleted_at": null,
+            "pins.message_id": { "$ne": &pin.message_id },
+        };
+        // The chat is full when the array has an element at index `max_pins - 1`.
+        filter.insert(format!("pins.{}", max_pins.saturating_sub(1)), doc! { "$exists": false });
+        let result = self
+            .chats
+            .update_one(filter, doc! { "$push": { "pins": to_bson(pin)? } }, None)
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn unpin(&self, chat_id: ObjectId, message_id: ObjectId) -> Result<bool, RepositoryError> {
+        let result = self
+            .chats
+            .update_one(
+                doc! { "_id": &chat_id, "pins.message_id": &message_id },
+                doc! { "$pull": { "pins": { "message_id": &message_id } } },
+                None,
+            )
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn hide(&self, chat_id: ObjectId, user_id: ObjectId, at: DateTime<Utc>) -> Result<Option<Chat>, RepositoryError> {
+        // A pipeline update drops the user's earlier hide, appends the new one and, once every
+        // participant hid the chat after its last message, marks it deleted, all in a single write.
+        let last_seq = doc! { "$ifNull": ["$last_seq", 0_i64] };
+        let update = vec![
+            doc! {
+                "$set": {
+                    "hidden_by": {
+                        "$concatArrays": [
+                            {
+                                "$filter": {
+                                    "input": { "$ifNull": ["$hidden_by", []] },
+                                    "cond": { "$ne": ["$$this.user_id", &user_id] }
+                                }
+                            },
+                            [{
+                                "user_id": &user_id,
+                                "hidden_at": to_bson(&at)?,
+                                "seq": last_seq.clone()
+                            }]
+                        ]
+                    }
+                }
+            },
+            doc! {
+                "$set": {
+                    "deleted_at": {
+                        "$cond": [
+                            {
+                                "$allElementsTrue": [{
+                                    "$map": {
+                                        "input": "$participant_ids",
+                                        "as": "participant",
+                                        "in": {
+                                            "$anyElementTrue": [{
+                                                "$map": {
+                                                    "input": "$hidden_by",
+                                                    "as": "hide",
+                                                    "in": {
+                                                        "$and": [
+                                                            { "$eq": ["$$hide.user_id", "$$participant"] },
+                                                            { "$gte": ["$$hide.seq", last_seq] }
+                                                        ]
+                                                    }
+                                                }
+                                            }]
+                                        }
+                                    }
+                                }]
+                            },
+                            to_bson(&at)?,
+                            "$$REMOVE"
+                        ]
+                    }
+                }
+            },
+        ];
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        Ok(self
+            .chats
+            .find_one_and_update(
+                doc! { "_id": &chat_id, "participant_ids": &user_id, "deleted_at": null },
+                update,
+                options,
+            )
+            .await?)
+    }
+
+    async fn find_deleted_before(&self, cutoff: DateTime<Utc>) -> Result<Vec<Chat>, RepositoryError> {
+        let cursor = self
+            .chats
+            .find(doc! { "deleted_at": { "$lte": to_bson(&cutoff)? } }, None)
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    /// Needs a replica set: standalone servers do not support transactions.
+    async fn delete(&self, chat_id: ObjectId) -> Result<bool, RepositoryError> {
+        let mut session = self.client.start_session(None).await?;
+        session.start_transaction(None).await?;
+        match self.delete_in_session(&mut session, chat_id).await {
+            Ok(deleted) => {
+                session.commit_transaction().await?;
+                Ok(deleted)
+            }
+            Err(e) => {
+                if let Err(abort_error) = session.abort_transaction().await {
+                    log::warn!("Failed to abort chat deletion: {}", abort_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn count(&self) -> Result<u64, RepositoryError> {
+        Ok(self.chats.estimated_document_count(None).await?)
+    }
+}
+
+pub struct MongoMessageRepository {
+    messages: Collection<Message>,
+}
+
+impl MongoMessageRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            messages: db.collection::<Message>(Message::collection_name()),
+        }
+    }
+
+    fn since_filter(chat_ids: &[ObjectId], excluded_sender: ObjectId, since: DateTime<Utc>) -> Result<Document, RepositoryError> {
+        Ok(doc! {
+            "chat_id": { "$in": chat_ids },
+            "sender_id": { "$ne": &excluded_sender },
+            "created_at": { "$gt": to_bson(&since)? },
+        })
+    }
+}
+
+#[async_trait]
+impl MessageRepository for MongoMessageRepository {
+    async fn insert(&self, message: &Message) -> Result<ObjectId, RepositoryError> {
+        inserted_id(self.messages.insert_one(message, None).await?.inserted_id)
+    }
+
+    async fn find_by_id(&self, message_id: ObjectId) -> Result<Option<Message>, RepositoryError> {
+        Ok(self.messages.find_one(doc! { "_id": &message_id }, None).await?)
+    }
+
+    async fn find_by_ids(&self, message_ids: &[ObjectId]) -> Result<Vec<Message>, RepositoryError> {
+        let cursor = self.messages.find(doc! { "_id": { "$in": message_ids } }, None).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn find_by_client_msg_id(&self, sender_id: ObjectId, client_msg_id: &str) -> Result<Option<Message>, RepositoryError> {
+        Ok(self
+            .messages
+            .find_one(doc! { "sender_id": &sender_id, "client_msg_id": client_msg_id }, None)
+            .await?)
+    }
+
+    async fn find_by_chat(&self, chat_id: ObjectId, range: &MessageRange) -> Result<Vec<Message>, RepositoryError> {
+        let mut seq = Document::new();
+        if let Some(lower_bound) = range.lower_bound() {
+            seq.insert("$gt", lower_bound);
+        }
+        if let Some(before_seq) = range.before_seq {
+            seq.insert("$lt", before_seq);
+        }
+        let mut filter = doc! { "chat_id": &chat_id };
+        if !seq.is_empty() {
+            filter.insert("seq", seq);
+        }
+        let direction = if range.newest_first() { -1 } else { 1 };
+        let options = FindOptions::builder()
+            .sort(doc! { "seq": direction, "_id": direction })
+            .limit(range.limit)
+            .build();
+        let mut messages: Vec<Message> = self.messages.find(filter, options).await?.try_collect().await?;
+        if range.newest_first() {
+            messages.reverse();
+        }
+        Ok(messages)
+    }
+
+    async fn find_last(&self, chat_id: ObjectId) -> Result<Option<Message>, RepositoryError> {
+        let options = FindOneOptions::builder().sort(doc! { "seq": -1, "_id": -1 }).build();
+        Ok(self.messages.find_one(doc! { "chat_id": &chat_id }, options).await?)
+    }
+
+    async fn delete(&self, message_id: ObjectId) -> Result<Option<Message>, RepositoryError> {
+        Ok(self
+            .messages
+            .find_one_and_delete(doc! { "_id": &message_id }, None)
+            .await?)
+    }
+
+    async fn count(&self) -> Result<u64, RepositoryError> {
+        Ok(self.messages.estimated_document_count(None).await?)
+    }
+
+    async fn count_since(
+        &self,
+        chat_ids: &[ObjectId],
+        excluded_sender: ObjectId,
+        since: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let filter = Self::since_filter(chat_ids, excluded_sender, since)?;
+        Ok(self.messages.count_documents(filter, None).await?)
+    }
+
+    async fn senders_since(
+        &self,
+        chat_ids: &[ObjectId],
+        excluded_sender: ObjectId,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<ObjectId>, RepositoryError> {
+        let filter = Self::since_filter(chat_ids, excluded_sender, since)?;
+        Ok(object_ids(self.messages.distinct("sender_id", filter, None).await?))
+    }
+}
+
+pub struct MongoNotificationRepository {
+    notifications: Collection<Notification>,
+}
+
+impl MongoNotificationRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            notifications: db.collection::<Notification>(Notification::collection_name()),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationRepository for MongoNotificationRepository {
+    async fn insert(&self, notification: &Notification) -> Result<ObjectId, RepositoryError> {
+        inserted_id(self.notifications.insert_one(notification, None).await?.inserted_id)
+    }
+
+    async fn find_unhandled(&self, recipient_id: ObjectId) -> Result<Vec<Notification>, RepositoryError> {
+        let cursor = self
+            .notifications
+            .find(doc! { "recipient_id": &recipient_id, "is_handled": false }, None)
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn find_recent(
+        &self,
+        recipient_id: ObjectId,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<Notification>, RepositoryError> {
+        let mut filter = doc! { "recipient_id": &recipient_id };
+        if unread_only {
+            filter.insert("is_read", doc! { "$ne": true });
+        }
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .build();
+        let cursor = self.notifications.find(filter, options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn count_unread(&self, recipient_id: ObjectId) -> Result<u64, RepositoryError> {
+        Ok(self
+            .notifications
+            .count_documents(doc! { "recipient_id": &recipient_id, "is_read": { "$ne": true } }, None)
+            .await?)
+    }
+
+    async fn mark_read(
+        &self,
+        recipient_id: ObjectId,
+        ids: Option<&[ObjectId]>,
+        at: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let mut filter = doc! { "recipient_id": &recipient_id, "is_read": { "$ne": true } };
+        if let Some(ids) = ids {
+            filter.insert("_id", doc! { "$in": ids });
+        }
+        let result = self
+            .notifications
+            .update_many(filter, doc! { "$set": { "is_read": true, "read_at": to_bson(&at)? } }, None)
+            .await?;
+        Ok(result.modified_count)
+    }
+
+    async fn delete(&self, notification_id: ObjectId, recipient_id: ObjectId) -> Result<bool, RepositoryError> {
+        let result = self
+            .notifications
+            .delete_one(doc! { "_id": notification_id, "recipient_id": recipient_id }, None)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn mark_handled(
+        &self,
+        notification_id: ObjectId,
+        recipient_id: ObjectId,
+    ) -> Result<Option<Notification>, RepositoryError> {
+        Ok(self
+            .notifications
+            .find_one_and_update(
+                doc! { "_id": &notification_id, "recipient_id": &recipient_id },
+                doc! { "$set": { "is_handled": true, "is_read": true } },
+                None,
+            )
+            .await?)
+    }
+
+    async fn count_unhandled_since(
+        &self,
+        recipient_id: ObjectId,
+        notification_types: &[NotificationType],
+        since: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let notification_types: Vec<&str> = notification_types.iter().map(NotificationType::as_str).collect();
+        Ok(self
+            .notifications
+            .count_documents(
+                doc! {
+                    "recipient_id": &recipient_id,
+                    "notification_type": { "$in": notification_types },
+                    "is_handled": false,
+                    "created_at": { "$gt": to_bson(&since)? },
+                },
+                None,
+            )
+            .await?)
+    }
+}
+
+pub struct MongoSavedMessageRepository {
+    saved_messages: Collection<SavedMessage>,
+}
+
+impl MongoSavedMessageRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            saved_messages: db.collection::<SavedMessage>(SavedMessage::collection_name()),
+        }
+    }
+}
+
+#[async_trait]
+impl SavedMessageRepository for MongoSavedMessageRepository {
+    async fn upsert(&self, saved: &SavedMessage) -> Result<SavedMessage, RepositoryError> {
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        self.saved_messages
+            .find_one_and_update(
+                doc! { "user_id": &saved.user_id, "message_id": &saved.message_id },
+                doc! {
+                    "$set": { "note": &saved.note, "tags": &saved.tags },
+                    "$setOnInsert": { "chat_id": &saved.chat_id, "created_at": to_bson(&saved.created_at)? },
+                },
+                options,
+            )
+            .await?
+            .ok_or_else(|| RepositoryError::Database("upsert returned no document".to_owned()))
+    }
+
+    async fn find_for_user(
+        &self,
+        user_id: ObjectId,
+        tag: Option<&str>,
+        skip: u64,
+        limit: Option<i64>,
+    ) -> Result<Vec<SavedMessage>, RepositoryError> {
+        let mut filter = doc! { "user_id": &user_id };
+        if let Some(tag) = tag {
+            filter.insert("tags", tag);
+        }
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .skip(skip)
+            .limit(limit)
+            .build();
+        let cursor = self.saved_messages.find(filter, options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn delete(&self, user_id: ObjectId, message_id: ObjectId) -> Result<bool, RepositoryError> {
+        let result = self
+            .saved_messages
+            .delete_one(doc! { "user_id": &user_id, "message_id": &message_id }, None)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn delete_by_message(&self, message_id: ObjectId) -> Result<u64, RepositoryError> {
+        let result = self
+            .saved_messages
+            .delete_many(doc! { "message_id": &message_id }, None)
+            .await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn delete_by_chat(&self, chat_id: ObjectId, user_id: Option<ObjectId>) -> Result<u64, RepositoryError> {
+        let mut filter = doc! { "chat_id": &chat_id };
+        if let Some(user_id) = user_id {
+            filter.insert("user_id", user_id);
+        }
+        let result = self.saved_messages.delete_many(filter, None).await?;
+        Ok(result.deleted_count)
+    }
+}
+
+pub struct MongoSessionRepository {
+    sessions: Collection<StoredSession>,
+}
+
+impl MongoSessionRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            sessions: db.collection::<StoredSession>(StoredSession::collection_name()),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionRepository for MongoSessionRepository {
+    async fn insert(&self, session: &StoredSession) -> Result<(), RepositoryError> {
+        self.sessions.insert_one(session, None).await?;
+        Ok(())
+    }
+
+    async fn find_by_key_hash(&self, key_hash: &str, now: DateTime<Utc>) -> Result<Option<StoredSession>, RepositoryError> {
+        Ok(self
+            .sessions
+            .find_one(
+                doc! { "key_hash": key_hash, "expires_at": { "$gt": BsonDateTime::from_chrono(now) } },
+                None,
+            )
+            .await?)
+    }
+
+    async fn update_state(&self, session: &StoredSession) -> Result<bool, RepositoryError> {
+        let state: Document = session
+            .state
+            .iter()
+            .map(|(key, value)| (key.clone(), Bson::String(value.clone())))
+            .collect();
+        let result = self
+            .sessions
+            .update_one(
+                doc! { "key_hash": &session.key_hash },
+                doc! { "$set": {
+                    "state": state,
+                    "user_id": session.user_id.map_or(Bson::Null, Bson::ObjectId),
+                    "session_id": &session.session_id,
+                    "user_agent": &session.user_agent,
+                    "ip": &session.ip,
+                    "last_seen_at": to_bson(&session.last_seen_at)?,
+                    "expires_at": BsonDateTime::from_chrono(session.expires_at),
+                } },
+                None,
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn touch(
+        &self,
+        key_hash: &str,
+        last_seen_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        self.sessions
+            .update_one(
+                doc! { "key_hash": key_hash },
+                doc! { "$set": {
+                    "last_seen_at": to_bson(&last_seen_at)?,
+                    "expires_at": BsonDateTime::from_chrono(expires_at),
+                } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_by_key_hash(&self, key_hash: &str) -> Result<(), RepositoryError> {
+        self.sessions.delete_one(doc! { "key_hash": key_hash }, None).await?;
+        Ok(())
+    }
+
+    async fn find_for_user(&self, user_id: ObjectId, now: DateTime<Utc>) -> Result<Vec<StoredSession>, RepositoryError> {
+        let cursor = self
+            .sessions
+            .find(
+                doc! { "user_id": &user_id, "expires_at": { "$gt": BsonDateTime::from_chrono(now) } },
+                FindOptions::builder().sort(doc! { "last_seen_at": -1 }).build(),
+            )
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn delete_for_user(&self, user_id: ObjectId, session_id: &str) -> Result<bool, RepositoryError> {
+        let result = self
+            .sessions
+            .delete_one(doc! { "user_id": &user_id, "session_id": session_id }, None)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn delete_all_for_user(&self, user_id: ObjectId) -> Result<u64, RepositoryError> {
+        Ok(self.sessions.delete_many(doc! { "user_id": &user_id }, None).await?.deleted_count)
+    }
+
+    async fn count_signed_in(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        Ok(self
+            .sessions
+            .count_documents(
+                doc! { "user_id": { "$ne": null }, "expires_at": { "$gt": BsonDateTime::from_chrono(now) } },
+                None,
+            )
+            .await?)
+    }
+
+    async fn delete_expired(&self, _now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        // The TTL index on `expires_at` removes them.
+        Ok(0)
+    }
+}
+
+pub struct MongoApiTokenRepository {
+    tokens: Collection<ApiToken>,
+}
+
+impl MongoApiTokenRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            tokens: db.collection::<ApiToken>(ApiToken::collection_name()),
+        }
+    }
+}
+
+#[async_trait]
+impl ApiTokenRepository for MongoApiTokenRepository {
+    async fn insert(&self, token: &ApiToken) -> Result<ObjectId, RepositoryError> {
+        inserted_id(self.tokens.insert_one(token, None).await?.inserted_id)
+    }
+
+    async fn record_use(&self, token_hash: &str, at: DateTime<Utc>) -> Result<Option<ApiToken>, RepositoryError> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        Ok(self
+            .tokens
+            .find_one_and_update(
+                doc! { "token_hash": token_hash, "kind": { "$ne": to_bson(&ApiTokenKind::Refresh)? } },
+                doc! { "$set": { "last_used_at": to_bson(&at)? } },
+                options,
+            )
+            .await?)
+    }
+
+    async fn take_refresh_token(&self, token_hash: &str) -> Result<Option<ApiToken>, RepositoryError> {
+        Ok(self
+            .tokens
+            .find_one_and_delete(
+                doc! { "token_hash": token_hash, "kind": to_bson(&ApiTokenKind::Refresh)? },
+                None,
+            )
+            .await?)
+    }
+
+    async fn delete(&self, token_id: ObjectId) -> Result<bool, RepositoryError> {
+        Ok(self.tokens.delete_one(doc! { "_id": &token_id }, None).await?.deleted_count > 0)
+    }
+
+    async fn find_personal(&self, user_id: ObjectId) -> Result<Vec<ApiToken>, RepositoryError> {
+        let cursor = self
+            .tokens
+            .find(
+                doc! { "user_id": &user_id, "kind": to_bson(&ApiTokenKind::Personal)? },
+                FindOptions::builder().sort(doc! { "created_at": -1 }).build(),
+            )
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn delete_personal(&self, user_id: ObjectId, token_id: ObjectId) -> Result<bool, RepositoryError> {
+        let result = self
+            .tokens
+            .delete_one(
+                doc! { "_id": &token_id, "user_id": &user_id, "kind": to_bson(&ApiTokenKind::Personal)? },
+                None,
+            )
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn delete_for_user(&self, user_id: ObjectId) -> Result<u64, RepositoryError> {
+        Ok(self.tokens.delete_many(doc! { "user_id": &user_id }, None).await?.deleted_count)
+    }
+
+    async fn delete_expired(&self, _now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        // The TTL index on `expires_at` removes them.
+        Ok(0)
+    }
+}
+
+pub struct MongoThrottleRepository {
+    throttles: Collection<LoginThrottle>,
+}
+
+impl MongoThrottleRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            throttles: db.collection::<LoginThrottle>(LoginThrottle::collection_name()),
+        }
+    }
+}
+
+#[async_trait]
+impl ThrottleRepository for MongoThrottleRepository {
+    async fn find(&self, key: &str, now: DateTime<Utc>) -> Result<Option<LoginThrottle>, RepositoryError> {
+        Ok(self
+            .throttles
+            .find_one(
+                doc! { "key": key, "expires_at": { "$gt": BsonDateTime::from_chrono(now) } },
+                None,
+            )
+            .await?)
+    }
+
+    async fn increment(
+        &self,
+        key: &str,
+        at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<LoginThrottle, RepositoryError> {
+        // The TTL monitor only runs once a minute, so an expired record may still be there;
+        // count it as gone rather than adding to its old failures.
+        let live = doc! { "$gt": ["$expires_at", BsonDateTime::from_chrono(at)] };
+        let update = vec![doc! { "$set": {
+            "failures": { "$cond": [&live, { "$add": [{ "$ifNull": ["$failures", 0] }, 1] }, 1] },
+            "locked_until": { "$cond": [&live, "$locked_until", "$$REMOVE"] },
+            "last_failure_at": to_bson(&at)?,
+            "expires_at": BsonDateTime::from_chrono(expires_at),
+        } }];
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        self.throttles
+            .find_one_and_update(doc! { "key": key }, update, options)
+            .await?
+            .ok_or_else(|| RepositoryError::Database("upsert returned no throttle".to_owned()))
+    }
+
+    async fn lock(
+        &self,
+        key: &str,
+        locked_until: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        self.throttles
+            .update_one(
+                doc! { "key": key },
+                doc! { "$set": {
+                    "failures": 0,
+                    "locked_until": to_bson(&locked_until)?,
+                    "expires_at": BsonDateTime::from_chrono(expires_at),
+                } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), RepositoryError> {
+        self.throttles.delete_one(doc! { "key": key }, None).await?;
+        Ok(())
+    }
+
+    async fn delete_with_prefix(&self, prefix: &str) -> Result<(), RepositoryError> {
+        let pattern = format!("^{}", regex::escape(prefix));
+        self.throttles
+            .delete_many(doc! { "key": { "$regex": pattern } }, None)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_expired(&self, _now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        // The TTL index on `expires_at` removes them.
+        Ok(0)
+    }
+}
+
+pub struct MongoAuditRepository {
+    events: Collection<AuditEvent>,
+}
+
+impl MongoAuditRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            events: db.collection::<AuditEvent>(AuditEvent::collection_name()),
+        }
+    }
+
+    fn filter(filter: &AuditFilter) -> Result<Document, RepositoryError> {
+        let mut document = Document::new();
+        if let Some(actor_id) = filter.actor_id {
+            document.insert("actor_id", actor_id);
+        }
+        if let Some(actor_name) = &filter.actor_name {
+            document.insert("actor_name", actor_name);
+        }
+        if let Some(action) = filter.action {
+            document.insert("action", to_bson(&action)?);
+        }
+        if let Some(target_id) = filter.target_id {
+            document.insert("target_id", target_id);
+        }
+        if let Some(ip) = &filter.ip {
+            document.insert("ip", ip);
+        }
+        if let Some(outcome) = filter.outcome {
+            document.insert("outcome", to_bson(&outcome)?);
+        }
+        let mut created_at = Document::new();
+        if let Some(from) = filter.from {
+            created_at.insert("$gte", to_bson(&from)?);
+        }
+        if let Some(to) = filter.to {
+            created_at.insert("$lt", to_bson(&to)?);
+        }
+        if !created_at.is_empty() {
+            document.insert("created_at", created_at);
+        }
+        Ok(document)
+    }
+}
+
+#[async_trait]
+impl AuditRepository for MongoAuditRepository {
+    async fn insert(&self, event: &AuditEvent) -> Result<ObjectId, RepositoryError> {
+        inserted_id(self.events.insert_one(event, None).await?.inserted_id)
+    }
+
+    async fn find(
+        &self,
+        filter: &AuditFilter,
+        oldest_first: bool,
+        skip: u64,
+        limit: i64,
+    ) -> Result<Vec<AuditEvent>, RepositoryError> {
+        let direction = if oldest_first { 1 } else { -1 };
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": direction, "_id": direction })
+            .skip(skip)
+            .limit(limit)
+            .build();
+        let cursor = self.events.find(Self::filter(filter)?, options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn count(&self, filter: &AuditFilter) -> Result<u64, RepositoryError> {
+        Ok(self.events.count_documents(Self::filter(filter)?, None).await?)
+    }
+}
+
+pub struct MongoOutboxRepository {
+    outbox: Collection<OutboxEmail>,
+}
+
+impl MongoOutboxRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            outbox: db.collection::<OutboxEmail>(OutboxEmail::collection_name()),
+        }
+    }
+
+    async fn update(&self, id: ObjectId, update: Document) -> Result<(), RepositoryError> {
+        self.outbox.update_one(doc! { "_id": &id }, update, None).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OutboxRepository for MongoOutboxRepository {
+    async fn insert(&self, email: &OutboxEmail) -> Result<ObjectId, RepositoryError> {
+        inserted_id(self.outbox.insert_one(email, None).await?.inserted_id)
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> Result<Option<OutboxEmail>, RepositoryError> {
+        Ok(self.outbox.find_one(doc! { "_id": &id }, None).await?)
+    }
+
+    async fn find_due(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<OutboxEmail>, RepositoryError> {
+        let options = FindOptions::builder()
+            .sort(doc! { "next_attempt_at": 1 })
+            .limit(limit)
+            .build();
+        let cursor = self
+            .outbox
+            .find(
+                doc! {
+                    "status": to_bson(&OutboxStatus::Pending)?,
+                    "next_attempt_at": { "$lte": to_bson(&now)? },
+                },
+                options,
+            )
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn record_sent(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        sent_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        self.update(
+            id,
+            doc! {
+                "$set": {
+                    "status": to_bson(&OutboxStatus::Sent)?,
+                    "attempts": attempts,
+                    "sent_at": to_bson(&sent_at)?,
+                    "email.text_body": "",
+                    "email.html_body": "",
+                    "expires_at": BsonDateTime::from_chrono(expires_at),
+                },
+                "$unset": { "last_error": "" },
+            },
+        )
+        .await
+    }
+
+    async fn record_failure(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        self.update(
+            id,
+            doc! { "$set": {
+                "status": to_bson(&OutboxStatus::Pending)?,
+                "attempts": attempts,
+                "last_error": error,
+                "next_attempt_at": to_bson(&next_attempt_at)?,
+            } },
+        )
+        .await
+    }
+
+    async fn give_up(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        error: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        self.update(
+            id,
+            doc! { "$set": {
+                "status": to_bson(&OutboxStatus::Failed)?,
+                "attempts": attempts,
+                "last_error": error,
+                "email.text_body": "",
+                "email.html_body": "",
+                "expires_at": BsonDateTime::from_chrono(expires_at),
+            } },
+        )
+        .await
+    }
+
+    async fn delete_expired(&self, _now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        // The TTL index on `expires_at` removes them.
+        Ok(0)
+    }
+}
+
+pub struct MongoNotificationPreferenceRepository {
+    preferences: Collection<NotificationPreference>,
+}
+
+impl MongoNotificationPreferenceRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            preferences: db.collection::<NotificationPreference>(NotificationPreference::collection_name()),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationPreferenceRepository for MongoNotificationPreferenceRepository {
+    async fn find(
+        &self,
+        user_id: ObjectId,
+        notification_type: NotificationType,
+    ) -> Result<Option<NotificationPreference>, RepositoryError> {
+        Ok(self
+            .preferences
+            .find_one(
+                doc! { "user_id": &user_id, "notification_type": notification_type.as_str() },
+                None,
+            )
+            .await?)
+    }
+
+    async fn find_for_user(&self, user_id: ObjectId) -> Result<Vec<NotificationPreference>, RepositoryError> {
+        let cursor = self.preferences.find(doc! { "user_id": &user_id }, None).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn upsert(&self, preference: &NotificationPreference) -> Result<(), RepositoryError> {
+        let mut fields = to_document(preference)?;
+        fields.remove("_id");
+        self.preferences
+            .update_one(
+                doc! {
+                    "user_id": &preference.user_id,
+                    "notification_type": preference.notification_type.as_str(),
+                },
+                doc! { "$set": fields },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+pub struct MongoDigestPreferenceRepository {
+    preferences: Collection<DigestPreference>,
+}
+
+impl MongoDigestPreferenceRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            preferences: db.collection::<DigestPreference>(DigestPreference::collection_name()),
+        }
+    }
+}
+
+#[async_trait]
+impl DigestPreferenceRepository for MongoDigestPreferenceRepository {
+    async fn find(&self, user_id: ObjectId) -> Result<Option<DigestPreference>, RepositoryError> {
+        Ok(self.preferences.find_one(doc! { "user_id": &user_id }, None).await?)
+    }
+
+    async fn find_for_users(&self, user_ids: &[ObjectId]) -> Result<Vec<DigestPreference>, RepositoryError> {
+        let cursor = self.preferences.find(doc! { "user_id": { "$in": user_ids } }, None).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn upsert(&self, preference: &DigestPreference) -> Result<(), RepositoryError> {
+        let mut fields = to_document(preference)?;
+        fields.remove("_id");
+        fields.remove("last_sent_at");
+        let mut update = doc! { "$set": fields };
+        // Clearing quiet hours has to remove the stored values as well.
+        if preference.quiet_hours_start.is_none() || preference.quiet_hours_end.is_none() {
+            update.insert("$unset", doc! { "quiet_hours_start": "", "quiet_hours_end": "" });
+        }
+        self.preferences
+            .update_one(
+                doc! { "user_id": &preference.user_id },
+                update,
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn record_sent(&self, user_id: ObjectId, at: DateTime<Utc>) -> Result<(), RepositoryError> {
+        let default_preference = DigestPreference::default_for(user_id);
+        self.preferences
+            .update_one(
+                doc! { "user_id": &user_id },
+                doc! {
+                    "$set": { "last_sent_at": to_bson(&at)? },
+                    "$setOnInsert": {
+                        "frequency": to_bson(&default_preference.frequency)?,
+                        "utc_offset_minutes": default_preference.utc_offset_minutes,
+                    },
+                },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+pub struct MongoReportRepository {
+    reports: Collection<Report>,
+}
+
+impl MongoReportRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            reports: db.collection::<Report>(Report::collection_name()),
+        }
+    }
+
+    fn filter(filter: &ReportFilter) -> Result<Document, RepositoryError> {
+        let mut document = doc! { "status": to_bson(&filter.status)? };
+        if let Some(reason) = filter.reason {
+            document.insert("reason", to_bson(&reason)?);
+        }
+        Ok(document)
+    }
+}
+
+#[async_trait]
+impl ReportRepository for MongoReportRepository {
+    async fn insert(&self, report: &Report) -> Result<ObjectId, RepositoryError> {
+        inserted_id(self.reports.insert_one(report, None).await?.inserted_id)
+    }
+
+    async fn find_by_id(&self, report_id: ObjectId) -> Result<Option<Report>, RepositoryError> {
+        Ok(self.reports.find_one(doc! { "_id": &report_id }, None).await?)
+    }
+
+    async fn has_open_duplicate(&self, report: &Report) -> Result<bool, RepositoryError> {
+        let filter = doc! {
+            "reporter_id": &report.reporter_id,
+            "reported_user_id": &report.reported_user_id,
+            "status": to_bson(&ReportStatus::Open)?,
+            "message_id": report.message_id.map_or(doc! { "$exists": false }, |id| doc! { "$eq": id }),
+        };
+        Ok(self.reports.find_one(filter, None).await?.is_some())
+    }
+
+    async fn find(&self, filter: &ReportFilter, skip: u64, limit: i64) -> Result<Vec<Report>, RepositoryError> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": 1, "_id": 1 })
+            .skip(skip)
+            .limit(limit)
+            .build();
+        let cursor = self.reports.find(Self::filter(filter)?, options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn count(&self, filter: &ReportFilter) -> Result<u64, RepositoryError> {
+        Ok(self.reports.count_documents(Self::filter(filter)?, None).await?)
+    }
+
+    async fn close(
+        &self,
+        selection: ReportSelection,
+        status: ReportStatus,
+        handled_by: ObjectId,
+        at: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let mut filter = match selection {
+            ReportSelection::Report(report_id) => doc! { "_id": report_id },
+            ReportSelection::Message(message_id) => doc! { "message_id": message_id },
+            ReportSelection::ReportedUser(user_id) => doc! { "reported_user_id": user_id },
+        };
+        filter.insert("status", to_bson(&ReportStatus::Open)?);
+        let update = doc! { "$set": {
+            "status": to_bson(&status)?,
+            "handled_by": &handled_by,
+            "handled_at": to_bson(&at)?,
+        } };
+        Ok(self.reports.update_many(filter, update, None).await?.modified_count)
+    }
+}
+
+pub struct MongoModerationActionRepository {
+    actions: Collection<ModerationAction>,
+}
+
+impl MongoModerationActionRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            actions: db.collection::<ModerationAction>(ModerationAction::collection_name()),
+        }
+    }
+}
+
+#[async_trait]
+impl ModerationActionRepository for MongoModerationActionRepository {
+    async fn insert(&self, action: &ModerationAction) -> Result<ObjectId, RepositoryError> {
+        inserted_id(self.actions.insert_one(action, None).await?.inserted_id)
+    }
+
+    async fn find(
+        &self,
+        target_user_id: Option<ObjectId>,
+        skip: u64,
+        limit: i64,
+    ) -> Result<Vec<ModerationAction>, RepositoryError> {
+        let filter = target_user_id.map_or_else(Document::new, |user_id| doc! { "target_user_id": user_id });
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .skip(skip)
+            .limit(limit)
+            .build();
+        let cursor = self.actions.find(filter, options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+}
@@ -0,0 +1,19 @@
+use crate::{
+    models::notification_preference_model::NotificationPreference, repositories::repository::RepositoryError,
+    types::notification_types::NotificationType,
+};
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+
+/// Per-type notification settings. Users without a stored preference get the defaults.
+#[async_trait]
+pub trait NotificationPreferenceRepository: Send + Sync {
+    async fn find(
+        &self,
+        user_id: ObjectId,
+        notification_type: NotificationType,
+    ) -> Result<Option<NotificationPreference>, RepositoryError>;
+    async fn find_for_user(&self, user_id: ObjectId) -> Result<Vec<NotificationPreference>, RepositoryError>;
+    /// Create or replace the user's preference for the type.
+    async fn upsert(&self, preference: &NotificationPreference) -> Result<(), RepositoryError>;
+}
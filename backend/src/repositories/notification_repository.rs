@@ -0,0 +1,45 @@
+use crate::{
+    models::notification_model::Notification, repositories::repository::RepositoryError,
+    types::notification_types::NotificationType,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+#[async_trait]
+pub trait NotificationRepository: Send + Sync {
+    async fn insert(&self, notification: &Notification) -> Result<ObjectId, RepositoryError>;
+    /// Notifications still awaiting a response, oldest first.
+    async fn find_unhandled(&self, recipient_id: ObjectId) -> Result<Vec<Notification>, RepositoryError>;
+    /// The newest notifications, optionally only unread ones.
+    async fn find_recent(
+        &self,
+        recipient_id: ObjectId,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<Notification>, RepositoryError>;
+    async fn count_unread(&self, recipient_id: ObjectId) -> Result<u64, RepositoryError>;
+    /// Mark the given unread notifications, or all of them when `ids` is `None`, as read.
+    /// Returns how many changed.
+    async fn mark_read(
+        &self,
+        recipient_id: ObjectId,
+        ids: Option<&[ObjectId]>,
+        at: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError>;
+    /// Returns false when the recipient has no such notification.
+    async fn delete(&self, notification_id: ObjectId, recipient_id: ObjectId) -> Result<bool, RepositoryError>;
+    /// Mark a notification handled and read, returning it as it was before.
+    async fn mark_handled(
+        &self,
+        notification_id: ObjectId,
+        recipient_id: ObjectId,
+    ) -> Result<Option<Notification>, RepositoryError>;
+    /// Unhandled notifications of these types created after `since`.
+    async fn count_unhandled_since(
+        &self,
+        recipient_id: ObjectId,
+        notification_types: &[NotificationType],
+        since: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError>;
+}
@@ -0,0 +1,39 @@
+use crate::{models::outbox_model::OutboxEmail, repositories::repository::RepositoryError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+/// The persisted email outbox. Recording a send, or giving up on one, blanks the bodies,
+/// since they can hold live tokens, and sets the time the email is removed at.
+#[async_trait]
+pub trait OutboxRepository: Send + Sync {
+    async fn insert(&self, email: &OutboxEmail) -> Result<ObjectId, RepositoryError>;
+    async fn find_by_id(&self, id: ObjectId) -> Result<Option<OutboxEmail>, RepositoryError>;
+    /// Pending emails whose next attempt is due, soonest first.
+    async fn find_due(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<OutboxEmail>, RepositoryError>;
+    async fn record_sent(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        sent_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError>;
+    /// Keep the email pending for another attempt at `next_attempt_at`.
+    async fn record_failure(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError>;
+    /// Mark the email as failed for good.
+    async fn give_up(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        error: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError>;
+    /// Removes expired emails. Returns how many were removed.
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError>;
+}
@@ -0,0 +1,2165 @@
+use crate::{
+    mail::mailer::EmailMessage,
+    models::{
+        api_token_model::{ApiToken, ApiTokenKind},
+        audit_event_model::AuditEvent,
+        chat_model::{Chat, ChatHide, ChatPin, LastMessage},
+        digest_preference_model::DigestPreference,
+        login_throttle_model::LoginThrottle,
+        message_model::Message,
+        moderation_action_model::ModerationAction,
+        notification_model::Notification,
+        notification_preference_model::NotificationPreference,
+        outbox_model::{OutboxEmail, OutboxStatus},
+        report_model::Report,
+        saved_message_model::SavedMessage,
+        session_model::StoredSession,
+        user_model::{OidcIdentity, User, UserRole},
+    },
+    repositories::{
+        api_token_repository::ApiTokenRepository,
+        audit_repository::{AuditFilter, AuditRepository},
+        chat_repository::ChatRepository,
+        digest_preference_repository::DigestPreferenceRepository,
+        message_repository::{MessageRange, MessageRepository},
+        moderation_action_repository::ModerationActionRepository,
+        notification_preference_repository::NotificationPreferenceRepository,
+        notification_repository::NotificationRepository,
+        outbox_repository::OutboxRepository,
+        report_repository::{ReportFilter, ReportRepository, ReportSelection},
+        repository::RepositoryError,
+        saved_message_repository::SavedMessageRepository,
+        session_repository::SessionRepository,
+        throttle_repository::ThrottleRepository,
+        user_repository::{UserFilter, UserRepository},
+    },
+    types::{moderation_types::ReportStatus, notification_types::NotificationType},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{
+    postgres::{PgArguments, PgRow},
+    query::Query,
+    PgPool, Postgres, QueryBuilder, Row,
+};
+
+// Ids are stored as ObjectId hex strings so records keep the same ids as under MongoDB.
+
+fn object_id(value: &str) -> Result<ObjectId, RepositoryError> {
+    ObjectId::parse_str(value).map_err(|e| RepositoryError::Database(format!("invalid id {:?}: {}", value, e)))
+}
+
+fn object_id_column(row: &PgRow, column: &str) -> Result<ObjectId, RepositoryError> {
+    object_id(&row.try_get::<String, _>(column)?)
+}
+
+fn hex_ids(ids: &[ObjectId]) -> Vec<String> {
+    ids.iter().map(|id| id.to_hex()).collect()
+}
+
+/// The given id, or a freshly generated one (the default `ObjectId` is a new id).
+fn new_id(id: Option<ObjectId>) -> ObjectId {
+    id.unwrap_or_default()
+}
+
+/// Every column of `users` plus the linked OIDC identities, in link order.
+const USER_COLUMNS: &str = "id, username, email, password_hash, password_history, email_verified, \
+    totp_enabled, totp_secret, totp_last_used_step, recovery_code_hashes, reset_token_hash, \
+    reset_token_expiry_at, directory_account, role, suspended, suspension_reason, session_version, last_seen_at, \
+    created_at, ARRAY(SELECT i.issuer FROM user_oidc_identities i WHERE i.user_id = users.id \
+        ORDER BY i.linked_at, i.issuer, i.subject) AS oidc_issuers, \
+    ARRAY(SELECT i.subject FROM user_oidc_identities i WHERE i.user_id = users.id \
+        ORDER BY i.linked_at, i.issuer, i.subject) AS oidc_subjects";
+
+fn user_role(value: &str) -> Result<UserRole, RepositoryError> {
+    [UserRole::User, UserRole::Moderator, UserRole::Admin]
+        .into_iter()
+        .find(|role| role.as_str() == value)
+        .ok_or_else(|| RepositoryError::Database(format!("unknown role {:?}", value)))
+}
+
+fn user_from_row(row: &PgRow) -> Result<User, RepositoryError> {
+    let issuers: Vec<String> = row.try_get("oidc_issuers")?;
+    let subjects: Vec<String> = row.try_get("oidc_subjects")?;
+    Ok(User {
+        id: Some(object_id_column(row, "id")?),
+        username: row.try_get("username")?,
+        email: row.try_get("email")?,
+        password_hash: row.try_get("password_hash")?,
+        password_history: row.try_get("password_history")?,
+        email_verified: row.try_get("email_verified")?,
+        totp_enabled: row.try_get("totp_enabled")?,
+        totp_secret: row.try_get("totp_secret")?,
+        totp_last_used_step: row.try_get("totp_last_used_step")?,
+        recovery_code_hashes: row.try_get("recovery_code_hashes")?,
+        reset_token_hash: row.try_get("reset_token_hash")?,
+        reset_token_expiry_at: row.try_get("reset_token_expiry_at")?,
+        oidc_identities: issuers
+            .into_iter()
+            .zip(subjects)
+            .map(|(issuer, subject)| OidcIdentity { issuer, subject })
+            .collect(),
+        directory_account: row.try_get("directory_account")?,
+        role: user_role(row.try_get("role")?)?,
+        suspended: row.try_get("suspended")?,
+        suspension_reason: row.try_get("suspension_reason")?,
+        session_version: row.try_get("session_version")?,
+        last_seen_at: row.try_get("last_seen_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+pub struct PostgresUserRepository {
+    pool: PgPool,
+}
+
+impl PostgresUserRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    fn push_filter(builder: &mut QueryBuilder<'_, Postgres>, filter: &UserFilter) {
+        builder.push(" WHERE TRUE");
+        if let Some(query) = filter.query.as_deref().map(str::trim).filter(|query| !query.is_empty()) {
+            // Match the query literally, like the escaped regex of the MongoDB backend.
+            let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            let pattern = format!("%{}%", escaped);
+            builder
+                .push(" AND (username ILIKE ")
+                .push_bind(pattern.clone())
+                .push(" OR email ILIKE ")
+                .push_bind(pattern)
+                .push(")");
+        }
+        if let Some(role) = filter.role {
+            builder.push(" AND role = ").push_bind(role.as_str());
+        }
+        if let Some(suspended) = filter.suspended {
+            builder.push(" AND suspended = ").push_bind(suspended);
+        }
+    }
+
+    async fn find_one(&self, condition: &str, value: &str) -> Result<Option<User>, RepositoryError> {
+        let sql = format!("SELECT {} FROM users WHERE {} LIMIT 1", USER_COLUMNS, condition);
+        let row = sqlx::query(&sql).bind(value).fetch_optional(&self.pool).await?;
+        row.as_ref().map(user_from_row).transpose()
+    }
+
+    /// Runs an `UPDATE users ... WHERE id = $1` and reports whether the user exists.
+    async fn update(&self, query: Query<'_, Postgres, PgArguments>) -> Result<bool, RepositoryError> {
+        Ok(query.execute(&self.pool).await?.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl UserRepository for PostgresUserRepository {
+    async fn insert(&self, user: &User) -> Result<ObjectId, RepositoryError> {
+        let id = new_id(user.id);
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO users (id, username, email, password_hash, password_history, email_verified, \
+             totp_enabled, totp_secret, totp_last_used_step, recovery_code_hashes, reset_token_hash, \
+             reset_token_expiry_at, directory_account, role, suspended, suspension_reason, session_version, \
+             last_seen_at, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)",
+        )
+        .bind(id.to_hex())
+        .bind(&user.username)
+        .bind(&user.email)
+        .bind(&user.password_hash)
+        .bind(&user.password_history)
+        .bind(user.email_verified)
+        .bind(user.totp_enabled)
+        .bind(&user.totp_secret)
+        .bind(user.totp_last_used_step)
+        .bind(&user.recovery_code_hashes)
+        .bind(&user.reset_token_hash)
+        .bind(user.reset_token_expiry_at)
+        .bind(user.directory_account)
+        .bind(user.role.as_str())
+        .bind(user.suspended)
+        .bind(&user.suspension_reason)
+        .bind(user.session_version)
+        .bind(user.last_seen_at)
+        .bind(user.created_at)
+        .execute(&mut *tx)
+        .await?;
+        for identity in &user.oidc_identities {
+            sqlx::query("INSERT INTO user_oidc_identities (user_id, issuer, subject) VALUES ($1, $2, $3)")
+                .bind(id.to_hex())
+                .bind(&identity.issuer)
+                .bind(&identity.subject)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, user_id: ObjectId) -> Result<Option<User>, RepositoryError> {
+        self.find_one("id = $1", &user_id.to_hex()).await
+    }
+
+    async fn find_by_ids(&self, user_ids: &[ObjectId]) -> Result<Vec<User>, RepositoryError> {
+        let sql = format!("SELECT {} FROM users WHERE id = ANY($1)", USER_COLUMNS);
+        let rows = sqlx::query(&sql).bind(hex_ids(user_ids)).fetch_all(&self.pool).await?;
+        rows.iter().map(user_from_row).collect()
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, RepositoryError> {
+        self.find_one("username = $1", username).await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepositoryError> {
+        self.find_one("email = $1", email).await
+    }
+
+    async fn find_by_username_or_email(&self, username: &str, email: &str) -> Result<Option<User>, RepositoryError> {
+        let sql = format!("SELECT {} FROM users WHERE username = $1 OR email = $2 LIMIT 1", USER_COLUMNS);
+        let row = sqlx::query(&sql)
+            .bind(username)
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(user_from_row).transpose()
+    }
+
+    async fn find_by_reset_token_hash(&self, token_hash: &str) -> Result<Option<User>, RepositoryError> {
+        self.find_one("reset_token_hash = $1", token_hash).await
+    }
+
+    async fn find_by_oidc_identity(&self, identity: &OidcIdentity) -> Result<Option<User>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM users WHERE id = \
+             (SELECT user_id FROM user_oidc_identities WHERE issuer = $1 AND subject = $2)",
+            USER_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(&identity.issuer)
+            .bind(&identity.subject)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(user_from_row).transpose()
+    }
+
+    async fn find_users(&self, filter: &UserFilter, skip: u64, limit: Option<i64>) -> Result<Vec<User>, RepositoryError> {
+        let mut builder = QueryBuilder::new(format!("SELECT {} FROM users", USER_COLUMNS));
+        Self::push_filter(&mut builder, filter);
+        builder.push(" ORDER BY created_at DESC, id DESC");
+        if let Some(limit) = limit {
+            builder.push(" LIMIT ").push_bind(limit);
+        }
+        builder.push(" OFFSET ").push_bind(skip as i64);
+        let rows = builder.build().fetch_all(&self.pool).await?;
+        rows.iter().map(user_from_row).collect()
+    }
+
+    async fn count_users(&self, filter: &UserFilter) -> Result<u64, RepositoryError> {
+        let mut builder = QueryBuilder::new("SELECT COUNT(*) FROM users");
+        Self::push_filter(&mut builder, filter);
+        let count: i64 = builder.build().fetch_one(&self.pool).await?.try_get(0)?;
+        Ok(count as u64)
+    }
+
+    async fn set_email_verified(&self, user_id: ObjectId) -> Result<bool, RepositoryError> {
+        self.update(sqlx::query("UPDATE users SET email_verified = TRUE WHERE id = $1").bind(user_id.to_hex()))
+            .await
+    }
+
+    async fn set_reset_token(&self, user_id: ObjectId, token_hash: &str, expires_at_millis: i64) -> Result<(), RepositoryError> {
+        self.update(
+            sqlx::query("UPDATE users SET reset_token_hash = $2, reset_token_expiry_at = $3 WHERE id = $1")
+                .bind(user_id.to_hex())
+                .bind(token_hash)
+                .bind(expires_at_millis),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn complete_password_reset(
+        &self,
+        user_id: ObjectId,
+        token_hash: &str,
+        now_millis: i64,
+        password_hash: &str,
+        password_history: &[String],
+    ) -> Result<bool, RepositoryError> {
+        // Matching on the token hash and expiry makes the token single use even under concurrent requests.
+        self.update(
+            sqlx::query(
+                "UPDATE users SET password_hash = $4, password_history = $5, \
+                 reset_token_hash = NULL, reset_token_expiry_at = NULL \
+                 WHERE id = $1 AND reset_token_hash = $2 AND reset_token_expiry_at >= $3",
+            )
+            .bind(user_id.to_hex())
+            .bind(token_hash)
+            .bind(now_millis)
+            .bind(password_hash)
+            .bind(password_history),
+        )
+        .await
+    }
+
+    async fn set_password_hash(&self, user_id: ObjectId, password_hash: &str) -> Result<bool, RepositoryError> {
+        self.update(
+            sqlx::query("UPDATE users SET password_hash = $2 WHERE id = $1")
+                .bind(user_id.to_hex())
+                .bind(password_hash),
+        )
+        .await
+    }
+
+    async fn increment_session_version(&self, user_id: ObjectId) -> Result<(), RepositoryError> {
+        self.update(
+            sqlx::query("UPDATE users SET session_version = session_version + 1 WHERE id = $1").bind(user_id.to_hex()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn touch_last_seen(&self, user_id: ObjectId, at: DateTime<Utc>) -> Result<(), RepositoryError> {
+        self.update(
+            sqlx::query("UPDATE users SET last_seen_at = $2 WHERE id = $1")
+                .bind(user_id.to_hex())
+                .bind(at),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn set_totp_secret(&self, user_id: ObjectId, secret: &str) -> Result<(), RepositoryError> {
+        self.update(
+            sqlx::query("UPDATE users SET totp_secret = $2 WHERE id = $1")
+                .bind(user_id.to_hex())
+                .bind(secret),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn enable_totp(&self, user_id: ObjectId, step: i64, recovery_code_hashes: &[String]) -> Result<(), RepositoryError> {
+        self.update(
+            sqlx::query(
+                "UPDATE users SET totp_enabled = TRUE, totp_last_used_step = $2, recovery_code_hashes = $3 \
+                 WHERE id = $1",
+            )
+            .bind(user_id.to_hex())
+            .bind(step)
+            .bind(recovery_code_hashes),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn disable_totp(&self, user_id: ObjectId) -> Result<(), RepositoryError> {
+        self.update(
+            sqlx::query(
+                "UPDATE users SET totp_enabled = FALSE, totp_secret = NULL, totp_last_used_step = NULL, \
+                 recovery_code_hashes = '{}' WHERE id = $1",
+            )
+            .bind(user_id.to_hex()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn consume_totp_step(&self, user_id: ObjectId, step: i64) -> Result<bool, RepositoryError> {
+        // The step condition makes concurrent requests with the same code race for a single use.
+        self.update(
+            sqlx::query(
+                "UPDATE users SET totp_last_used_step = $2 \
+                 WHERE id = $1 AND (totp_last_used_step IS NULL OR totp_last_used_step < $2)",
+            )
+            .bind(user_id.to_hex())
+            .bind(step),
+        )
+        .await
+    }
+
+    async fn consume_recovery_code(&self, user_id: ObjectId, code_hash: &str) -> Result<bool, RepositoryError> {
+        self.update(
+            sqlx::query(
+                "UPDATE users SET recovery_code_hashes = array_remove(recovery_code_hashes, $2) \
+                 WHERE id = $1 AND $2 = ANY(recovery_code_hashes)",
+            )
+            .bind(user_id.to_hex())
+            .bind(code_hash),
+        )
+        .await
+    }
+
+    async fn link_oidc_identity_by_email(&self, email: &str, identity: &OidcIdentity) -> Result<Option<User>, RepositoryError> {
+        let mut tx = self.pool.begin().await?;
+        let user_id: Option<String> =
+            sqlx::query_scalar("SELECT id FROM users WHERE email = $1 AND email_verified FOR UPDATE")
+                .bind(email)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let Some(user_id) = user_id else {
+            return Ok(None);
+        };
+        sqlx::query(
+            "INSERT INTO user_oidc_identities (user_id, issuer, subject) VALUES ($1, $2, $3) \
+             ON CONFLICT (issuer, subject) DO NOTHING",
+        )
+        .bind(&user_id)
+        .bind(&identity.issuer)
+        .bind(&identity.subject)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        self.find_by_id(object_id(&user_id)?).await
+    }
+
+    async fn sync_directory_email(&self, username: &str, email: &str) -> Result<Option<User>, RepositoryError> {
+        let user_id: Option<String> =
+            sqlx::query_scalar("UPDATE users SET email = $2, email_verified = TRUE WHERE username = $1 AND directory_account RETURNING id")
+                .bind(username)
+                .bind(email)
+                .fetch_optional(&self.pool)
+                .await?;
+        match user_id {
+            Some(user_id) => self.find_by_id(object_id(&user_id)?).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn set_role(&self, user_id: ObjectId, role: UserRole) -> Result<bool, RepositoryError> {
+        self.update(
+            sqlx::query("UPDATE users SET role = $2 WHERE id = $1")
+                .bind(user_id.to_hex())
+                .bind(role.as_str()),
+        )
+        .await
+    }
+
+    async fn set_suspended(&self, user_id: ObjectId, suspended: bool, reason: Option<&str>) -> Result<bool, RepositoryError> {
+        self.update(
+            sqlx::query(
+                "UPDATE users SET suspended = $2, suspension_reason = CASE WHEN $2 THEN $3 END WHERE id = $1",
+            )
+            .bind(user_id.to_hex())
+            .bind(suspended)
+            .bind(reason),
+        )
+        .await
+    }
+}
+
+/// Every chat column plus the participants, in the order the chat was created with, their hides and the pins.
+const CHAT_COLUMNS: &str = "chats.id, chats.created_at, chats.last_seq, chats.deleted_at, \
+    chats.last_message_id, chats.last_message_sender_id, chats.last_message_seq, chats.last_message_content, \
+    chats.last_message_created_at, chats.last_activity_at, \
+    ARRAY(SELECT p.user_id FROM chat_participants p WHERE p.chat_id = chats.id ORDER BY p.position) \
+    AS participant_ids, \
+    ARRAY(SELECT p.user_id FROM chat_participants p WHERE p.chat_id = chats.id AND p.hidden_at IS NOT NULL \
+        ORDER BY p.position) AS hidden_user_ids, \
+    ARRAY(SELECT p.hidden_at FROM chat_participants p WHERE p.chat_id = chats.id AND p.hidden_at IS NOT NULL \
+        ORDER BY p.position) AS hidden_ats, \
+    ARRAY(SELECT p.hidden_seq FROM chat_participants p WHERE p.chat_id = chats.id AND p.hidden_at IS NOT NULL \
+        ORDER BY p.position) AS hidden_seqs, \
+    ARRAY(SELECT pin.message_id FROM chat_pins pin WHERE pin.chat_id = chats.id \
+        ORDER BY pin.pinned_at, pin.message_id) AS pinned_message_ids, \
+    ARRAY(SELECT pin.pinned_by FROM chat_pins pin WHERE pin.chat_id = chats.id \
+        ORDER BY pin.pinned_at, pin.message_id) AS pinned_bys, \
+    ARRAY(SELECT pin.pinned_at FROM chat_pins pin WHERE pin.chat_id = chats.id \
+        ORDER BY pin.pinned_at, pin.message_id) AS pinned_ats";
+
+fn chat_from_row(row: &PgRow) -> Result<Chat, RepositoryError> {
+    let participant_ids: Vec<String> = row.try_get("participant_ids")?;
+    let hidden_user_ids: Vec<String> = row.try_get("hidden_user_ids")?;
+    let hidden_ats: Vec<DateTime<Utc>> = row.try_get("hidden_ats")?;
+    let hidden_seqs: Vec<i64> = row.try_get("hidden_seqs")?;
+    let pinned_message_ids: Vec<String> = row.try_get("pinned_message_ids")?;
+    let pinned_bys: Vec<String> = row.try_get("pinned_bys")?;
+    let pinned_ats: Vec<DateTime<Utc>> = row.try_get("pinned_ats")?;
+    let last_message = match row.try_get::<Option<String>, _>("last_message_id")? {
+        Some(message_id) => Some(LastMessage {
+            message_id: object_id(&message_id)?,
+            sender_id: object_id_column(row, "last_message_sender_id")?,
+            seq: row.try_get("last_message_seq")?,
+            content: row.try_get("last_message_content")?,
+            created_at: row.try_get("last_message_created_at")?,
+        }),
+        None => None,
+    };
+    Ok(Chat {
+        id: Some(object_id_column(row, "id")?),
+        participant_ids: participant_ids.iter().map(|id| object_id(id)).collect::<Result<_, _>>()?,
+        created_at: row.try_get("created_at")?,
+        last_seq: row.try_get("last_seq")?,
+        last_message,
+        last_activity_at: row.try_get("last_activity_at")?,
+        pins: pinned_message_ids
+            .iter()
+            .zip(pinned_bys)
+            .zip(pinned_ats)
+            .map(|((message_id, pinned_by), pinned_at)| {
+                Ok(ChatPin {
+                    message_id: object_id(message_id)?,
+                    pinned_by: object_id(&pinned_by)?,
+                    pinned_at,
+                })
+            })
+            .collect::<Result<_, RepositoryError>>()?,
+        hidden_by: hidden_user_ids
+            .iter()
+            .zip(hidden_ats)
+            .zip(hidden_seqs)
+            .map(|((user_id, hidden_at), seq)| {
+                Ok(ChatHide {
+                    user_id: object_id(user_id)?,
+                    hidden_at,
+                    seq,
+                })
+            })
+            .collect::<Result<_, RepositoryError>>()?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+pub struct PostgresChatRepository {
+    pool: PgPool,
+}
+
+impl PostgresChatRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl ChatRepository for PostgresChatRepository {
+    async fn insert(&self, chat: &Chat) -> Result<ObjectId, RepositoryError> {
+        let id = new_id(chat.id);
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO chats (id, created_at, last_activity_at) VALUES ($1, $2, $3)")
+            .bind(id.to_hex())
+            .bind(chat.created_at)
+            .bind(chat.last_activity_at)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO chat_participants (chat_id, user_id, position) \
+             SELECT $1, user_id, position::INTEGER FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS p (user_id, position)",
+        )
+        .bind(id.to_hex())
+        .bind(hex_ids(&chat.participant_ids))
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, chat_id: ObjectId) -> Result<Option<Chat>, RepositoryError> {
+        let sql = format!("SELECT {} FROM chats WHERE chats.id = $1", CHAT_COLUMNS);
+        let row = sqlx::query(&sql).bind(chat_id.to_hex()).fetch_optional(&self.pool).await?;
+        row.as_ref().map(chat_from_row).transpose()
+    }
+
+    async fn find_for_participant(&self, chat_id: ObjectId, user_id: ObjectId) -> Result<Option<Chat>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM chats JOIN chat_participants p ON p.chat_id = chats.id \
+             WHERE chats.id = $1 AND p.user_id = $2 AND chats.deleted_at IS NULL",
+            CHAT_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(chat_id.to_hex())
+            .bind(user_id.to_hex())
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(chat_from_row).transpose()
+    }
+
+    async fn find_by_participants(&self, participant_ids: &[ObjectId]) -> Result<Option<Chat>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM chats WHERE chats.deleted_at IS NULL AND chats.id IN ( \
+                 SELECT chat_id FROM chat_participants GROUP BY chat_id \
+                 HAVING COUNT(*) = $2 AND COUNT(*) FILTER (WHERE user_id = ANY($1)) = $2 \
+             ) LIMIT 1",
+            CHAT_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(hex_ids(participant_ids))
+            .bind(participant_ids.len() as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(chat_from_row).transpose()
+    }
+
+    async fn find_for_user(&self, user_id: ObjectId) -> Result<Vec<Chat>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM chats JOIN chat_participants p ON p.chat_id = chats.id \
+             WHERE p.user_id = $1 AND chats.deleted_at IS NULL",
+            CHAT_COLUMNS
+        );
+        let rows = sqlx::query(&sql).bind(user_id.to_hex()).fetch_all(&self.pool).await?;
+        rows.iter().map(chat_from_row).collect()
+    }
+
+    async fn list_for_user(&self, user_id: ObjectId, skip: u64, limit: Option<i64>) -> Result<Vec<Chat>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM chats JOIN chat_participants p ON p.chat_id = chats.id \
+             WHERE p.user_id = $1 AND chats.deleted_at IS NULL \
+             AND (p.hidden_at IS NULL OR p.hidden_seq < chats.last_seq) \
+             ORDER BY chats.last_activity_at DESC, chats.id DESC OFFSET $2 LIMIT $3",
+            CHAT_COLUMNS
+        );
+        let rows = sqlx::query(&sql)
+            .bind(user_id.to_hex())
+            .bind(skip as i64)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(chat_from_row).collect()
+    }
+
+    async fn next_seq(&self, chat_id: ObjectId) -> Result<Option<i64>, RepositoryError> {
+        Ok(sqlx::query_scalar(
+            "UPDATE chats SET last_seq = last_seq + 1 WHERE id = $1 AND deleted_at IS NULL RETURNING last_seq",
+        )
+        .bind(chat_id.to_hex())
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn record_message(&self, chat_id: ObjectId, last_message: &LastMessage) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "UPDATE chats SET last_message_id = $2, last_message_sender_id = $3, last_message_seq = $4, \
+             last_message_content = $5, last_message_created_at = $6, last_activity_at = GREATEST(last_activity_at, $6) \
+             WHERE id = $1 AND (last_message_seq IS NULL OR last_message_seq < $4)",
+        )
+        .bind(chat_id.to_hex())
+        .bind(last_message.message_id.to_hex())
+        .bind(last_message.sender_id.to_hex())
+        .bind(last_message.seq)
+        .bind(&last_message.content)
+        .bind(last_message.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn replace_last_message(&self, chat_id: ObjectId, last_message: Option<&LastMessage>) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "UPDATE chats SET last_message_id = $2, last_message_sender_id = $3, last_message_seq = $4, \
+             last_message_content = $5, last_message_created_at = $6 WHERE id = $1",
+        )
+        .bind(chat_id.to_hex())
+        .bind(last_message.map(|last_message| last_message.message_id.to_hex()))
+        .bind(last_message.map(|last_message| last_message.sender_id.to_hex()))
+        .bind(last_message.map(|last_message| last_message.seq))
+        .bind(last_message.map(|last_message| last_message.content.clone()))
+        .bind(last_message.map(|last_message| last_message.created_at))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn pin(&self, chat_id: ObjectId, pin: &ChatPin, max_pins: usize) -> Result<bool, RepositoryError> {
+        // Locking the chat row keeps concurrent pins from both slipping under the cap.
+        let mut tx = self.pool.begin().await?;
+        let locked = sqlx::query("SELECT id FROM chats WHERE id = $1 AND deleted_at IS NULL FOR UPDATE")
+            .bind(chat_id.to_hex())
+            .fetch_optional(&mut *tx)
+            .await?;
+        if locked.is_none() {
+            return Ok(false);
+        }
+        let result = sqlx::query(
+            "INSERT INTO chat_pins (chat_id, message_id, pinned_by, pinned_at) \
+             SELECT $1, $2, $3, $4 WHERE (SELECT COUNT(*) FROM chat_pins WHERE chat_id = $1) < $5 \
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(chat_id.to_hex())
+        .bind(pin.message_id.to_hex())
+        .bind(pin.pinned_by.to_hex())
+        .bind(pin.pinned_at)
+        .bind(max_pins as i64)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn unpin(&self, chat_id: ObjectId, message_id: ObjectId) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM chat_pins WHERE chat_id = $1 AND message_id = $2")
+            .bind(chat_id.to_hex())
+            .bind(message_id.to_hex())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn hide(&self, chat_id: ObjectId, user_id: ObjectId, at: DateTime<Utc>) -> Result<Option<Chat>, RepositoryError> {
+        let mut tx = self.pool.begin().await?;
+        // Lock the chat so participants hiding it at the same time see each other's hides.
+        sqlx::query("SELECT 1 FROM chats WHERE id = $1 FOR UPDATE")
+            .bind(chat_id.to_hex())
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query(
+            "UPDATE chat_participants p SET hidden_at = $3, hidden_seq = chats.last_seq FROM chats \
+             WHERE p.chat_id = $1 AND p.user_id = $2 AND chats.id = p.chat_id AND chats.deleted_at IS NULL",
+        )
+        .bind(chat_id.to_hex())
+        .bind(user_id.to_hex())
+        .bind(at)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        sqlx::query(
+            "UPDATE chats SET deleted_at = $2 WHERE id = $1 AND NOT EXISTS \
+             (SELECT 1 FROM chat_participants p WHERE p.chat_id = chats.id \
+              AND (p.hidden_seq IS NULL OR p.hidden_seq < chats.last_seq))",
+        )
+        .bind(chat_id.to_hex())
+        .bind(at)
+        .execute(&mut *tx)
+        .await?;
+        let sql = format!("SELECT {} FROM chats WHERE chats.id = $1", CHAT_COLUMNS);
+        let row = sqlx::query(&sql).bind(chat_id.to_hex()).fetch_one(&mut *tx).await?;
+        tx.commit().await?;
+        chat_from_row(&row).map(Some)
+    }
+
+    async fn find_deleted_before(&self, cutoff: DateTime<Utc>) -> Result<Vec<Chat>, RepositoryError> {
+        let sql = format!("SELECT {} FROM chats WHERE chats.deleted_at <= $1", CHAT_COLUMNS);
+        let rows = sqlx::query(&sql).bind(cutoff).fetch_all(&self.pool).await?;
+        rows.iter().map(chat_from_row).collect()
+    }
+
+    /// Messages and participants go with the chat through `ON DELETE CASCADE`, in the same statement.
+    async fn delete(&self, chat_id: ObjectId) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM chats WHERE id = $1")
+            .bind(chat_id.to_hex())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count(&self) -> Result<u64, RepositoryError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM chats").fetch_one(&self.pool).await?;
+        Ok(count as u64)
+    }
+}
+
+const MESSAGE_COLUMNS: &str = "id, chat_id, sender_id, seq, content, created_at, client_sent_at, client_msg_id";
+
+fn message_from_row(row: &PgRow) -> Result<Message, RepositoryError> {
+    Ok(Message {
+        id: Some(object_id_column(row, "id")?),
+        chat_id: object_id_column(row, "chat_id")?,
+        sender_id: object_id_column(row, "sender_id")?,
+        seq: row.try_get("seq")?,
+        content: row.try_get("content")?,
+        created_at: row.try_get("created_at")?,
+        client_sent_at: row.try_get("client_sent_at")?,
+        client_msg_id: row.try_get("client_msg_id")?,
+    })
+}
+
+pub struct PostgresMessageRepository {
+    pool: PgPool,
+}
+
+impl PostgresMessageRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl MessageRepository for PostgresMessageRepository {
+    async fn insert(&self, message: &Message) -> Result<ObjectId, RepositoryError> {
+        let id = new_id(message.id);
+        sqlx::query(
+            "INSERT INTO messages (id, chat_id, sender_id, seq, content, created_at, client_sent_at, client_msg_id) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(id.to_hex())
+        .bind(message.chat_id.to_hex())
+        .bind(message.sender_id.to_hex())
+        .bind(message.seq)
+        .bind(&message.content)
+        .bind(message.created_at)
+        .bind(message.client_sent_at)
+        .bind(&message.client_msg_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, message_id: ObjectId) -> Result<Option<Message>, RepositoryError> {
+        let sql = format!("SELECT {} FROM messages WHERE id = $1", MESSAGE_COLUMNS);
+        let row = sqlx::query(&sql).bind(message_id.to_hex()).fetch_optional(&self.pool).await?;
+        row.as_ref().map(message_from_row).transpose()
+    }
+
+    async fn find_by_ids(&self, message_ids: &[ObjectId]) -> Result<Vec<Message>, RepositoryError> {
+        let sql = format!("SELECT {} FROM messages WHERE id = ANY($1)", MESSAGE_COLUMNS);
+        let rows = sqlx::query(&sql).bind(hex_ids(message_ids)).fetch_all(&self.pool).await?;
+        rows.iter().map(message_from_row).collect()
+    }
+
+    async fn find_by_client_msg_id(&self, sender_id: ObjectId, client_msg_id: &str) -> Result<Option<Message>, RepositoryError> {
+        let sql = format!("SELECT {} FROM messages WHERE sender_id = $1 AND client_msg_id = $2", MESSAGE_COLUMNS);
+        let row = sqlx::query(&sql)
+            .bind(sender_id.to_hex())
+            .bind(client_msg_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(message_from_row).transpose()
+    }
+
+    async fn find_by_chat(&self, chat_id: ObjectId, range: &MessageRange) -> Result<Vec<Message>, RepositoryError> {
+        let mut builder = QueryBuilder::new(format!("SELECT {} FROM messages WHERE chat_id = ", MESSAGE_COLUMNS));
+        builder.push_bind(chat_id.to_hex());
+        if let Some(lower_bound) = range.lower_bound() {
+            builder.push(" AND seq > ").push_bind(lower_bound);
+        }
+        if let Some(before_seq) = range.before_seq {
+            builder.push(" AND seq < ").push_bind(before_seq);
+        }
+        builder.push(if range.newest_first() { " ORDER BY seq DESC" } else { " ORDER BY seq" });
+        if let Some(limit) = range.limit {
+            builder.push(" LIMIT ").push_bind(limit);
+        }
+        let mut messages = builder
+            .build()
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(message_from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+        if range.newest_first() {
+            messages.reverse();
+        }
+        Ok(messages)
+    }
+
+    async fn find_last(&self, chat_id: ObjectId) -> Result<Option<Message>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM messages WHERE chat_id = $1 ORDER BY seq DESC LIMIT 1",
+            MESSAGE_COLUMNS
+        );
+        let row = sqlx::query(&sql).bind(chat_id.to_hex()).fetch_optional(&self.pool).await?;
+        row.as_ref().map(message_from_row).transpose()
+    }
+
+    async fn delete(&self, message_id: ObjectId) -> Result<Option<Message>, RepositoryError> {
+        let sql = format!("DELETE FROM messages WHERE id = $1 RETURNING {}", MESSAGE_COLUMNS);
+        let row = sqlx::query(&sql).bind(message_id.to_hex()).fetch_optional(&self.pool).await?;
+        row.as_ref().map(message_from_row).transpose()
+    }
+
+    async fn count(&self) -> Result<u64, RepositoryError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM messages").fetch_one(&self.pool).await?;
+        Ok(count as u64)
+    }
+
+    async fn count_since(
+        &self,
+        chat_ids: &[ObjectId],
+        excluded_sender: ObjectId,
+        since: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM messages WHERE chat_id = ANY($1) AND sender_id <> $2 AND created_at > $3",
+        )
+        .bind(hex_ids(chat_ids))
+        .bind(excluded_sender.to_hex())
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u64)
+    }
+
+    async fn senders_since(
+        &self,
+        chat_ids: &[ObjectId],
+        excluded_sender: ObjectId,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<ObjectId>, RepositoryError> {
+        let senders: Vec<String> = sqlx::query_scalar(
+            "SELECT DISTINCT sender_id FROM messages WHERE chat_id = ANY($1) AND sender_id <> $2 AND created_at > $3",
+        )
+        .bind(hex_ids(chat_ids))
+        .bind(excluded_sender.to_hex())
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        senders.iter().map(|id| object_id(id)).collect()
+    }
+}
+
+const NOTIFICATION_COLUMNS: &str =
+    "id, notification_type, recipient_id, sender_id, message, is_handled, is_read, read_at, created_at";
+
+fn notification_type(value: &str) -> Result<NotificationType, RepositoryError> {
+    NotificationType::ALL
+        .into_iter()
+        .find(|notification_type| notification_type.as_str() == value)
+        .ok_or_else(|| RepositoryError::Database(format!("unknown notification type {:?}", value)))
+}
+
+fn notification_from_row(row: &PgRow) -> Result<Notification, RepositoryError> {
+    Ok(Notification {
+        id: Some(object_id_column(row, "id")?),
+        notification_type: notification_type(row.try_get("notification_type")?)?,
+        recipient_id: object_id_column(row, "recipient_id")?,
+        sender_id: object_id_column(row, "sender_id")?,
+        message: row.try_get("message")?,
+        is_handled: row.try_get("is_handled")?,
+        is_read: row.try_get("is_read")?,
+        read_at: row.try_get("read_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+pub struct PostgresNotificationRepository {
+    pool: PgPool,
+}
+
+impl PostgresNotificationRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl NotificationRepository for PostgresNotificationRepository {
+    async fn insert(&self, notification: &Notification) -> Result<ObjectId, RepositoryError> {
+        let id = new_id(notification.id);
+        sqlx::query(
+            "INSERT INTO notifications (id, notification_type, recipient_id, sender_id, message, \
+             is_handled, is_read, read_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(id.to_hex())
+        .bind(notification.notification_type.as_str())
+        .bind(notification.recipient_id.to_hex())
+        .bind(notification.sender_id.to_hex())
+        .bind(&notification.message)
+        .bind(notification.is_handled)
+        .bind(notification.is_read)
+        .bind(notification.read_at)
+        .bind(notification.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn find_unhandled(&self, recipient_id: ObjectId) -> Result<Vec<Notification>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM notifications WHERE recipient_id = $1 AND NOT is_handled ORDER BY created_at, id",
+            NOTIFICATION_COLUMNS
+        );
+        let rows = sqlx::query(&sql).bind(recipient_id.to_hex()).fetch_all(&self.pool).await?;
+        rows.iter().map(notification_from_row).collect()
+    }
+
+    async fn find_recent(
+        &self,
+        recipient_id: ObjectId,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<Notification>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM notifications WHERE recipient_id = $1 AND (NOT $2 OR NOT is_read) \
+             ORDER BY created_at DESC, id DESC LIMIT $3",
+            NOTIFICATION_COLUMNS
+        );
+        let rows = sqlx::query(&sql)
+            .bind(recipient_id.to_hex())
+            .bind(unread_only)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(notification_from_row).collect()
+    }
+
+    async fn count_unread(&self, recipient_id: ObjectId) -> Result<u64, RepositoryError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM notifications WHERE recipient_id = $1 AND NOT is_read")
+            .bind(recipient_id.to_hex())
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    async fn mark_read(
+        &self,
+        recipient_id: ObjectId,
+        ids: Option<&[ObjectId]>,
+        at: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let result = sqlx::query(
+            "UPDATE notifications SET is_read = TRUE, read_at = $2 \
+             WHERE recipient_id = $1 AND NOT is_read AND ($3::TEXT[] IS NULL OR id = ANY($3))",
+        )
+        .bind(recipient_id.to_hex())
+        .bind(at)
+        .bind(ids.map(hex_ids))
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete(&self, notification_id: ObjectId, recipient_id: ObjectId) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM notifications WHERE id = $1 AND recipient_id = $2")
+            .bind(notification_id.to_hex())
+            .bind(recipient_id.to_hex())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn mark_handled(
+        &self,
+        notification_id: ObjectId,
+        recipient_id: ObjectId,
+    ) -> Result<Option<Notification>, RepositoryError> {
+        // The locked snapshot in `before` is what the caller gets back.
+        let sql = format!(
+            "WITH before AS ( \
+                 SELECT {} FROM notifications WHERE id = $1 AND recipient_id = $2 FOR UPDATE \
+             ) \
+             UPDATE notifications SET is_handled = TRUE, is_read = TRUE FROM before \
+             WHERE notifications.id = before.id RETURNING before.*",
+            NOTIFICATION_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(notification_id.to_hex())
+            .bind(recipient_id.to_hex())
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(notification_from_row).transpose()
+    }
+
+    async fn count_unhandled_since(
+        &self,
+        recipient_id: ObjectId,
+        notification_types: &[NotificationType],
+        since: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let notification_types: Vec<&str> = notification_types.iter().map(NotificationType::as_str).collect();
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM notifications WHERE recipient_id = $1 AND notification_type = ANY($2) \
+             AND NOT is_handled AND created_at > $3",
+        )
+        .bind(recipient_id.to_hex())
+        .bind(notification_types)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u64)
+    }
+}
+
+const SAVED_MESSAGE_COLUMNS: &str = "id, user_id, message_id, chat_id, note, tags, created_at";
+
+fn saved_message_from_row(row: &PgRow) -> Result<SavedMessage, RepositoryError> {
+    Ok(SavedMessage {
+        id: Some(object_id_column(row, "id")?),
+        user_id: object_id_column(row, "user_id")?,
+        message_id: object_id_column(row, "message_id")?,
+        chat_id: object_id_column(row, "chat_id")?,
+        note: row.try_get("note")?,
+        tags: row.try_get("tags")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+pub struct PostgresSavedMessageRepository {
+    pool: PgPool,
+}
+
+impl PostgresSavedMessageRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl SavedMessageRepository for PostgresSavedMessageRepository {
+    async fn upsert(&self, saved: &SavedMessage) -> Result<SavedMessage, RepositoryError> {
+        let sql = format!(
+            "INSERT INTO saved_messages (id, user_id, message_id, chat_id, note, tags, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) \
+             ON CONFLICT (user_id, message_id) DO UPDATE SET note = EXCLUDED.note, tags = EXCLUDED.tags \
+             RETURNING {}",
+            SAVED_MESSAGE_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(new_id(saved.id).to_hex())
+            .bind(saved.user_id.to_hex())
+            .bind(saved.message_id.to_hex())
+            .bind(saved.chat_id.to_hex())
+            .bind(&saved.note)
+            .bind(&saved.tags)
+            .bind(saved.created_at)
+            .fetch_one(&self.pool)
+            .await?;
+        saved_message_from_row(&row)
+    }
+
+    async fn find_for_user(
+        &self,
+        user_id: ObjectId,
+        tag: Option<&str>,
+        skip: u64,
+        limit: Option<i64>,
+    ) -> Result<Vec<SavedMessage>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM saved_messages WHERE user_id = $1 AND ($2::TEXT IS NULL OR $2 = ANY(tags)) \
+             ORDER BY created_at DESC, id DESC OFFSET $3 LIMIT $4",
+            SAVED_MESSAGE_COLUMNS
+        );
+        let rows = sqlx::query(&sql)
+            .bind(user_id.to_hex())
+            .bind(tag)
+            .bind(skip as i64)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(saved_message_from_row).collect()
+    }
+
+    async fn delete(&self, user_id: ObjectId, message_id: ObjectId) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM saved_messages WHERE user_id = $1 AND message_id = $2")
+            .bind(user_id.to_hex())
+            .bind(message_id.to_hex())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_by_message(&self, message_id: ObjectId) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM saved_messages WHERE message_id = $1")
+            .bind(message_id.to_hex())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_by_chat(&self, chat_id: ObjectId, user_id: Option<ObjectId>) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM saved_messages WHERE chat_id = $1 AND ($2::TEXT IS NULL OR user_id = $2)")
+            .bind(chat_id.to_hex())
+            .bind(user_id.map(|user_id| user_id.to_hex()))
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// The serde name of a unit enum variant, such as `login_failed`, as stored in TEXT columns.
+fn enum_text<T: Serialize>(value: &T) -> Result<String, RepositoryError> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(text)) => Ok(text),
+        _ => Err(RepositoryError::Database("value does not serialize to a string".to_owned())),
+    }
+}
+
+fn enum_from_text<T: DeserializeOwned>(value: &str) -> Result<T, RepositoryError> {
+    serde_json::from_value(serde_json::Value::String(value.to_owned()))
+        .map_err(|_| RepositoryError::Database(format!("unknown value {:?}", value)))
+}
+
+fn optional_object_id_column(row: &PgRow, column: &str) -> Result<Option<ObjectId>, RepositoryError> {
+    row.try_get::<Option<String>, _>(column)?
+        .as_deref()
+        .map(object_id)
+        .transpose()
+}
+
+const SESSION_COLUMNS: &str =
+    "id, key_hash, session_id, user_id, state::TEXT AS state, user_agent, ip, created_at, last_seen_at, expires_at";
+
+fn session_from_row(row: &PgRow) -> Result<StoredSession, RepositoryError> {
+    let state: String = row.try_get("state")?;
+    Ok(StoredSession {
+        id: Some(object_id_column(row, "id")?),
+        key_hash: row.try_get("key_hash")?,
+        session_id: row.try_get("session_id")?,
+        user_id: optional_object_id_column(row, "user_id")?,
+        state: serde_json::from_str(&state).map_err(|e| RepositoryError::Database(e.to_string()))?,
+        user_agent: row.try_get("user_agent")?,
+        ip: row.try_get("ip")?,
+        created_at: row.try_get("created_at")?,
+        last_seen_at: row.try_get("last_seen_at")?,
+        expires_at: row.try_get("expires_at")?,
+    })
+}
+
+fn session_state(session: &StoredSession) -> Result<String, RepositoryError> {
+    serde_json::to_string(&session.state).map_err(|e| RepositoryError::Database(e.to_string()))
+}
+
+pub struct PostgresSessionRepository {
+    pool: PgPool,
+}
+
+impl PostgresSessionRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl SessionRepository for PostgresSessionRepository {
+    async fn insert(&self, session: &StoredSession) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO sessions (id, key_hash, session_id, user_id, state, user_agent, ip, created_at, \
+             last_seen_at, expires_at) VALUES ($1, $2, $3, $4, $5::JSONB, $6, $7, $8, $9, $10)",
+        )
+        .bind(new_id(session.id).to_hex())
+        .bind(&session.key_hash)
+        .bind(&session.session_id)
+        .bind(session.user_id.map(|user_id| user_id.to_hex()))
+        .bind(session_state(session)?)
+        .bind(&session.user_agent)
+        .bind(&session.ip)
+        .bind(session.created_at)
+        .bind(session.last_seen_at)
+        .bind(session.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn find_by_key_hash(&self, key_hash: &str, now: DateTime<Utc>) -> Result<Option<StoredSession>, RepositoryError> {
+        let sql = format!("SELECT {} FROM sessions WHERE key_hash = $1 AND expires_at > $2", SESSION_COLUMNS);
+        let row = sqlx::query(&sql)
+            .bind(key_hash)
+            .bind(now)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(session_from_row).transpose()
+    }
+
+    async fn update_state(&self, session: &StoredSession) -> Result<bool, RepositoryError> {
+        let result = sqlx::query(
+            "UPDATE sessions SET state = $2::JSONB, user_id = $3, session_id = $4, user_agent = $5, ip = $6, \
+             last_seen_at = $7, expires_at = $8 WHERE key_hash = $1",
+        )
+        .bind(&session.key_hash)
+        .bind(session_state(session)?)
+        .bind(session.user_id.map(|user_id| user_id.to_hex()))
+        .bind(&session.session_id)
+        .bind(&session.user_agent)
+        .bind(&session.ip)
+        .bind(session.last_seen_at)
+        .bind(session.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn touch(
+        &self,
+        key_hash: &str,
+        last_seen_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query("UPDATE sessions SET last_seen_at = $2, expires_at = $3 WHERE key_hash = $1")
+            .bind(key_hash)
+            .bind(last_seen_at)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_by_key_hash(&self, key_hash: &str) -> Result<(), RepositoryError> {
+        sqlx::query("DELETE FROM sessions WHERE key_hash = $1")
+            .bind(key_hash)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn find_for_user(&self, user_id: ObjectId, now: DateTime<Utc>) -> Result<Vec<StoredSession>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM sessions WHERE user_id = $1 AND expires_at > $2 ORDER BY last_seen_at DESC",
+            SESSION_COLUMNS
+        );
+        let rows = sqlx::query(&sql)
+            .bind(user_id.to_hex())
+            .bind(now)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(session_from_row).collect()
+    }
+
+    async fn delete_for_user(&self, user_id: ObjectId, session_id: &str) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1 AND session_id = $2")
+            .bind(user_id.to_hex())
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_all_for_user(&self, user_id: ObjectId) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+            .bind(user_id.to_hex())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn count_signed_in(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions WHERE user_id IS NOT NULL AND expires_at > $1")
+            .bind(now)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM sessions WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+const API_TOKEN_COLUMNS: &str =
+    "id, user_id, kind, token_hash, name, scopes, access_token_id, expires_at, last_used_at, created_at";
+
+fn api_token_from_row(row: &PgRow) -> Result<ApiToken, RepositoryError> {
+    let scopes: Vec<String> = row.try_get("scopes")?;
+    Ok(ApiToken {
+        id: Some(object_id_column(row, "id")?),
+        user_id: object_id_column(row, "user_id")?,
+        kind: enum_from_text(row.try_get("kind")?)?,
+        token_hash: row.try_get("token_hash")?,
+        name: row.try_get("name")?,
+        scopes: scopes.iter().map(|scope| enum_from_text(scope)).collect::<Result<_, _>>()?,
+        access_token_id: optional_object_id_column(row, "access_token_id")?,
+        expires_at: row.try_get("expires_at")?,
+        last_used_at: row.try_get("last_used_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+pub struct PostgresApiTokenRepository {
+    pool: PgPool,
+}
+
+impl PostgresApiTokenRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl ApiTokenRepository for PostgresApiTokenRepository {
+    async fn insert(&self, token: &ApiToken) -> Result<ObjectId, RepositoryError> {
+        let id = new_id(token.id);
+        let scopes = token.scopes.iter().map(enum_text).collect::<Result<Vec<_>, _>>()?;
+        sqlx::query(
+            "INSERT INTO api_tokens (id, user_id, kind, token_hash, name, scopes, access_token_id, expires_at, \
+             last_used_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+        )
+        .bind(id.to_hex())
+        .bind(token.user_id.to_hex())
+        .bind(enum_text(&token.kind)?)
+        .bind(&token.token_hash)
+        .bind(&token.name)
+        .bind(scopes)
+        .bind(token.access_token_id.map(|id| id.to_hex()))
+        .bind(token.expires_at)
+        .bind(token.last_used_at)
+        .bind(token.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn record_use(&self, token_hash: &str, at: DateTime<Utc>) -> Result<Option<ApiToken>, RepositoryError> {
+        let sql = format!(
+            "UPDATE api_tokens SET last_used_at = $2 WHERE token_hash = $1 AND kind <> $3 RETURNING {}",
+            API_TOKEN_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(token_hash)
+            .bind(at)
+            .bind(enum_text(&ApiTokenKind::Refresh)?)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(api_token_from_row).transpose()
+    }
+
+    async fn take_refresh_token(&self, token_hash: &str) -> Result<Option<ApiToken>, RepositoryError> {
+        let sql = format!(
+            "DELETE FROM api_tokens WHERE token_hash = $1 AND kind = $2 RETURNING {}",
+            API_TOKEN_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(token_hash)
+            .bind(enum_text(&ApiTokenKind::Refresh)?)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(api_token_from_row).transpose()
+    }
+
+    async fn delete(&self, token_id: ObjectId) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE id = $1")
+            .bind(token_id.to_hex())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_personal(&self, user_id: ObjectId) -> Result<Vec<ApiToken>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM api_tokens WHERE user_id = $1 AND kind = $2 ORDER BY created_at DESC, id DESC",
+            API_TOKEN_COLUMNS
+        );
+        let rows = sqlx::query(&sql)
+            .bind(user_id.to_hex())
+            .bind(enum_text(&ApiTokenKind::Personal)?)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(api_token_from_row).collect()
+    }
+
+    async fn delete_personal(&self, user_id: ObjectId, token_id: ObjectId) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE id = $1 AND user_id = $2 AND kind = $3")
+            .bind(token_id.to_hex())
+            .bind(user_id.to_hex())
+            .bind(enum_text(&ApiTokenKind::Personal)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_for_user(&self, user_id: ObjectId) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE user_id = $1")
+            .bind(user_id.to_hex())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+const THROTTLE_COLUMNS: &str = "id, key, failures, last_failure_at, locked_until, expires_at";
+
+fn throttle_from_row(row: &PgRow) -> Result<LoginThrottle, RepositoryError> {
+    Ok(LoginThrottle {
+        id: Some(object_id_column(row, "id")?),
+        key: row.try_get("key")?,
+        failures: row.try_get("failures")?,
+        last_failure_at: row.try_get("last_failure_at")?,
+        locked_until: row.try_get("locked_until")?,
+        expires_at: row.try_get("expires_at")?,
+    })
+}
+
+pub struct PostgresThrottleRepository {
+    pool: PgPool,
+}
+
+impl PostgresThrottleRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl ThrottleRepository for PostgresThrottleRepository {
+    async fn find(&self, key: &str, now: DateTime<Utc>) -> Result<Option<LoginThrottle>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM login_throttles WHERE key = $1 AND (expires_at IS NULL OR expires_at > $2)",
+            THROTTLE_COLUMNS
+        );
+        let row = sqlx::query(&sql).bind(key).bind(now).fetch_optional(&self.pool).await?;
+        row.as_ref().map(throttle_from_row).transpose()
+    }
+
+    async fn increment(
+        &self,
+        key: &str,
+        at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<LoginThrottle, RepositoryError> {
+        // An expired record that has not been purged yet starts counting afresh.
+        let sql = format!(
+            "INSERT INTO login_throttles AS t (id, key, failures, last_failure_at, expires_at) \
+             VALUES ($1, $2, 1, $3, $4) \
+             ON CONFLICT (key) DO UPDATE SET \
+                 failures = CASE WHEN t.expires_at IS NULL OR t.expires_at > $3 THEN t.failures + 1 ELSE 1 END, \
+                 locked_until = CASE WHEN t.expires_at IS NULL OR t.expires_at > $3 THEN t.locked_until END, \
+                 last_failure_at = $3, expires_at = $4 \
+             RETURNING {}",
+            THROTTLE_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(ObjectId::new().to_hex())
+            .bind(key)
+            .bind(at)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+        throttle_from_row(&row)
+    }
+
+    async fn lock(
+        &self,
+        key: &str,
+        locked_until: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query("UPDATE login_throttles SET failures = 0, locked_until = $2, expires_at = $3 WHERE key = $1")
+            .bind(key)
+            .bind(locked_until)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), RepositoryError> {
+        sqlx::query("DELETE FROM login_throttles WHERE key = $1")
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_with_prefix(&self, prefix: &str) -> Result<(), RepositoryError> {
+        sqlx::query("DELETE FROM login_throttles WHERE starts_with(key, $1)")
+            .bind(prefix)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM login_throttles WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+const AUDIT_EVENT_COLUMNS: &str = "id, actor_id, actor_name, action, target_id, ip, user_agent, outcome, detail, created_at";
+
+fn audit_event_from_row(row: &PgRow) -> Result<AuditEvent, RepositoryError> {
+    Ok(AuditEvent {
+        id: Some(object_id_column(row, "id")?),
+        actor_id: optional_object_id_column(row, "actor_id")?,
+        actor_name: row.try_get("actor_name")?,
+        action: enum_from_text(row.try_get("action")?)?,
+        target_id: optional_object_id_column(row, "target_id")?,
+        ip: row.try_get("ip")?,
+        user_agent: row.try_get("user_agent")?,
+        outcome: enum_from_text(row.try_get("outcome")?)?,
+        detail: row.try_get("detail")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+pub struct PostgresAuditRepository {
+    pool: PgPool,
+}
+
+impl PostgresAuditRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    fn push_filter(builder: &mut QueryBuilder<'_, Postgres>, filter: &AuditFilter) -> Result<(), RepositoryError> {
+        builder.push(" WHERE TRUE");
+        if let Some(actor_id) = filter.actor_id {
+            builder.push(" AND actor_id = ").push_bind(actor_id.to_hex());
+        }
+        if let Some(actor_name) = &filter.actor_name {
+            builder.push(" AND actor_name = ").push_bind(actor_name.clone());
+        }
+        if let Some(action) = filter.action {
+            builder.push(" AND action = ").push_bind(enum_text(&action)?);
+        }
+        if let Some(target_id) = filter.target_id {
+            builder.push(" AND target_id = ").push_bind(target_id.to_hex());
+        }
+        if let Some(ip) = &filter.ip {
+            builder.push(" AND ip = ").push_bind(ip.clone());
+        }
+        if let Some(outcome) = filter.outcome {
+            builder.push(" AND outcome = ").push_bind(enum_text(&outcome)?);
+        }
+        if let Some(from) = filter.from {
+            builder.push(" AND created_at >= ").push_bind(from);
+        }
+        if let Some(to) = filter.to {
+            builder.push(" AND created_at < ").push_bind(to);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AuditRepository for PostgresAuditRepository {
+    async fn insert(&self, event: &AuditEvent) -> Result<ObjectId, RepositoryError> {
+        let id = new_id(event.id);
+        sqlx::query(
+            "INSERT INTO audit_events (id, actor_id, actor_name, action, target_id, ip, user_agent, outcome, detail, \
+             created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+        )
+        .bind(id.to_hex())
+        .bind(event.actor_id.map(|id| id.to_hex()))
+        .bind(&event.actor_name)
+        .bind(enum_text(&event.action)?)
+        .bind(event.target_id.map(|id| id.to_hex()))
+        .bind(&event.ip)
+        .bind(&event.user_agent)
+        .bind(enum_text(&event.outcome)?)
+        .bind(&event.detail)
+        .bind(event.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn find(
+        &self,
+        filter: &AuditFilter,
+        oldest_first: bool,
+        skip: u64,
+        limit: i64,
+    ) -> Result<Vec<AuditEvent>, RepositoryError> {
+        let mut builder = QueryBuilder::new(format!("SELECT {} FROM audit_events", AUDIT_EVENT_COLUMNS));
+        Self::push_filter(&mut builder, filter)?;
+        builder.push(if oldest_first {
+            " ORDER BY created_at, id"
+        } else {
+            " ORDER BY created_at DESC, id DESC"
+        });
+        builder.push(" OFFSET ").push_bind(skip as i64);
+        builder.push(" LIMIT ").push_bind(limit);
+        let rows = builder.build().fetch_all(&self.pool).await?;
+        rows.iter().map(audit_event_from_row).collect()
+    }
+
+    async fn count(&self, filter: &AuditFilter) -> Result<u64, RepositoryError> {
+        let mut builder = QueryBuilder::new("SELECT COUNT(*) FROM audit_events");
+        Self::push_filter(&mut builder, filter)?;
+        let count: i64 = builder.build_query_scalar().fetch_one(&self.pool).await?;
+        Ok(count as u64)
+    }
+}
+
+const OUTBOX_COLUMNS: &str = "id, recipient, subject, text_body, html_body, status, attempts, last_error, \
+    next_attempt_at, sent_at, created_at, expires_at";
+
+fn outbox_email_from_row(row: &PgRow) -> Result<OutboxEmail, RepositoryError> {
+    Ok(OutboxEmail {
+        id: Some(object_id_column(row, "id")?),
+        email: EmailMessage {
+            to: row.try_get("recipient")?,
+            subject: row.try_get("subject")?,
+            text_body: row.try_get("text_body")?,
+            html_body: row.try_get("html_body")?,
+        },
+        status: enum_from_text(row.try_get("status")?)?,
+        attempts: row.try_get("attempts")?,
+        last_error: row.try_get("last_error")?,
+        next_attempt_at: row.try_get("next_attempt_at")?,
+        sent_at: row.try_get("sent_at")?,
+        created_at: row.try_get("created_at")?,
+        expires_at: row.try_get("expires_at")?,
+    })
+}
+
+pub struct PostgresOutboxRepository {
+    pool: PgPool,
+}
+
+impl PostgresOutboxRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl OutboxRepository for PostgresOutboxRepository {
+    async fn insert(&self, email: &OutboxEmail) -> Result<ObjectId, RepositoryError> {
+        let id = new_id(email.id);
+        sqlx::query(
+            "INSERT INTO mail_outbox (id, recipient, subject, text_body, html_body, status, attempts, last_error, \
+             next_attempt_at, sent_at, created_at, expires_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+        )
+        .bind(id.to_hex())
+        .bind(&email.email.to)
+        .bind(&email.email.subject)
+        .bind(&email.email.text_body)
+        .bind(&email.email.html_body)
+        .bind(enum_text(&email.status)?)
+        .bind(email.attempts)
+        .bind(&email.last_error)
+        .bind(email.next_attempt_at)
+        .bind(email.sent_at)
+        .bind(email.created_at)
+        .bind(email.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> Result<Option<OutboxEmail>, RepositoryError> {
+        let sql = format!("SELECT {} FROM mail_outbox WHERE id = $1", OUTBOX_COLUMNS);
+        let row = sqlx::query(&sql).bind(id.to_hex()).fetch_optional(&self.pool).await?;
+        row.as_ref().map(outbox_email_from_row).transpose()
+    }
+
+    async fn find_due(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<OutboxEmail>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM mail_outbox WHERE status = $1 AND next_attempt_at <= $2 ORDER BY next_attempt_at LIMIT $3",
+            OUTBOX_COLUMNS
+        );
+        let rows = sqlx::query(&sql)
+            .bind(enum_text(&OutboxStatus::Pending)?)
+            .bind(now)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(outbox_email_from_row).collect()
+    }
+
+    async fn record_sent(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        sent_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "UPDATE mail_outbox SET status = $2, attempts = $3, sent_at = $4, last_error = NULL, text_body = '', \
+             html_body = '', expires_at = $5 WHERE id = $1",
+        )
+        .bind(id.to_hex())
+        .bind(enum_text(&OutboxStatus::Sent)?)
+        .bind(attempts)
+        .bind(sent_at)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_failure(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "UPDATE mail_outbox SET status = $2, attempts = $3, last_error = $4, next_attempt_at = $5 WHERE id = $1",
+        )
+        .bind(id.to_hex())
+        .bind(enum_text(&OutboxStatus::Pending)?)
+        .bind(attempts)
+        .bind(error)
+        .bind(next_attempt_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn give_up(
+        &self,
+        id: ObjectId,
+        attempts: i32,
+        error: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "UPDATE mail_outbox SET status = $2, attempts = $3, last_error = $4, text_body = '', html_body = '', \
+             expires_at = $5 WHERE id = $1",
+        )
+        .bind(id.to_hex())
+        .bind(enum_text(&OutboxStatus::Failed)?)
+        .bind(attempts)
+        .bind(error)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM mail_outbox WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+const NOTIFICATION_PREFERENCE_COLUMNS: &str = "id, user_id, notification_type, in_app, email, muted, updated_at";
+
+fn notification_preference_from_row(row: &PgRow) -> Result<NotificationPreference, RepositoryError> {
+    Ok(NotificationPreference {
+        id: Some(object_id_column(row, "id")?),
+        user_id: object_id_column(row, "user_id")?,
+        notification_type: notification_type(row.try_get("notification_type")?)?,
+        in_app: row.try_get("in_app")?,
+        email: row.try_get("email")?,
+        muted: row.try_get("muted")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+pub struct PostgresNotificationPreferenceRepository {
+    pool: PgPool,
+}
+
+impl PostgresNotificationPreferenceRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl NotificationPreferenceRepository for PostgresNotificationPreferenceRepository {
+    async fn find(
+        &self,
+        user_id: ObjectId,
+        notification_type: NotificationType,
+    ) -> Result<Option<NotificationPreference>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM notification_preferences WHERE user_id = $1 AND notification_type = $2",
+            NOTIFICATION_PREFERENCE_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(user_id.to_hex())
+            .bind(notification_type.as_str())
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(notification_preference_from_row).transpose()
+    }
+
+    async fn find_for_user(&self, user_id: ObjectId) -> Result<Vec<NotificationPreference>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM notification_preferences WHERE user_id = $1",
+            NOTIFICATION_PREFERENCE_COLUMNS
+        );
+        let rows = sqlx::query(&sql).bind(user_id.to_hex()).fetch_all(&self.pool).await?;
+        rows.iter().map(notification_preference_from_row).collect()
+    }
+
+    async fn upsert(&self, preference: &NotificationPreference) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO notification_preferences (id, user_id, notification_type, in_app, email, muted, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) \
+             ON CONFLICT (user_id, notification_type) DO UPDATE SET in_app = EXCLUDED.in_app, \
+             email = EXCLUDED.email, muted = EXCLUDED.muted, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(new_id(preference.id).to_hex())
+        .bind(preference.user_id.to_hex())
+        .bind(preference.notification_type.as_str())
+        .bind(preference.in_app)
+        .bind(preference.email)
+        .bind(preference.muted)
+        .bind(preference.updated_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+const DIGEST_PREFERENCE_COLUMNS: &str =
+    "id, user_id, frequency, quiet_hours_start, quiet_hours_end, utc_offset_minutes, last_sent_at";
+
+fn digest_preference_from_row(row: &PgRow) -> Result<DigestPreference, RepositoryError> {
+    let hour = |column: &str| -> Result<Option<u32>, RepositoryError> {
+        Ok(row.try_get::<Option<i32>, _>(column)?.map(|hour| hour as u32))
+    };
+    Ok(DigestPreference {
+        id: Some(object_id_column(row, "id")?),
+        user_id: object_id_column(row, "user_id")?,
+        frequency: enum_from_text(row.try_get("frequency")?)?,
+        quiet_hours_start: hour("quiet_hours_start")?,
+        quiet_hours_end: hour("quiet_hours_end")?,
+        utc_offset_minutes: row.try_get("utc_offset_minutes")?,
+        last_sent_at: row.try_get("last_sent_at")?,
+    })
+}
+
+pub struct PostgresDigestPreferenceRepository {
+    pool: PgPool,
+}
+
+impl PostgresDigestPreferenceRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl DigestPreferenceRepository for PostgresDigestPreferenceRepository {
+    async fn find(&self, user_id: ObjectId) -> Result<Option<DigestPreference>, RepositoryError> {
+        let sql = format!("SELECT {} FROM digest_preferences WHERE user_id = $1", DIGEST_PREFERENCE_COLUMNS);
+        let row = sqlx::query(&sql).bind(user_id.to_hex()).fetch_optional(&self.pool).await?;
+        row.as_ref().map(digest_preference_from_row).transpose()
+    }
+
+    async fn find_for_users(&self, user_ids: &[ObjectId]) -> Result<Vec<DigestPreference>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM digest_preferences WHERE user_id = ANY($1)",
+            DIGEST_PREFERENCE_COLUMNS
+        );
+        let rows = sqlx::query(&sql).bind(hex_ids(user_ids)).fetch_all(&self.pool).await?;
+        rows.iter().map(digest_preference_from_row).collect()
+    }
+
+    async fn upsert(&self, preference: &DigestPreference) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO digest_preferences (id, user_id, frequency, quiet_hours_start, quiet_hours_end, \
+             utc_offset_minutes) VALUES ($1, $2, $3, $4, $5, $6) \
+             ON CONFLICT (user_id) DO UPDATE SET frequency = EXCLUDED.frequency, \
+             quiet_hours_start = EXCLUDED.quiet_hours_start, quiet_hours_end = EXCLUDED.quiet_hours_end, \
+             utc_offset_minutes = EXCLUDED.utc_offset_minutes",
+        )
+        .bind(new_id(preference.id).to_hex())
+        .bind(preference.user_id.to_hex())
+        .bind(enum_text(&preference.frequency)?)
+        .bind(preference.quiet_hours_start.map(|hour| hour as i32))
+        .bind(preference.quiet_hours_end.map(|hour| hour as i32))
+        .bind(preference.utc_offset_minutes)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_sent(&self, user_id: ObjectId, at: DateTime<Utc>) -> Result<(), RepositoryError> {
+        let default_preference = DigestPreference::default_for(user_id);
+        sqlx::query(
+            "INSERT INTO digest_preferences (id, user_id, frequency, utc_offset_minutes, last_sent_at) \
+             VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT (user_id) DO UPDATE SET last_sent_at = EXCLUDED.last_sent_at",
+        )
+        .bind(ObjectId::new().to_hex())
+        .bind(user_id.to_hex())
+        .bind(enum_text(&default_preference.frequency)?)
+        .bind(default_preference.utc_offset_minutes)
+        .bind(at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+const REPORT_COLUMNS: &str = "id, reporter_id, reported_user_id, message_id, chat_id, message_content, reason, \
+    details, status, handled_by, handled_at, created_at";
+
+fn report_from_row(row: &PgRow) -> Result<Report, RepositoryError> {
+    Ok(Report {
+        id: Some(object_id_column(row, "id")?),
+        reporter_id: object_id_column(row, "reporter_id")?,
+        reported_user_id: object_id_column(row, "reported_user_id")?,
+        message_id: optional_object_id_column(row, "message_id")?,
+        chat_id: optional_object_id_column(row, "chat_id")?,
+        message_content: row.try_get("message_content")?,
+        reason: enum_from_text(row.try_get("reason")?)?,
+        details: row.try_get("details")?,
+        status: enum_from_text(row.try_get("status")?)?,
+        handled_by: optional_object_id_column(row, "handled_by")?,
+        handled_at: row.try_get("handled_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+pub struct PostgresReportRepository {
+    pool: PgPool,
+}
+
+impl PostgresReportRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl ReportRepository for PostgresReportRepository {
+    async fn insert(&self, report: &Report) -> Result<ObjectId, RepositoryError> {
+        let id = new_id(report.id);
+        sqlx::query(
+            "INSERT INTO reports (id, reporter_id, reported_user_id, message_id, chat_id, message_content, reason, \
+             details, status, handled_by, handled_at, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+        )
+        .bind(id.to_hex())
+        .bind(report.reporter_id.to_hex())
+        .bind(report.reported_user_id.to_hex())
+        .bind(report.message_id.map(|id| id.to_hex()))
+        .bind(report.chat_id.map(|id| id.to_hex()))
+        .bind(&report.message_content)
+        .bind(enum_text(&report.reason)?)
+        .bind(&report.details)
+        .bind(enum_text(&report.status)?)
+        .bind(report.handled_by.map(|id| id.to_hex()))
+        .bind(report.handled_at)
+        .bind(report.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, report_id: ObjectId) -> Result<Option<Report>, RepositoryError> {
+        let sql = format!("SELECT {} FROM reports WHERE id = $1", REPORT_COLUMNS);
+        let row = sqlx::query(&sql).bind(report_id.to_hex()).fetch_optional(&self.pool).await?;
+        row.as_ref().map(report_from_row).transpose()
+    }
+
+    async fn has_open_duplicate(&self, report: &Report) -> Result<bool, RepositoryError> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM reports WHERE reporter_id = $1 AND reported_user_id = $2 \
+             AND message_id IS NOT DISTINCT FROM $3 AND status = $4)",
+        )
+        .bind(report.reporter_id.to_hex())
+        .bind(report.reported_user_id.to_hex())
+        .bind(report.message_id.map(|id| id.to_hex()))
+        .bind(enum_text(&ReportStatus::Open)?)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(exists)
+    }
+
+    async fn find(&self, filter: &ReportFilter, skip: u64, limit: i64) -> Result<Vec<Report>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM reports WHERE status = $1 AND ($2::TEXT IS NULL OR reason = $2) \
+             ORDER BY created_at, id OFFSET $3 LIMIT $4",
+            REPORT_COLUMNS
+        );
+        let rows = sqlx::query(&sql)
+            .bind(enum_text(&filter.status)?)
+            .bind(filter.reason.as_ref().map(enum_text).transpose()?)
+            .bind(skip as i64)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(report_from_row).collect()
+    }
+
+    async fn count(&self, filter: &ReportFilter) -> Result<u64, RepositoryError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM reports WHERE status = $1 AND ($2::TEXT IS NULL OR reason = $2)")
+            .bind(enum_text(&filter.status)?)
+            .bind(filter.reason.as_ref().map(enum_text).transpose()?)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    async fn close(
+        &self,
+        selection: ReportSelection,
+        status: ReportStatus,
+        handled_by: ObjectId,
+        at: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let (column, id) = match selection {
+            ReportSelection::Report(report_id) => ("id", report_id),
+            ReportSelection::Message(message_id) => ("message_id", message_id),
+            ReportSelection::ReportedUser(user_id) => ("reported_user_id", user_id),
+        };
+        let sql = format!(
+            "UPDATE reports SET status = $2, handled_by = $3, handled_at = $4 WHERE {} = $1 AND status = $5",
+            column
+        );
+        let result = sqlx::query(&sql)
+            .bind(id.to_hex())
+            .bind(enum_text(&status)?)
+            .bind(handled_by.to_hex())
+            .bind(at)
+            .bind(enum_text(&ReportStatus::Open)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+const MODERATION_ACTION_COLUMNS: &str = "id, moderator_id, action, target_user_id, message_id, report_id, note, created_at";
+
+fn moderation_action_from_row(row: &PgRow) -> Result<ModerationAction, RepositoryError> {
+    Ok(ModerationAction {
+        id: Some(object_id_column(row, "id")?),
+        moderator_id: object_id_column(row, "moderator_id")?,
+        action: enum_from_text(row.try_get("action")?)?,
+        target_user_id: object_id_column(row, "target_user_id")?,
+        message_id: optional_object_id_column(row, "message_id")?,
+        report_id: optional_object_id_column(row, "report_id")?,
+        note: row.try_get("note")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+pub struct PostgresModerationActionRepository {
+    pool: PgPool,
+}
+
+impl PostgresModerationActionRepository {
+    pub fn new(pool: &PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+#[async_trait]
+impl ModerationActionRepository for PostgresModerationActionRepository {
+    async fn insert(&self, action: &ModerationAction) -> Result<ObjectId, RepositoryError> {
+        let id = new_id(action.id);
+        sqlx::query(
+            "INSERT INTO moderation_actions (id, moderator_id, action, target_user_id, message_id, report_id, note, \
+             created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(id.to_hex())
+        .bind(action.moderator_id.to_hex())
+        .bind(enum_text(&action.action)?)
+        .bind(action.target_user_id.to_hex())
+        .bind(action.message_id.map(|id| id.to_hex()))
+        .bind(action.report_id.map(|id| id.to_hex()))
+        .bind(&action.note)
+        .bind(action.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn find(
+        &self,
+        target_user_id: Option<ObjectId>,
+        skip: u64,
+        limit: i64,
+    ) -> Result<Vec<ModerationAction>, RepositoryError> {
+        let sql = format!(
+            "SELECT {} FROM moderation_actions WHERE ($1::TEXT IS NULL OR target_user_id = $1) \
+             ORDER BY created_at DESC, id DESC OFFSET $2 LIMIT $3",
+            MODERATION_ACTION_COLUMNS
+        );
+        let rows = sqlx::query(&sql)
+            .bind(target_user_id.map(|id| id.to_hex()))
+            .bind(skip as i64)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(moderation_action_from_row).collect()
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{
+    models::report_model::Report,
+    repositories::repository::RepositoryError,
+    types::moderation_types::{ReportReason, ReportStatus},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+/// Narrows the moderation queue to one status and, optionally, one reason.
+#[derive(Debug, Clone, Copy)]
+pub struct ReportFilter {
+    pub status: ReportStatus,
+    pub reason: Option<ReportReason>,
+}
+
+impl ReportFilter {
+    pub fn matches(&self, report: &Report) -> bool {
+        report.status == self.status && self.reason.is_none_or(|reason| report.reason == reason)
+    }
+}
+
+/// Which open reports a moderator's action closes.
+#[derive(Debug, Clone, Copy)]
+pub enum ReportSelection {
+    Report(ObjectId),
+    /// Every report about the message.
+    Message(ObjectId),
+    /// Every report against the user.
+    ReportedUser(ObjectId),
+}
+
+impl ReportSelection {
+    pub fn matches(&self, report: &Report) -> bool {
+        match *self {
+            Self::Report(report_id) => report.id == Some(report_id),
+            Self::Message(message_id) => report.message_id == Some(message_id),
+            Self::ReportedUser(user_id) => report.reported_user_id == user_id,
+        }
+    }
+}
+
+#[async_trait]
+pub trait ReportRepository: Send + Sync {
+    async fn insert(&self, report: &Report) -> Result<ObjectId, RepositoryError>;
+    async fn find_by_id(&self, report_id: ObjectId) -> Result<Option<Report>, RepositoryError>;
+    /// Whether the reporter already has an open report about the same user and message.
+    async fn has_open_duplicate(&self, report: &Report) -> Result<bool, RepositoryError>;
+    /// Matching reports, oldest first.
+    async fn find(&self, filter: &ReportFilter, skip: u64, limit: i64) -> Result<Vec<Report>, RepositoryError>;
+    async fn count(&self, filter: &ReportFilter) -> Result<u64, RepositoryError>;
+    /// Give the selected open reports `status`, handled by the moderator. Returns how many changed.
+    async fn close(
+        &self,
+        selection: ReportSelection,
+        status: ReportStatus,
+        handled_by: ObjectId,
+        at: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError>;
+}
@@ -0,0 +1,170 @@
+use crate::config::database::Storage;
+use crate::repositories::{
+    api_token_repository::ApiTokenRepository,
+    audit_repository::AuditRepository,
+    chat_repository::ChatRepository,
+    digest_preference_repository::DigestPreferenceRepository,
+    memory_repository::{
+        MemoryApiTokenRepository, MemoryAuditRepository, MemoryChatRepository, MemoryDigestPreferenceRepository,
+        MemoryMessageRepository, MemoryModerationActionRepository, MemoryNotificationPreferenceRepository,
+        MemoryNotificationRepository, MemoryOutboxRepository, MemoryReportRepository, MemorySavedMessageRepository,
+        MemorySessionRepository, MemoryThrottleRepository, MemoryUserRepository,
+    },
+    message_repository::MessageRepository,
+    moderation_action_repository::ModerationActionRepository,
+    mongo_repository::{
+        MongoApiTokenRepository, MongoAuditRepository, MongoChatRepository, MongoDigestPreferenceRepository,
+        MongoMessageRepository, MongoModerationActionRepository, MongoNotificationPreferenceRepository,
+        MongoNotificationRepository, MongoOutboxRepository, MongoReportRepository, MongoSavedMessageRepository,
+        MongoSessionRepository, MongoThrottleRepository, MongoUserRepository,
+    },
+    notification_preference_repository::NotificationPreferenceRepository,
+    notification_repository::NotificationRepository,
+    outbox_repository::OutboxRepository,
+    postgres_repository::{
+        PostgresApiTokenRepository, PostgresAuditRepository, PostgresChatRepository,
+        PostgresDigestPreferenceRepository, PostgresMessageRepository, PostgresModerationActionRepository,
+        PostgresNotificationPreferenceRepository, PostgresNotificationRepository, PostgresOutboxRepository,
+        PostgresReportRepository, PostgresSavedMessageRepository, PostgresSessionRepository,
+        PostgresThrottleRepository, PostgresUserRepository,
+    },
+    report_repository::ReportRepository,
+    saved_message_repository::SavedMessageRepository,
+    session_repository::SessionRepository,
+    throttle_repository::ThrottleRepository,
+    user_repository::UserRepository,
+};
+use mongodb::{
+    error::{ErrorKind, WriteFailure},
+    Client, Database,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RepositoryError {
+    #[error("database error: {0}")]
+    Database(String),
+    /// A unique index, such as the one on usernames, already holds the value.
+    #[error("a record with the same unique value already exists")]
+    Duplicate,
+}
+
+/// MongoDB's error code for a unique index violation.
+const MONGO_DUPLICATE_KEY: i32 = 11000;
+
+impl From<mongodb::error::Error> for RepositoryError {
+    fn from(e: mongodb::error::Error) -> Self {
+        match e.kind.as_ref() {
+            ErrorKind::Write(WriteFailure::WriteError(write_error)) if write_error.code == MONGO_DUPLICATE_KEY => {
+                Self::Duplicate
+            }
+            ErrorKind::Command(command_error) if command_error.code == MONGO_DUPLICATE_KEY => Self::Duplicate,
+            _ => Self::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<mongodb::bson::ser::Error> for RepositoryError {
+    fn from(e: mongodb::bson::ser::Error) -> Self {
+        Self::Database(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for RepositoryError {
+    fn from(e: sqlx::Error) -> Self {
+        match e.as_database_error() {
+            Some(database_error) if database_error.is_unique_violation() => Self::Duplicate,
+            _ => Self::Database(e.to_string()),
+        }
+    }
+}
+
+/// Every store the server keeps state in, shared through `AppState`.
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    pub chats: Arc<dyn ChatRepository>,
+    pub messages: Arc<dyn MessageRepository>,
+    pub notifications: Arc<dyn NotificationRepository>,
+    pub saved_messages: Arc<dyn SavedMessageRepository>,
+    pub sessions: Arc<dyn SessionRepository>,
+    pub api_tokens: Arc<dyn ApiTokenRepository>,
+    pub throttles: Arc<dyn ThrottleRepository>,
+    pub audit_events: Arc<dyn AuditRepository>,
+    pub outbox: Arc<dyn OutboxRepository>,
+    pub notification_preferences: Arc<dyn NotificationPreferenceRepository>,
+    pub digest_preferences: Arc<dyn DigestPreferenceRepository>,
+    pub reports: Arc<dyn ReportRepository>,
+    pub moderation_actions: Arc<dyn ModerationActionRepository>,
+}
+
+impl Repositories {
+    pub fn mongo(client: &Client, db: &Database) -> Self {
+        Self {
+            users: Arc::new(MongoUserRepository::new(db)),
+            chats: Arc::new(MongoChatRepository::new(client, db)),
+            messages: Arc::new(MongoMessageRepository::new(db)),
+            notifications: Arc::new(MongoNotificationRepository::new(db)),
+            saved_messages: Arc::new(MongoSavedMessageRepository::new(db)),
+            sessions: Arc::new(MongoSessionRepository::new(db)),
+            api_tokens: Arc::new(MongoApiTokenRepository::new(db)),
+            throttles: Arc::new(MongoThrottleRepository::new(db)),
+            audit_events: Arc::new(MongoAuditRepository::new(db)),
+            outbox: Arc::new(MongoOutboxRepository::new(db)),
+            notification_preferences: Arc::new(MongoNotificationPreferenceRepository::new(db)),
+            digest_preferences: Arc::new(MongoDigestPreferenceRepository::new(db)),
+            reports: Arc::new(MongoReportRepository::new(db)),
+            moderation_actions: Arc::new(MongoModerationActionRepository::new(db)),
+        }
+    }
+
+    pub fn postgres(pool: &PgPool) -> Self {
+        Self {
+            users: Arc::new(PostgresUserRepository::new(pool)),
+            chats: Arc::new(PostgresChatRepository::new(pool)),
+            messages: Arc::new(PostgresMessageRepository::new(pool)),
+            notifications: Arc::new(PostgresNotificationRepository::new(pool)),
+            saved_messages: Arc::new(PostgresSavedMessageRepository::new(pool)),
+            sessions: Arc::new(PostgresSessionRepository::new(pool)),
+            api_tokens: Arc::new(PostgresApiTokenRepository::new(pool)),
+            throttles: Arc::new(PostgresThrottleRepository::new(pool)),
+            audit_events: Arc::new(PostgresAuditRepository::new(pool)),
+            outbox: Arc::new(PostgresOutboxRepository::new(pool)),
+            notification_preferences: Arc::new(PostgresNotificationPreferenceRepository::new(pool)),
+            digest_preferences: Arc::new(PostgresDigestPreferenceRepository::new(pool)),
+            reports: Arc::new(PostgresReportRepository::new(pool)),
+            moderation_actions: Arc::new(PostgresModerationActionRepository::new(pool)),
+        }
+    }
+
+    /// Empty stores that live in memory, for tests and local experiments.
+    pub fn in_memory() -> Self {
+        let messages = Arc::new(MemoryMessageRepository::default());
+        Self {
+            users: Arc::new(MemoryUserRepository::default()),
+            chats: Arc::new(MemoryChatRepository::new(messages.clone())),
+            messages,
+            notifications: Arc::new(MemoryNotificationRepository::default()),
+            saved_messages: Arc::new(MemorySavedMessageRepository::default()),
+            sessions: Arc::new(MemorySessionRepository::default()),
+            api_tokens: Arc::new(MemoryApiTokenRepository::default()),
+            throttles: Arc::new(MemoryThrottleRepository::default()),
+            audit_events: Arc::new(MemoryAuditRepository::default()),
+            outbox: Arc::new(MemoryOutboxRepository::default()),
+            notification_preferences: Arc::new(MemoryNotificationPreferenceRepository::default()),
+            digest_preferences: Arc::new(MemoryDigestPreferenceRepository::default()),
+            reports: Arc::new(MemoryReportRepository::default()),
+            moderation_actions: Arc::new(MemoryModerationActionRepository::default()),
+        }
+    }
+}
+
+/// Opens the stores of the connected storage backend.
+pub fn repositories_for(storage: &Storage) -> Repositories {
+    match storage {
+        Storage::Mongo { client, db } => Repositories::mongo(client, db),
+        Storage::Postgres(pool) => Repositories::postgres(pool),
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{models::saved_message_model::SavedMessage, repositories::repository::RepositoryError};
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+
+#[async_trait]
+pub trait SavedMessageRepository: Send + Sync {
+    /// Save the message for the user, or replace the note and tags if they already saved it.
+    /// Returns the stored entry, which keeps the time it was first saved.
+    async fn upsert(&self, saved: &SavedMessage) -> Result<SavedMessage, RepositoryError>;
+    /// The user's saved messages, newest first, optionally only those with `tag`.
+    async fn find_for_user(
+        &self,
+        user_id: ObjectId,
+        tag: Option<&str>,
+        skip: u64,
+        limit: Option<i64>,
+    ) -> Result<Vec<SavedMessage>, RepositoryError>;
+    /// Returns false when the user had not saved the message.
+    async fn delete(&self, user_id: ObjectId, message_id: ObjectId) -> Result<bool, RepositoryError>;
+    /// Removes every user's entry for the message. Returns how many were removed.
+    async fn delete_by_message(&self, message_id: ObjectId) -> Result<u64, RepositoryError>;
+    /// Removes the entries for messages in the chat, only the user's when `user_id` is given.
+    /// Returns how many were removed.
+    async fn delete_by_chat(&self, chat_id: ObjectId, user_id: Option<ObjectId>) -> Result<u64, RepositoryError>;
+}
@@ -0,0 +1,31 @@
+use crate::{models::session_model::StoredSession, repositories::repository::RepositoryError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+/// Server-side sessions, looked up by the hash of their key. Sessions past `expires_at` count as gone.
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    async fn insert(&self, session: &StoredSession) -> Result<(), RepositoryError>;
+    async fn find_by_key_hash(&self, key_hash: &str, now: DateTime<Utc>) -> Result<Option<StoredSession>, RepositoryError>;
+    /// Replace the state, the fields copied out of it, `last_seen_at` and `expires_at`.
+    /// Returns false when there is no such session.
+    async fn update_state(&self, session: &StoredSession) -> Result<bool, RepositoryError>;
+    async fn touch(
+        &self,
+        key_hash: &str,
+        last_seen_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError>;
+    async fn delete_by_key_hash(&self, key_hash: &str) -> Result<(), RepositoryError>;
+    /// The user's sessions, most recently used first.
+    async fn find_for_user(&self, user_id: ObjectId, now: DateTime<Utc>) -> Result<Vec<StoredSession>, RepositoryError>;
+    /// Returns false when the user has no session with this public id.
+    async fn delete_for_user(&self, user_id: ObjectId, session_id: &str) -> Result<bool, RepositoryError>;
+    /// Removes every session of the user. Returns how many were removed.
+    async fn delete_all_for_user(&self, user_id: ObjectId) -> Result<u64, RepositoryError>;
+    /// Sessions that are signed in to an account.
+    async fn count_signed_in(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError>;
+    /// Removes expired sessions. Returns how many were removed.
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError>;
+}
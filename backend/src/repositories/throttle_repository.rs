@@ -0,0 +1,28 @@
+use crate::{models::login_throttle_model::LoginThrottle, repositories::repository::RepositoryError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+/// Failed-attempt counters by throttle key. Records past `expires_at` count as absent.
+#[async_trait]
+pub trait ThrottleRepository: Send + Sync {
+    async fn find(&self, key: &str, now: DateTime<Utc>) -> Result<Option<LoginThrottle>, RepositoryError>;
+    /// Count a failure at `at`, starting a new record when there is none, and return the updated record.
+    async fn increment(
+        &self,
+        key: &str,
+        at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<LoginThrottle, RepositoryError>;
+    /// Lock the key until `locked_until` and reset its failure count.
+    async fn lock(
+        &self,
+        key: &str,
+        locked_until: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError>;
+    async fn delete(&self, key: &str) -> Result<(), RepositoryError>;
+    /// Remove every record whose key starts with `prefix`.
+    async fn delete_with_prefix(&self, prefix: &str) -> Result<(), RepositoryError>;
+    /// Removes expired records. Returns how many were removed.
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64, RepositoryError>;
+}
@@ -0,0 +1,69 @@
+use crate::{
+    models::user_model::{OidcIdentity, User, UserRole},
+    repositories::repository::RepositoryError,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+/// Narrows a listing of users; unset fields match everyone.
+#[derive(Debug, Default, Clone)]
+pub struct UserFilter {
+    /// Case-insensitive substring of the username or email.
+    pub query: Option<String>,
+    pub role: Option<UserRole>,
+    pub suspended: Option<bool>,
+}
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn insert(&self, user: &User) -> Result<ObjectId, RepositoryError>;
+    async fn find_by_id(&self, user_id: ObjectId) -> Result<Option<User>, RepositoryError>;
+    async fn find_by_ids(&self, user_ids: &[ObjectId]) -> Result<Vec<User>, RepositoryError>;
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, RepositoryError>;
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepositoryError>;
+    async fn find_by_username_or_email(&self, username: &str, email: &str) -> Result<Option<User>, RepositoryError>;
+    async fn find_by_reset_token_hash(&self, token_hash: &str) -> Result<Option<User>, RepositoryError>;
+    async fn find_by_oidc_identity(&self, identity: &OidcIdentity) -> Result<Option<User>, RepositoryError>;
+    /// Matching users, newest first.
+    async fn find_users(&self, filter: &UserFilter, skip: u64, limit: Option<i64>) -> Result<Vec<User>, RepositoryError>;
+    async fn count_users(&self, filter: &UserFilter) -> Result<u64, RepositoryError>;
+
+    /// Returns false when there is no such user.
+    async fn set_email_verified(&self, user_id: ObjectId) -> Result<bool, RepositoryError>;
+    async fn set_reset_token(&self, user_id: ObjectId, token_hash: &str, expires_at_millis: i64) -> Result<(), RepositoryError>;
+    /// Swap in a new password if the reset token is still outstanding and unexpired, consuming it.
+    /// Returns false when the token was already used or has expired.
+    async fn complete_password_reset(
+        &self,
+        user_id: ObjectId,
+        token_hash: &str,
+        now_millis: i64,
+        password_hash: &str,
+        password_history: &[String],
+    ) -> Result<bool, RepositoryError>;
+    /// Returns false when there is no such user.
+    async fn set_password_hash(&self, user_id: ObjectId, password_hash: &str) -> Result<bool, RepositoryError>;
+    async fn increment_session_version(&self, user_id: ObjectId) -> Result<(), RepositoryError>;
+    async fn touch_last_seen(&self, user_id: ObjectId, at: DateTime<Utc>) -> Result<(), RepositoryError>;
+
+    async fn set_totp_secret(&self, user_id: ObjectId, secret: &str) -> Result<(), RepositoryError>;
+    async fn enable_totp(&self, user_id: ObjectId, step: i64, recovery_code_hashes: &[String]) -> Result<(), RepositoryError>;
+    async fn disable_totp(&self, user_id: ObjectId) -> Result<(), RepositoryError>;
+    /// Record `step` as used unless it or a later step already was. Returns whether it was recorded.
+    async fn consume_totp_step(&self, user_id: ObjectId, step: i64) -> Result<bool, RepositoryError>;
+    /// Remove a recovery code hash. Returns whether it was still there.
+    async fn consume_recovery_code(&self, user_id: ObjectId, code_hash: &str) -> Result<bool, RepositoryError>;
+
+    /// Attach the identity to the account with this email, if that email is verified.
+    /// Unverified accounts are left alone, since whoever registered them may not own the address.
+    async fn link_oidc_identity_by_email(&self, email: &str, identity: &OidcIdentity) -> Result<Option<User>, RepositoryError>;
+    /// Overwrite the email of a directory account with the directory's copy.
+    /// Local accounts with the same username are left alone and `None` is returned.
+    async fn sync_directory_email(&self, username: &str, email: &str) -> Result<Option<User>, RepositoryError>;
+
+    /// Returns false when there is no such user.
+    async fn set_role(&self, user_id: ObjectId, role: UserRole) -> Result<bool, RepositoryError>;
+    /// Returns false when there is no such user. The reason is dropped when lifting a suspension.
+    async fn set_suspended(&self, user_id: ObjectId, suspended: bool, reason: Option<&str>) -> Result<bool, RepositoryError>;
+}
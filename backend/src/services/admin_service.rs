@@ -0,0 +1,367 @@
+use crate::{
+    config::app_config::AppConfig,
+    constants::ADMIN_USER_PAGE_LIMIT,
+    models::{
+        audit_event_model::AuditEvent,
+        user_model::{User, UserRole},
+    },
+    services::{
+        audit_service::record_audit_event,
+        auth_service::issue_password_reset,
+        chat_service::hard_delete_chat,
+        throttle_service::unlock_account,
+        user_service::{get_user_by_id, revoke_user_sessions},
+    },
+    repositories::{repository::RepositoryError, user_repository::UserFilter},
+    states::app_state::AppState,
+    types::audit_types::{AuditAction, AuditOutcome},
+    utils::{
+        auth_util::{generate_reset_token, hash_password},
+        request_util::RequestContext,
+    },
+};
+use actix_web::{
+    error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct AdminUserQuery {
+    /// Matched against username and email.
+    pub q: Option<String>,
+    pub role: Option<UserRole>,
+    pub suspended: Option<bool>,
+    pub page: Option<u64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminUserRequest {
+    pub user_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminChatRequest {
+    pub chat_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuspendUserRequest {
+    pub user_id: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetRoleRequest {
+    pub user_id: String,
+    pub role: UserRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminUserSummary {
+    pub user_id: String,
+    pub username: String,
+    pub email: String,
+    pub role: UserRole,
+    pub email_verified: bool,
+    pub totp_enabled: bool,
+    pub suspended: bool,
+    pub suspension_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminUserList {
+    pub users: Vec<AdminUserSummary>,
+    /// Users matching the query across all pages.
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerStats {
+    /// Users with a live WebSocket connection.
+    pub connected_websockets: usize,
+    /// Signed-in browser sessions that have not expired.
+    pub active_sessions: u64,
+    pub users: u64,
+    pub suspended_users: u64,
+    pub chats: u64,
+    pub messages: u64,
+}
+
+impl From<User> for AdminUserSummary {
+    fn from(user: User) -> Self {
+        Self {
+            user_id: user.id.map_or_else(String::new, |id| id.to_hex()),
+            username: user.username,
+            email: user.email,
+            role: user.role,
+            email_verified: user.email_verified,
+            totp_enabled: user.totp_enabled,
+            suspended: user.suspended,
+            suspension_reason: user.suspension_reason,
+            created_at: user.created_at,
+            last_seen_at: user.last_seen_at,
+        }
+    }
+}
+
+pub(crate) fn parse_user_id(user_id: &str) -> Result<ObjectId, Error> {
+    ObjectId::parse_str(user_id).map_err(|_| ErrorBadRequest("Invalid user ID"))
+}
+
+/// Actions on one's own account through the admin API could lock the last admin out.
+fn ensure_not_self(admin_id: ObjectId, user_id: ObjectId) -> Result<(), Error> {
+    if admin_id == user_id {
+        return Err(ErrorBadRequest("You cannot do this to your own account"));
+    }
+    Ok(())
+}
+
+/// Record an admin action on a user or chat in the audit log, with the outcome of `result`.
+async fn audit_admin_action(
+    state: &AppState,
+    context: &RequestContext,
+    action: AuditAction,
+    actor_id: ObjectId,
+    target_id: ObjectId,
+    detail: Option<String>,
+    result: &Result<(), Error>,
+) {
+    let outcome = match result {
+        Ok(()) => AuditOutcome::Success,
+        Err(_) => AuditOutcome::Failure,
+    };
+    let mut event = AuditEvent::new(action, outcome, context);
+    event.actor_id = Some(actor_id);
+    event.target_id = Some(target_id);
+    event.detail = match result {
+        Ok(()) => detail,
+        Err(e) => Some(e.to_string()),
+    };
+    record_audit_event(state, event).await;
+}
+
+/// List users, optionally filtered by a search term, role or suspension, newest first.
+pub async fn list_users(state: &AppState, query: &AdminUserQuery) -> Result<AdminUserList, Error> {
+    let filter = UserFilter {
+        query: query.q.clone(),
+        role: query.role,
+        suspended: query.suspended,
+    };
+    let per_page = query
+        .per_page
+        .unwrap_or(ADMIN_USER_PAGE_LIMIT)
+        .clamp(1, ADMIN_USER_PAGE_LIMIT);
+    let page = query.page.unwrap_or(0);
+    let users_repository = &state.repositories.users;
+    let total = users_repository
+        .count_users(&filter)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to count users"))?;
+    let users = users_repository
+        .find_users(&filter, page * per_page as u64, Some(per_page))
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get users"))?;
+
+    Ok(AdminUserList {
+        users: users.into_iter().map(AdminUserSummary::from).collect(),
+        total,
+    })
+}
+
+/// Turn a repository update that matched nothing into a 404.
+fn user_updated(updated: Result<bool, RepositoryError>) -> Result<(), Error> {
+    match updated {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ErrorNotFound("User not found")),
+        Err(e) => {
+            log::error!("Database error: {}", e);
+            Err(ErrorInternalServerError("Failed to update user"))
+        }
+    }
+}
+
+/// Suspend an account and sign it out everywhere, closing its WebSocket connection.
+///
+/// Moderators may only suspend regular users; admins may suspend anyone but themselves.
+pub async fn suspend_account(
+    state: &AppState,
+    context: &RequestContext,
+    actor_id: ObjectId,
+    user_id: ObjectId,
+    reason: Option<&str>,
+) -> Result<(), Error> {
+    let reason = reason.map(str::trim).filter(|reason| !reason.is_empty());
+    let result = apply_suspension(state, actor_id, user_id, reason).await;
+    audit_admin_action(
+        state,
+        context,
+        AuditAction::UserSuspended,
+        actor_id,
+        user_id,
+        reason.map(str::to_owned),
+        &result,
+    )
+    .await;
+    result
+}
+
+async fn apply_suspension(
+    state: &AppState,
+    actor_id: ObjectId,
+    user_id: ObjectId,
+    reason: Option<&str>,
+) -> Result<(), Error> {
+    ensure_not_self(actor_id, user_id)?;
+    let actor = get_user_by_id(state, actor_id).await?;
+    let target = get_user_by_id(state, user_id).await?;
+    if actor.role != UserRole::Admin && target.role >= actor.role {
+        return Err(ErrorForbidden("You cannot suspend this account"));
+    }
+
+    user_updated(state.repositories.users.set_suspended(user_id, true, reason).await)?;
+    revoke_user_sessions(state, user_id).await?;
+    log::info!("User {} suspended by {}", user_id, actor_id);
+    Ok(())
+}
+
+pub async fn suspend_user(
+    state: &AppState,
+    context: &RequestContext,
+    admin_id: ObjectId,
+    req: &SuspendUserRequest,
+) -> Result<(), Error> {
+    suspend_account(state, context, admin_id, parse_user_id(&req.user_id)?, req.reason.as_deref()).await
+}
+
+pub async fn unsuspend_user(
+    state: &AppState,
+    context: &RequestContext,
+    admin_id: ObjectId,
+    user_id: &str,
+) -> Result<(), Error> {
+    let user_id = parse_user_id(user_id)?;
+    let result = user_updated(state.repositories.users.set_suspended(user_id, false, None).await);
+    audit_admin_action(state, context, AuditAction::UserUnsuspended, admin_id, user_id, None, &result).await;
+    result
+}
+
+pub async fn set_user_role(
+    state: &AppState,
+    context: &RequestContext,
+    admin_id: ObjectId,
+    req: &SetRoleRequest,
+) -> Result<(), Error> {
+    let user_id = parse_user_id(&req.user_id)?;
+    let result = apply_role(state, admin_id, user_id, req.role).await;
+    audit_admin_action(
+        state,
+        context,
+        AuditAction::RoleChanged,
+        admin_id,
+        user_id,
+        Some(req.role.as_str().to_owned()),
+        &result,
+    )
+    .await;
+    result
+}
+
+async fn apply_role(state: &AppState, admin_id: ObjectId, user_id: ObjectId, role: UserRole) -> Result<(), Error> {
+    ensure_not_self(admin_id, user_id)?;
+    user_updated(state.repositories.users.set_role(user_id, role).await)?;
+    log::info!("User {} given the {} role by {}", user_id, role.as_str(), admin_id);
+    Ok(())
+}
+
+/// Replace the user's password with a random one, sign them out and email them a reset link.
+pub async fn force_password_reset(
+    state: &AppState,
+    context: &RequestContext,
+    admin_id: ObjectId,
+    user_id: &str,
+) -> Result<(), Error> {
+    let user_id = parse_user_id(user_id)?;
+    let result = apply_password_reset(state, admin_id, user_id).await;
+    audit_admin_action(state, context, AuditAction::PasswordResetForced, admin_id, user_id, None, &result).await;
+    result
+}
+
+async fn apply_password_reset(state: &AppState, admin_id: ObjectId, user_id: ObjectId) -> Result<(), Error> {
+    let config = AppConfig::new().map_err(|e| {
+        log::error!("Config error: {}", e);
+        ErrorInternalServerError("Config error")
+    })?;
+    let user = get_user_by_id(state, user_id).await?;
+    let password_hash = hash_password(&generate_reset_token())
+        .map_err(|_| ErrorInternalServerError("Failed to hash password"))?;
+    user_updated(state.repositories.users.set_password_hash(user_id, &password_hash).await)?;
+    revoke_user_sessions(state, user_id).await?;
+    issue_password_reset(state, &config, &user).await?;
+    log::info!("Password reset forced for user {} by {}", user_id, admin_id);
+    Ok(())
+}
+
+/// Lift a lockout caused by failed logins.
+pub async fn unlock_user(
+    state: &AppState,
+    context: &RequestContext,
+    admin_id: ObjectId,
+    user_id: &str,
+) -> Result<(), Error> {
+    let user_id = parse_user_id(user_id)?;
+    let result = match get_user_by_id(state, user_id).await {
+        Ok(user) => unlock_account(state, &user.username).await,
+        Err(e) => Err(e),
+    };
+    audit_admin_action(state, context, AuditAction::AccountUnlocked, admin_id, user_id, None, &result).await;
+    result
+}
+
+/// Remove a chat and its messages for every participant right away.
+pub async fn delete_chat_for_everyone(
+    state: &AppState,
+    context: &RequestContext,
+    admin_id: ObjectId,
+    chat_id: &str,
+) -> Result<(), Error> {
+    let chat_id = ObjectId::parse_str(chat_id).map_err(|_| ErrorBadRequest("Invalid chat ID"))?;
+    let result = hard_delete_chat(state, chat_id).await;
+    let detail = Some("deleted by an admin".to_owned());
+    audit_admin_action(state, context, AuditAction::ChatDeleted, admin_id, chat_id, detail, &result).await;
+    result
+}
+
+pub async fn server_stats(state: &AppState) -> Result<ServerStats, Error> {
+    let count_error = |e: RepositoryError| {
+        log::error!("Database error: {}", e);
+        ErrorInternalServerError("Failed to count documents")
+    };
+    let suspended = UserFilter {
+        suspended: Some(true),
+        ..UserFilter::default()
+    };
+    let repositories = &state.repositories;
+
+    Ok(ServerStats {
+        connected_websockets: state.ws_sessions.read().await.len(),
+        active_sessions: repositories
+            .sessions
+            .count_signed_in(Utc::now())
+            .await
+            .map_err(count_error)?,
+        users: repositories
+            .users
+            .count_users(&UserFilter::default())
+            .await
+            .map_err(count_error)?,
+        suspended_users: repositories.users.count_users(&suspended).await.map_err(count_error)?,
+        chats: repositories.chats.count().await.map_err(count_error)?,
+        messages: repositories.messages.count().await.map_err(count_error)?,
+    })
+}
@@ -0,0 +1,157 @@
+use crate::{
+    constants::{AUDIT_EVENT_PAGE_LIMIT, AUDIT_EXPORT_BATCH_SIZE},
+    models::audit_event_model::AuditEvent,
+    repositories::audit_repository::AuditFilter,
+    states::app_state::AppState,
+    types::audit_types::{AuditAction, AuditOutcome},
+};
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    web::Bytes,
+    Error,
+};
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct AuditEventQuery {
+    pub actor_id: Option<String>,
+    /// Matches the username given on failed logins.
+    pub actor_name: Option<String>,
+    pub action: Option<AuditAction>,
+    pub target_id: Option<String>,
+    pub ip: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEventSummary {
+    pub id: String,
+    pub actor_id: Option<String>,
+    pub actor_name: Option<String>,
+    pub action: AuditAction,
+    pub target_id: Option<String>,
+    pub ip: String,
+    pub user_agent: String,
+    pub outcome: AuditOutcome,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEventList {
+    pub events: Vec<AuditEventSummary>,
+    /// Events matching the query across all pages.
+    pub total: u64,
+}
+
+impl From<AuditEvent> for AuditEventSummary {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            id: event.id.map_or_else(String::new, |id| id.to_hex()),
+            actor_id: event.actor_id.map(|id| id.to_hex()),
+            actor_name: event.actor_name,
+            action: event.action,
+            target_id: event.target_id.map(|id| id.to_hex()),
+            ip: event.ip,
+            user_agent: event.user_agent,
+            outcome: event.outcome,
+            detail: event.detail,
+            created_at: event.created_at,
+        }
+    }
+}
+
+/// Append an event to the audit log.
+///
+/// A failed write is logged rather than returned, so auditing never blocks the action itself.
+pub async fn record_audit_event(state: &AppState, event: AuditEvent) {
+    if let Err(e) = state.repositories.audit_events.insert(&event).await {
+        log::error!("Database error: {}", e);
+        log::error!("Unrecorded audit event: {:?}", event);
+    }
+}
+
+fn audit_filter(query: &AuditEventQuery) -> Result<AuditFilter, Error> {
+    let parse_id = |id: &str| ObjectId::parse_str(id).map_err(|_| ErrorBadRequest("Invalid ID"));
+    Ok(AuditFilter {
+        actor_id: query.actor_id.as_deref().map(parse_id).transpose()?,
+        actor_name: query.actor_name.clone(),
+        action: query.action,
+        target_id: query.target_id.as_deref().map(parse_id).transpose()?,
+        ip: query.ip.clone(),
+        outcome: query.outcome,
+        from: query.from,
+        to: query.to,
+    })
+}
+
+/// Audit events matching the query, newest first.
+pub async fn list_audit_events(state: &AppState, query: &AuditEventQuery) -> Result<AuditEventList, Error> {
+    let filter = audit_filter(query)?;
+    let audit_events = &state.repositories.audit_events;
+    let total = audit_events
+        .count(&filter)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to count audit events"))?;
+    let events = audit_events
+        .find(
+            &filter,
+            false,
+            query.page.unwrap_or(0) * AUDIT_EVENT_PAGE_LIMIT as u64,
+            AUDIT_EVENT_PAGE_LIMIT,
+        )
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get audit events"))?;
+
+    Ok(AuditEventList {
+        events: events.into_iter().map(AuditEventSummary::from).collect(),
+        total,
+    })
+}
+
+/// Every audit event matching the query as JSON lines, oldest first. Paging is ignored.
+///
+/// Events are read in batches as the response is sent. Events are only ever appended, so later
+/// batches cannot skip or repeat any.
+pub async fn export_audit_events(
+    state: &AppState,
+    query: &AuditEventQuery,
+) -> Result<impl Stream<Item = Result<Bytes, Error>>, Error> {
+    let filter = audit_filter(query)?;
+    let audit_events = state.repositories.audit_events.clone();
+
+    Ok(stream::try_unfold(Some(0), move |skip| {
+        let filter = filter.clone();
+        let audit_events = audit_events.clone();
+        async move {
+            let Some(skip) = skip else {
+                return Ok(None);
+            };
+            let events = audit_events
+                .find(&filter, true, skip, AUDIT_EXPORT_BATCH_SIZE)
+                .await
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    ErrorInternalServerError("Failed to read audit events")
+                })?;
+            if events.is_empty() {
+                return Ok(None);
+            }
+            let next_skip = (events.len() as i64 == AUDIT_EXPORT_BATCH_SIZE).then_some(skip + events.len() as u64);
+
+            let mut lines = Vec::new();
+            for event in events {
+                serde_json::to_writer(&mut lines, &AuditEventSummary::from(event))
+                    .map_err(|_| ErrorInternalServerError("Failed to encode audit event"))?;
+                lines.push(b'\n');
+            }
+            Ok(Some((Bytes::from(lines), next_skip)))
+        }
+    }))
+}
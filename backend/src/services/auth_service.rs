@@ -1,15 +1,37 @@
 use crate::{
-    models::user_model::User,
+    auth::auth_provider::AuthProviderError,
+    constants::{EMAIL_VERIFICATION_PURPOSE, PASSWORD_RESET_LOCKOUT_MINUTES, PASSWORD_RESET_MAX_IP_REQUESTS},
+    models::{audit_event_model::AuditEvent, user_model::User},
+    repositories::repository::RepositoryError,
     states::app_state::AppState,
-    utils::auth_util::{generate_reset_token, hash_password, send_reset_email, verify_password},
+    types::audit_types::{AuditAction, AuditOutcome},
+    utils::{
+        auth_util::{generate_reset_token, hash_password, hash_token, sign_token, verify_signed_token},
+        password_util::password_policy_error,
+        request_util::RequestContext,
+        validation_util::{validate_email, validate_username},
+    },
     config::app_config::AppConfig,
-    services::user_service::{extract_user_id_from_session, get_user_by_id},
+    mail::mail_templates::{account_locked_email, email_verification_email, password_reset_email},
+    services::{
+        audit_service::record_audit_event,
+        mail_service::queue_email,
+        throttle_service::{
+            check_throttle, clear_login_failures, login_keys, record_failure, record_login_failure, FailureOutcome,
+            ThrottleScope,
+        },
+        user_service::{
+            authenticate_session, ensure_not_suspended, extract_user_id_from_session, get_user_by_id,
+            revoke_user_sessions,
+        },
+    },
 };
 use actix_session::Session;
 use actix_web::{web, Error};
 use chrono::{Duration, Utc};
-use mongodb::bson::{doc, Bson};
+use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
 #[derive(Debug, Deserialize)]
 pub struct RegisterRequest {
@@ -22,6 +44,7 @@ pub struct RegisterRequest {
 pub struct RegisterResponse {
     pub user_id: String,
     pub username: String,
+    pub email_verified: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +57,7 @@ pub struct LoginRequest {
 pub struct LoginResponse {
     pub user_id: String,
     pub username: String,
+    pub email_verified: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,27 +71,68 @@ pub struct ChangePasswordRequest {
     pub new_password: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthStatusResponse {
     pub user_id: Option<String>,
     pub username: Option<String>,
+    pub email_verified: Option<bool>,
+}
+
+/// An audit event for something done to the user's own account.
+fn account_event(action: AuditAction, outcome: AuditOutcome, context: &RequestContext, user_id: Option<ObjectId>) -> AuditEvent {
+    let mut event = AuditEvent::new(action, outcome, context);
+    event.actor_id = user_id;
+    event.target_id = user_id;
+    event
+}
+
+/// Reject registrations with a malformed username, email or password.
+fn validate_registration(config: &AppConfig, req: &RegisterRequest) -> Result<(), Error> {
+    if !validate_username(&req.username) {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Username must be at least 3 characters long and contain only letters, digits or underscores",
+        ));
+    }
+    if !validate_email(&req.email) {
+        return Err(actix_web::error::ErrorBadRequest("Invalid email format"));
+    }
+    let violations = config
+        .password_policy
+        .check(&req.password, Some(&req.username), &[]);
+    if !violations.is_empty() {
+        return Err(password_policy_error(violations));
+    }
+    Ok(())
 }
 
 pub async fn register_user(
     state: &web::Data<AppState>,
     req: &RegisterRequest,
+    context: &RequestContext,
 ) -> Result<User, Error> {
-    let user_collection = state.db.collection::<User>(User::collection_name());
-    
+    let config = AppConfig::new().map_err(|e| {
+        log::error!("Config error: {}", e);
+        actix_web::error::ErrorInternalServerError("Config error")
+    })?;
+    // Directory accounts are created on their first sign-in instead.
+    if config.auth_backend == "ldap" {
+        return Err(actix_web::error::ErrorForbidden("Accounts are managed in the directory"));
+    }
+    validate_registration(&config, req)?;
+
     // Check if username or email already exists
-    let existing_user = user_collection
-        .find_one(
-            doc! { "$or": [ { "username": &req.username }, { "email": &req.email } ] },
-            None,
-        )
+    let existing_user = state
+        .repositories
+        .users
+        .find_by_username_or_email(&req.username, &req.email)
         .await
         .map_err(|e| {
-            log::error!("MongoDB error: {}", e);
+            log::error!("Database error: {}", e);
             actix_web::error::ErrorInternalServerError("Database error")
         })?;
     if existing_user.is_some() {
@@ -80,42 +145,215 @@ pub async fn register_user(
     let hashed_password = hash_password(&req.password)
         .map_err(|_| actix_web::error::ErrorInternalServerError("Failed to hash password"))?;
     let mut user = User::new(&req.username, &req.email, &hashed_password);
-    let insert_result = user_collection.insert_one(&user, None).await.map_err(|e| {
-        log::error!("MongoDB error: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to register user")
+    user.validate()
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+    // The unique indexes catch a registration that raced past the check above.
+    let user_id = state.repositories.users.insert(&user).await.map_err(|e| match e {
+        RepositoryError::Duplicate => actix_web::error::ErrorBadRequest("Username or email already exists"),
+        e => {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to register user")
+        }
     })?;
-    user.id = insert_result.inserted_id.as_object_id();
+    user.id = Some(user_id);
+    record_audit_event(
+        state,
+        account_event(AuditAction::AccountRegistered, AuditOutcome::Success, context, user.id),
+    )
+    .await;
+
+    // The account exists by now; the user can ask for another link if this one is lost.
+    if let Err(e) = send_verification_email(state, &user).await {
+        log::error!("Email error: {}", e);
+    }
 
     Ok(user)
 }
 
+/// Queue an email containing a signed link that verifies the user's address.
+async fn send_verification_email(state: &web::Data<AppState>, user: &User) -> Result<(), Error> {
+    let config = AppConfig::new().map_err(|e| {
+        log::error!("Config error: {}", e);
+        actix_web::error::ErrorInternalServerError("Config error")
+    })?;
+    let user_id = user
+        .id
+        .ok_or_else(|| actix_web::error::ErrorInternalServerError("User ID is None"))?;
+
+    let expires_at = Utc::now() + Duration::hours(config.email_verification_expiration_hours);
+    let payload = format!("{}.{}", user_id.to_hex(), expires_at.timestamp());
+    let token = sign_token(&config.app_secret, EMAIL_VERIFICATION_PURPOSE, &payload);
+
+    queue_email(
+        state,
+        email_verification_email(&user.email, &config.frontend_url, &user.username, &token),
+    )
+    .await
+}
+
+/// Mark the account named by a verification token as verified.
+pub async fn verify_email(
+    state: &web::Data<AppState>,
+    req: &VerifyEmailRequest,
+    context: &RequestContext,
+) -> Result<String, Error> {
+    let config = AppConfig::new().map_err(|e| {
+        log::error!("Config error: {}", e);
+        actix_web::error::ErrorInternalServerError("Config error")
+    })?;
+    let invalid_token = || actix_web::error::ErrorBadRequest("Invalid verification token");
+
+    let payload = verify_signed_token(&config.app_secret, EMAIL_VERIFICATION_PURPOSE, &req.token)
+        .ok_or_else(invalid_token)?;
+    let (user_id, expires_at) = payload.split_once('.').ok_or_else(invalid_token)?;
+    let user_id = ObjectId::parse_str(user_id).map_err(|_| invalid_token())?;
+    let expires_at: i64 = expires_at.parse().map_err(|_| invalid_token())?;
+    if expires_at < Utc::now().timestamp() {
+        return Err(actix_web::error::ErrorBadRequest("Verification token expired"));
+    }
+
+    let verified = state
+        .repositories
+        .users
+        .set_email_verified(user_id)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to verify email")
+        })?;
+    if !verified {
+        return Err(invalid_token());
+    }
+    record_audit_event(
+        state,
+        account_event(AuditAction::EmailVerified, AuditOutcome::Success, context, Some(user_id)),
+    )
+    .await;
+
+    Ok("Email verified successfully".to_string())
+}
+
+/// Send a fresh verification link to the signed-in user.
+pub async fn resend_verification_email(
+    state: &web::Data<AppState>,
+    session: &Session,
+) -> Result<String, Error> {
+    let user_id = authenticate_session(state, session).await?;
+    let user = get_user_by_id(state, user_id).await?;
+    if user.email_verified {
+        return Err(actix_web::error::ErrorBadRequest("Email is already verified"));
+    }
+
+    send_verification_email(state, &user).await?;
+
+    Ok("Verification email sent".to_string())
+}
+
 pub async fn authenticate_user(
     credentials: &LoginRequest,
     state: &web::Data<AppState>,
+    context: &RequestContext,
 ) -> Result<User, Error> {
-    let user_collection = state.db.collection::<User>(User::collection_name());
-    let user = user_collection
-        .find_one(doc! { "username": &credentials.username }, None)
+    let config = AppConfig::new().map_err(|e| {
+        log::error!("Config error: {}", e);
+        actix_web::error::ErrorInternalServerError("Config error")
+    })?;
+    let login_failed = |user_id: Option<ObjectId>, detail: &str| {
+        let mut event = account_event(AuditAction::LoginFailed, AuditOutcome::Failure, context, user_id);
+        event.actor_name = Some(credentials.username.clone());
+        event.detail = Some(detail.to_owned());
+        event
+    };
+    if let Err(e) = check_throttle(state, &login_keys(&credentials.username, &context.ip)).await {
+        record_audit_event(state, login_failed(None, "throttled")).await;
+        return Err(e);
+    }
+
+    let user = match state
+        .auth_provider
+        .authenticate(state.repositories.users.as_ref(), &credentials.username, &credentials.password)
+        .await
+    {
+        Ok(user) => user,
+        Err(e) => {
+            log::error!("Authentication error: {}", e);
+            record_audit_event(state, login_failed(None, "authentication backend error")).await;
+            return Err(match e {
+                AuthProviderError::Conflict(_) => {
+                    actix_web::error::ErrorConflict("This account cannot be signed in here")
+                }
+                AuthProviderError::Directory(_) => {
+                    actix_web::error::ErrorBadGateway("Directory unavailable")
+                }
+                _ => actix_web::error::ErrorInternalServerError("Failed to verify password"),
+            });
+        }
+    };
+    if let Some(user) = user {
+        clear_login_failures(state, &credentials.username, &context.ip).await?;
+        if let Err(e) = ensure_not_suspended(&user) {
+            record_audit_event(state, login_failed(user.id, "account suspended")).await;
+            return Err(e);
+        }
+        let mut event = account_event(AuditAction::LoginSucceeded, AuditOutcome::Success, context, user.id);
+        if user.totp_enabled {
+            event.detail = Some("second factor required".to_owned());
+        }
+        record_audit_event(state, event).await;
+        return Ok(user);
+    }
+
+    // Unknown usernames back off like wrong passwords, so they cannot be told apart, but only real accounts are locked.
+    let account = state
+        .repositories
+        .users
+        .find_by_username(&credentials.username)
         .await
         .map_err(|e| {
-            log::error!("MongoDB error: {}", e);
+            log::error!("Database error: {}", e);
             actix_web::error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Invalid credentials"))?;
+        })?;
+    let outcome =
+        record_login_failure(state, &config, &credentials.username, &context.ip, account.is_some()).await?;
+    record_audit_event(
+        state,
+        login_failed(
+            account.as_ref().and_then(|account| account.id),
+            match outcome {
+                FailureOutcome::LockedOut => "invalid credentials; account locked",
+                FailureOutcome::Counted => "invalid credentials",
+            },
+        ),
+    )
+    .await;
+    if let (FailureOutcome::LockedOut, Some(account)) = (outcome, &account) {
+        log::warn!("Account {} locked for {} after repeated failed logins", account.username, context.ip);
+        notify_account_locked(state, &config, account).await;
+    }
 
-    let is_password_valid = verify_password(&credentials.password, &user.password_hash)
-        .map_err(|_| actix_web::error::ErrorInternalServerError("Failed to verify password"))?;
+    Err(actix_web::error::ErrorUnauthorized("Invalid credentials"))
+}
 
-    if is_password_valid {
-        Ok(user)
-    } else {
-        Err(actix_web::error::ErrorUnauthorized("Invalid credentials"))
+/// Tell the owner of an account that it was locked.
+async fn notify_account_locked(state: &web::Data<AppState>, config: &AppConfig, user: &User) {
+    let email = account_locked_email(&user.email, &config.frontend_url, &user.username, config.login_lockout_minutes);
+    if let Err(e) = queue_email(state, email).await {
+        log::error!("Email error: {}", e);
     }
 }
 
 pub async fn logout_user(
-    session: &Session
+    state: &web::Data<AppState>,
+    session: &Session,
+    context: &RequestContext,
 ) -> Result<String, Error> {
+    if let Ok(user_id) = extract_user_id_from_session(session) {
+        record_audit_event(
+            state,
+            account_event(AuditAction::Logout, AuditOutcome::Success, context, Some(user_id)),
+        )
+        .await;
+    }
     session.purge();
     Ok("Logged out successfully".to_string())
 }
@@ -124,7 +362,7 @@ pub async fn auth_status(
     state: &web::Data<AppState>,
     session: &Session,
 ) -> Result<AuthStatusResponse, Error> {
-    let user_id_result = extract_user_id_from_session(session);
+    let user_id_result = authenticate_session(state, session).await;
     
     match user_id_result {
         Ok(user_id) => {
@@ -137,12 +375,14 @@ pub async fn auth_status(
                 Ok(AuthStatusResponse {
                     user_id: Some(user.id.unwrap().to_hex()),
                     username: Some(user.username),
+                    email_verified: Some(user.email_verified),
                 })
             } else {
                 session.purge();
                 Ok(AuthStatusResponse {
                     user_id: None,
                     username: None,
+                    email_verified: None,
                 })
             }
         },
@@ -150,6 +390,7 @@ pub async fn auth_status(
             Ok(AuthStatusResponse {
                 user_id: None,
                 username: None,
+                email_verified: None,
             })
         }
     }
@@ -158,72 +399,187 @@ pub async fn auth_status(
 pub async fn send_reset_password_email(
     state: &web::Data<AppState>,
     req: &ResetPasswordRequest,
+    context: &RequestContext,
 ) -> Result<String, Error> {
-    let config = AppConfig::new().unwrap();
-    let user_collection = state.db.collection::<User>(User::collection_name());
-    let user = user_collection
-        .find_one(doc! { "email": &req.email }, None)
-        .await
-        .map_err(|e| {
-            log::error!("MongoDB error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Email not found"))?;
+    let config = AppConfig::new().map_err(|e| {
+        log::error!("Config error: {}", e);
+        actix_web::error::ErrorInternalServerError("Config error")
+    })?;
+
+    // Every request counts, so one client cannot flood inboxes with reset emails.
+    let ip_key = ThrottleScope::PasswordResetIp.key(&context.ip);
+    check_throttle(state, std::slice::from_ref(&ip_key)).await?;
+    record_failure(
+        state,
+        &ip_key,
+        PASSWORD_RESET_MAX_IP_REQUESTS,
+        Duration::minutes(PASSWORD_RESET_LOCKOUT_MINUTES),
+    )
+    .await?;
+
+    // The response is the same whether or not the email is registered, and whether or not sending
+    // the link worked, so it cannot be used to probe accounts. Failures are logged and audited instead.
+    let response = "If an account exists for that email, a reset link has been sent".to_string();
+    let reset_failed = |user_id: Option<ObjectId>, detail: &str| {
+        let mut event = account_event(AuditAction::PasswordResetRequested, AuditOutcome::Failure, context, user_id);
+        event.detail = Some(detail.to_owned());
+        event
+    };
+
+    let user = match state.repositories.users.find_by_email(&req.email).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            record_audit_event(state, reset_failed(None, "unknown email")).await;
+            return Ok(response);
+        }
+        Err(e) => {
+            log::error!("Database error: {}", e);
+            record_audit_event(state, reset_failed(None, "database error")).await;
+            return Ok(response);
+        }
+    };
+
+    if let Err(e) = issue_password_reset(state, &config, &user).await {
+        log::error!("Failed to issue a password reset for {}: {}", user.username, e);
+        record_audit_event(state, reset_failed(user.id, "failed to issue the reset link")).await;
+        return Ok(response);
+    }
+    record_audit_event(
+        state,
+        account_event(AuditAction::PasswordResetRequested, AuditOutcome::Success, context, user.id),
+    )
+    .await;
+
+    Ok(response)
+}
+
+/// Store a new reset token for the user and email them the link.
+pub async fn issue_password_reset(state: &AppState, config: &AppConfig, user: &User) -> Result<(), Error> {
+    // Only the hash is stored, so a leaked database cannot be used to reset passwords.
     let reset_token = generate_reset_token();
     let expires_at = Utc::now() + Duration::minutes(config.reset_token_expiration_minutes);
-    user_collection
-        .update_one(
-            doc! { "_id": &user.id },
-            doc! { "$set": { "reset_token": &reset_token, "reset_token_expiry_at": expires_at.timestamp_millis() } },
-            None,
-        )
+    let user_id = user
+        .id
+        .ok_or_else(|| actix_web::error::ErrorInternalServerError("User ID is None"))?;
+    state
+        .repositories
+        .users
+        .set_reset_token(user_id, &hash_token(&reset_token), expires_at.timestamp_millis())
         .await
         .map_err(|e| {
-            log::error!("MongoDB error: {}", e);
+            log::error!("Database error: {}", e);
             actix_web::error::ErrorInternalServerError("Failed to set reset token")
         })?;
 
-    send_reset_email(&req.email, &reset_token).map_err(|e| {
-        log::error!("Email error: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to send reset email")
-    })?;
-
-    Ok("Reset password email sent".to_string())
+    // The outbox copy holds the live link until it is sent, when the mail service blanks it.
+    queue_email(
+        state,
+        password_reset_email(&user.email, &config.frontend_url, &reset_token),
+    )
+    .await
 }
 
 pub async fn change_password(
     state: &web::Data<AppState>,
     req: &ChangePasswordRequest,
+    context: &RequestContext,
 ) -> Result<String, Error> {
-    let user_collection = state.db.collection::<User>(User::collection_name());
+    let config = AppConfig::new().map_err(|e| {
+        log::error!("Config error: {}", e);
+        actix_web::error::ErrorInternalServerError("Config error")
+    })?;
 
-    let user = user_collection
-        .find_one(doc! { "reset_token": &req.reset_token }, None)
+    // Guessing reset tokens is throttled like guessing passwords.
+    let ip_key = ThrottleScope::ResetTokenIp.key(&context.ip);
+    let reset_failed = |user_id: Option<ObjectId>, detail: &str| {
+        let mut event = account_event(AuditAction::PasswordResetCompleted, AuditOutcome::Failure, context, user_id);
+        event.detail = Some(detail.to_owned());
+        event
+    };
+    check_throttle(state, std::slice::from_ref(&ip_key)).await?;
+
+    let reset_token_hash = hash_token(&req.reset_token);
+
+    let user = state
+        .repositories
+        .users
+        .find_by_reset_token_hash(&reset_token_hash)
         .await
         .map_err(|e| {
-            log::error!("MongoDB error: {}", e);
+            log::error!("Database error: {}", e);
             actix_web::error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Invalid reset token"))?;
+        })?;
+    let user = match user {
+        Some(user) => user,
+        None => {
+            record_failure(
+                state,
+                &ip_key,
+                config.login_max_ip_failures,
+                Duration::minutes(config.login_lockout_minutes),
+            )
+            .await?;
+            record_audit_event(state, reset_failed(None, "invalid token")).await;
+            return Err(actix_web::error::ErrorBadRequest("Invalid reset token"));
+        }
+    };
 
-    if user.reset_token_expiry_at.unwrap_or_else(|| 0) < Utc::now().timestamp_millis() {
+    if user.reset_token_expiry_at.unwrap_or(0) < Utc::now().timestamp_millis() {
+        record_audit_event(state, reset_failed(user.id, "expired token")).await;
         return Err(actix_web::error::ErrorBadRequest("Reset token expired"));
     }
 
+    let policy = config.password_policy.clone();
+    let history_size = policy.history_size;
+
+    // The current password counts as the most recent entry of the history.
+    let mut previous_hashes = vec![user.password_hash.clone()];
+    previous_hashes.extend(user.password_history.iter().cloned());
+    // Checking the history verifies an argon2 hash per entry, so it runs off the async executor.
+    let (new_password, username) = (req.new_password.clone(), user.username.clone());
+    let (violations, mut previous_hashes) = web::block(move || {
+        let violations = policy.check(&new_password, Some(&username), &previous_hashes);
+        (violations, previous_hashes)
+    })
+    .await
+    .map_err(|_| actix_web::error::ErrorInternalServerError("Failed to check password"))?;
+    if !violations.is_empty() {
+        return Err(password_policy_error(violations));
+    }
+
     let hashed_password = hash_password(&req.new_password)
         .map_err(|_| actix_web::error::ErrorInternalServerError("Failed to hash password"))?;
+    previous_hashes.truncate(history_size);
 
-    user_collection
-        .update_one(
-            doc! { "_id": &user.id },
-            doc! { "$set": { "password_hash": &hashed_password, "reset_token": "", "reset_token_expiry_at": Bson::Null } },
-            None,
+    let user_id = user
+        .id
+        .ok_or_else(|| actix_web::error::ErrorInternalServerError("User ID is None"))?;
+    let password_changed = state
+        .repositories
+        .users
+        .complete_password_reset(
+            user_id,
+            &reset_token_hash,
+            Utc::now().timestamp_millis(),
+            &hashed_password,
+            &previous_hashes,
         )
         .await
         .map_err(|e| {
-            log::error!("MongoDB error: {}", e);
+            log::error!("Database error: {}", e);
             actix_web::error::ErrorInternalServerError("Failed to update password")
         })?;
+    if !password_changed {
+        return Err(actix_web::error::ErrorBadRequest("Invalid reset token"));
+    }
+
+    // Whoever knew the old password may still be signed in.
+    revoke_user_sessions(state, user_id).await?;
+    record_audit_event(
+        state,
+        account_event(AuditAction::PasswordResetCompleted, AuditOutcome::Success, context, Some(user_id)),
+    )
+    .await;
 
     Ok("Password changed successfully".to_string())
 }
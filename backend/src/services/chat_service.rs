@@ -1,15 +1,29 @@
 use crate::{
-    models::{chat_model::Chat, message_model::Message, user_model::User},
+    config::app_config::AppConfig,
+    constants::{
+        CHAT_PAGE_LIMIT, CLIENT_MSG_ID_MAX_LENGTH, DELETED_CHAT_RETENTION_HOURS, MAX_PINNED_MESSAGES, MESSAGE_PAGE_LIMIT,
+    },
+    models::{
+        audit_event_model::AuditEvent,
+        chat_model::{Chat, ChatPin, LastMessage},
+        message_model::Message,
+    },
+    repositories::{message_repository::MessageRange, repository::RepositoryError},
+    services::audit_service::record_audit_event,
     states::app_state::AppState,
+    types::audit_types::{AuditAction, AuditOutcome},
+    utils::request_util::RequestContext,
+    websocket::websocket_session::TextMessage,
 };
 use actix_web::{
-    error::{ErrorForbidden, ErrorInternalServerError},
-    Error,
+    error::{ErrorBadRequest, ErrorConflict, ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    web, Error,
 };
-use futures::TryStreamExt;
-use mongodb::bson::{doc, oid::ObjectId};
+use chrono::{Duration, Utc};
+use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Deserialize)]
 pub struct CreateChatRoomRequest {
@@ -25,6 +39,35 @@ pub struct DeleteChatRequest {
 pub struct SendMessageRequest {
     pub chat_id: ObjectId,
     pub content: String,
+    /// Lets the client retry a send without storing the message twice.
+    pub client_msg_id: Option<String>,
+}
+
+/// A stored message, and whether an earlier send with the same `client_msg_id` stored it.
+#[derive(Debug)]
+pub struct SentMessage {
+    pub message: Message,
+    pub duplicate: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PinMessageRequest {
+    pub message_id: ObjectId,
+}
+
+/// Without `after_seq`, the newest messages (before `before_seq`, when given) are returned.
+#[derive(Debug, Default, Deserialize)]
+pub struct MessageQuery {
+    pub after_seq: Option<i64>,
+    pub before_seq: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// One page of the chat list.
+#[derive(Debug, Default, Deserialize)]
+pub struct ChatListQuery {
+    pub page: Option<u64>,
+    pub per_page: Option<i64>,
 }
 
 /// Retrieve chat rooms for a given user.
@@ -35,105 +78,89 @@ pub struct ChatSummary {
     pub participant_user_id: String,
     pub last_message: Option<String>,
     pub last_message_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_activity_at: chrono::DateTime<chrono::Utc>,
 }
 
 /// Retrieve a chat room by its ID.
 pub async fn get_chat_by_id(state: &AppState, chat_id: ObjectId) -> Result<Chat, Error> {
-    let chats = state.db.collection::<Chat>(Chat::collection_name());
-    let chat = chats
-        .find_one(doc! { "_id": &chat_id }, None)
+    let chat = state
+        .repositories
+        .chats
+        .find_by_id(chat_id)
         .await
         .map_err(|_| ErrorInternalServerError("Database error retrieving chat"))?
         .ok_or_else(|| ErrorForbidden("Chat not found"))?;
     Ok(chat)
 }
 
+/// The user's chats, most recently active first, including ones nobody has written in yet.
 pub async fn get_user_chats(
     state: &AppState,
     user_id: ObjectId,
+    query: &ChatListQuery,
 ) -> Result<Vec<ChatSummary>, Error> {
-    let chats_collection = state.db.collection::<Chat>(Chat::collection_name());
-    let users_collection = state
-        .db
-        .collection::<crate::models::user_model::User>("users");
-    let messages_collection = state.db.collection::<Message>(Message::collection_name());
-
-    // Get all chats for this user
-    let cursor = chats_collection
-        .find(doc! { "participant_ids": &user_id }, None)
+    let per_page = query.per_page.unwrap_or(CHAT_PAGE_LIMIT).clamp(1, CHAT_PAGE_LIMIT);
+    let chats = state
+        .repositories
+        .chats
+        .list_for_user(user_id, query.page.unwrap_or(0) * per_page as u64, Some(per_page))
         .await
         .map_err(|_| ErrorInternalServerError("Failed to get chat rooms"))?;
 
-    let chats: Vec<Chat> = cursor
-        .try_collect()
+    // Look up every other participant at once (assuming 2-person chats)
+    let other_participant_ids: Vec<ObjectId> = chats
+        .iter()
+        .filter_map(|chat| other_participant_id(chat, user_id))
+        .collect();
+    let usernames: HashMap<ObjectId, String> = state
+        .repositories
+        .users
+        .find_by_ids(&other_participant_ids)
         .await
-        .map_err(|_| ErrorInternalServerError("Failed to collect chat rooms"))?;
-
-    let mut chat_summaries = Vec::new();
-
-    for chat in chats {
-        // Find the other participant (assuming 2-person chats)
-        let other_participant_id = chat
-            .participant_ids
-            .iter()
-            .find(|&id| id != &user_id)
-            .ok_or_else(|| ErrorInternalServerError("Failed to find other participant"))?;
-
-        // Get other participant's username
-        let other_user = users_collection
-            .find_one(doc! { "_id": other_participant_id }, None)
-            .await
-            .map_err(|_| ErrorInternalServerError("Failed to get other participant info"))?
-            .ok_or_else(|| ErrorInternalServerError("Other participant not found"))?;
-
-        // Get last message
-        let mut last_message_cursor = messages_collection
-            .find(
-                doc! { "chat_id": &chat.id },
-                Some(
-                    mongodb::options::FindOptions::builder()
-                        .sort(doc! { "created_at": -1 })
-                        .limit(1)
-                        .build(),
-                ),
-            )
-            .await
-            .map_err(|_| ErrorInternalServerError("Failed to get last message"))?;
-
-        let last_message = match last_message_cursor.try_next().await {
-            Ok(Some(message)) => Some((message.content, message.created_at)),
-            Ok(None) => None,
-            Err(_) => None, // Instead of returning an error, just treat as no message
-        };
-
-        // Ensure chat ID exists
-        let chat_id = match chat.id {
-            Some(id) => id,
-            None => return Err(ErrorInternalServerError("Chat ID is None")),
-        };
-
-        if last_message.is_none() {
-            continue;
-        }
+        .map_err(|_| ErrorInternalServerError("Failed to get other participant info"))?
+        .into_iter()
+        .filter_map(|user| Some((user.id?, user.username)))
+        .collect();
 
-        // Create chat summary
-        chat_summaries.push(ChatSummary {
-            id: chat_id.to_string(),
-            participant_username: other_user.username,
-            participant_user_id: other_participant_id.clone().to_string(),
-            last_message: last_message.as_ref().map(|(content, _)| content.clone()),
-            last_message_timestamp: last_message.map(|(_, timestamp)| timestamp),
-        });
-    }
+    chats
+        .into_iter()
+        .map(|chat| {
+            let other_participant_id = other_participant_id(&chat, user_id)
+                .ok_or_else(|| ErrorInternalServerError("Failed to find other participant"))?;
+            let username = usernames
+                .get(&other_participant_id)
+                .cloned()
+                .ok_or_else(|| ErrorInternalServerError("Other participant not found"))?;
+            chat_summary(&chat, user_id, other_participant_id, username)
+        })
+        .collect()
+}
 
-    // Sort chat summaries by last message timestamp, most recent first
-    chat_summaries.sort_by(|a, b| {
-        b.last_message_timestamp
-            .unwrap_or_else(chrono::Utc::now)
-            .cmp(&a.last_message_timestamp.unwrap_or_else(chrono::Utc::now))
-    });
+fn other_participant_id(chat: &Chat, user_id: ObjectId) -> Option<ObjectId> {
+    chat.participant_ids.iter().find(|&&id| id != user_id).copied()
+}
 
-    Ok(chat_summaries)
+/// The summary as `user_id` sees it: a last message they deleted the chat after is not shown.
+fn chat_summary(
+    chat: &Chat,
+    user_id: ObjectId,
+    other_participant_id: ObjectId,
+    participant_username: String,
+) -> Result<ChatSummary, Error> {
+    let chat_id = chat.id.ok_or_else(|| ErrorInternalServerError("Chat ID is None"))?;
+    let hidden_seq = chat.hidden_seq(user_id).unwrap_or(0);
+    let last_message = chat
+        .last_message
+        .as_ref()
+        .filter(|last_message| last_message.seq > hidden_seq);
+    Ok(ChatSummary {
+        id: chat_id.to_string(),
+        participant_username,
+        participant_user_id: other_participant_id.to_string(),
+        last_message: last_message.map(|last_message| last_message.content.clone()),
+        last_message_timestamp: last_message.map(|last_message| last_message.created_at),
+        last_activity_at: chat.last_activity_at,
+    })
 }
 
 pub async fn get_chat_summary(
@@ -141,57 +168,28 @@ pub async fn get_chat_summary(
     chat_id: ObjectId,
     user_id: ObjectId,
 ) -> Result<ChatSummary, Error> {
-    let chat_collection = state.db.collection::<Chat>(Chat::collection_name());
-    let users_collection = state.db.collection::<User>(User::collection_name());
-    let messages_collection = state.db.collection::<Message>(Message::collection_name());
-
-    let chat = chat_collection
-        .find_one(doc! { "_id": &chat_id, "participant_ids": &user_id }, None)
+    let chat = state
+        .repositories
+        .chats
+        .find_for_participant(chat_id, user_id)
         .await
         .map_err(|_| ErrorInternalServerError("Database error retrieving chat"))?
         .ok_or_else(|| ErrorForbidden("Chat not found"))?;
 
     // Find the other participant
-    let other_participant_id = chat
-        .participant_ids
-        .iter()
-        .find(|&id| id != &user_id)
+    let other_participant_id = other_participant_id(&chat, user_id)
         .ok_or_else(|| ErrorInternalServerError("Failed to find other participant"))?;
 
     // Get other participant's username
-    let other_user = users_collection
-        .find_one(doc! { "_id": other_participant_id }, None)
+    let other_user = state
+        .repositories
+        .users
+        .find_by_id(other_participant_id)
         .await
         .map_err(|_| ErrorInternalServerError("Failed to get other participant info"))?
         .ok_or_else(|| ErrorInternalServerError("Other participant not found"))?;
 
-    // Get last message
-    let mut last_message_cursor = messages_collection
-        .find(
-            doc! { "chat_id": &chat_id },
-            Some(
-                mongodb::options::FindOptions::builder()
-                    .sort(doc! { "created_at": -1 })
-                    .limit(1)
-                    .build(),
-            ),
-        )
-        .await
-        .map_err(|_| ErrorInternalServerError("Failed to get last message"))?;
-
-    let last_message = match last_message_cursor.try_next().await {
-        Ok(Some(message)) => Some((message.content, message.created_at)),
-        Ok(None) => None,
-        Err(_) => None,
-    };
-
-    Ok(ChatSummary {
-        id: chat_id.to_string(),
-        participant_username: other_user.username,
-        participant_user_id: other_participant_id.to_string(),
-        last_message: last_message.as_ref().map(|(content, _)| content.clone()),
-        last_message_timestamp: last_message.map(|(_, timestamp)| timestamp),
-    })
+    chat_summary(&chat, user_id, other_participant_id, other_user.username)
 }
 
 /// Create a new chat room.
@@ -200,20 +198,12 @@ pub async fn create_chat(
     chat_id: Option<ObjectId>,
     participant_ids: HashSet<ObjectId>,
 ) -> Result<serde_json::Value, Error> {
-    let chats = state.db.collection::<Chat>(Chat::collection_name());
-
     // Check if a chat between these participants already exists
     let participant_ids_vec: Vec<ObjectId> = participant_ids.iter().cloned().collect();
-    let existing_chat = chats
-        .find_one(
-            doc! {
-                "participant_ids": {
-                    "$all": &participant_ids_vec,
-                    "$size": participant_ids_vec.len() as i32
-                }
-            },
-            None,
-        )
+    let existing_chat = state
+        .repositories
+        .chats
+        .find_by_participants(&participant_ids_vec)
         .await
         .map_err(|_| ErrorInternalServerError("Failed to check for existing chat"))?;
 
@@ -229,16 +219,12 @@ pub async fn create_chat(
 
     // Otherwise, create a new Chat document
     let new_chat = Chat::new(chat_id, participant_ids.into_iter().collect(), None);
-    let insert_result = chats
-        .insert_one(new_chat.clone(), None)
+    let inserted_id = state
+        .repositories
+        .chats
+        .insert(&new_chat)
         .await
         .map_err(|_| ErrorInternalServerError("Failed to create chat room"))?;
-    
-    // Get the MongoDB-generated ID from the insert result
-    let inserted_id = insert_result
-        .inserted_id
-        .as_object_id()
-        .ok_or_else(|| ErrorInternalServerError("Failed to get inserted chat ID"))?;
 
     // Use the actual inserted ID in the response
     let new_result = serde_json::json!({
@@ -249,121 +235,463 @@ pub async fn create_chat(
     Ok(new_result)
 }
 
-/// Delete a chat room if the given user is a participant.
+/// "Delete for me": hide the chat from the user until someone posts in it again.
+/// Once every participant has hidden it with nothing newer, it is marked deleted and purged later.
 pub async fn delete_chat(
     state: &AppState,
     chat_id: ObjectId,
     user_id: ObjectId,
+    context: &RequestContext,
 ) -> Result<(), Error> {
-    // First verify the user is a participant in the chat
-    let chats = state.db.collection::<Chat>(Chat::collection_name());
-    let _chat = chats
-        .find_one(doc! { "_id": &chat_id, "participant_ids": &user_id }, None)
-        .await
-        .map_err(|_| ErrorInternalServerError("Database error during chat verification"))?
-        .ok_or_else(|| ErrorForbidden("You are not a participant in this chat"))?;
-    
-    // Delete all messages associated with the chat
-    let messages = state.db.collection::<Message>(Message::collection_name());
-    messages
-        .delete_many(doc! { "chat_id": &chat_id }, None)
-        .await
-        .map_err(|_| ErrorInternalServerError("Failed to delete chat messages"))?;
-    
-    // Delete the chat itself
-    let delete_result = chats
-        .delete_one(doc! { "_id": &chat_id }, None)
+    let chat_event = |outcome: AuditOutcome, detail: &str| {
+        let mut event = AuditEvent::new(AuditAction::ChatDeleted, outcome, context);
+        event.actor_id = Some(user_id);
+        event.target_id = Some(chat_id);
+        event.detail = Some(detail.to_owned());
+        event
+    };
+
+    let now = Utc::now();
+    let chat = state
+        .repositories
+        .chats
+        .hide(chat_id, user_id, now)
         .await
         .map_err(|_| ErrorInternalServerError("Database error during chat deletion"))?;
-    
-    if delete_result.deleted_count == 0 {
-        Err(ErrorInternalServerError("Failed to delete chat"))
+    let Some(chat) = chat else {
+        record_audit_event(state, chat_event(AuditOutcome::Failure, "not a participant")).await;
+        return Err(ErrorForbidden("You are not a participant in this chat"));
+    };
+
+    // The messages are out of the user's reach now, so are their bookmarks of them
+    if let Err(e) = state.repositories.saved_messages.delete_by_chat(chat_id, Some(user_id)).await {
+        log::error!("Failed to remove saved messages of chat {}: {}", chat_id, e);
+    }
+
+    let detail = if chat.deleted_at.is_some() {
+        "deleted by every participant"
     } else {
-        Ok(())
+        "hidden for the participant"
+    };
+    record_audit_event(state, chat_event(AuditOutcome::Success, detail)).await;
+    Ok(())
+}
+
+/// Remove a chat and its messages for everyone, in one transaction, then everyone's saved messages from it.
+pub async fn hard_delete_chat(state: &AppState, chat_id: ObjectId) -> Result<(), Error> {
+    let deleted = state
+        .repositories
+        .chats
+        .delete(chat_id)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            ErrorInternalServerError("Failed to delete chat")
+        })?;
+    if !deleted {
+        return Err(ErrorNotFound("Chat not found"));
+    }
+    if let Err(e) = state.repositories.saved_messages.delete_by_chat(chat_id, None).await {
+        log::error!("Failed to remove saved messages of chat {}: {}", chat_id, e);
+    }
+    Ok(())
+}
+
+/// Remove the chats every participant deleted more than `DELETED_CHAT_RETENTION_HOURS` ago.
+/// Returns how many were removed.
+pub async fn purge_deleted_chats(state: &AppState) -> Result<usize, Error> {
+    let cutoff = Utc::now() - Duration::hours(DELETED_CHAT_RETENTION_HOURS);
+    let chats = state
+        .repositories
+        .chats
+        .find_deleted_before(cutoff)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to collect deleted chats"))?;
+
+    let mut purged = 0;
+    for chat_id in chats.into_iter().filter_map(|chat| chat.id) {
+        match hard_delete_chat(state, chat_id).await {
+            Ok(()) => purged += 1,
+            Err(e) => log::error!("Failed to purge chat {}: {}", chat_id, e),
+        }
+    }
+    Ok(purged)
+}
+
+/// Periodically purge deleted chats in the background.
+pub fn start_chat_purge_job(state: web::Data<AppState>, interval_minutes: u64) {
+    actix_web::rt::spawn(async move {
+        let period = std::time::Duration::from_secs(interval_minutes.max(1) * 60);
+        let mut interval = actix_web::rt::time::interval(period);
+        loop {
+            interval.tick().await;
+            match purge_deleted_chats(&state).await {
+                Ok(0) => {}
+                Ok(purged) => log::info!("Purged {} deleted chats", purged),
+                Err(e) => log::error!("Chat purge error: {}", e),
+            }
+        }
+    });
+}
+
+/// The message the sender already stored under `client_msg_id`, if any.
+async fn find_client_message(
+    state: &AppState,
+    chat_id: ObjectId,
+    user_id: ObjectId,
+    client_msg_id: &str,
+) -> Result<Option<SentMessage>, Error> {
+    let message = state
+        .repositories
+        .messages
+        .find_by_client_msg_id(user_id, client_msg_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error during duplicate check"))?;
+    match message {
+        Some(message) if message.chat_id != chat_id => {
+            Err(ErrorConflict("This client_msg_id was already used in another chat"))
+        }
+        message => Ok(message.map(|message| SentMessage { message, duplicate: true })),
     }
 }
 
 /// Send a message in a chat room.  
-/// First verifies that the user is a participant.
+/// First verifies that the user is a participant. The server stamps the message with its own time and
+/// the chat's next sequence number; the sender's clock is only kept as `client_sent_at`.
+/// Sending again with the same `client_msg_id` returns the message stored the first time.
 pub async fn send_message(
     state: &AppState,
     chat_id: ObjectId,
     user_id: ObjectId,
     content: &str,
-    created_at: Option<chrono::DateTime<chrono::Utc>>,
-) -> Result<Message, Error> {
-    let chats = state.db.collection::<Chat>(Chat::collection_name());
+    client_sent_at: Option<chrono::DateTime<chrono::Utc>>,
+    client_msg_id: Option<&str>,
+) -> Result<SentMessage, Error> {
     // Verify the user is a participant in the chat.
-    chats
-        .find_one(doc! { "_id": &chat_id, "participant_ids": &user_id }, None)
+    state
+        .repositories
+        .chats
+        .find_for_participant(chat_id, user_id)
         .await
         .map_err(|_| ErrorInternalServerError("Database error during participation check"))?
         .ok_or_else(|| ErrorForbidden("You are not a participant in this chat"))?;
 
-    // Insert the new message
-    let new_message = Message::new(chat_id.clone(), user_id, content, created_at);
-    let messages = state.db.collection::<Message>(Message::collection_name());
-    let message = messages
-        .insert_one(new_message.clone(), None)
+    // Optionally hold back unverified accounts from messaging.
+    let config = AppConfig::new().map_err(|_| ErrorInternalServerError("Config error"))?;
+    if config.require_verified_email {
+        let sender = state
+            .repositories
+            .users
+            .find_by_id(user_id)
+            .await
+            .map_err(|_| ErrorInternalServerError("Database error during sender lookup"))?
+            .ok_or_else(|| ErrorForbidden("Sender not found"))?;
+        if !sender.email_verified {
+            return Err(ErrorForbidden("Verify your email address before sending messages"));
+        }
+    }
+
+    // A retried send gets the original back without using up a sequence number
+    if let Some(client_msg_id) = client_msg_id {
+        if client_msg_id.is_empty() || client_msg_id.len() > CLIENT_MSG_ID_MAX_LENGTH {
+            return Err(ErrorBadRequest(format!(
+                "client_msg_id must be 1 to {} characters",
+                CLIENT_MSG_ID_MAX_LENGTH
+            )));
+        }
+        if let Some(sent) = find_client_message(state, chat_id, user_id, client_msg_id).await? {
+            return Ok(sent);
+        }
+    }
+
+    // Numbers only ever grow, but a failed insert below leaves a gap
+    let seq = state
+        .repositories
+        .chats
+        .next_seq(chat_id)
         .await
-        .map_err(|_| ErrorInternalServerError("Failed to insert message"))?;
+        .map_err(|_| ErrorInternalServerError("Database error during sequencing"))?
+        .ok_or_else(|| ErrorForbidden("Chat not found"))?;
 
-    // Return the newly created message with its auto-generated ID
-    let new_message_id = message
-        .inserted_id
-        .as_object_id()
-        .ok_or_else(|| ErrorInternalServerError("Failed to get inserted message ID"))?;
+    // Insert the new message
+    let mut new_message = Message::new(chat_id, user_id, seq, content, client_sent_at);
+    new_message.client_msg_id = client_msg_id.map(str::to_owned);
+    let new_message_id = match (state.repositories.messages.insert(&new_message).await, client_msg_id) {
+        (Ok(message_id), _) => message_id,
+        // A concurrent retry stored it first
+        (Err(RepositoryError::Duplicate), Some(client_msg_id)) => {
+            return find_client_message(state, chat_id, user_id, client_msg_id)
+                .await?
+                .ok_or_else(|| ErrorInternalServerError("Failed to insert message"));
+        }
+        (Err(_), _) => return Err(ErrorInternalServerError("Failed to insert message")),
+    };
+
+    // Keep the chat list's copy up to date; the message itself is already stored
+    let last_message = LastMessage::new(new_message_id, &new_message);
+    if let Err(e) = state.repositories.chats.record_message(chat_id, &last_message).await {
+        log::error!("Failed to record the last message of chat {}: {}", chat_id, e);
+    }
 
     // Update the message model with the new ID
     let mut message_model = new_message.clone();
     message_model.id = Some(new_message_id);
 
-    Ok(message_model)
+    Ok(SentMessage {
+        message: message_model,
+        duplicate: false,
+    })
 }
 
-/// Retrieve all messages for a chat room if the user is a participant.
+/// A message as the API returns it.
+pub fn message_json(message: &Message) -> serde_json::Value {
+    serde_json::json!({
+        "id": message.id.map_or_else(String::new, |id| id.to_string()),
+        "chat_id": message.chat_id.to_string(),
+        "sender_id": message.sender_id.to_string(),
+        "seq": message.seq,
+        "content": message.content,
+        "created_at": message.created_at,
+        "client_sent_at": message.client_sent_at,
+        "client_msg_id": message.client_msg_id
+    })
+}
+
+/// Retrieve a page of a chat room's messages, in sequence order, if the user is a participant.
 pub async fn get_chat_messages(
     state: &AppState,
     chat_id: ObjectId,
     user_id: ObjectId,
+    query: &MessageQuery,
 ) -> Result<Vec<serde_json::Value>, Error> {
-    let chats = state.db.collection::<Chat>(Chat::collection_name());
     // Verify that the user is a participant.
-    chats
-        .find_one(doc! { "_id": &chat_id, "participant_ids": &user_id }, None)
+    let chat = state
+        .repositories
+        .chats
+        .find_for_participant(chat_id, user_id)
         .await
         .map_err(|_| ErrorInternalServerError("Database error during participation check"))?
         .ok_or_else(|| ErrorForbidden("You are not a participant in this chat"))?;
 
-    let messages_coll = state.db.collection::<Message>(Message::collection_name());
-
-    // Create find options to sort by created_at in ascending order (oldest to newest)
-    let find_options = mongodb::options::FindOptions::builder()
-        .sort(doc! { "created_at": 1 })
-        .build();
+    let range = MessageRange {
+        after_seq: query.after_seq,
+        before_seq: query.before_seq,
+        // Messages from before the user deleted the chat stay gone for them
+        hidden_seq: chat.hidden_seq(user_id),
+        limit: Some(query.limit.unwrap_or(MESSAGE_PAGE_LIMIT).clamp(1, MESSAGE_PAGE_LIMIT)),
+    };
 
-    let cursor = messages_coll
-        .find(doc! { "chat_id": &chat_id }, find_options)
+    // Oldest to newest
+    let messages = state
+        .repositories
+        .messages
+        .find_by_chat(chat_id, &range)
         .await
         .map_err(|e| ErrorInternalServerError(format!("Failed to get messages: {}", e)))?;
 
-    let messages: Vec<Message> = cursor
-        .try_collect()
+    Ok(messages.iter().map(message_json).collect())
+}
+
+/// Copy the newest remaining message onto the chat, after the recorded one was deleted.
+async fn refresh_last_message(state: &AppState, chat_id: ObjectId) {
+    let result = match state.repositories.messages.find_last(chat_id).await {
+        Ok(message) => {
+            let last_message = message.and_then(|message| Some(LastMessage::new(message.id?, &message)));
+            state
+                .repositories
+                .chats
+                .replace_last_message(chat_id, last_message.as_ref())
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        log::error!("Failed to refresh the last message of chat {}: {}", chat_id, e);
+    }
+}
+
+/// Delete a single message and tell the chat's online participants to drop it.
+pub async fn delete_message(state: &AppState, message_id: ObjectId) -> Result<Message, Error> {
+    let message = state
+        .repositories
+        .messages
+        .delete(message_id)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            ErrorInternalServerError("Failed to delete message")
+        })?
+        .ok_or_else(|| ErrorNotFound("Message not found"))?;
+
+    let chat = get_chat_by_id(state, message.chat_id).await?;
+    if chat.last_message.as_ref().is_some_and(|last_message| Some(last_message.message_id) == message.id) {
+        refresh_last_message(state, message.chat_id).await;
+    }
+    if let Err(e) = state.repositories.saved_messages.delete_by_message(message_id).await {
+        log::error!("Failed to remove saved entries of message {}: {}", message_id, e);
+    }
+    if chat.is_pinned(message_id) {
+        if let Err(e) = state.repositories.chats.unpin(message.chat_id, message_id).await {
+            log::error!("Failed to unpin deleted message {}: {}", message_id, e);
+        }
+    }
+    let event = json!({
+        "type": "message_deleted",
+        "chat_id": message.chat_id.to_hex(),
+        "message_id": message_id.to_hex(),
+    });
+    notify_participants(state, &chat, &event).await;
+
+    Ok(message)
+}
+
+/// Send a WebSocket event to every participant of the chat who is connected.
+async fn notify_participants(state: &AppState, chat: &Chat, event: &serde_json::Value) {
+    let event = event.to_string();
+    let ws_sessions = state.ws_sessions.read().await;
+    for participant_id in &chat.participant_ids {
+        if let Some((addr, _)) = ws_sessions.get(participant_id) {
+            addr.do_send(TextMessage(event.clone()));
+        }
+    }
+}
+
+/// A participant's view of a message: history they deleted the chat after is out of reach.
+async fn find_visible_message(state: &AppState, chat: &Chat, user_id: ObjectId, message_id: ObjectId) -> Result<Message, Error> {
+    let hidden_seq = chat.hidden_seq(user_id).unwrap_or(0);
+    state
+        .repositories
+        .messages
+        .find_by_id(message_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error retrieving message"))?
+        .filter(|message| Some(message.chat_id) == chat.id && message.seq > hidden_seq)
+        .ok_or_else(|| ErrorNotFound("Message not found"))
+}
+
+fn pin_json(pin: &ChatPin, message: &Message) -> serde_json::Value {
+    json!({
+        "message": message_json(message),
+        "pinned_by": pin.pinned_by.to_hex(),
+        "pinned_at": pin.pinned_at,
+    })
+}
+
+/// Pin a message for every participant. Pinning it again changes nothing.
+pub async fn pin_message(
+    state: &AppState,
+    chat_id: ObjectId,
+    user_id: ObjectId,
+    message_id: ObjectId,
+) -> Result<serde_json::Value, Error> {
+    let chat = state
+        .repositories
+        .chats
+        .find_for_participant(chat_id, user_id)
         .await
-        .map_err(|e| ErrorInternalServerError(format!("Failed to collect messages: {}", e)))?;
+        .map_err(|_| ErrorInternalServerError("Database error retrieving chat"))?
+        .ok_or_else(|| ErrorForbidden("Chat not found"))?;
+    let message = find_visible_message(state, &chat, user_id, message_id).await?;
+    if let Some(pin) = chat.pins.iter().find(|pin| pin.message_id == message_id) {
+        return Ok(pin_json(pin, &message));
+    }
 
-    let results = messages
+    let pin = ChatPin {
+        message_id,
+        pinned_by: user_id,
+        pinned_at: Utc::now(),
+    };
+    let pinned = state
+        .repositories
+        .chats
+        .pin(chat_id, &pin, MAX_PINNED_MESSAGES)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to pin message"))?;
+    if !pinned {
+        return Err(ErrorConflict(format!(
+            "A chat can have at most {} pinned messages",
+            MAX_PINNED_MESSAGES
+        )));
+    }
+
+    let pin_json = pin_json(&pin, &message);
+    let event = json!({
+        "type": "message_pinned",
+        "chat_id": chat_id.to_hex(),
+        "message_id": message_id.to_hex(),
+        "pin": pin_json,
+    });
+    notify_participants(state, &chat, &event).await;
+    Ok(pin_json)
+}
+
+pub async fn unpin_message(
+    state: &AppState,
+    chat_id: ObjectId,
+    user_id: ObjectId,
+    message_id: ObjectId,
+) -> Result<(), Error> {
+    let chat = state
+        .repositories
+        .chats
+        .find_for_participant(chat_id, user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error retrieving chat"))?
+        .ok_or_else(|| ErrorForbidden("Chat not found"))?;
+    let unpinned = state
+        .repositories
+        .chats
+        .unpin(chat_id, message_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to unpin message"))?;
+    if !unpinned {
+        return Err(ErrorNotFound("Message is not pinned"));
+    }
+
+    let event = json!({
+        "type": "message_unpinned",
+        "chat_id": chat_id.to_hex(),
+        "message_id": message_id.to_hex(),
+        "unpinned_by": user_id.to_hex(),
+    });
+    notify_participants(state, &chat, &event).await;
+    Ok(())
+}
+
+/// The chat's pinned messages, most recently pinned first.
+pub async fn get_pinned_messages(
+    state: &AppState,
+    chat_id: ObjectId,
+    user_id: ObjectId,
+) -> Result<Vec<serde_json::Value>, Error> {
+    let chat = state
+        .repositories
+        .chats
+        .find_for_participant(chat_id, user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error retrieving chat"))?
+        .ok_or_else(|| ErrorForbidden("Chat not found"))?;
+    if chat.pins.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let message_ids: Vec<ObjectId> = chat.pins.iter().map(|pin| pin.message_id).collect();
+    let hidden_seq = chat.hidden_seq(user_id).unwrap_or(0);
+    let messages: HashMap<ObjectId, Message> = state
+        .repositories
+        .messages
+        .find_by_ids(&message_ids)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get pinned messages"))?
         .into_iter()
-        .map(|message| {
-            serde_json::json!({
-                "id": message.id.map_or_else(String::new, |id| id.to_string()),
-                "chat_id": message.chat_id.to_string(),
-                "sender_id": message.sender_id.to_string(),
-                "content": message.content,
-                "created_at": message.created_at
-            })
-        })
+        .filter(|message| message.seq > hidden_seq)
+        .filter_map(|message| Some((message.id?, message)))
         .collect();
 
-    Ok(results)
+    Ok(chat
+        .pins
+        .iter()
+        .rev()
+        .filter_map(|pin| Some(pin_json(pin, messages.get(&pin.message_id)?)))
+        .collect())
 }
@@ -0,0 +1,93 @@
+use crate::{
+    config::app_config::AppConfig,
+    utils::auth_util::{constant_time_eq, generate_reset_token},
+};
+use actix_session::Session;
+use actix_web::{
+    error::{ErrorForbidden, ErrorInternalServerError},
+    http::header::ORIGIN,
+    Error, HttpRequest,
+};
+use serde::Serialize;
+
+/// Session entry holding the synchronizer token for the session.
+pub const SESSION_CSRF_TOKEN: &str = "csrf_token";
+
+#[derive(Debug, Serialize)]
+pub struct CsrfTokenResponse {
+    pub csrf_token: String,
+}
+
+/// The session's CSRF token, created on first use.
+pub fn csrf_token(session: &Session) -> Result<String, Error> {
+    if let Some(token) = session
+        .get::<String>(SESSION_CSRF_TOKEN)
+        .map_err(|_| ErrorInternalServerError("Session error"))?
+    {
+        return Ok(token);
+    }
+    let token = generate_reset_token();
+    session.insert(SESSION_CSRF_TOKEN, &token)?;
+    Ok(token)
+}
+
+/// Whether `provided` matches the token stored in the session.
+pub fn is_valid_csrf_token(session: &Session, provided: &str) -> bool {
+    session
+        .get::<String>(SESSION_CSRF_TOKEN)
+        .ok()
+        .flatten()
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), provided.as_bytes()))
+}
+
+/// Whether a browser origin is in the allow list. Trailing slashes are ignored.
+pub fn is_allowed_origin(allowed_origins: &[String], origin: &str) -> bool {
+    let origin = origin.trim_end_matches('/');
+    allowed_origins
+        .iter()
+        .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
+
+/// The origin part (`scheme://host[:port]`) of a `Referer` URL.
+fn referer_origin(referer: &str) -> Option<&str> {
+    let authority_start = referer.find("://")? + 3;
+    let authority_end = referer[authority_start..]
+        .find(['/', '?', '#'])
+        .map_or(referer.len(), |end| authority_start + end);
+    (authority_end > authority_start).then(|| &referer[..authority_end])
+}
+
+/// Whether a request without session state may change anything, judged by its `Origin` header,
+/// or by its `Referer` when a browser left `Origin` out.
+///
+/// Browsers name the page that sent a cross-site form or fetch in one of the two, so a forged
+/// login or registration from a foreign page is refused. Clients outside a browser send neither
+/// and are let through.
+pub fn is_allowed_request_origin(allowed_origins: &[String], origin: Option<&str>, referer: Option<&str>) -> bool {
+    match (origin, referer) {
+        (Some(origin), _) => is_allowed_origin(allowed_origins, origin),
+        (None, Some(referer)) => referer_origin(referer).is_some_and(|origin| is_allowed_origin(allowed_origins, origin)),
+        (None, None) => true,
+    }
+}
+
+/// Reject WebSocket handshakes started by pages on other sites.
+///
+/// Browsers always send `Origin` with a WebSocket handshake but do not apply CORS to it, so a
+/// foreign page could otherwise open a socket with the user's cookie. Clients outside a browser
+/// send no `Origin` and are let through.
+pub fn verify_websocket_origin(req: &HttpRequest) -> Result<(), Error> {
+    let Some(origin) = req.headers().get(ORIGIN) else {
+        return Ok(());
+    };
+    let config = AppConfig::new().map_err(|e| {
+        log::error!("Config error: {}", e);
+        ErrorInternalServerError("Config error")
+    })?;
+    let origin = origin.to_str().map_err(|_| ErrorForbidden("Origin not allowed"))?;
+    if !is_allowed_origin(&config.cors_allowed_origins, origin) {
+        log::warn!("Rejected WebSocket handshake from origin {}", origin);
+        return Err(ErrorForbidden("Origin not allowed"));
+    }
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use crate::{
+    config::app_config::AppConfig,
+    constants::DIGEST_USER_BATCH_SIZE,
+    mail::mail_templates::activity_digest_email,
+    models::{digest_preference_model::DigestPreference, user_model::User},
+    repositories::user_repository::UserFilter,
+    services::mail_service::queue_email,
+    states::app_state::AppState,
+    types::notification_types::{DigestFrequency, NotificationType},
+};
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    web, Error,
+};
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDigestPreferenceRequest {
+    pub frequency: DigestFrequency,
+    pub quiet_hours_start: Option<u32>,
+    pub quiet_hours_end: Option<u32>,
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DigestPreferenceSummary {
+    pub frequency: DigestFrequency,
+    pub quiet_hours_start: Option<u32>,
+    pub quiet_hours_end: Option<u32>,
+    pub utc_offset_minutes: i32,
+    pub last_sent_at: Option<DateTime<Utc>>,
+}
+
+impl From<DigestPreference> for DigestPreferenceSummary {
+    fn from(preference: DigestPreference) -> Self {
+        Self {
+            frequency: preference.frequency,
+            quiet_hours_start: preference.quiet_hours_start,
+            quiet_hours_end: preference.quiet_hours_end,
+            utc_offset_minutes: preference.utc_offset_minutes,
+            last_sent_at: preference.last_sent_at,
+        }
+    }
+}
+
+/// Activity a user missed while offline.
+#[derive(Debug, Default)]
+pub struct ActivityDigest {
+    pub unread_messages: u64,
+    pub message_senders: Vec<String>,
+    pub missed_calls: u64,
+    pub pending_friend_requests: u64,
+}
+
+impl ActivityDigest {
+    pub fn is_empty(&self) -> bool {
+        self.unread_messages == 0 && self.missed_calls == 0 && self.pending_friend_requests == 0
+    }
+
+    /// One line per kind of missed activity, for the digest email.
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.unread_messages > 0 {
+            lines.push(format!(
+                "- {} unread message(s) from {}",
+                self.unread_messages,
+                self.message_senders.join(", ")
+            ));
+        }
+        if self.missed_calls > 0 {
+            lines.push(format!("- {} missed call(s)", self.missed_calls));
+        }
+        if self.pending_friend_requests > 0 {
+            lines.push(format!("- {} pending friend request(s)", self.pending_friend_requests));
+        }
+        lines
+    }
+}
+
+/// Get a user's digest preference, falling back to the default.
+pub async fn get_digest_preference(
+    state: &AppState,
+    user_id: ObjectId,
+) -> Result<DigestPreference, Error> {
+    let preference = state
+        .repositories
+        .digest_preferences
+        .find(user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get digest preference"))?;
+    Ok(preference.unwrap_or_else(|| DigestPreference::default_for(user_id)))
+}
+
+/// Create or replace a user's digest preference.
+pub async fn update_digest_preference(
+    state: &AppState,
+    user_id: ObjectId,
+    req: &UpdateDigestPreferenceRequest,
+) -> Result<DigestPreferenceSummary, Error> {
+    if req.quiet_hours_start.is_some() != req.quiet_hours_end.is_some() {
+        return Err(ErrorBadRequest("Quiet hours need both a start and an end"));
+    }
+    if req.quiet_hours_start.is_some_and(|hour| hour > 23)
+        || req.quiet_hours_end.is_some_and(|hour| hour > 23)
+    {
+        return Err(ErrorBadRequest("Quiet hours must be between 0 and 23"));
+    }
+    if req.utc_offset_minutes.abs() > 14 * 60 {
+        return Err(ErrorBadRequest("Invalid UTC offset"));
+    }
+
+    let mut preference = get_digest_preference(state, user_id).await?;
+    preference.frequency = req.frequency;
+    preference.quiet_hours_start = req.quiet_hours_start;
+    preference.quiet_hours_end = req.quiet_hours_end;
+    preference.utc_offset_minutes = req.utc_offset_minutes;
+
+    state
+        .repositories
+        .digest_preferences
+        .upsert(&preference)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to update digest preference"))?;
+
+    Ok(preference.into())
+}
+
+/// Collect the activity a user received after `since`.
+pub async fn collect_activity_digest(
+    state: &AppState,
+    user_id: ObjectId,
+    since: DateTime<Utc>,
+) -> Result<ActivityDigest, Error> {
+    let mut digest = ActivityDigest::default();
+
+    // Unread messages: anything other participants posted in the user's chats.
+    let chat_ids: Vec<ObjectId> = state
+        .repositories
+        .chats
+        .find_for_user(user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get chat rooms"))?
+        .into_iter()
+        .filter_map(|chat| chat.id)
+        .collect();
+
+    if !chat_ids.is_empty() {
+        let messages = &state.repositories.messages;
+        digest.unread_messages = messages
+            .count_since(&chat_ids, user_id, since)
+            .await
+            .map_err(|_| ErrorInternalServerError("Failed to count messages"))?;
+
+        if digest.unread_messages > 0 {
+            let sender_ids = messages
+                .senders_since(&chat_ids, user_id, since)
+                .await
+                .map_err(|_| ErrorInternalServerError("Failed to get message senders"))?;
+            let senders = state
+                .repositories
+                .users
+                .find_by_ids(&sender_ids)
+                .await
+                .map_err(|_| ErrorInternalServerError("Failed to fetch users"))?;
+            digest.message_senders = senders.into_iter().map(|user| user.username).collect();
+        }
+    }
+
+    // Missed calls and friend requests are unhandled notifications.
+    let notifications = &state.repositories.notifications;
+    digest.missed_calls = notifications
+        .count_unhandled_since(user_id, &[NotificationType::VideoCall, NotificationType::MissedCall], since)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to count missed calls"))?;
+    digest.pending_friend_requests = notifications
+        .count_unhandled_since(user_id, &[NotificationType::FriendRequest], since)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to count friend requests"))?;
+
+    Ok(digest)
+}
+
+/// Send a digest to every offline user whose digest is due. Returns how many were sent.
+/// A failure for one user is logged and does not stop the others from getting theirs.
+pub async fn send_due_digests(state: &AppState) -> Result<usize, Error> {
+    let config = AppConfig::new().map_err(|_| ErrorInternalServerError("Config error"))?;
+    let now = Utc::now();
+    // Suspended accounts cannot sign in to read what they missed.
+    let filter = UserFilter {
+        suspended: Some(false),
+        ..Default::default()
+    };
+
+    let mut sent = 0;
+    let mut skip = 0;
+    loop {
+        let users = state
+            .repositories
+            .users
+            .find_users(&filter, skip, Some(DIGEST_USER_BATCH_SIZE))
+            .await
+            .map_err(|_| ErrorInternalServerError("Failed to get users"))?;
+        if users.is_empty() {
+            break;
+        }
+        skip += users.len() as u64;
+
+        // Unverified addresses may not belong to the user, so they get no mail beyond the verification link.
+        let users: Vec<User> = users.into_iter().filter(|user| user.email_verified).collect();
+        let user_ids: Vec<ObjectId> = users.iter().filter_map(|user| user.id).collect();
+        let preferences: HashMap<ObjectId, DigestPreference> = state
+            .repositories
+            .digest_preferences
+            .find_for_users(&user_ids)
+            .await
+            .map_err(|_| ErrorInternalServerError("Failed to get digest preferences"))?
+            .into_iter()
+            .map(|preference| (preference.user_id, preference))
+            .collect();
+
+        for user in users {
+            let Some(user_id) = user.id else {
+                continue;
+            };
+            let preference = preferences
+                .get(&user_id)
+                .cloned()
+                .unwrap_or_else(|| DigestPreference::default_for(user_id));
+            match send_user_digest(state, &config, &user, &preference, now).await {
+                Ok(true) => sent += 1,
+                Ok(false) => {}
+                Err(e) => log::error!("Digest error for user {}: {}", user_id, e),
+            }
+        }
+    }
+
+    Ok(sent)
+}
+
+/// Send one user their digest if it is due and they missed anything. Returns whether it was sent.
+async fn send_user_digest(
+    state: &AppState,
+    config: &AppConfig,
+    user: &User,
+    preference: &DigestPreference,
+    now: DateTime<Utc>,
+) -> Result<bool, Error> {
+    let Some(interval) = preference.frequency.interval().filter(|_| preference.is_due_at(now)) else {
+        return Ok(false);
+    };
+    if state.ws_sessions.read().await.contains_key(&preference.user_id) {
+        return Ok(false);
+    }
+
+    // Only report what the user has not already seen or been told about.
+    let since = [user.last_seen_at, preference.last_sent_at]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or(now - interval);
+    let digest = collect_activity_digest(state, preference.user_id, since).await?;
+    if digest.is_empty() {
+        return Ok(false);
+    }
+
+    queue_email(
+        state,
+        activity_digest_email(&user.email, &config.frontend_url, &user.username, &digest.summary_lines()),
+    )
+    .await?;
+
+    state
+        .repositories
+        .digest_preferences
+        .record_sent(preference.user_id, now)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to record digest"))?;
+    Ok(true)
+}
+
+/// Periodically send digests in the background.
+pub fn start_digest_scheduler(state: web::Data<AppState>, interval_minutes: u64) {
+    actix_web::rt::spawn(async move {
+        let period = std::time::Duration::from_secs(interval_minutes.max(1) * 60);
+        let mut interval = actix_web::rt::time::interval(period);
+        loop {
+            interval.tick().await;
+            match send_due_digests(&state).await {
+                Ok(0) => {}
+                Ok(sent) => log::info!("Sent {} activity digest(s)", sent),
+                Err(e) => log::error!("Digest error: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,31 @@
+use crate::{constants::EXPIRY_PURGE_INTERVAL_MINUTES, repositories::repository::RepositoryError, states::app_state::AppState};
+use actix_web::web;
+use chrono::Utc;
+
+/// Remove expired sessions, API tokens, throttle records and outbox emails. Returns how many were removed.
+///
+/// MongoDB's TTL indexes already do this, so there it removes nothing.
+pub async fn purge_expired(state: &AppState) -> Result<u64, RepositoryError> {
+    let now = Utc::now();
+    let repositories = &state.repositories;
+    Ok(repositories.sessions.delete_expired(now).await?
+        + repositories.api_tokens.delete_expired(now).await?
+        + repositories.throttles.delete_expired(now).await?
+        + repositories.outbox.delete_expired(now).await?)
+}
+
+/// Periodically purge expired records in the background.
+pub fn start_expiry_job(state: web::Data<AppState>) {
+    actix_web::rt::spawn(async move {
+        let period = std::time::Duration::from_secs(EXPIRY_PURGE_INTERVAL_MINUTES * 60);
+        let mut interval = actix_web::rt::time::interval(period);
+        loop {
+            interval.tick().await;
+            match purge_expired(&state).await {
+                Ok(0) => {}
+                Ok(purged) => log::info!("Purged {} expired records", purged),
+                Err(e) => log::error!("Expiry purge error: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,100 @@
+use crate::{
+    constants::{OUTBOX_MAX_ATTEMPTS, OUTBOX_RETENTION_DAYS},
+    mail::mailer::{EmailMessage, Mailer},
+    models::outbox_model::OutboxEmail,
+    repositories::outbox_repository::OutboxRepository,
+    states::app_state::AppState,
+};
+use actix_web::{error::ErrorInternalServerError, web, Error};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+/// How long to wait before retrying a send that failed `attempts` times.
+pub fn retry_delay(attempts: i32) -> Duration {
+    Duration::minutes(1 << attempts.clamp(0, 10))
+}
+
+/// Store an email in the outbox and try to deliver it right away without blocking the caller.
+/// Failed deliveries are retried by the outbox worker.
+pub async fn queue_email(state: &AppState, email: EmailMessage) -> Result<(), Error> {
+    // Leave the first retry far enough out that the worker does not race the immediate attempt.
+    let mut outbox_email = OutboxEmail::new(email, Utc::now() + retry_delay(0));
+    let outbox = state.repositories.outbox.clone();
+    let email_id = outbox.insert(&outbox_email).await.map_err(|e| {
+        log::error!("Database error: {}", e);
+        ErrorInternalServerError("Failed to queue email")
+    })?;
+    outbox_email.id = Some(email_id);
+
+    let mailer = state.mailer.clone();
+    actix_web::rt::spawn(async move {
+        if let Err(e) = deliver_outbox_email(outbox.as_ref(), mailer, outbox_email).await {
+            log::error!("Outbox error: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// Try to send one outbox email and record the outcome.
+/// Once the email is sent or given up on, its bodies are blanked and it is removed after the retention period.
+async fn deliver_outbox_email(
+    outbox: &dyn OutboxRepository,
+    mailer: Arc<dyn Mailer>,
+    outbox_email: OutboxEmail,
+) -> Result<(), Error> {
+    let id = outbox_email
+        .id
+        .ok_or_else(|| ErrorInternalServerError("Outbox email has no ID"))?;
+    let attempts = outbox_email.attempts + 1;
+    let now = Utc::now();
+    let expires_at = now + Duration::days(OUTBOX_RETENTION_DAYS);
+
+    let result = match mailer.send(&outbox_email.email).await {
+        Ok(()) => outbox.record_sent(id, attempts, now, expires_at).await,
+        Err(e) => {
+            log::warn!("Failed to send email to {} (attempt {}): {}", outbox_email.email.to, attempts, e);
+            if attempts >= OUTBOX_MAX_ATTEMPTS {
+                outbox.give_up(id, attempts, &e.to_string(), expires_at).await
+            } else {
+                outbox
+                    .record_failure(id, attempts, &e.to_string(), now + retry_delay(attempts))
+                    .await
+            }
+        }
+    };
+    result.map_err(|e| {
+        log::error!("Database error: {}", e);
+        ErrorInternalServerError("Failed to update outbox")
+    })
+}
+
+/// Retry every pending outbox email that is due. Returns how many were attempted.
+pub async fn deliver_pending_emails(state: &AppState) -> Result<usize, Error> {
+    let outbox = state.repositories.outbox.as_ref();
+    let due = outbox
+        .find_due(Utc::now(), 100)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to read outbox"))?;
+
+    let attempted = due.len();
+    for outbox_email in due {
+        deliver_outbox_email(outbox, state.mailer.clone(), outbox_email).await?;
+    }
+
+    Ok(attempted)
+}
+
+/// Periodically retry failed sends in the background.
+pub fn start_outbox_worker(state: web::Data<AppState>, interval_seconds: u64) {
+    actix_web::rt::spawn(async move {
+        let period = std::time::Duration::from_secs(interval_seconds.max(1));
+        let mut interval = actix_web::rt::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(e) = deliver_pending_emails(&state).await {
+                log::error!("Outbox error: {}", e);
+            }
+        }
+    });
+}
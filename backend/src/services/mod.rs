@@ -1,5 +1,18 @@
+pub mod admin_service;
+pub mod audit_service;
 pub mod auth_service;
 pub mod chat_service;
+pub mod csrf_service;
+pub mod digest_service;
+pub mod expiry_service;
+pub mod mail_service;
+pub mod moderation_service;
 pub mod notification_service;
+pub mod oidc_service;
+pub mod saved_message_service;
+pub mod session_service;
+pub mod throttle_service;
+pub mod token_service;
+pub mod two_factor_service;
 pub mod user_service;
 pub mod video_call_service;
@@ -0,0 +1,418 @@
+use crate::{
+    constants::MODERATION_PAGE_LIMIT,
+    models::{
+        moderation_action_model::ModerationAction, notification_model::Notification, report_model::Report,
+    },
+    repositories::report_repository::{ReportFilter, ReportSelection},
+    services::{
+        admin_service::{parse_user_id, suspend_account},
+        chat_service::delete_message,
+        notification_service::create_notification,
+        user_service::get_user_by_id,
+    },
+    states::app_state::AppState,
+    types::{
+        moderation_types::{ModerationActionKind, ReportReason, ReportStatus},
+        notification_types::NotificationType,
+    },
+    utils::request_util::RequestContext,
+};
+use actix_web::{
+    error::{ErrorBadRequest, ErrorConflict, ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Longest free-text explanation accepted on reports and moderator actions.
+const MAX_NOTE_LENGTH: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReportRequest {
+    /// Report a message; its sender becomes the reported user.
+    pub message_id: Option<String>,
+    /// Report a user directly.
+    pub user_id: Option<String>,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    /// Defaults to open reports.
+    pub status: Option<ReportStatus>,
+    pub reason: Option<ReportReason>,
+    pub page: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DismissReportRequest {
+    pub report_id: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoveMessageRequest {
+    pub message_id: String,
+    pub report_id: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WarnUserRequest {
+    pub user_id: String,
+    /// Shown to the user in the warning notification.
+    pub note: String,
+    pub report_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModeratorSuspendRequest {
+    pub user_id: String,
+    pub note: Option<String>,
+    pub report_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModerationActionQuery {
+    /// Only actions taken against this user.
+    pub user_id: Option<String>,
+    pub page: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportSummary {
+    pub id: String,
+    pub reporter_id: String,
+    pub reporter_username: Option<String>,
+    pub reported_user_id: String,
+    pub reported_username: Option<String>,
+    pub message_id: Option<String>,
+    pub chat_id: Option<String>,
+    pub message_content: Option<String>,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+    pub status: ReportStatus,
+    pub handled_by: Option<String>,
+    pub handled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportList {
+    pub reports: Vec<ReportSummary>,
+    /// Reports matching the query across all pages.
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModerationActionSummary {
+    pub id: String,
+    pub moderator_id: String,
+    pub action: ModerationActionKind,
+    pub target_user_id: String,
+    pub message_id: Option<String>,
+    pub report_id: Option<String>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ModerationAction> for ModerationActionSummary {
+    fn from(action: ModerationAction) -> Self {
+        Self {
+            id: action.id.map_or_else(String::new, |id| id.to_hex()),
+            moderator_id: action.moderator_id.to_hex(),
+            action: action.action,
+            target_user_id: action.target_user_id.to_hex(),
+            message_id: action.message_id.map(|id| id.to_hex()),
+            report_id: action.report_id.map(|id| id.to_hex()),
+            note: action.note,
+            created_at: action.created_at,
+        }
+    }
+}
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    ErrorInternalServerError("Database error")
+}
+
+fn parse_object_id(id: &str, what: &'static str) -> Result<ObjectId, Error> {
+    ObjectId::parse_str(id).map_err(|_| ErrorBadRequest(what))
+}
+
+/// Trim a free-text field, treating blank text as absent.
+fn clean_note(note: Option<&str>) -> Result<Option<String>, Error> {
+    let note = note.map(str::trim).filter(|note| !note.is_empty());
+    if note.is_some_and(|note| note.chars().count() > MAX_NOTE_LENGTH) {
+        return Err(ErrorBadRequest("Text is too long"));
+    }
+    Ok(note.map(str::to_owned))
+}
+
+fn to_summary(report: Report, usernames: &HashMap<ObjectId, String>) -> ReportSummary {
+    ReportSummary {
+        id: report.id.map_or_else(String::new, |id| id.to_hex()),
+        reporter_id: report.reporter_id.to_hex(),
+        reporter_username: usernames.get(&report.reporter_id).cloned(),
+        reported_user_id: report.reported_user_id.to_hex(),
+        reported_username: usernames.get(&report.reported_user_id).cloned(),
+        message_id: report.message_id.map(|id| id.to_hex()),
+        chat_id: report.chat_id.map(|id| id.to_hex()),
+        message_content: report.message_content,
+        reason: report.reason,
+        details: report.details,
+        status: report.status,
+        handled_by: report.handled_by.map(|id| id.to_hex()),
+        handled_at: report.handled_at,
+        created_at: report.created_at,
+    }
+}
+
+/// Report a message or a user. A reporter has at most one open report per message or user.
+pub async fn create_report(
+    state: &AppState,
+    reporter_id: ObjectId,
+    req: &CreateReportRequest,
+) -> Result<ReportSummary, Error> {
+    let details = clean_note(req.details.as_deref())?;
+    let mut report = match (&req.message_id, &req.user_id) {
+        (Some(message_id), None) => {
+            let message_id = parse_object_id(message_id, "Invalid message ID")?;
+            let message = state
+                .repositories
+                .messages
+                .find_by_id(message_id)
+                .await
+                .map_err(database_error)?
+                .ok_or_else(|| ErrorNotFound("Message not found"))?;
+            // Only participants can see a message, so only they may report it.
+            state
+                .repositories
+                .chats
+                .find_for_participant(message.chat_id, reporter_id)
+                .await
+                .map_err(database_error)?
+                .ok_or_else(|| ErrorForbidden("You are not a participant in this chat"))?;
+
+            let mut report = Report::new(reporter_id, message.sender_id, req.reason, details);
+            report.message_id = Some(message_id);
+            report.chat_id = Some(message.chat_id);
+            report.message_content = Some(message.content);
+            report
+        }
+        (None, Some(user_id)) => {
+            let user_id = parse_user_id(user_id)?;
+            get_user_by_id(state, user_id).await?;
+            Report::new(reporter_id, user_id, req.reason, details)
+        }
+        _ => return Err(ErrorBadRequest("Report either a message or a user")),
+    };
+    if report.reported_user_id == reporter_id {
+        return Err(ErrorBadRequest("You cannot report yourself"));
+    }
+
+    let reports = &state.repositories.reports;
+    if reports.has_open_duplicate(&report).await.map_err(database_error)? {
+        return Err(ErrorConflict("You have already reported this"));
+    }
+
+    report.id = Some(reports.insert(&report).await.map_err(database_error)?);
+    log::info!("User {} reported user {}", reporter_id, report.reported_user_id);
+    Ok(to_summary(report, &HashMap::new()))
+}
+
+/// The moderation queue: reports with the given status, oldest first so nothing waits forever.
+pub async fn list_reports(state: &AppState, query: &ReportQuery) -> Result<ReportList, Error> {
+    let filter = ReportFilter {
+        status: query.status.unwrap_or(ReportStatus::Open),
+        reason: query.reason,
+    };
+    let reports_repository = &state.repositories.reports;
+    let total = reports_repository.count(&filter).await.map_err(database_error)?;
+    let reports = reports_repository
+        .find(&filter, query.page.unwrap_or(0) * MODERATION_PAGE_LIMIT as u64, MODERATION_PAGE_LIMIT)
+        .await
+        .map_err(database_error)?;
+
+    let user_ids: HashSet<ObjectId> = reports
+        .iter()
+        .flat_map(|report| [report.reporter_id, report.reported_user_id])
+        .collect();
+    let usernames: HashMap<ObjectId, String> = state
+        .repositories
+        .users
+        .find_by_ids(&user_ids.into_iter().collect::<Vec<_>>())
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .filter_map(|user| user.id.map(|id| (id, user.username)))
+        .collect();
+
+    Ok(ReportList {
+        reports: reports
+            .into_iter()
+            .map(|report| to_summary(report, &usernames))
+            .collect(),
+        total,
+    })
+}
+
+async fn record_action(state: &AppState, action: &ModerationAction) -> Result<(), Error> {
+    state
+        .repositories
+        .moderation_actions
+        .insert(action)
+        .await
+        .map_err(database_error)?;
+    log::info!(
+        "Moderator {} took action {:?} against user {}",
+        action.moderator_id,
+        action.action,
+        action.target_user_id
+    );
+    Ok(())
+}
+
+/// Close the selected open reports as handled by the moderator.
+async fn close_reports(
+    state: &AppState,
+    moderator_id: ObjectId,
+    selection: ReportSelection,
+    status: ReportStatus,
+) -> Result<u64, Error> {
+    state
+        .repositories
+        .reports
+        .close(selection, status, moderator_id, Utc::now())
+        .await
+        .map_err(database_error)
+}
+
+/// Resolve the report an action was taken for, if any.
+async fn resolve_report(state: &AppState, moderator_id: ObjectId, report_id: Option<ObjectId>) -> Result<(), Error> {
+    if let Some(report_id) = report_id {
+        close_reports(state, moderator_id, ReportSelection::Report(report_id), ReportStatus::Resolved).await?;
+    }
+    Ok(())
+}
+
+pub async fn dismiss_report(
+    state: &AppState,
+    moderator_id: ObjectId,
+    req: &DismissReportRequest,
+) -> Result<(), Error> {
+    let report_id = parse_object_id(&req.report_id, "Invalid report ID")?;
+    let report = state
+        .repositories
+        .reports
+        .find_by_id(report_id)
+        .await
+        .map_err(database_error)?
+        .ok_or_else(|| ErrorNotFound("Report not found"))?;
+    if close_reports(state, moderator_id, ReportSelection::Report(report_id), ReportStatus::Dismissed).await? == 0 {
+        return Err(ErrorConflict("Report has already been handled"));
+    }
+
+    let mut action = ModerationAction::new(moderator_id, ModerationActionKind::DismissReport, report.reported_user_id);
+    action.message_id = report.message_id;
+    action.report_id = Some(report_id);
+    action.note = clean_note(req.note.as_deref())?;
+    record_action(state, &action).await
+}
+
+/// Delete a message for everyone in its chat and resolve every open report about it.
+pub async fn remove_message(
+    state: &AppState,
+    moderator_id: ObjectId,
+    req: &RemoveMessageRequest,
+) -> Result<(), Error> {
+    let message_id = parse_object_id(&req.message_id, "Invalid message ID")?;
+    let report_id = req
+        .report_id
+        .as_deref()
+        .map(|id| parse_object_id(id, "Invalid report ID"))
+        .transpose()?;
+    let note = clean_note(req.note.as_deref())?;
+    let message = delete_message(state, message_id).await?;
+
+    close_reports(state, moderator_id, ReportSelection::Message(message_id), ReportStatus::Resolved).await?;
+    resolve_report(state, moderator_id, report_id).await?;
+
+    let mut action = ModerationAction::new(moderator_id, ModerationActionKind::RemoveMessage, message.sender_id);
+    action.message_id = Some(message_id);
+    action.report_id = report_id;
+    action.note = note;
+    record_action(state, &action).await
+}
+
+/// Send the user a system notification with the moderator's note.
+pub async fn warn_user(state: &AppState, moderator_id: ObjectId, req: &WarnUserRequest) -> Result<(), Error> {
+    let user_id = parse_user_id(&req.user_id)?;
+    let report_id = req
+        .report_id
+        .as_deref()
+        .map(|id| parse_object_id(id, "Invalid report ID"))
+        .transpose()?;
+    let note = clean_note(Some(&req.note))?.ok_or_else(|| ErrorBadRequest("A warning needs a message"))?;
+    get_user_by_id(state, user_id).await?;
+
+    create_notification(
+        state,
+        Notification::new(
+            NotificationType::System,
+            user_id,
+            moderator_id,
+            &format!("You have received a warning from a moderator: {}", note),
+        ),
+    )
+    .await?;
+    resolve_report(state, moderator_id, report_id).await?;
+
+    let mut action = ModerationAction::new(moderator_id, ModerationActionKind::WarnUser, user_id);
+    action.report_id = report_id;
+    action.note = Some(note);
+    record_action(state, &action).await
+}
+
+/// Suspend the user and resolve every open report against them.
+pub async fn moderator_suspend_user(
+    state: &AppState,
+    context: &RequestContext,
+    moderator_id: ObjectId,
+    req: &ModeratorSuspendRequest,
+) -> Result<(), Error> {
+    let user_id = parse_user_id(&req.user_id)?;
+    let report_id = req
+        .report_id
+        .as_deref()
+        .map(|id| parse_object_id(id, "Invalid report ID"))
+        .transpose()?;
+    let note = clean_note(req.note.as_deref())?;
+    suspend_account(state, context, moderator_id, user_id, note.as_deref()).await?;
+
+    close_reports(state, moderator_id, ReportSelection::ReportedUser(user_id), ReportStatus::Resolved).await?;
+    resolve_report(state, moderator_id, report_id).await?;
+
+    let mut action = ModerationAction::new(moderator_id, ModerationActionKind::SuspendUser, user_id);
+    action.report_id = report_id;
+    action.note = note;
+    record_action(state, &action).await
+}
+
+/// The moderation trail, newest first.
+pub async fn list_moderation_actions(
+    state: &AppState,
+    query: &ModerationActionQuery,
+) -> Result<Vec<ModerationActionSummary>, Error> {
+    let target_user_id = query.user_id.as_deref().map(parse_user_id).transpose()?;
+    let actions = state
+        .repositories
+        .moderation_actions
+        .find(target_user_id, query.page.unwrap_or(0) * MODERATION_PAGE_LIMIT as u64, MODERATION_PAGE_LIMIT)
+        .await
+        .map_err(database_error)?;
+    Ok(actions.into_iter().map(ModerationActionSummary::from).collect())
+}
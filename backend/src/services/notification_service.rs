@@ -1,66 +1,115 @@
 // src/services/notification.rs
-use crate::{models::notification_model::Notification, states::app_state::AppState};
-use actix_web::{error::ErrorInternalServerError, Error};
-use futures::TryStreamExt;
-use mongodb::bson::{doc, oid::ObjectId};
-use serde::Serialize;
+use crate::{
+    config::app_config::AppConfig,
+    constants::NOTIFICATION_PAGE_LIMIT,
+    mail::mail_templates::notification_email,
+    models::{
+        notification_model::Notification,
+        notification_preference_model::NotificationPreference,
+    },
+    services::mail_service::queue_email,
+    states::app_state::AppState,
+    types::notification_types::NotificationType,
+    websocket::websocket_session::TextMessage,
+};
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[derive(Debug, Serialize)]
 pub struct NotificationSummary {
     pub id: String,
-    pub notification_type: String,
+    pub notification_type: NotificationType,
     pub sender_user_id: String,
     pub sender_username: String,
     pub message: String,
+    pub is_read: bool,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
-/// Retrieve notifications for a given user.
-pub async fn get_user_notifications(
+#[derive(Debug, Deserialize)]
+pub struct ListNotificationsQuery {
+    #[serde(default)]
+    pub unread_only: bool,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateNotificationRequest {
+    pub recipient_id: String,
+    pub notification_type: NotificationType,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarkReadRequest {
+    /// Notifications to mark as read; when omitted every unread notification is marked.
+    pub notification_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DismissNotificationRequest {
+    pub notification_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePreferenceRequest {
+    pub notification_type: NotificationType,
+    pub in_app: bool,
+    pub email: bool,
+    pub muted: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreferenceSummary {
+    pub notification_type: NotificationType,
+    pub in_app: bool,
+    pub email: bool,
+    pub muted: bool,
+}
+
+impl From<NotificationPreference> for PreferenceSummary {
+    fn from(preference: NotificationPreference) -> Self {
+        Self {
+            notification_type: preference.notification_type,
+            in_app: preference.in_app,
+            email: preference.email,
+            muted: preference.muted,
+        }
+    }
+}
+
+/// Resolve sender usernames for a batch of notifications and build their summaries.
+async fn summarize_notifications(
     state: &AppState,
-    user_id: ObjectId,
+    notifications: Vec<Notification>,
 ) -> Result<Vec<NotificationSummary>, Error> {
-    let notifications_collection = state
-        .db
-        .collection::<Notification>(Notification::collection_name());
-    let cursor = notifications_collection
-        .find(doc! { "recipient_id": &user_id, "is_handled": false }, None)
-        .await
-        .map_err(|_| ErrorInternalServerError("Failed to get notifications"))?;
-    let notifications: Vec<Notification> = cursor
-        .try_collect()
-        .await
-        .map_err(|_| ErrorInternalServerError("Failed to collect notifications"))?;
-    
-    // Convert Notification objects to NotificationSummary objects
-    // Collect notifications first to prepare for user fetch
     let mut summaries = Vec::with_capacity(notifications.len());
-    
+
     // Gather unique sender IDs to batch fetch user data
     let sender_ids = notifications.iter()
         .map(|notification| notification.sender_id)
         .collect::<std::collections::HashSet<ObjectId>>();
-    
+
     // Fetch user data for all sender_ids in one query
-    let users_collection = state.db.collection::<crate::models::user_model::User>(
-        crate::models::user_model::User::collection_name());
-    
-    let user_cursor = users_collection
-        .find(doc! { "_id": { "$in": sender_ids.into_iter().collect::<Vec<_>>() } }, None)
+    let users = state
+        .repositories
+        .users
+        .find_by_ids(&sender_ids.into_iter().collect::<Vec<_>>())
         .await
         .map_err(|_| ErrorInternalServerError("Failed to fetch users"))?;
-    
-    let users: Vec<crate::models::user_model::User> = user_cursor
-        .try_collect()
-        .await
-        .map_err(|_| ErrorInternalServerError("Failed to collect users"))?;
-    
+
     // Create a map of user_id -> username for quick lookup
     let username_map: std::collections::HashMap<ObjectId, String> = users
         .into_iter()
         .map(|user| (user.id.unwrap_or_default(), user.username))
         .collect();
-    
+
     // Create notification summaries with usernames
     for notification in notifications {
         let sender_id = notification.sender_id;
@@ -68,43 +117,285 @@ pub async fn get_user_notifications(
             .get(&sender_id)
             .cloned()
             .unwrap_or_else(|| "Unknown User".to_string());
-        
+
         summaries.push(NotificationSummary {
             id: notification.id.unwrap_or_default().to_hex(),
             notification_type: notification.notification_type,
             sender_user_id: sender_id.to_hex(),
             sender_username,
             message: notification.message,
+            is_read: notification.is_read,
             timestamp: notification.created_at,
         });
     }
-    
+
     Ok(summaries)
 }
 
-/// Create a new notification.
+/// Retrieve notifications for a given user.
+pub async fn get_user_notifications(
+    state: &AppState,
+    user_id: ObjectId,
+) -> Result<Vec<NotificationSummary>, Error> {
+    let notifications = state
+        .repositories
+        .notifications
+        .find_unhandled(user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get notifications"))?;
+
+    summarize_notifications(state, notifications).await
+}
+
+/// List a user's notifications, newest first, optionally restricted to unread ones.
+pub async fn list_notifications(
+    state: &AppState,
+    user_id: ObjectId,
+    query: &ListNotificationsQuery,
+) -> Result<Vec<NotificationSummary>, Error> {
+    let limit = query
+        .limit
+        .unwrap_or(NOTIFICATION_PAGE_LIMIT)
+        .clamp(1, NOTIFICATION_PAGE_LIMIT);
+    let notifications = state
+        .repositories
+        .notifications
+        .find_recent(user_id, query.unread_only, limit)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get notifications"))?;
+
+    summarize_notifications(state, notifications).await
+}
+
+/// Count a user's unread notifications.
+pub async fn count_unread_notifications(state: &AppState, user_id: ObjectId) -> Result<u64, Error> {
+    state
+        .repositories
+        .notifications
+        .count_unread(user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to count notifications"))
+}
+
+/// Create a new notification and deliver it according to the recipient's preferences.
 pub async fn create_notification(
     state: &AppState,
-    new_notification: Notification,
+    mut new_notification: Notification,
 ) -> Result<Notification, Error> {
-    let notifications_collection = state
-        .db
-        .collection::<Notification>(Notification::collection_name());
-    notifications_collection
-        .insert_one(&new_notification, None)
+    let preference = get_notification_preference(
+        state,
+        new_notification.recipient_id,
+        new_notification.notification_type,
+    )
+    .await?;
+
+    // Muted notifications are kept for the record but never surface as unread.
+    if preference.muted {
+        new_notification.is_read = true;
+        new_notification.read_at = Some(Utc::now());
+    }
+
+    let notification_id = state
+        .repositories
+        .notifications
+        .insert(&new_notification)
         .await
         .map_err(|_| ErrorInternalServerError("Failed to create notification"))?;
+    new_notification.id = Some(notification_id);
+
+    // The notification itself is stored already; a failed push or email must not fail the request.
+    if preference.pushes_live() {
+        if let Err(e) = push_notification(state, &new_notification).await {
+            log::error!("Notification push error: {}", e);
+        }
+    }
+
+    if preference.sends_email() {
+        if let Err(e) = send_notification_email(state, &new_notification).await {
+            log::error!("Email error: {}", e);
+        }
+    }
+
     Ok(new_notification)
 }
 
-/// Delete a notification by its ID.
-pub async fn delete_notification(state: &AppState, notification_id: ObjectId) -> Result<(), Error> {
-    let notifications_collection = state
-        .db
-        .collection::<Notification>(Notification::collection_name());
-    notifications_collection
-        .delete_one(doc! { "_id": notification_id }, None)
+/// Create a notification on behalf of a user, e.g. a friend request.
+pub async fn create_user_notification(
+    state: &AppState,
+    sender_id: ObjectId,
+    req: &CreateNotificationRequest,
+) -> Result<NotificationSummary, Error> {
+    if !req.notification_type.is_user_creatable() {
+        return Err(ErrorBadRequest("This notification type cannot be created"));
+    }
+
+    let recipient_id = ObjectId::parse_str(&req.recipient_id)
+        .map_err(|_| ErrorBadRequest("Invalid recipient ID"))?;
+    if recipient_id == sender_id {
+        return Err(ErrorBadRequest("Cannot notify yourself"));
+    }
+
+    state
+        .repositories
+        .users
+        .find_by_id(recipient_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error"))?
+        .ok_or_else(|| ErrorNotFound("Recipient not found"))?;
+
+    let notification = Notification::new(req.notification_type, recipient_id, sender_id, &req.message);
+    let notification = create_notification(state, notification).await?;
+
+    summarize_notifications(state, vec![notification])
+        .await?
+        .pop()
+        .ok_or_else(|| ErrorInternalServerError("Failed to summarize notification"))
+}
+
+/// Push a notification to the recipient's live WebSocket session, if any.
+async fn push_notification(state: &AppState, notification: &Notification) -> Result<(), Error> {
+    let ws_sessions = state.ws_sessions.read().await;
+    let recipient_addr = match ws_sessions.get(&notification.recipient_id) {
+        Some((addr, _)) => addr.clone(),
+        None => return Ok(()),
+    };
+    drop(ws_sessions);
+
+    let notification_summary = summarize_notifications(state, vec![notification.clone()])
+        .await?
+        .pop()
+        .ok_or_else(|| ErrorInternalServerError("Failed to summarize notification"))?;
+
+    let ws_message = json!({
+        "type": notification.notification_type.ws_event(),
+        "notification": notification_summary
+    });
+    recipient_addr.do_send(TextMessage(ws_message.to_string()));
+
+    Ok(())
+}
+
+/// Queue an email copy of a notification for its recipient.
+async fn send_notification_email(state: &AppState, notification: &Notification) -> Result<(), Error> {
+    let config = AppConfig::new().map_err(|_| ErrorInternalServerError("Config error"))?;
+    let recipient = state
+        .repositories
+        .users
+        .find_by_id(notification.recipient_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error"))?
+        .ok_or_else(|| ErrorNotFound("Recipient not found"))?;
+
+    let kind = notification.notification_type.as_str().replace('_', " ");
+    queue_email(
+        state,
+        notification_email(&recipient.email, &config.frontend_url, &kind, &notification.message),
+    )
+    .await
+}
+
+/// Mark notifications as read. Marks every unread notification when no ids are given.
+pub async fn mark_notifications_read(
+    state: &AppState,
+    user_id: ObjectId,
+    req: &MarkReadRequest,
+) -> Result<u64, Error> {
+    let ids = req
+        .notification_ids
+        .as_ref()
+        .map(|ids| {
+            ids.iter()
+                .map(ObjectId::parse_str)
+                .collect::<Result<Vec<ObjectId>, _>>()
+                .map_err(|_| ErrorBadRequest("Invalid notification ID"))
+        })
+        .transpose()?;
+
+    state
+        .repositories
+        .notifications
+        .mark_read(user_id, ids.as_deref(), Utc::now())
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to mark notifications as read"))
+}
+
+/// Delete a notification by its ID, provided it belongs to the given user.
+pub async fn delete_notification(
+    state: &AppState,
+    user_id: ObjectId,
+    notification_id: ObjectId,
+) -> Result<(), Error> {
+    let deleted = state
+        .repositories
+        .notifications
+        .delete(notification_id, user_id)
         .await
         .map_err(|_| ErrorInternalServerError("Failed to delete notification"))?;
+    if !deleted {
+        return Err(ErrorNotFound("Notification not found"));
+    }
     Ok(())
 }
+
+/// Get a user's preference for one notification type, falling back to the default.
+pub async fn get_notification_preference(
+    state: &AppState,
+    user_id: ObjectId,
+    notification_type: NotificationType,
+) -> Result<NotificationPreference, Error> {
+    let preference = state
+        .repositories
+        .notification_preferences
+        .find(user_id, notification_type)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get notification preference"))?;
+    Ok(preference.unwrap_or_else(|| NotificationPreference::default_for(user_id, notification_type)))
+}
+
+/// Get a user's preferences for every notification type.
+pub async fn get_notification_preferences(
+    state: &AppState,
+    user_id: ObjectId,
+) -> Result<Vec<PreferenceSummary>, Error> {
+    let stored = state
+        .repositories
+        .notification_preferences
+        .find_for_user(user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get notification preferences"))?;
+
+    let preferences = NotificationType::ALL
+        .iter()
+        .map(|&notification_type| {
+            stored
+                .iter()
+                .find(|preference| preference.notification_type == notification_type)
+                .cloned()
+                .unwrap_or_else(|| NotificationPreference::default_for(user_id, notification_type))
+                .into()
+        })
+        .collect();
+
+    Ok(preferences)
+}
+
+/// Create or replace a user's preference for one notification type.
+pub async fn update_notification_preference(
+    state: &AppState,
+    user_id: ObjectId,
+    req: &UpdatePreferenceRequest,
+) -> Result<PreferenceSummary, Error> {
+    let mut preference = NotificationPreference::default_for(user_id, req.notification_type);
+    preference.in_app = req.in_app;
+    preference.email = req.email;
+    preference.muted = req.muted;
+
+    state
+        .repositories
+        .notification_preferences
+        .upsert(&preference)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to update notification preference"))?;
+
+    Ok(preference.into())
+}
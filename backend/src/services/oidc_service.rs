@@ -0,0 +1,366 @@
+use crate::{
+    config::app_config::{AppConfig, OidcConfig},
+    constants::OIDC_LOGIN_TIMEOUT_MINUTES,
+    models::user_model::{OidcIdentity, User},
+    repositories::repository::RepositoryError,
+    services::user_service::ensure_not_suspended,
+    states::app_state::AppState,
+    utils::{
+        auth_util::{generate_pkce_verifier, generate_reset_token, hash_password, pkce_challenge},
+        validation_util::validate_username,
+    },
+};
+use actix_session::Session;
+use actix_web::{
+    error::{ErrorBadGateway, ErrorBadRequest, ErrorConflict, ErrorForbidden, ErrorInternalServerError, ErrorNotFound, ErrorUnauthorized},
+    Error,
+};
+use chrono::Utc;
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+const OIDC_LOGIN_KEY: &str = "oidc_login";
+
+/// The parts of the provider's discovery document the login flow needs.
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenEndpointResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+}
+
+/// Login state kept in the session between the redirect to the provider and the callback.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingOidcLogin {
+    state: String,
+    nonce: String,
+    code_verifier: String,
+    started_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+fn oidc_config() -> Result<(AppConfig, OidcConfig), Error> {
+    let config = AppConfig::new().map_err(|e| {
+        log::error!("Config error: {}", e);
+        ErrorInternalServerError("Config error")
+    })?;
+    let oidc = config
+        .oidc
+        .clone()
+        .ok_or_else(|| ErrorNotFound("Single sign-on is not configured"))?;
+    Ok((config, oidc))
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+fn provider_error(e: reqwest::Error) -> Error {
+    log::error!("OIDC provider error: {}", e);
+    ErrorBadGateway("Identity provider unavailable")
+}
+
+async fn fetch_provider_metadata(oidc: &OidcConfig) -> Result<ProviderMetadata, Error> {
+    let url = format!("{}/.well-known/openid-configuration", oidc.issuer);
+    let metadata: ProviderMetadata = http_client()
+        .get(&url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(provider_error)?
+        .json()
+        .await
+        .map_err(provider_error)?;
+    if !oidc.allowed_issuers.contains(&metadata.issuer) {
+        log::error!("OIDC issuer {} is not allowed", metadata.issuer);
+        return Err(ErrorForbidden("Identity provider is not allowed"));
+    }
+    Ok(metadata)
+}
+
+/// Start a login: remember the PKCE verifier, state and nonce and return the provider URL to redirect to.
+pub async fn begin_oidc_login(session: &Session) -> Result<String, Error> {
+    let (_, oidc) = oidc_config()?;
+    let metadata = fetch_provider_metadata(&oidc).await?;
+
+    let pending = PendingOidcLogin {
+        state: generate_reset_token(),
+        nonce: generate_reset_token(),
+        code_verifier: generate_pkce_verifier(),
+        started_at: Utc::now().timestamp(),
+    };
+    let mut url = reqwest::Url::parse(&metadata.authorization_endpoint)
+        .map_err(|_| ErrorBadGateway("Invalid authorization endpoint"))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &oidc.client_id)
+        .append_pair("redirect_uri", &oidc.redirect_url)
+        .append_pair("scope", "openid email profile")
+        .append_pair("state", &pending.state)
+        .append_pair("nonce", &pending.nonce)
+        .append_pair("code_challenge", &pkce_challenge(&pending.code_verifier))
+        .append_pair("code_challenge_method", "S256");
+
+    session.insert(OIDC_LOGIN_KEY, &pending)?;
+    Ok(url.to_string())
+}
+
+/// Verify the ID token's signature against the provider's published keys and check its claims.
+async fn verify_id_token(
+    oidc: &OidcConfig,
+    metadata: &ProviderMetadata,
+    id_token: &str,
+    expected_nonce: &str,
+) -> Result<IdTokenClaims, Error> {
+    let invalid_token = |e: jsonwebtoken::errors::Error| {
+        log::warn!("Rejected ID token: {}", e);
+        ErrorUnauthorized("Invalid ID token")
+    };
+    let header = decode_header(id_token).map_err(invalid_token)?;
+    // Only asymmetric algorithms; a token signed with a shared secret could be forged by anyone holding it.
+    if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+        return Err(ErrorUnauthorized("Unsupported ID token algorithm"));
+    }
+
+    let jwks: JwkSet = http_client()
+        .get(&metadata.jwks_uri)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(provider_error)?
+        .json()
+        .await
+        .map_err(provider_error)?;
+    let jwk = match header.kid.as_deref() {
+        Some(kid) => jwks.find(kid),
+        None => jwks.keys.first(),
+    }
+    .ok_or_else(|| ErrorUnauthorized("Unknown ID token signing key"))?;
+    let key = DecodingKey::from_jwk(jwk).map_err(invalid_token)?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[&oidc.client_id]);
+    validation.set_issuer(&oidc.allowed_issuers);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(invalid_token)?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(expected_nonce) {
+        return Err(ErrorUnauthorized("ID token nonce mismatch"));
+    }
+    Ok(claims)
+}
+
+/// Finish a login: check the callback, redeem the code and return the user to sign in.
+pub async fn complete_oidc_login(
+    state: &AppState,
+    session: &Session,
+    query: &OidcCallbackQuery,
+) -> Result<User, Error> {
+    let (_, oidc) = oidc_config()?;
+    let pending = session
+        .get::<PendingOidcLogin>(OIDC_LOGIN_KEY)
+        .map_err(|_| ErrorInternalServerError("Session error"))?
+        .ok_or_else(|| ErrorBadRequest("No single sign-on login in progress"))?;
+    // The login state is single use.
+    session.remove(OIDC_LOGIN_KEY);
+
+    if let Some(error) = &query.error {
+        log::warn!(
+            "OIDC login failed at the provider: {} {}",
+            error,
+            query.error_description.as_deref().unwrap_or_default()
+        );
+        return Err(ErrorUnauthorized("Single sign-on was not completed"));
+    }
+    if query.state.as_deref() != Some(pending.state.as_str()) {
+        return Err(ErrorBadRequest("Invalid login state"));
+    }
+    if Utc::now().timestamp() - pending.started_at > OIDC_LOGIN_TIMEOUT_MINUTES * 60 {
+        return Err(ErrorBadRequest("Single sign-on login expired, please try again"));
+    }
+    let code = query
+        .code
+        .as_deref()
+        .ok_or_else(|| ErrorBadRequest("Missing authorization code"))?;
+
+    let metadata = fetch_provider_metadata(&oidc).await?;
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", oidc.redirect_url.as_str()),
+        ("client_id", oidc.client_id.as_str()),
+        ("code_verifier", pending.code_verifier.as_str()),
+    ];
+    if let Some(client_secret) = &oidc.client_secret {
+        form.push(("client_secret", client_secret.as_str()));
+    }
+    let token_response: TokenEndpointResponse = http_client()
+        .post(&metadata.token_endpoint)
+        .form(&form)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(provider_error)?
+        .json()
+        .await
+        .map_err(provider_error)?;
+
+    let claims = verify_id_token(&oidc, &metadata, &token_response.id_token, &pending.nonce).await?;
+    let user = find_or_create_oidc_user(state, &oidc, &claims).await?;
+    ensure_not_suspended(&user)?;
+    Ok(user)
+}
+
+/// Whether the email's domain may sign in.
+pub fn is_allowed_email_domain(oidc: &OidcConfig, email: Option<&str>) -> bool {
+    if oidc.allowed_domains.is_empty() {
+        return true;
+    }
+    email
+        .and_then(|email| email.rsplit_once('@'))
+        .is_some_and(|(_, domain)| oidc.allowed_domains.contains(&domain.to_lowercase()))
+}
+
+/// A free username based on the provider's preferred username or the email's local part.
+async fn available_username(state: &AppState, claims: &IdTokenClaims) -> Result<String, Error> {
+    let candidate = claims
+        .preferred_username
+        .as_deref()
+        .or(claims.email.as_deref().and_then(|email| email.split('@').next()))
+        .unwrap_or("user");
+    let mut base: String = candidate
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    while base.len() < 3 {
+        base.push('_');
+    }
+
+    for suffix in 0..100 {
+        let username = match suffix {
+            0 => base.clone(),
+            _ => format!("{}{}", base, suffix),
+        };
+        let taken = state
+            .repositories
+            .users
+            .find_by_username(&username)
+            .await
+            .map_err(|e| {
+                log::error!("Database error: {}", e);
+                ErrorInternalServerError("Database error")
+            })?
+            .is_some();
+        if !taken && validate_username(&username) {
+            return Ok(username);
+        }
+    }
+    Err(ErrorInternalServerError("Could not find a free username"))
+}
+
+/// Find the user linked to the identity, link an existing account whose email is verified on both
+/// sides, or create a new account just in time.
+async fn find_or_create_oidc_user(state: &AppState, oidc: &OidcConfig, claims: &IdTokenClaims) -> Result<User, Error> {
+    if !is_allowed_email_domain(oidc, claims.email.as_deref()) {
+        return Err(ErrorForbidden("Your email domain is not allowed to sign in"));
+    }
+    let identity = OidcIdentity {
+        issuer: claims.iss.clone(),
+        subject: claims.sub.clone(),
+    };
+    let users = &state.repositories.users;
+
+    let linked_user = users
+        .find_by_oidc_identity(&identity)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            ErrorInternalServerError("Database error")
+        })?;
+    if let Some(user) = linked_user {
+        return Ok(user);
+    }
+
+    // Only link by email when the provider vouches for it, otherwise anyone could claim an account.
+    let email = claims.email.as_deref().filter(|_| claims.email_verified);
+    if let Some(email) = email {
+        let existing_user = users
+            .link_oidc_identity_by_email(email, &identity)
+            .await
+            .map_err(|e| {
+                log::error!("Database error: {}", e);
+                ErrorInternalServerError("Database error")
+            })?;
+        if let Some(user) = existing_user {
+            log::info!("Linked {} to an identity from {}", user.username, identity.issuer);
+            return Ok(user);
+        }
+        // An unverified account may have been registered by someone else to hijack the address later.
+        let unverified_user = users.find_by_email(email).await.map_err(|e| {
+            log::error!("Database error: {}", e);
+            ErrorInternalServerError("Database error")
+        })?;
+        if unverified_user.is_some() {
+            return Err(ErrorConflict(
+                "An account with this email exists but has not verified it; sign in with its password and verify the email first",
+            ));
+        }
+    }
+
+    let email = email.ok_or_else(|| ErrorForbidden("The identity provider did not supply a verified email"))?;
+    // SSO accounts get an unguessable password; a local one can be set through a password reset.
+    let password_hash = hash_password(&generate_reset_token())
+        .map_err(|_| ErrorInternalServerError("Failed to hash password"))?;
+    let mut user = User::new(&available_username(state, claims).await?, email, &password_hash);
+    user.email_verified = true;
+    user.oidc_identities.push(identity);
+    let user_id = users.insert(&user).await.map_err(|e| match e {
+        RepositoryError::Duplicate => ErrorConflict("An account with this username or email already exists"),
+        e => {
+            log::error!("Database error: {}", e);
+            ErrorInternalServerError("Failed to register user")
+        }
+    })?;
+    user.id = Some(user_id);
+    log::info!("Created {} from an identity from {}", user.username, user.oidc_identities[0].issuer);
+    Ok(user)
+}
+
+/// A link into the frontend to send the browser back to after the provider redirect.
+pub fn frontend_redirect(path: &str) -> Result<String, Error> {
+    let (config, _) = oidc_config()?;
+    Ok(format!(
+        "{}/{}",
+        config.frontend_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    ))
+}
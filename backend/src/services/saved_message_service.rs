@@ -0,0 +1,217 @@
+use crate::{
+    constants::{
+        SAVED_MESSAGE_MAX_TAGS, SAVED_MESSAGE_NOTE_MAX_LENGTH, SAVED_MESSAGE_PAGE_LIMIT, SAVED_MESSAGE_PREVIEW_LENGTH,
+        SAVED_MESSAGE_TAG_MAX_LENGTH,
+    },
+    models::{message_model::Message, saved_message_model::SavedMessage},
+    states::app_state::AppState,
+};
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize)]
+pub struct SaveMessageRequest {
+    pub message_id: ObjectId,
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnsaveMessageRequest {
+    pub message_id: ObjectId,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SavedMessageQuery {
+    pub tag: Option<String>,
+    pub page: Option<u64>,
+    pub per_page: Option<i64>,
+}
+
+/// The start of the saved message as it reads now.
+#[derive(Debug, Serialize)]
+pub struct MessagePreview {
+    pub sender_id: String,
+    pub sender_username: Option<String>,
+    pub seq: i64,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavedMessageSummary {
+    pub id: String,
+    pub message_id: String,
+    pub chat_id: String,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    pub saved_at: DateTime<Utc>,
+    pub preview: MessagePreview,
+}
+
+/// Trim the tags, drop blanks and repeats, and enforce the limits.
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>, Error> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|tag| tag.trim().to_lowercase()) {
+        if tag.is_empty() || normalized.contains(&tag) {
+            continue;
+        }
+        if tag.chars().count() > SAVED_MESSAGE_TAG_MAX_LENGTH {
+            return Err(ErrorBadRequest(format!(
+                "Tags must be at most {} characters",
+                SAVED_MESSAGE_TAG_MAX_LENGTH
+            )));
+        }
+        normalized.push(tag);
+    }
+    if normalized.len() > SAVED_MESSAGE_MAX_TAGS {
+        return Err(ErrorBadRequest(format!("At most {} tags are allowed", SAVED_MESSAGE_MAX_TAGS)));
+    }
+    Ok(normalized)
+}
+
+fn summary(saved: &SavedMessage, message: &Message, sender_username: Option<String>) -> Result<SavedMessageSummary, Error> {
+    let id = saved.id.ok_or_else(|| ErrorInternalServerError("Saved message ID is None"))?;
+    Ok(SavedMessageSummary {
+        id: id.to_hex(),
+        message_id: saved.message_id.to_hex(),
+        chat_id: saved.chat_id.to_hex(),
+        note: saved.note.clone(),
+        tags: saved.tags.clone(),
+        saved_at: saved.created_at,
+        preview: MessagePreview {
+            sender_id: message.sender_id.to_hex(),
+            sender_username,
+            seq: message.seq,
+            content: message.content.chars().take(SAVED_MESSAGE_PREVIEW_LENGTH).collect(),
+            created_at: message.created_at,
+        },
+    })
+}
+
+/// Save a message from one of the user's chats, or update the note and tags of an earlier save.
+pub async fn save_message(
+    state: &AppState,
+    user_id: ObjectId,
+    request: &SaveMessageRequest,
+) -> Result<SavedMessageSummary, Error> {
+    let note = request
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|note| !note.is_empty())
+        .map(str::to_owned);
+    if note.as_ref().is_some_and(|note| note.chars().count() > SAVED_MESSAGE_NOTE_MAX_LENGTH) {
+        return Err(ErrorBadRequest(format!(
+            "Notes must be at most {} characters",
+            SAVED_MESSAGE_NOTE_MAX_LENGTH
+        )));
+    }
+    let tags = normalize_tags(&request.tags)?;
+
+    // Messages in chats the user is not in, or deleted the chat after, are treated as missing
+    let message = state
+        .repositories
+        .messages
+        .find_by_id(request.message_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error retrieving message"))?
+        .ok_or_else(|| ErrorNotFound("Message not found"))?;
+    let chat = state
+        .repositories
+        .chats
+        .find_for_participant(message.chat_id, user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error retrieving chat"))?
+        .ok_or_else(|| ErrorNotFound("Message not found"))?;
+    if message.seq <= chat.hidden_seq(user_id).unwrap_or(0) {
+        return Err(ErrorNotFound("Message not found"));
+    }
+
+    let saved = state
+        .repositories
+        .saved_messages
+        .upsert(&SavedMessage::new(user_id, request.message_id, message.chat_id, note, tags))
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to save message"))?;
+    let sender_username = state
+        .repositories
+        .users
+        .find_by_id(message.sender_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get sender info"))?
+        .map(|user| user.username);
+    summary(&saved, &message, sender_username)
+}
+
+pub async fn unsave_message(state: &AppState, user_id: ObjectId, message_id: ObjectId) -> Result<(), Error> {
+    let deleted = state
+        .repositories
+        .saved_messages
+        .delete(user_id, message_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to remove saved message"))?;
+    if !deleted {
+        return Err(ErrorNotFound("Saved message not found"));
+    }
+    Ok(())
+}
+
+/// The user's saved messages, newest first, with a preview of each message as it reads now.
+pub async fn list_saved_messages(
+    state: &AppState,
+    user_id: ObjectId,
+    query: &SavedMessageQuery,
+) -> Result<Vec<SavedMessageSummary>, Error> {
+    let per_page = query
+        .per_page
+        .unwrap_or(SAVED_MESSAGE_PAGE_LIMIT)
+        .clamp(1, SAVED_MESSAGE_PAGE_LIMIT);
+    let tag = query.tag.as_deref().map(|tag| tag.trim().to_lowercase());
+    let saved_messages = state
+        .repositories
+        .saved_messages
+        .find_for_user(user_id, tag.as_deref(), query.page.unwrap_or(0) * per_page as u64, Some(per_page))
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get saved messages"))?;
+    if saved_messages.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // One lookup for the messages and one for their senders
+    let message_ids: Vec<ObjectId> = saved_messages.iter().map(|saved| saved.message_id).collect();
+    let messages: HashMap<ObjectId, Message> = state
+        .repositories
+        .messages
+        .find_by_ids(&message_ids)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get saved messages"))?
+        .into_iter()
+        .filter_map(|message| Some((message.id?, message)))
+        .collect();
+    let sender_ids: Vec<ObjectId> = messages.values().map(|message| message.sender_id).collect();
+    let usernames: HashMap<ObjectId, String> = state
+        .repositories
+        .users
+        .find_by_ids(&sender_ids)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get sender info"))?
+        .into_iter()
+        .filter_map(|user| Some((user.id?, user.username)))
+        .collect();
+
+    saved_messages
+        .iter()
+        .filter_map(|saved| {
+            let message = messages.get(&saved.message_id)?;
+            Some(summary(saved, message, usernames.get(&message.sender_id).cloned()))
+        })
+        .collect()
+}
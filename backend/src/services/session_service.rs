@@ -0,0 +1,84 @@
+use crate::{
+    session::repository_session_store::SESSION_ID,
+    states::app_state::AppState,
+    utils::request_util::describe_device,
+    websocket::websocket_session::StopAuthSession,
+};
+use actix_session::Session;
+use actix_web::{
+    error::{ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeSessionRequest {
+    pub session_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub device: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    /// Whether this is the session making the request.
+    pub current: bool,
+}
+
+/// The public id of the session making the request.
+pub fn current_session_id(session: &Session) -> Option<String> {
+    session.get::<String>(SESSION_ID).unwrap_or(None)
+}
+
+/// List a user's active sessions, most recently used first.
+pub async fn list_sessions(
+    state: &AppState,
+    user_id: ObjectId,
+    current_session_id: Option<&str>,
+) -> Result<Vec<SessionSummary>, Error> {
+    let sessions = state
+        .repositories
+        .sessions
+        .find_for_user(user_id, Utc::now())
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get sessions"))?;
+
+    Ok(sessions
+        .into_iter()
+        .filter_map(|session| {
+            let session_id = session.session_id?;
+            Some(SessionSummary {
+                current: current_session_id == Some(session_id.as_str()),
+                device: describe_device(session.user_agent.as_deref().unwrap_or_default()),
+                session_id,
+                user_agent: session.user_agent,
+                ip: session.ip,
+                created_at: session.created_at,
+                last_seen_at: session.last_seen_at,
+            })
+        })
+        .collect())
+}
+
+/// Sign out one of the user's sessions and close the WebSocket it opened.
+pub async fn revoke_session(state: &AppState, user_id: ObjectId, session_id: &str) -> Result<(), Error> {
+    let deleted = state
+        .repositories
+        .sessions
+        .delete_for_user(user_id, session_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to revoke session"))?;
+    if !deleted {
+        return Err(ErrorNotFound("Session not found"));
+    }
+
+    if let Some((addr, _)) = state.ws_sessions.read().await.get(&user_id) {
+        addr.do_send(StopAuthSession(session_id.to_owned()));
+    }
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use crate::{
+    config::app_config::AppConfig,
+    constants::{LOGIN_MAX_BACKOFF_SECONDS, LOGIN_THROTTLE_RETENTION_HOURS},
+    models::login_throttle_model::LoginThrottle,
+    states::app_state::AppState,
+};
+use actix_web::{
+    error::{ErrorInternalServerError, InternalError},
+    Error, HttpResponse,
+};
+use chrono::{DateTime, Duration, Utc};
+
+/// What is being throttled. Each scope keeps its own counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleScope {
+    /// Failed logins against an account from anywhere. Only ever backs off, so nobody can lock the owner out.
+    LoginAccount,
+    /// Failed logins against an existing account from one client IP. This is what gets locked.
+    LoginAccountIp,
+    LoginIp,
+    PasswordResetIp,
+    ResetTokenIp,
+    /// Attempts to turn off two-factor authentication, per account.
+    DisableTwoFactor,
+}
+
+impl ThrottleScope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::LoginAccount => "login_account",
+            Self::LoginAccountIp => "login_account_ip",
+            Self::LoginIp => "login_ip",
+            Self::PasswordResetIp => "password_reset_ip",
+            Self::ResetTokenIp => "reset_token_ip",
+            Self::DisableTwoFactor => "disable_two_factor",
+        }
+    }
+
+    pub fn key(&self, subject: &str) -> String {
+        format!("{}:{}", self.as_str(), subject.to_lowercase())
+    }
+}
+
+/// The keys a login for `username` from `ip` is checked against.
+pub fn login_keys(username: &str, ip: &str) -> [String; 3] {
+    [
+        ThrottleScope::LoginAccount.key(username),
+        ThrottleScope::LoginAccountIp.key(&format!("{}|{}", username, ip)),
+        ThrottleScope::LoginIp.key(ip),
+    ]
+}
+
+/// Outcome of recording a failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureOutcome {
+    Counted,
+    /// The attempt pushed the key over its limit and it is now locked.
+    LockedOut,
+}
+
+fn too_many_requests(retry_after: Duration) -> Error {
+    let seconds = retry_after.num_seconds().max(1);
+    let message = format!("Too many attempts, try again in {} seconds", seconds);
+    let response = HttpResponse::TooManyRequests()
+        .insert_header(("Retry-After", seconds.to_string()))
+        .json(&message);
+    InternalError::from_response(message, response).into()
+}
+
+/// How long to wait after `failures` consecutive failures: 1s, 2s, 4s… capped.
+pub fn backoff(failures: i32) -> Duration {
+    if failures <= 0 {
+        return Duration::zero();
+    }
+    let seconds = 1i64 << (failures - 1).min(16);
+    Duration::seconds(seconds.min(LOGIN_MAX_BACKOFF_SECONDS))
+}
+
+/// When the key may next be tried, if it is currently held back.
+pub fn blocked_until(throttle: &LoginThrottle, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Some(locked_until) = throttle.locked_until {
+        if locked_until > now {
+            return Some(locked_until);
+        }
+    }
+    let next_allowed = throttle.last_failure_at + backoff(throttle.failures);
+    (next_allowed > now).then_some(next_allowed)
+}
+
+/// Reject the request with `429 Too Many Requests` if any of the keys is locked or backing off.
+pub async fn check_throttle(state: &AppState, keys: &[String]) -> Result<(), Error> {
+    let now = Utc::now();
+    for key in keys {
+        let throttle = state
+            .repositories
+            .throttles
+            .find(key, now)
+            .await
+            .map_err(|_| ErrorInternalServerError("Database error"))?;
+        if let Some(until) = throttle.as_ref().and_then(|throttle| blocked_until(throttle, now)) {
+            return Err(too_many_requests(until - now));
+        }
+    }
+    Ok(())
+}
+
+/// Count a failed attempt against `key`, locking it once it reaches `max_failures`.
+pub async fn record_failure(
+    state: &AppState,
+    key: &str,
+    max_failures: i32,
+    lockout: Duration,
+) -> Result<FailureOutcome, Error> {
+    let now = Utc::now();
+    let retention = Duration::hours(LOGIN_THROTTLE_RETENTION_HOURS);
+    let throttles = &state.repositories.throttles;
+
+    let throttle = throttles
+        .increment(key, now, now + retention)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error"))?;
+
+    if throttle.failures < max_failures {
+        return Ok(FailureOutcome::Counted);
+    }
+
+    // Lock the key and start counting afresh once the lockout expires.
+    throttles
+        .lock(key, now + lockout, now + lockout + retention)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error"))?;
+    Ok(FailureOutcome::LockedOut)
+}
+
+/// Count a failed login for `username` from `ip`. Every failure backs the account off and counts
+/// against the IP, but only an existing account is locked, and only for the IP the failures came from.
+pub async fn record_login_failure(
+    state: &AppState,
+    config: &AppConfig,
+    username: &str,
+    ip: &str,
+    account_exists: bool,
+) -> Result<FailureOutcome, Error> {
+    let [account_key, account_ip_key, ip_key] = login_keys(username, ip);
+    let lockout = Duration::minutes(config.login_lockout_minutes);
+    record_failure(state, &ip_key, config.login_max_ip_failures, lockout).await?;
+    // The account-wide key never reaches its limit, so it only backs off.
+    record_failure(state, &account_key, i32::MAX, lockout).await?;
+    if !account_exists {
+        return Ok(FailureOutcome::Counted);
+    }
+    record_failure(state, &account_ip_key, config.login_max_account_failures, lockout).await
+}
+
+/// Forget the failures recorded against `username` after it signed in from `ip`.
+pub async fn clear_login_failures(state: &AppState, username: &str, ip: &str) -> Result<(), Error> {
+    let [account_key, account_ip_key, _] = login_keys(username, ip);
+    clear_throttle(state, &account_key).await?;
+    clear_throttle(state, &account_ip_key).await
+}
+
+/// Forget all failures recorded against `key`.
+pub async fn clear_throttle(state: &AppState, key: &str) -> Result<(), Error> {
+    state
+        .repositories
+        .throttles
+        .delete(key)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error"))?;
+    Ok(())
+}
+
+/// Lift a lockout on an account so its owner can sign in again right away.
+pub async fn unlock_account(state: &AppState, username: &str) -> Result<(), Error> {
+    clear_throttle(state, &ThrottleScope::LoginAccount.key(username)).await?;
+    state
+        .repositories
+        .throttles
+        .delete_with_prefix(&ThrottleScope::LoginAccountIp.key(&format!("{}|", username)))
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error"))
+}
@@ -0,0 +1,287 @@
+use crate::{
+    constants::{ACCESS_TOKEN_TTL_MINUTES, API_TOKEN_LENGTH, REFRESH_TOKEN_TTL_DAYS},
+    models::api_token_model::{ApiToken, ApiTokenKind},
+    services::{
+        auth_service::{authenticate_user, LoginRequest},
+        two_factor_service::verify_login_code,
+    },
+    states::app_state::AppState,
+    types::auth_types::{AuthenticatedUser, TokenScope},
+    utils::{auth_util::hash_token, request_util::RequestContext},
+};
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound, ErrorUnauthorized},
+    web, Error,
+};
+use chrono::{DateTime, Duration, Utc};
+use mongodb::bson::oid::ObjectId;
+use rand::{distributions::Alphanumeric, rngs::OsRng, Rng};
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /auth/token`, modelled on the OAuth 2 token endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "grant_type", rename_all = "snake_case")]
+pub enum TokenRequest {
+    Password {
+        username: String,
+        password: String,
+        /// Required when the account has two-factor authentication enabled.
+        code: Option<String>,
+        /// Defaults to every scope.
+        #[serde(default)]
+        scopes: Vec<TokenScope>,
+    },
+    RefreshToken {
+        refresh_token: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: &'static str,
+    /// Lifetime of the access token in seconds.
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub scopes: Vec<TokenScope>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePersonalTokenRequest {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    /// Leave out for a token that does not expire.
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeTokenRequest {
+    pub token_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTokenSummary {
+    pub token_id: String,
+    pub name: Option<String>,
+    pub scopes: Vec<TokenScope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedTokenResponse {
+    /// Shown once; only its hash is stored.
+    pub token: String,
+    #[serde(flatten)]
+    pub summary: ApiTokenSummary,
+}
+
+impl TryFrom<ApiToken> for ApiTokenSummary {
+    type Error = Error;
+
+    fn try_from(token: ApiToken) -> Result<Self, Self::Error> {
+        Ok(Self {
+            token_id: token
+                .id
+                .ok_or_else(|| ErrorInternalServerError("Token ID is None"))?
+                .to_hex(),
+            name: token.name,
+            scopes: token.scopes,
+            created_at: token.created_at,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+        })
+    }
+}
+
+fn generate_api_token(kind: ApiTokenKind) -> String {
+    let random: String = OsRng
+        .sample_iter(&Alphanumeric)
+        .take(API_TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+    format!("{}{}", kind.prefix(), random)
+}
+
+/// Store a new token and return it together with the raw token string.
+async fn insert_token(state: &AppState, mut token: ApiToken, raw_token: String) -> Result<(ApiToken, String), Error> {
+    let token_id = state.repositories.api_tokens.insert(&token).await.map_err(|e| {
+        log::error!("Database error: {}", e);
+        ErrorInternalServerError("Failed to issue token")
+    })?;
+    token.id = Some(token_id);
+    Ok((token, raw_token))
+}
+
+/// Issue a short-lived access token and the refresh token that renews it.
+async fn issue_token_pair(state: &AppState, user_id: ObjectId, scopes: Vec<TokenScope>) -> Result<TokenResponse, Error> {
+    let now = Utc::now();
+    let raw_access_token = generate_api_token(ApiTokenKind::Access);
+    let (access_token, raw_access_token) = insert_token(
+        state,
+        ApiToken::new(
+            user_id,
+            ApiTokenKind::Access,
+            &hash_token(&raw_access_token),
+            scopes.clone(),
+            Some(now + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)),
+        ),
+        raw_access_token,
+    )
+    .await?;
+
+    let raw_refresh_token = generate_api_token(ApiTokenKind::Refresh);
+    let mut refresh_token = ApiToken::new(
+        user_id,
+        ApiTokenKind::Refresh,
+        &hash_token(&raw_refresh_token),
+        scopes.clone(),
+        Some(now + Duration::days(REFRESH_TOKEN_TTL_DAYS)),
+    );
+    refresh_token.access_token_id = access_token.id;
+    let (_, raw_refresh_token) = insert_token(state, refresh_token, raw_refresh_token).await?;
+
+    Ok(TokenResponse {
+        access_token: raw_access_token,
+        token_type: "Bearer",
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+        refresh_token: raw_refresh_token,
+        scopes,
+    })
+}
+
+/// Exchange credentials or a refresh token for a new access/refresh pair.
+pub async fn exchange_token(
+    state: &web::Data<AppState>,
+    req: &TokenRequest,
+    context: &RequestContext,
+) -> Result<TokenResponse, Error> {
+    match req {
+        TokenRequest::Password {
+            username,
+            password,
+            code,
+            scopes,
+        } => {
+            let credentials = LoginRequest {
+                username: username.clone(),
+                password: password.clone(),
+            };
+            let user = authenticate_user(&credentials, state, context).await?;
+            if user.totp_enabled {
+                let code = code
+                    .as_deref()
+                    .ok_or_else(|| ErrorUnauthorized("Two-factor code required"))?;
+                verify_login_code(state, &user, code, &context.ip).await?;
+            }
+            let user_id = user.id.ok_or_else(|| ErrorInternalServerError("User ID is None"))?;
+            let scopes = if scopes.is_empty() {
+                TokenScope::ALL.to_vec()
+            } else {
+                scopes.clone()
+            };
+            issue_token_pair(state, user_id, scopes).await
+        }
+        TokenRequest::RefreshToken { refresh_token } => {
+            let tokens = &state.repositories.api_tokens;
+            // Deleting the refresh token as it is read makes it single use.
+            let token = tokens
+                .take_refresh_token(&hash_token(refresh_token))
+                .await
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    ErrorInternalServerError("Database error")
+                })?
+                .filter(|token| !token.is_expired_at(Utc::now()))
+                .ok_or_else(|| ErrorUnauthorized("Invalid refresh token"))?;
+
+            if let Some(access_token_id) = token.access_token_id {
+                tokens.delete(access_token_id).await.map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    ErrorInternalServerError("Database error")
+                })?;
+            }
+            issue_token_pair(state, token.user_id, token.scopes).await
+        }
+    }
+}
+
+/// Resolve a bearer token to the user it acts for. Refresh tokens are not accepted.
+pub async fn authenticate_bearer_token(state: &AppState, raw_token: &str) -> Result<AuthenticatedUser, Error> {
+    let now = Utc::now();
+    let token = state
+        .repositories
+        .api_tokens
+        .record_use(&hash_token(raw_token), now)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error"))?
+        .filter(|token| !token.is_expired_at(now))
+        .ok_or_else(|| ErrorUnauthorized("Invalid or expired token"))?;
+
+    Ok(AuthenticatedUser {
+        user_id: token.user_id,
+        scopes: Some(token.scopes),
+    })
+}
+
+/// Create a personal access token for scripts and integrations.
+pub async fn create_personal_access_token(
+    state: &AppState,
+    user_id: ObjectId,
+    req: &CreatePersonalTokenRequest,
+) -> Result<CreatedTokenResponse, Error> {
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(ErrorBadRequest("Token name is required"));
+    }
+    if req.scopes.is_empty() {
+        return Err(ErrorBadRequest("At least one scope is required"));
+    }
+    if req.expires_in_days.is_some_and(|days| days <= 0) {
+        return Err(ErrorBadRequest("Token expiry must be in the future"));
+    }
+
+    let raw_token = generate_api_token(ApiTokenKind::Personal);
+    let mut token = ApiToken::new(
+        user_id,
+        ApiTokenKind::Personal,
+        &hash_token(&raw_token),
+        req.scopes.clone(),
+        req.expires_in_days.map(|days| Utc::now() + Duration::days(days)),
+    );
+    token.name = Some(name.to_owned());
+    let (token, raw_token) = insert_token(state, token, raw_token).await?;
+
+    Ok(CreatedTokenResponse {
+        token: raw_token,
+        summary: token.try_into()?,
+    })
+}
+
+/// List a user's personal access tokens, newest first.
+pub async fn list_personal_access_tokens(state: &AppState, user_id: ObjectId) -> Result<Vec<ApiTokenSummary>, Error> {
+    let tokens = state
+        .repositories
+        .api_tokens
+        .find_personal(user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to get tokens"))?;
+
+    tokens.into_iter().map(ApiTokenSummary::try_from).collect()
+}
+
+/// Revoke one of the user's personal access tokens.
+pub async fn revoke_personal_access_token(state: &AppState, user_id: ObjectId, token_id: &str) -> Result<(), Error> {
+    let token_id = ObjectId::parse_str(token_id).map_err(|_| ErrorBadRequest("Invalid token ID"))?;
+    let deleted = state
+        .repositories
+        .api_tokens
+        .delete_personal(user_id, token_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to revoke token"))?;
+    if !deleted {
+        return Err(ErrorNotFound("Token not found"));
+    }
+    Ok(())
+}
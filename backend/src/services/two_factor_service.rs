@@ -0,0 +1,267 @@
+use crate::{
+    config::app_config::AppConfig,
+    constants::{
+        RECOVERY_CODE_COUNT, TOTP_ISSUER, TWO_FACTOR_DISABLE_LOCKOUT_MINUTES, TWO_FACTOR_DISABLE_MAX_FAILURES,
+        TWO_FACTOR_LOGIN_TIMEOUT_MINUTES,
+    },
+    models::user_model::User,
+    services::{
+        throttle_service::{
+            check_throttle, clear_login_failures, clear_throttle, login_keys, record_failure, record_login_failure,
+            ThrottleScope,
+        },
+        user_service::get_user_by_id,
+    },
+    states::app_state::AppState,
+    utils::{
+        auth_util::{hash_password, verify_password},
+        totp_util::{
+            generate_recovery_codes, generate_totp_secret, is_recovery_code, normalize_recovery_code, otpauth_uri,
+            verify_totp,
+        },
+    },
+};
+use actix_session::Session;
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError, ErrorUnauthorized},
+    web, Error,
+};
+use chrono::{Duration, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+const PENDING_USER_KEY: &str = "pending_two_factor_user_id";
+const PENDING_EXPIRES_KEY: &str = "pending_two_factor_expires_at";
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    /// A code from the authenticator app, or an unused recovery code.
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    /// Shown once; only their hashes are stored.
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallengeResponse {
+    pub two_factor_required: bool,
+}
+
+/// Start enrollment by generating a new secret. 2FA stays off until a code from it is confirmed.
+pub async fn begin_two_factor_enrollment(
+    state: &AppState,
+    user_id: ObjectId,
+) -> Result<TwoFactorEnrollmentResponse, Error> {
+    let user = get_user_by_id(state, user_id).await?;
+    if user.totp_enabled {
+        return Err(ErrorBadRequest("Two-factor authentication is already enabled"));
+    }
+
+    let secret = generate_totp_secret();
+    state
+        .repositories
+        .users
+        .set_totp_secret(user_id, &secret)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            ErrorInternalServerError("Failed to start two-factor enrollment")
+        })?;
+
+    Ok(TwoFactorEnrollmentResponse {
+        otpauth_uri: otpauth_uri(TOTP_ISSUER, &user.username, &secret),
+        secret,
+    })
+}
+
+/// Enable 2FA once the first code checks out, returning freshly generated recovery codes.
+pub async fn confirm_two_factor_enrollment(
+    state: &AppState,
+    user_id: ObjectId,
+    req: &TwoFactorCodeRequest,
+) -> Result<RecoveryCodesResponse, Error> {
+    let user = get_user_by_id(state, user_id).await?;
+    if user.totp_enabled {
+        return Err(ErrorBadRequest("Two-factor authentication is already enabled"));
+    }
+    let secret = user
+        .totp_secret
+        .as_deref()
+        .ok_or_else(|| ErrorBadRequest("Two-factor enrollment has not been started"))?;
+    let step = verify_totp(secret, &req.code, Utc::now().timestamp(), None)
+        .ok_or_else(|| ErrorBadRequest("Invalid two-factor code"))?;
+
+    let recovery_codes = generate_recovery_codes(RECOVERY_CODE_COUNT);
+    // Argon2 is slow on purpose, so it runs off the async executor.
+    let codes = recovery_codes.clone();
+    let recovery_code_hashes = web::block(move || {
+        codes
+            .iter()
+            .map(|code| hash_password(&normalize_recovery_code(code)))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|_| ErrorInternalServerError("Failed to hash recovery codes"))?
+    .map_err(|_| ErrorInternalServerError("Failed to hash recovery codes"))?;
+
+    state
+        .repositories
+        .users
+        .enable_totp(user_id, step, &recovery_code_hashes)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            ErrorInternalServerError("Failed to enable two-factor authentication")
+        })?;
+
+    Ok(RecoveryCodesResponse { recovery_codes })
+}
+
+/// Check a TOTP or recovery code for a user with 2FA enabled, consuming it on success.
+pub async fn verify_two_factor_code(state: &AppState, user: &User, code: &str) -> Result<bool, Error> {
+    let user_id = user.id.ok_or_else(|| ErrorInternalServerError("User ID is None"))?;
+    let secret = match (user.totp_enabled, user.totp_secret.as_deref()) {
+        (true, Some(secret)) => secret,
+        _ => return Ok(false),
+    };
+    let users = &state.repositories.users;
+
+    if let Some(step) = verify_totp(secret, code, Utc::now().timestamp(), user.totp_last_used_step) {
+        // Concurrent requests with the same code race for a single use.
+        return users.consume_totp_step(user_id, step).await.map_err(|e| {
+            log::error!("Database error: {}", e);
+            ErrorInternalServerError("Database error")
+        });
+    }
+
+    if !is_recovery_code(code) {
+        return Ok(false);
+    }
+    let recovery_code = normalize_recovery_code(code);
+    let recovery_code_hashes = user.recovery_code_hashes.clone();
+    let matched_hash = web::block(move || {
+        recovery_code_hashes
+            .into_iter()
+            .find(|hash| verify_password(&recovery_code, hash).unwrap_or(false))
+    })
+    .await
+    .map_err(|_| ErrorInternalServerError("Failed to verify recovery code"))?;
+    let Some(matched_hash) = matched_hash else {
+        return Ok(false);
+    };
+    users.consume_recovery_code(user_id, &matched_hash).await.map_err(|e| {
+        log::error!("Database error: {}", e);
+        ErrorInternalServerError("Database error")
+    })
+}
+
+/// Turn 2FA off. The user has to re-enter their password and a current code.
+pub async fn disable_two_factor(
+    state: &AppState,
+    user_id: ObjectId,
+    req: &DisableTwoFactorRequest,
+) -> Result<String, Error> {
+    let user = get_user_by_id(state, user_id).await?;
+    if !user.totp_enabled {
+        return Err(ErrorBadRequest("Two-factor authentication is not enabled"));
+    }
+    // Both factors are guessable by whoever holds the session, so attempts are throttled per account.
+    let throttle_key = ThrottleScope::DisableTwoFactor.key(&user_id.to_hex());
+    check_throttle(state, std::slice::from_ref(&throttle_key)).await?;
+
+    let password = req.password.clone();
+    let password_hash = user.password_hash.clone();
+    let is_password_valid = web::block(move || verify_password(&password, &password_hash))
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to verify password"))?
+        .map_err(|_| ErrorInternalServerError("Failed to verify password"))?;
+    if !is_password_valid || !verify_two_factor_code(state, &user, &req.code).await? {
+        let lockout = Duration::minutes(TWO_FACTOR_DISABLE_LOCKOUT_MINUTES);
+        record_failure(state, &throttle_key, TWO_FACTOR_DISABLE_MAX_FAILURES, lockout).await?;
+        return Err(ErrorUnauthorized("Invalid credentials"));
+    }
+    clear_throttle(state, &throttle_key).await?;
+
+    state
+        .repositories
+        .users
+        .disable_totp(user_id)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            ErrorInternalServerError("Failed to disable two-factor authentication")
+        })?;
+
+    Ok("Two-factor authentication disabled".to_string())
+}
+
+/// Check the second factor of a login. Codes are guessable, so they share the login throttle with passwords.
+pub async fn verify_login_code(state: &AppState, user: &User, code: &str, client_ip: &str) -> Result<(), Error> {
+    let config = AppConfig::new().map_err(|e| {
+        log::error!("Config error: {}", e);
+        ErrorInternalServerError("Config error")
+    })?;
+    check_throttle(state, &login_keys(&user.username, client_ip)).await?;
+
+    if !verify_two_factor_code(state, user, code).await? {
+        record_login_failure(state, &config, &user.username, client_ip, true).await?;
+        return Err(ErrorUnauthorized("Invalid two-factor code"));
+    }
+    clear_login_failures(state, &user.username, client_ip).await
+}
+
+/// Remember a user who passed the password step. The session is not signed in until the code is verified.
+pub fn start_two_factor_login(session: &Session, user_id: ObjectId) -> Result<(), Error> {
+    let expires_at = Utc::now() + Duration::minutes(TWO_FACTOR_LOGIN_TIMEOUT_MINUTES);
+    session.remove("user_id");
+    session.insert(PENDING_USER_KEY, user_id.to_hex())?;
+    session.insert(PENDING_EXPIRES_KEY, expires_at.timestamp())?;
+    session.renew();
+    Ok(())
+}
+
+/// Finish a login started by [`start_two_factor_login`] and return the user to sign in.
+pub async fn complete_two_factor_login(
+    state: &AppState,
+    session: &Session,
+    req: &TwoFactorCodeRequest,
+    client_ip: &str,
+) -> Result<User, Error> {
+    let pending_user_id = session
+        .get::<String>(PENDING_USER_KEY)
+        .map_err(|_| ErrorInternalServerError("Session error"))?
+        .ok_or_else(|| ErrorUnauthorized("No login awaiting a two-factor code"))?;
+    let expires_at = session
+        .get::<i64>(PENDING_EXPIRES_KEY)
+        .map_err(|_| ErrorInternalServerError("Session error"))?
+        .unwrap_or(0);
+    if expires_at < Utc::now().timestamp() {
+        session.remove(PENDING_USER_KEY);
+        session.remove(PENDING_EXPIRES_KEY);
+        return Err(ErrorUnauthorized("Two-factor login expired, please sign in again"));
+    }
+    let user_id = ObjectId::parse_str(&pending_user_id).map_err(|_| ErrorBadRequest("Invalid user ID in session"))?;
+    let user = get_user_by_id(state, user_id).await?;
+
+    verify_login_code(state, &user, &req.code, client_ip).await?;
+
+    session.remove(PENDING_USER_KEY);
+    session.remove(PENDING_EXPIRES_KEY);
+
+    Ok(user)
+}
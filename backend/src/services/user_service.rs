@@ -1,16 +1,25 @@
 use crate::models::user_model::User;
+use crate::repositories::user_repository::UserFilter;
+use crate::session::repository_session_store::{SESSION_ID, SESSION_IP, SESSION_USER_AGENT, SESSION_USER_ID};
 use crate::states::app_state::AppState;
+use crate::types::auth_types::AuthenticatedUser;
+use crate::utils::request_util::{client_ip, user_agent};
+use crate::websocket::websocket_session::StopSession;
+use actix_session::SessionExt;
 use actix_web::{
+    HttpMessage, HttpRequest,
     error::{
         ErrorInternalServerError,
         ErrorNotFound,
         ErrorBadRequest,
+        ErrorForbidden,
+        ErrorUnauthorized,
     },
     Error
 };
-use futures::TryStreamExt;
 use mongodb::bson::{doc, oid::ObjectId};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Debug, Deserialize)]
 pub struct BatchCheckOnlineRequest {
@@ -46,30 +55,108 @@ pub fn extract_user_id_from_session(session: &actix_session::Session) -> Result<
     ObjectId::parse_str(&user_id_str).map_err(|_| ErrorBadRequest("Invalid user ID in session"))
 }
 
+/// The user a request is made by, as established by the auth middleware from a bearer token
+/// or the session cookie.
+pub fn extract_authenticated_user_id(req: &HttpRequest) -> Result<ObjectId, Error> {
+    if let Some(user) = req.extensions().get::<AuthenticatedUser>() {
+        return Ok(user.user_id);
+    }
+    extract_user_id_from_session(&req.get_session())
+}
+
+/// Sign the session in as `user`, tagging it with the user's current session version
+/// and the device it was opened from.
+pub fn start_user_session(
+    session: &actix_session::Session,
+    user: &User,
+    req: &HttpRequest,
+) -> Result<ObjectId, Error> {
+    let user_id = user.id.ok_or_else(|| ErrorInternalServerError("User ID is None"))?;
+    session.insert(SESSION_USER_ID, user_id.to_hex())?;
+    session.insert("session_version", user.session_version)?;
+    session.insert(SESSION_ID, Uuid::new_v4().to_string())?;
+    session.insert(SESSION_USER_AGENT, user_agent(req))?;
+    session.insert(SESSION_IP, client_ip(req))?;
+    session.renew();
+    Ok(user_id)
+}
+
+/// Extract the user ID from the session and check the session has not been revoked since it was issued.
+pub async fn authenticate_session(state: &AppState, session: &actix_session::Session) -> Result<ObjectId, Error> {
+    let user_id = extract_user_id_from_session(session)?;
+    // Sessions issued before versioning count as version 0.
+    let session_version = session
+        .get::<i32>("session_version")
+        .map_err(|_| ErrorInternalServerError("Session error"))?
+        .unwrap_or(0);
+    let user = state
+        .repositories
+        .users
+        .find_by_id(user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error: Failed to get user by id"))?;
+    match user {
+        Some(user) if user.session_version == session_version => Ok(user_id),
+        _ => {
+            session.purge();
+            Err(ErrorUnauthorized("Session expired"))
+        }
+    }
+}
+
+/// Reject sign-ins to suspended accounts.
+pub fn ensure_not_suspended(user: &User) -> Result<(), Error> {
+    if user.suspended {
+        return Err(ErrorForbidden("This account has been suspended"));
+    }
+    Ok(())
+}
+
+/// Sign a user out everywhere: revoke every session and API token and close their WebSocket connection.
+pub async fn revoke_user_sessions(state: &AppState, user_id: ObjectId) -> Result<(), Error> {
+    state
+        .repositories
+        .users
+        .increment_session_version(user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error: Failed to revoke sessions"))?;
+    state
+        .repositories
+        .sessions
+        .delete_all_for_user(user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error: Failed to revoke sessions"))?;
+    state
+        .repositories
+        .api_tokens
+        .delete_for_user(user_id)
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error: Failed to revoke tokens"))?;
+    disconnect_user(state, user_id).await;
+    Ok(())
+}
+
+/// Close the user's live WebSocket connection, if any.
+pub async fn disconnect_user(state: &AppState, user_id: ObjectId) {
+    if let Some((addr, _)) = state.ws_sessions.read().await.get(&user_id) {
+        addr.do_send(StopSession);
+    }
+}
+
 /// Search users by username or email (matching a search slice)
 pub async fn search_users(state: &AppState, query: &str) -> Result<Vec<serde_json::Value>, Error> {
-    let users_collection = state.db.collection::<User>(User::collection_name());
-
-    // Match against both username and email using regex (case-insensitive)
-    let filter = doc! {
-        "$or": [
-            { "username": { "$regex": query, "$options": "i" } },
-            { "email": { "$regex": query, "$options": "i" } },
-        ]
+    // Match against both username and email (case-insensitive)
+    let filter = UserFilter {
+        query: Some(query.to_owned()),
+        ..UserFilter::default()
     };
-
-    let cursor = users_collection
-        .find(filter, None)
+    let users = state
+        .repositories
+        .users
+        .find_users(&filter, 0, None)
         .await
         .map_err(|_| ErrorInternalServerError("Database error: Failed to search users"))?;
 
-    let users: Vec<User> = cursor
-        .try_collect()
-        .await
-        .map_err(|_| {
-            ErrorInternalServerError("Database error: Failed to collect users")
-        })?;
-
     // Convert to simplified format with just id and username
     let results = users.into_iter()
         .map(|user| {
@@ -85,10 +172,10 @@ pub async fn search_users(state: &AppState, query: &str) -> Result<Vec<serde_jso
 
 /// Get a user by their ObjectId.
 pub async fn get_user_by_id(state: &AppState, user_id: ObjectId) -> Result<User, Error> {
-    let users_collection = state.db.collection::<User>(User::collection_name());
-    let filter = doc! { "_id": user_id };
-    let user = users_collection
-        .find_one(filter, None)
+    let user = state
+        .repositories
+        .users
+        .find_by_id(user_id)
         .await
         .map_err(|_| ErrorInternalServerError("Database error: Failed to get user by id"))?;
     user.ok_or_else(|| ErrorNotFound("User not found"))
@@ -119,3 +206,14 @@ pub async fn is_user_online(state: &AppState, user_id: ObjectId) -> bool {
         false
     }
 }
+
+/// Record the moment a user was last connected.
+pub async fn touch_last_seen(state: &AppState, user_id: ObjectId) -> Result<(), Error> {
+    state
+        .repositories
+        .users
+        .touch_last_seen(user_id, chrono::Utc::now())
+        .await
+        .map_err(|_| ErrorInternalServerError("Database error: Failed to update last seen"))?;
+    Ok(())
+}
@@ -1,12 +1,12 @@
 use crate::{
-    config::app_config::AppConfig,
-    models::{chat_model::Chat, notification_model::Notification, user_model::User},
-    services::notification_service::NotificationSummary,
+    models::notification_model::Notification,
+    services::notification_service::create_notification,
     states::app_state::AppState,
+    types::notification_types::NotificationType,
     websocket::websocket_session::TextMessage,
 };
 use actix_web::{error::ErrorInternalServerError, Error};
-use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::bson::oid::ObjectId;
 use serde::Deserialize;
 use serde_json::json;
 
@@ -31,9 +31,6 @@ pub async fn initiate_video_call_logic(
     recipient_id: ObjectId,
     chat_id: ObjectId,
 ) -> Result<(), Error> {
-    // Load configuration
-    let config = AppConfig::new().map_err(|_| ErrorInternalServerError("Config error"))?;
-
     // Check if the recipient is online
     let ws_sessions = state.ws_sessions.read().await;
     if !ws_sessions.contains_key(&recipient_id) {
@@ -42,9 +39,10 @@ pub async fn initiate_video_call_logic(
     drop(ws_sessions);
 
     // Validate chat participants
-    let chats_collection = state.db.collection::<Chat>(Chat::collection_name());
-    let chat = chats_collection
-        .find_one(doc! { "_id": &chat_id }, None)
+    let chat = state
+        .repositories
+        .chats
+        .find_by_id(chat_id)
         .await
         .map_err(|_| ErrorInternalServerError("Database error"))?
         .ok_or_else(|| actix_web::error::ErrorNotFound("Chat not found"))?;
@@ -53,50 +51,15 @@ pub async fn initiate_video_call_logic(
         return Err(actix_web::error::ErrorForbidden("Users are not participants in the chat"));
     }
 
-    // Create notification
+    // Create the notification; it is pushed to the recipient's WebSocket session as a
+    // `video_call_request` frame even when they muted calls, which only affects the record and email.
     let notification = Notification::new(
-        config.video_call_notification,
+        NotificationType::VideoCall,
         recipient_id,
-        caller_id.clone(),
+        caller_id,
         "Incoming video call request",
     );
-
-    let notifications_collection = state.db.collection::<Notification>(Notification::collection_name());
-    let insert_result = notifications_collection
-        .insert_one(notification.clone(), None)
-        .await
-        .map_err(|_| ErrorInternalServerError("Failed to create notification"))?;
-    
-    let notification_id = insert_result.inserted_id.as_object_id()
-        .ok_or_else(|| ErrorInternalServerError("Failed to get notification ID"))?;
-    
-    // Get sender's username
-    let users_collection = state.db.collection::<User>(User::collection_name());
-    let sender = users_collection
-        .find_one(doc! { "_id": &caller_id }, None)
-        .await
-        .map_err(|_| ErrorInternalServerError("Database error"))?
-        .ok_or_else(|| ErrorInternalServerError("Sender user not found"))?;
-
-    // Create notification summary and send WebSocket message
-    let notification_summary = NotificationSummary {
-        id: notification_id.to_hex(),
-        notification_type: config.video_call_notification.to_string(),
-        sender_user_id: caller_id.to_hex(),
-        sender_username: sender.username,
-        message: "Incoming video call request".to_string(),
-        timestamp: notification.created_at
-    };
-
-    let ws_message = json!({
-        "type": "video_call_request",
-        "notification": notification_summary
-    });
-
-    let ws_sessions = state.ws_sessions.read().await;
-    if let Some((recipient_addr, _)) = ws_sessions.get(&recipient_id) {
-        recipient_addr.do_send(TextMessage(ws_message.to_string()));
-    }
+    create_notification(state, notification).await?;
 
     Ok(())
 }
@@ -110,13 +73,10 @@ pub async fn respond_video_call_logic(
     accepted: bool,
 ) -> Result<(), Error> {
     // Update notification status
-    let notifications_collection = state.db.collection::<Notification>(Notification::collection_name());
-    let notification = notifications_collection
-        .find_one_and_update(
-            doc! { "_id": &notification_id, "recipient_id": &recipient_id },
-            doc! { "$set": { "is_handled": true } },
-            None,
-        )
+    let notification = state
+        .repositories
+        .notifications
+        .mark_handled(notification_id, recipient_id)
         .await
         .map_err(|_| ErrorInternalServerError("Database error"))?
         .ok_or_else(|| actix_web::error::ErrorNotFound("Notification not found"))?;
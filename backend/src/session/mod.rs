@@ -0,0 +1 @@
+pub mod repository_session_store;
@@ -0,0 +1,110 @@
+use crate::{
+    models::session_model::StoredSession, repositories::session_repository::SessionRepository,
+    utils::auth_util::hash_token,
+};
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::cookie::time::Duration;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use rand::{distributions::Alphanumeric, rngs::OsRng, Rng};
+use std::{collections::HashMap, sync::Arc};
+
+/// Session entries that are copied out of the session state so sessions can be queried.
+pub const SESSION_USER_ID: &str = "user_id";
+pub const SESSION_ID: &str = "session_id";
+pub const SESSION_USER_AGENT: &str = "user_agent";
+pub const SESSION_IP: &str = "ip";
+
+type SessionState = HashMap<String, String>;
+
+/// Stores sessions in the configured repository so they can be listed and revoked from the server.
+#[derive(Clone)]
+pub struct RepositorySessionStore {
+    sessions: Arc<dyn SessionRepository>,
+}
+
+impl RepositorySessionStore {
+    pub fn new(sessions: Arc<dyn SessionRepository>) -> Self {
+        Self { sessions }
+    }
+}
+
+fn generate_session_key() -> SessionKey {
+    let key: String = OsRng.sample_iter(&Alphanumeric).take(64).map(char::from).collect();
+    // 64 alphanumeric characters are always a valid session key.
+    SessionKey::try_from(key).expect("generated session key is valid")
+}
+
+/// Session values are JSON encoded by `actix-session`; read one back as a string.
+fn state_string(state: &SessionState, key: &str) -> Option<String> {
+    state.get(key).and_then(|value| serde_json::from_str(value).ok())
+}
+
+fn expires_at(ttl: &Duration) -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::seconds(ttl.whole_seconds())
+}
+
+/// The session stored under `session_key`, with the queryable fields copied out of its state.
+fn stored_session(session_key: &SessionKey, session_state: SessionState, ttl: &Duration) -> StoredSession {
+    let now = Utc::now();
+    StoredSession {
+        id: None,
+        key_hash: hash_token(session_key.as_ref()),
+        session_id: state_string(&session_state, SESSION_ID),
+        user_id: state_string(&session_state, SESSION_USER_ID).and_then(|user_id| ObjectId::parse_str(user_id).ok()),
+        user_agent: state_string(&session_state, SESSION_USER_AGENT),
+        ip: state_string(&session_state, SESSION_IP),
+        state: session_state,
+        created_at: now,
+        last_seen_at: now,
+        expires_at: expires_at(ttl),
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl SessionStore for RepositorySessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let session = self
+            .sessions
+            .find_by_key_hash(&hash_token(session_key.as_ref()), Utc::now())
+            .await
+            .map_err(|e| LoadError::Other(e.into()))?;
+        Ok(session.map(|session| session.state))
+    }
+
+    async fn save(&self, session_state: SessionState, ttl: &Duration) -> Result<SessionKey, SaveError> {
+        let session_key = generate_session_key();
+        self.sessions
+            .insert(&stored_session(&session_key, session_state, ttl))
+            .await
+            .map_err(|e| SaveError::Other(e.into()))?;
+        Ok(session_key)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        // If the session expired or was revoked in the meantime nothing is saved, so the
+        // cookie no longer loads a session and the request that raced the revocation cannot revive it.
+        self.sessions
+            .update_state(&stored_session(&session_key, session_state, ttl))
+            .await
+            .map_err(|e| UpdateError::Other(e.into()))?;
+        Ok(session_key)
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> Result<(), anyhow::Error> {
+        self.sessions
+            .touch(&hash_token(session_key.as_ref()), Utc::now(), expires_at(ttl))
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        self.sessions.delete_by_key_hash(&hash_token(session_key.as_ref())).await?;
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
-use crate::websocket::websocket_session::WsSession;
+use crate::{
+    auth::auth_provider::AuthProvider, mail::mailer::Mailer, repositories::repository::Repositories,
+    websocket::websocket_session::WsSession,
+};
 use actix::Addr;
-use mongodb::{bson::oid::ObjectId, Client, Database};
+use mongodb::bson::oid::ObjectId;
 use uuid::Uuid;
 use std::{
     collections::{HashMap, HashSet},
@@ -8,20 +11,29 @@ use std::{
 };
 use tokio::sync::RwLock;
 
+/// Live WebSocket sessions keyed by user, with the id of the session that owns the slot.
+pub type WsSessionMap = HashMap<ObjectId, (Addr<WsSession>, Uuid)>;
+
 pub struct AppState {
-    pub ws_sessions: Arc<RwLock<HashMap<ObjectId, (Addr<WsSession>, Uuid)>>>,
+    pub ws_sessions: Arc<RwLock<WsSessionMap>>,
     pub chats: Arc<RwLock<HashMap<String, HashSet<ObjectId>>>>,
-    pub mongo_client: Client,
-    pub db: Database,
+    pub mailer: Arc<dyn Mailer>,
+    pub auth_provider: Arc<dyn AuthProvider>,
+    pub repositories: Repositories,
 }
 
 impl AppState {
-    pub fn new(client: Client, db: Database) -> Self {
+    pub fn new(
+        mailer: Arc<dyn Mailer>,
+        auth_provider: Arc<dyn AuthProvider>,
+        repositories: Repositories,
+    ) -> Self {
         Self {
             ws_sessions: Arc::new(RwLock::new(HashMap::new())),
             chats: Arc::new(RwLock::new(HashMap::new())),
-            mongo_client: client,
-            db,
+            mailer,
+            auth_provider,
+            repositories,
         }
     }
 }
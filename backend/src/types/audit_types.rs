@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// A security-relevant action recorded in the audit log.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    AccountRegistered,
+    EmailVerified,
+    LoginSucceeded,
+    LoginFailed,
+    Logout,
+    PasswordResetRequested,
+    PasswordResetCompleted,
+    ChatDeleted,
+    UserSuspended,
+    UserUnsuspended,
+    RoleChanged,
+    PasswordResetForced,
+    AccountUnlocked,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
@@ -0,0 +1,99 @@
+use actix_web::http::Method;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// What an API token is allowed to do. Cookie sessions are not scoped.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenScope {
+    #[serde(rename = "chats:read")]
+    ChatsRead,
+    #[serde(rename = "chats:write")]
+    ChatsWrite,
+    #[serde(rename = "notifications:read")]
+    NotificationsRead,
+    #[serde(rename = "notifications:write")]
+    NotificationsWrite,
+    #[serde(rename = "users:read")]
+    UsersRead,
+    #[serde(rename = "video_call")]
+    VideoCall,
+    #[serde(rename = "websocket")]
+    Websocket,
+    #[serde(rename = "reports:write")]
+    ReportsWrite,
+    /// Only has an effect for moderators and admins.
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl TokenScope {
+    pub const ALL: [TokenScope; 9] = [
+        Self::ChatsRead,
+        Self::ChatsWrite,
+        Self::NotificationsRead,
+        Self::NotificationsWrite,
+        Self::UsersRead,
+        Self::VideoCall,
+        Self::Websocket,
+        Self::ReportsWrite,
+        Self::Admin,
+    ];
+
+    /// Whether holding this scope allows an action that requires `required`.
+    /// Write access to a resource includes read access.
+    pub fn grants(&self, required: TokenScope) -> bool {
+        *self == required
+            || matches!(
+                (self, required),
+                (Self::ChatsWrite, Self::ChatsRead) | (Self::NotificationsWrite, Self::NotificationsRead)
+            )
+    }
+}
+
+/// A group of routes protected by the auth middleware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiResource {
+    Chats,
+    Notifications,
+    Users,
+    VideoCall,
+    Websocket,
+    Reports,
+    Admin,
+}
+
+impl ApiResource {
+    /// The scope a token needs to call a route of this resource with `method`.
+    pub fn required_scope(&self, method: &Method) -> TokenScope {
+        let is_read = *method == Method::GET || *method == Method::HEAD;
+        match self {
+            Self::Chats if is_read => TokenScope::ChatsRead,
+            Self::Chats => TokenScope::ChatsWrite,
+            Self::Notifications if is_read => TokenScope::NotificationsRead,
+            Self::Notifications => TokenScope::NotificationsWrite,
+            // Every route here only reads, even the POST lookups. Saved messages expose chat
+            // content and can be changed, so `/users/saved` is guarded as `Chats` instead.
+            Self::Users => TokenScope::UsersRead,
+            Self::VideoCall => TokenScope::VideoCall,
+            Self::Websocket => TokenScope::Websocket,
+            Self::Reports => TokenScope::ReportsWrite,
+            Self::Admin => TokenScope::Admin,
+        }
+    }
+}
+
+/// The caller of a request that passed the auth middleware, stored in the request extensions.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub user_id: ObjectId,
+    /// `None` for cookie sessions, which have full access.
+    pub scopes: Option<Vec<TokenScope>>,
+}
+
+impl AuthenticatedUser {
+    pub fn has_scope(&self, required: TokenScope) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.iter().any(|scope| scope.grants(required)))
+    }
+}
@@ -1 +1,5 @@
-pub mod ws_message_types;
\ No newline at end of file
+pub mod audit_types;
+pub mod auth_types;
+pub mod moderation_types;
+pub mod notification_types;
+pub mod ws_message_types;
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// Why a message or user was reported.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    HateSpeech,
+    Violence,
+    SexualContent,
+    Impersonation,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+    Open,
+    /// A moderator acted on the report.
+    Resolved,
+    /// A moderator looked at the report and took no action.
+    Dismissed,
+}
+
+/// Something a moderator did, as kept in the moderation trail.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationActionKind {
+    RemoveMessage,
+    WarnUser,
+    SuspendUser,
+    DismissReport,
+}
@@ -0,0 +1,75 @@
+use crate::constants;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationType {
+    FriendRequest,
+    VideoCall,
+    MissedCall,
+    ChatMessage,
+    System,
+}
+
+impl NotificationType {
+    pub const ALL: [NotificationType; 5] = [
+        Self::FriendRequest,
+        Self::VideoCall,
+        Self::MissedCall,
+        Self::ChatMessage,
+        Self::System,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FriendRequest => constants::FRIEND_REQUEST_NOTIFICATION,
+            Self::VideoCall => constants::VIDEO_CALL_NOTIFICATION,
+            Self::MissedCall => constants::MISSED_CALL_NOTIFICATION,
+            Self::ChatMessage => constants::CHAT_MESSAGE_NOTIFICATION,
+            Self::System => constants::SYSTEM_NOTIFICATION,
+        }
+    }
+
+    /// The WebSocket frame type used when pushing this notification live.
+    /// Video calls keep their dedicated frame so the call UI can ring.
+    pub fn ws_event(&self) -> &'static str {
+        match self {
+            Self::VideoCall => "video_call_request",
+            _ => "notification",
+        }
+    }
+
+    /// Whether the live push is the point of this notification, as with a ringing call, so it
+    /// reaches the recipient whatever their preferences say.
+    pub fn is_live_signal(&self) -> bool {
+        matches!(self, Self::VideoCall)
+    }
+
+    /// Whether a user may create this kind of notification through the API.
+    /// Everything else is raised by the server itself.
+    pub fn is_user_creatable(&self) -> bool {
+        matches!(self, Self::FriendRequest)
+    }
+}
+
+/// How often a user wants to receive the offline activity digest.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestFrequency {
+    Never,
+    Hourly,
+    Daily,
+    Weekly,
+}
+
+impl DigestFrequency {
+    /// The minimum time between two digests, or `None` when digests are disabled.
+    pub fn interval(&self) -> Option<chrono::Duration> {
+        match self {
+            Self::Never => None,
+            Self::Hourly => Some(chrono::Duration::hours(1)),
+            Self::Daily => Some(chrono::Duration::days(1)),
+            Self::Weekly => Some(chrono::Duration::weeks(1)),
+        }
+    }
+}
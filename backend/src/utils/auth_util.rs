@@ -2,10 +2,13 @@ use argon2::{
     password_hash::{Error as PHError, PasswordHash, SaltString},
     Argon2, PasswordHasher, PasswordVerifier,
 };
-use lettre::{transport::smtp::authentication::Credentials, Message, SmtpTransport, Transport};
-use rand::{rngs::OsRng, Rng};
-use std::error::Error;
-use crate::config::app_config::AppConfig;
+use hmac::{Hmac, Mac};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{rngs::OsRng, Rng, RngCore};
+use sha2::{Digest, Sha256};
+use crate::constants::RESET_TOKEN_LENGTH;
+
+type HmacSha256 = Hmac<Sha256>;
 
 pub fn hash_password(password: &str) -> Result<String, PHError> {
     let salt = SaltString::generate(&mut OsRng);
@@ -27,37 +30,72 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, PHError> {
 }
 
 pub fn generate_reset_token() -> String {
-    let config = AppConfig::new().unwrap();
     let mut rng = rand::thread_rng();
-    (0..config.reset_token_length)
+    (0..RESET_TOKEN_LENGTH)
         .map(|_| rng.sample(rand::distributions::Alphanumeric) as char)
         .collect()
 }
 
-pub fn send_reset_email(email: &str, token: &str) -> Result<(), Box<dyn Error>> {
-    let config = AppConfig::new().unwrap();
-    
-    let reset_link = format!("http://localhost/reset-password/{}", token);
-    // reset_link = format!("{}/reset_password?email={}&token={}", config.frontend_url, email, token);
-    // Build the email message
-    let email_message = Message::builder()
-        .from(config.email_address.parse().unwrap())
-        .to(email.parse().unwrap())
-        .subject("Password Reset")
-        .body(format!("Visit this link to reset your password: {}", reset_link))?;
-    
-    // Create credentials using your Gmail address and app password.
-    let creds = Credentials::new(
-        config.email_address.clone(),
-        config.email_password.clone(),  // Replace with your actual app password
-    );
-    
-    // Build an SMTP transport using Gmail's SMTP server.
-    let mailer = SmtpTransport::relay("smtp.gmail.com")?
-        .credentials(creds)
-        .build();
-    
-    // Send the email.
-    mailer.send(&email_message)?;
-    Ok(())
+/// SHA-256 of a random token, for storing tokens that are looked up but never shown again.
+/// Only suitable for high-entropy tokens; passwords go through [`hash_password`].
+pub fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+/// A random PKCE code verifier (RFC 7636).
+pub fn generate_pkce_verifier() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The `S256` code challenge for a PKCE code verifier.
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Compare two secrets without leaking where they differ through timing.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(value: &str) -> Option<Vec<u8>> {
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => u8::from_str_radix(std::str::from_utf8(&[*high, *low]).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+fn token_mac(secret: &str, purpose: &str, payload: &str) -> HmacSha256 {
+    // HMAC accepts keys of any length, so this cannot fail.
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(purpose.as_bytes());
+    mac.update(b":");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+/// Signs `payload` for the given purpose, producing `<payload>.<signature>`.
+/// The purpose keeps a token issued for one flow from being accepted by another.
+pub fn sign_token(secret: &str, purpose: &str, payload: &str) -> String {
+    let signature = token_mac(secret, purpose, payload).finalize().into_bytes();
+    format!("{}.{}", payload, to_hex(&signature))
+}
+
+/// Checks a token produced by [`sign_token`] and returns its payload if the signature matches.
+pub fn verify_signed_token(secret: &str, purpose: &str, token: &str) -> Option<String> {
+    let (payload, signature) = token.rsplit_once('.')?;
+    let signature = from_hex(signature)?;
+    token_mac(secret, purpose, payload)
+        .verify_slice(&signature)
+        .ok()?;
+    Some(payload.to_owned())
 }
@@ -1,2 +1,5 @@
 pub mod auth_util;
+pub mod password_util;
+pub mod request_util;
+pub mod totp_util;
 pub mod validation_util;
@@ -0,0 +1,150 @@
+use crate::utils::auth_util::verify_password;
+use actix_web::{error::InternalError, HttpResponse};
+use serde::Serialize;
+use std::{collections::HashSet, error::Error, sync::Arc};
+
+/// Rules a new password has to satisfy.
+#[derive(Debug, Clone, Default)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    /// Lowercased passwords that are too common to allow.
+    pub banned_passwords: Arc<HashSet<String>>,
+    /// How many previous passwords may not be reused.
+    pub history_size: usize,
+    /// Minimum [`PasswordStrength::score`] (0-4).
+    pub min_strength_score: u8,
+}
+
+/// A single failed password rule, reported back to the client.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PasswordRuleViolation {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub struct PasswordStrength {
+    pub entropy_bits: f64,
+    /// 0 (very weak) to 4 (very strong).
+    pub score: u8,
+}
+
+/// Estimate password strength from the size of the character pool it draws from.
+/// Repeated characters only count once towards the length, so `aaaaaaaa` scores low.
+pub fn estimate_strength(password: &str) -> PasswordStrength {
+    let mut pool = 0u32;
+    if password.chars().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if password.chars().any(|c| c.is_ascii_punctuation() || c == ' ') {
+        pool += 33;
+    }
+    if !password.is_ascii() {
+        pool += 100;
+    }
+
+    let unique_chars = password.chars().collect::<HashSet<char>>().len();
+    let effective_length = (unique_chars + password.chars().count()) as f64 / 2.0;
+    let entropy_bits = if pool == 0 {
+        0.0
+    } else {
+        effective_length * (pool as f64).log2()
+    };
+
+    let score = match entropy_bits {
+        bits if bits < 28.0 => 0,
+        bits if bits < 36.0 => 1,
+        bits if bits < 60.0 => 2,
+        bits if bits < 128.0 => 3,
+        _ => 4,
+    };
+
+    PasswordStrength { entropy_bits, score }
+}
+
+impl PasswordPolicy {
+    /// Check `password` against every rule and return the ones it breaks.
+    /// `previous_hashes` are the user's current and past password hashes, newest first.
+    pub fn check(
+        &self,
+        password: &str,
+        username: Option<&str>,
+        previous_hashes: &[String],
+    ) -> Vec<PasswordRuleViolation> {
+        let mut violations = Vec::new();
+
+        if password.chars().count() < self.min_length {
+            violations.push(PasswordRuleViolation {
+                rule: "min_length",
+                message: format!("Password must be at least {} characters long", self.min_length),
+            });
+        }
+
+        let lowered = password.to_lowercase();
+        if self.banned_passwords.contains(&lowered) {
+            violations.push(PasswordRuleViolation {
+                rule: "common_password",
+                message: "Password is too common".to_string(),
+            });
+        }
+
+        if username.is_some_and(|username| !username.is_empty() && lowered.contains(&username.to_lowercase())) {
+            violations.push(PasswordRuleViolation {
+                rule: "contains_username",
+                message: "Password must not contain the username".to_string(),
+            });
+        }
+
+        let strength = estimate_strength(password);
+        if strength.score < self.min_strength_score {
+            violations.push(PasswordRuleViolation {
+                rule: "strength",
+                message: format!(
+                    "Password is too weak (strength {} of 4, at least {} required)",
+                    strength.score, self.min_strength_score
+                ),
+            });
+        }
+
+        let reused = previous_hashes
+            .iter()
+            .take(self.history_size)
+            .any(|hash| verify_password(password, hash).unwrap_or(false));
+        if reused {
+            violations.push(PasswordRuleViolation {
+                rule: "reused",
+                message: format!("Password must differ from your last {} passwords", self.history_size),
+            });
+        }
+
+        violations
+    }
+}
+
+/// Turn policy violations into a `400 Bad Request` listing every failed rule.
+pub fn password_policy_error(violations: Vec<PasswordRuleViolation>) -> actix_web::Error {
+    let response = HttpResponse::BadRequest().json(serde_json::json!({
+        "error": "Password does not meet the password policy",
+        "violations": violations,
+    }));
+    InternalError::from_response("Password does not meet the password policy", response).into()
+}
+
+/// Read a banned-password list with one password per line.
+pub fn load_banned_passwords(path: &str) -> Result<Arc<HashSet<String>>, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| Box::<dyn Error>::from(format!("Failed to read {}: {}", path, e)))?;
+    Ok(Arc::new(
+        contents
+            .lines()
+            .map(|line| line.trim().to_lowercase())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect(),
+    ))
+}
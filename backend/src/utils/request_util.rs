@@ -0,0 +1,116 @@
+use actix_web::{
+    http::header::{AUTHORIZATION, USER_AGENT},
+    web, HttpRequest,
+};
+use std::{collections::HashMap, net::IpAddr};
+
+/// Reverse proxies allowed to report the client's address in `X-Forwarded-For`.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+/// The client's IP address.
+///
+/// This is the connection's peer unless the peer is a trusted proxy, in which case
+/// `X-Forwarded-For` is read from the right, skipping trusted proxies, so entries a client
+/// prepended itself are never believed.
+pub fn client_ip(req: &HttpRequest) -> String {
+    let Some(peer) = req.peer_addr().map(|addr| addr.ip()) else {
+        return "unknown".to_owned();
+    };
+    let trusted = req
+        .app_data::<web::Data<TrustedProxies>>()
+        .map(|proxies| proxies.0.as_slice())
+        .unwrap_or_default();
+    if !trusted.contains(&peer) {
+        return peer.to_string();
+    }
+
+    let forwarded: Vec<&str> = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    for hop in forwarded.into_iter().rev() {
+        match hop.parse::<IpAddr>() {
+            Ok(ip) if trusted.contains(&ip) => continue,
+            Ok(ip) => return ip.to_string(),
+            // Anything unparseable was not written by a proxy we trust.
+            Err(_) => break,
+        }
+    }
+    peer.to_string()
+}
+
+/// Where a request came from, as recorded in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestContext {
+    pub ip: String,
+    pub user_agent: String,
+}
+
+impl RequestContext {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        Self {
+            ip: client_ip(req),
+            user_agent: user_agent(req),
+        }
+    }
+}
+
+/// The token from an `Authorization: Bearer <token>` header.
+pub fn bearer_token(req: &HttpRequest) -> Option<String> {
+    let header = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = header.split_once(' ')?;
+    (scheme.eq_ignore_ascii_case("bearer") && !token.trim().is_empty()).then(|| token.trim().to_owned())
+}
+
+/// The `access_token` query parameter, for clients that cannot set headers such as browser WebSockets.
+pub fn query_access_token(req: &HttpRequest) -> Option<String> {
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()?
+        .get("access_token")
+        .filter(|token| !token.is_empty())
+        .cloned()
+}
+
+pub fn user_agent(req: &HttpRequest) -> String {
+    req.headers()
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("unknown")
+        .to_owned()
+}
+
+/// A short human readable description of the device behind a user agent, e.g. "Firefox on Linux".
+pub fn describe_device(user_agent: &str) -> String {
+    let browser = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+    ]
+    .iter()
+    .find(|(marker, _)| user_agent.contains(marker))
+    .map(|(_, name)| *name);
+    let os = [
+        ("Android", "Android"),
+        ("iPhone", "iOS"),
+        ("iPad", "iPadOS"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "macOS"),
+        ("CrOS", "ChromeOS"),
+        ("Linux", "Linux"),
+    ]
+    .iter()
+    .find(|(marker, _)| user_agent.contains(marker))
+    .map(|(_, name)| *name);
+
+    match (browser, os) {
+        (Some(browser), Some(os)) => format!("{} on {}", browser, os),
+        (Some(name), None) | (None, Some(name)) => name.to_owned(),
+        (None, None) => "Unknown device".to_owned(),
+    }
+}
@@ -0,0 +1,142 @@
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, Rng, RngCore};
+use sha1::Sha1;
+
+type HmacSha1 = Hmac<Sha1>;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const SECRET_BYTES: usize = 20;
+const CODE_DIGITS: u32 = 6;
+const STEP_SECONDS: i64 = 30;
+/// Codes from one step either side of the current one are accepted to allow for clock drift.
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+/// Encode bytes as unpadded RFC 4648 base32, the format authenticator apps expect.
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+/// Decode base32, ignoring case, spaces and padding.
+pub fn base32_decode(value: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in value.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let index = BASE32_ALPHABET
+            .iter()
+            .position(|&letter| letter as char == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | index;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+/// A new random secret, base32 encoded.
+pub fn generate_totp_secret() -> String {
+    let mut secret = [0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut secret);
+    base32_encode(&secret)
+}
+
+/// The `otpauth://` URI authenticator apps import, usually from a QR code.
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    let issuer = encode_uri_component(issuer);
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        encode_uri_component(account),
+        secret,
+        issuer,
+        CODE_DIGITS,
+        STEP_SECONDS
+    )
+}
+
+fn encode_uri_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// The HOTP code (RFC 4226) for `counter`.
+pub fn hotp(secret: &[u8], counter: u64) -> String {
+    // HMAC accepts keys of any length, so this cannot fail.
+    let mut mac = HmacSha1::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]])
+        & 0x7fff_ffff;
+    format!("{:0width$}", binary % 10u32.pow(CODE_DIGITS), width = CODE_DIGITS as usize)
+}
+
+/// The time step a unix timestamp falls into.
+pub fn totp_step(unix_seconds: i64) -> i64 {
+    unix_seconds.div_euclid(STEP_SECONDS)
+}
+
+/// Check a TOTP code (RFC 6238) and return the time step it matched.
+/// Steps at or before `last_used_step` are rejected so a code cannot be replayed.
+pub fn verify_totp(secret: &str, code: &str, unix_seconds: i64, last_used_step: Option<i64>) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != CODE_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let secret = base32_decode(secret)?;
+    let current_step = totp_step(unix_seconds);
+    (current_step - ALLOWED_DRIFT_STEPS..=current_step + ALLOWED_DRIFT_STEPS)
+        .filter(|step| *step >= 0 && last_used_step.is_none_or(|last| *step > last))
+        .find(|step| hotp(&secret, *step as u64) == code)
+}
+
+/// Random single-use recovery codes shaped like `abcde-fghij`.
+pub fn generate_recovery_codes(count: usize) -> Vec<String> {
+    const ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+    let mut rng = OsRng;
+    (0..count)
+        .map(|_| {
+            let code: String = (0..RECOVERY_CODE_LENGTH)
+                .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char)
+                .collect();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+/// Recovery codes are compared without the dash and case-insensitively.
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Whether a code has the shape of a recovery code rather than one from an authenticator app,
+/// so a mistyped app code does not cost a round of password hashing.
+pub fn is_recovery_code(code: &str) -> bool {
+    normalize_recovery_code(code).len() == RECOVERY_CODE_LENGTH
+}
@@ -1,6 +1,6 @@
 use crate::{
     services::chat_service::{get_chat_by_id, send_message},
-    services::user_service::get_user_by_id,
+    services::user_service::{get_user_by_id, touch_last_seen},
     states::app_state::AppState,
     types::ws_message_types::WsMessageType,
 };
@@ -20,17 +20,29 @@ pub struct TextMessage(pub String);
 #[rtype(result = "()")]
 pub struct StopSession;
 
+/// Close the connection if it was opened by the given HTTP session.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct StopAuthSession(pub String);
+
 pub struct WsSession {
     pub user_id: ObjectId,
     session_id: Uuid,
+    /// Public id of the HTTP session that opened the connection.
+    auth_session_id: Option<String>,
     pub state: actix_web::web::Data<AppState>,
 }
 
 impl WsSession {
-    pub fn new(user_id: ObjectId, state: actix_web::web::Data<AppState>) -> Self {
+    pub fn new(
+        user_id: ObjectId,
+        auth_session_id: Option<String>,
+        state: actix_web::web::Data<AppState>,
+    ) -> Self {
         Self {
             user_id,
             session_id: Uuid::new_v4(),
+            auth_session_id,
             state,
         }
     }
@@ -58,7 +70,7 @@ impl Actor for WsSession {
                 let msg = json!({ "type":"user_online", "user_id": user_id.to_hex() });
                 for (&session_user_id, (addr, _)) in sessions.iter() {
                     if session_user_id != user_id {
-                        let _ = addr.do_send(TextMessage(msg.to_string()));
+                        addr.do_send(TextMessage(msg.to_string()));
                     }
                 }
                 drop(sessions);
@@ -68,6 +80,7 @@ impl Actor for WsSession {
     }
 
     fn stopped(&mut self, _ctx: &mut Self::Context) {
+        let state = self.state.clone();
         let ws_sessions = self.state.ws_sessions.clone();
         let user_id = self.user_id;
         let sid = self.session_id;
@@ -79,11 +92,15 @@ impl Actor for WsSession {
                     sessions.remove(&user_id);
                     drop(sessions);
 
+                    if let Err(e) = touch_last_seen(&state, user_id).await {
+                        log::error!("Failed to record last seen: {}", e);
+                    }
+
                     let sessions_read = ws_sessions.read().await;
                     let msg = json!({ "type":"user_offline", "user_id": user_id.to_hex() });
                     for (&session_user_id, (addr, _)) in sessions_read.iter() {
                         if session_user_id != user_id {
-                            let _ = addr.do_send(TextMessage(msg.to_string()));
+                            addr.do_send(TextMessage(msg.to_string()));
                         }
                     }
                     drop(sessions_read);
@@ -102,6 +119,17 @@ impl Handler<StopSession> for WsSession {
     }
 }
 
+impl Handler<StopAuthSession> for WsSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: StopAuthSession, ctx: &mut Self::Context) -> Self::Result {
+        if self.auth_session_id.as_deref() == Some(msg.0.as_str()) {
+            ctx.close(None);
+            ctx.stop();
+        }
+    }
+}
+
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
         if let Ok(ws::Message::Text(text)) = msg {
@@ -200,11 +228,16 @@ async fn handle_chat_message(
         }
     };
 
-    let created_at = match msg_json.get("created_at").and_then(|v| v.as_str()) {
+    // The sender's clock is informational only; older clients send it as `created_at`.
+    let client_sent_at = match msg_json
+        .get("client_sent_at")
+        .or_else(|| msg_json.get("created_at"))
+        .and_then(|v| v.as_str())
+    {
         Some(c) => match chrono::DateTime::parse_from_rfc3339(c) {
             Ok(dt) => Some(dt.with_timezone(&chrono::Utc)),
             Err(e) => {
-                eprintln!("Failed to parse created_at: {}", e);
+                eprintln!("Failed to parse client_sent_at: {}", e);
                 None
             }
         },
@@ -219,7 +252,7 @@ async fn handle_chat_message(
         let mut participant_ids = HashSet::new();
         participant_ids.insert(user_id);
 
-        if let Ok(chat) = get_chat_by_id(&state, chat_id.clone()).await {
+        if let Ok(chat) = get_chat_by_id(state, chat_id).await {
             for participant_id in chat.participant_ids {
                 participant_ids.insert(participant_id);
             }
@@ -230,12 +263,15 @@ async fn handle_chat_message(
     drop(chats);
 
     // Store the message in the database.
+    let client_msg_id = msg_json.get("client_msg_id").and_then(|v| v.as_str());
+
     let message_result = send_message(
-        &state,
-        chat_id.clone(),
-        user_id.clone(),
+        state,
+        chat_id,
+        user_id,
         content,
-        created_at,
+        client_sent_at,
+        client_msg_id,
     )
     .await;
     if let Err(e) = message_result {
@@ -243,10 +279,30 @@ async fn handle_chat_message(
         return;
     }
 
-    let message = message_result.unwrap();
+    let sent = message_result.unwrap();
+    let message = sent.message;
+
+    // Confirm to the sender that the message is stored, with its server id
+    let ack = json!({
+        "type": "message_ack",
+        "chat_id": chat_id.to_hex(),
+        "message_id": message.id.map(|id| id.to_hex()),
+        "client_msg_id": message.client_msg_id,
+        "seq": message.seq,
+        "created_at": message.created_at.to_rfc3339(),
+    })
+    .to_string();
+    if let Some((addr, _)) = state.ws_sessions.read().await.get(&user_id) {
+        addr.do_send(TextMessage(ack));
+    }
+
+    // A retried send was already delivered the first time
+    if sent.duplicate {
+        return;
+    }
 
     // Get sender username
-    let sender = match get_user_by_id(&state, user_id.clone()).await {
+    let sender = match get_user_by_id(state, user_id).await {
         Ok(user) => user,
         Err(e) => {
             eprintln!("Failed to get sender user: {:?}", e);
@@ -270,6 +326,7 @@ async fn handle_chat_message(
                 );
                 outgoing_msg.insert("chat_id".to_string(), Value::String(chat_id.to_hex()));
                 outgoing_msg.insert("sender_id".to_string(), Value::String(user_id.to_hex()));
+                outgoing_msg.insert("seq".to_string(), Value::from(message.seq));
                 outgoing_msg.insert(
                     "sender_username".to_string(),
                     Value::String(sender.username.clone()),
@@ -279,9 +336,18 @@ async fn handle_chat_message(
                     "created_at".to_string(),
                     Value::String(message.created_at.to_string()),
                 );
+                if let Some(client_sent_at) = message.client_sent_at {
+                    outgoing_msg.insert(
+                        "client_sent_at".to_string(),
+                        Value::String(client_sent_at.to_rfc3339()),
+                    );
+                }
+                if let Some(client_msg_id) = &message.client_msg_id {
+                    outgoing_msg.insert("client_msg_id".to_string(), Value::String(client_msg_id.clone()));
+                }
 
                 let message_text = Value::Object(outgoing_msg).to_string();
-                let _ = addr.do_send(TextMessage(message_text));
+                addr.do_send(TextMessage(message_text));
             }
         }
         drop(chats);
@@ -294,7 +360,7 @@ async fn handle_delete_chat(msg_json: Value, state: &actix_web::web::Data<AppSta
             let ws_sessions = state.ws_sessions.read().await;
             if let Some((target_addr, _)) = ws_sessions.get(&target_user_id) {
                 let message_text = msg_json.to_string();
-                let _ = target_addr.do_send(TextMessage(message_text));
+                target_addr.do_send(TextMessage(message_text));
             } else {
                 eprintln!("Target user is not online");
             }
@@ -312,7 +378,7 @@ async fn handle_webrtc_signaling(msg_json: Value, state: &actix_web::web::Data<A
             let ws_sessions = state.ws_sessions.read().await;
             if let Some((target_addr, _)) = ws_sessions.get(&target_user_id) {
                 let message_text = msg_json.to_string();
-                let _ = target_addr.do_send(TextMessage(message_text));
+                target_addr.do_send(TextMessage(message_text));
             } else {
                 eprintln!("Target user is not online");
             }
@@ -330,7 +396,7 @@ async fn handle_end_call(msg_json: Value, state: &actix_web::web::Data<AppState>
             let ws_sessions = state.ws_sessions.read().await;
             if let Some((target_addr, _)) = ws_sessions.get(&target_user_id) {
                 let message_text = msg_json.to_string();
-                let _ = target_addr.do_send(TextMessage(message_text));
+                target_addr.do_send(TextMessage(message_text));
             } else {
                 log::warn!("Target user is not online");
             }
@@ -352,7 +418,7 @@ async fn handle_video_call_response(
             let ws_sessions = state.ws_sessions.read().await;
             if let Some((target_addr, _)) = ws_sessions.get(&caller_id) {
                 let message_text = msg_json.to_string();
-                let _ = target_addr.do_send(TextMessage(message_text));
+                target_addr.do_send(TextMessage(message_text));
             } else {
                 log::warn!("Caller is not online");
             }
@@ -0,0 +1,36 @@
+use cphere_backend::{
+    auth::ldap_provider::{is_member_of_allowed_group, user_search_filter},
+    config::app_config::LdapConfig,
+};
+
+fn config() -> LdapConfig {
+    LdapConfig {
+        url: "ldap://localhost:389".into(),
+        starttls: false,
+        bind_dn: None,
+        bind_password: None,
+        base_dn: "dc=example,dc=org".into(),
+        user_filter: "(objectClass=person)".into(),
+        username_attribute: "uid".into(),
+        email_attribute: "mail".into(),
+        group_attribute: "memberOf".into(),
+        allowed_groups: Vec::new(),
+    }
+}
+
+#[test]
+fn search_filter_escapes_username() {
+    assert_eq!(user_search_filter(&config(), "alice"), "(&(objectClass=person)(uid=alice))");
+    assert_eq!(
+        user_search_filter(&config(), "*)(uid=*"),
+        "(&(objectClass=person)(uid=\\2a\\29\\28uid=\\2a))"
+    );
+}
+
+#[test]
+fn group_restriction() {
+    let groups = vec!["cn=chat,ou=groups,dc=example,dc=org".to_owned()];
+    assert!(is_member_of_allowed_group(&[], &[]));
+    assert!(is_member_of_allowed_group(&["CN=Chat,ou=groups,dc=example,dc=org".to_owned()], &groups));
+    assert!(!is_member_of_allowed_group(&["cn=admins,ou=groups,dc=example,dc=org".to_owned()], &groups));
+}
@@ -0,0 +1,35 @@
+use cphere_backend::{
+    auth::local_provider::LocalProvider, mail::memory_mailer::MemoryMailer, repositories::repository::Repositories,
+    states::app_state::AppState, utils::request_util::RequestContext,
+};
+use std::sync::Arc;
+
+/// App state backed by in-memory repositories and a mailer that keeps what it sends.
+pub fn test_state_with_mailer(mailer: MemoryMailer) -> AppState {
+    // Services read their settings through `AppConfig`, which requires these to be set.
+    for (key, value) in [
+        ("DATABASE_URL", "mongodb://127.0.0.1:27017"),
+        ("DATABASE_NAME", "cphere_test"),
+        ("EMAIL_ADDRESS", "noreply@example.com"),
+        ("EMAIL_PASSWORD", "unused"),
+        ("APP_SECRET", "test-app-secret"),
+        ("SESSION_SECRET", "test-session-secret-that-is-long-enough-for-config"),
+    ] {
+        std::env::set_var(key, value);
+    }
+
+    AppState::new(Arc::new(mailer), Arc::new(LocalProvider), Repositories::in_memory())
+}
+
+/// App state backed by in-memory repositories.
+pub async fn test_state() -> AppState {
+    test_state_with_mailer(MemoryMailer::default())
+}
+
+/// Where test requests claim to come from.
+pub fn context() -> RequestContext {
+    RequestContext {
+        ip: "127.0.0.1".to_owned(),
+        user_agent: "test".to_owned(),
+    }
+}
@@ -1,32 +1,54 @@
-use actix_web::{test, App};
-use cphere_backend::services::auth_service::RegisterRequest;
+use crate::common::test_state;
+use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+use actix_web::{cookie::Key, test, web, App};
+use cphere_backend::handlers::auth_handler::register_handler;
 use serde_json::json;
 
-// #[actix_web::test]
-// async fn test_register_handler() {
-//     // Build a test application with our auth routes.
-//     let app = test::init_service(App::new().configure(cphere_backend::handlers::init_routes)).await;
-    
-//     // Create a JSON payload for registration.
-//     let payload = json!({
-//         "username": "testuser",
-//         "email": "test@example.com",
-//         "password": "secret"
-//     });
-    
-//     // Create a POST request to /auth/register.
-//     let req = test::TestRequest::post()
-//         .uri("/auth/register")
-//         .set_json(&payload)
-//         .to_request();
-    
-//     // Send the request and receive the response.
-//     let resp = test::call_service(&app, req).await;
-    
-//     // Check that the response has status 201 (Created).
-//     assert_eq!(resp.status(), 201);
-    
-//     // Optionally, you can read the response body and check it.
-//     let resp_body: serde_json::Value = test::read_body_json(resp).await;
-//     assert_eq!(resp_body, payload);
-// }
+#[actix_web::test]
+async fn test_register_handler() {
+    let state = web::Data::new(test_state().await);
+    let repositories = state.repositories.clone();
+    let app = test::init_service(
+        App::new()
+            .app_data(state)
+            .wrap(SessionMiddleware::new(CookieSessionStore::default(), Key::generate()))
+            .service(web::scope("/auth").service(register_handler)),
+    )
+    .await;
+
+    let payload = json!({
+        "username": "testuser",
+        "email": "test@example.com",
+        "password": "Quiet-Harbor-Lantern-42"
+    });
+    let req = test::TestRequest::post()
+        .uri("/auth/register")
+        .set_json(&payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let resp_body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(resp_body["username"], "testuser");
+    assert_eq!(resp_body["email_verified"], false);
+    let user = repositories
+        .users
+        .find_by_username("testuser")
+        .await
+        .unwrap()
+        .expect("the user is stored");
+    assert_eq!(resp_body["user_id"], user.id.unwrap().to_string());
+    assert_ne!(user.password_hash, "Quiet-Harbor-Lantern-42");
+
+    // The same username cannot be registered twice.
+    let req = test::TestRequest::post()
+        .uri("/auth/register")
+        .set_json(json!({
+            "username": "testuser",
+            "email": "other@example.com",
+            "password": "Quiet-Harbor-Lantern-42"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
@@ -1,4 +1,68 @@
-use actix_web::{test, App};
+use crate::common::test_state;
+use actix_web::{test, web, App, HttpMessage};
+use cphere_backend::{
+    handlers::video_call_handler,
+    models::{chat_model::Chat, notification_model::Notification},
+    types::{auth_types::AuthenticatedUser, notification_types::NotificationType},
+};
+use mongodb::bson::oid::ObjectId;
 use serde_json::json;
-use cphere_backend::handlers::video_call_handler;
 
+#[actix_web::test]
+async fn test_calls_need_an_online_recipient() {
+    let state = web::Data::new(test_state().await);
+    let (alice_id, bob_id) = (ObjectId::new(), ObjectId::new());
+    let chat_id = state
+        .repositories
+        .chats
+        .insert(&Chat::new(None, vec![alice_id, bob_id], None))
+        .await
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(state)
+            .service(web::scope("/video-call").service(video_call_handler::initiate_video_call)),
+    )
+    .await;
+    let payload = json!({ "recipient_id": bob_id.to_hex(), "chat_id": chat_id.to_hex() });
+
+    let req = test::TestRequest::post().uri("/video-call/initiate").set_json(&payload);
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert_eq!(resp.status(), 401);
+
+    let req = test::TestRequest::post().uri("/video-call/initiate").set_json(&payload).to_request();
+    req.extensions_mut().insert(AuthenticatedUser { user_id: alice_id, scopes: None });
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_answering_a_call_marks_it_handled() {
+    let state = web::Data::new(test_state().await);
+    let repositories = state.repositories.clone();
+    let (alice_id, bob_id) = (ObjectId::new(), ObjectId::new());
+    let notification_id = repositories
+        .notifications
+        .insert(&Notification::new(NotificationType::VideoCall, bob_id, alice_id, "Incoming video call request"))
+        .await
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(state)
+            .service(web::scope("/video-call").service(video_call_handler::respond_video_call)),
+    )
+    .await;
+    let payload = json!({ "notification_id": notification_id.to_hex(), "accepted": true });
+
+    // Only the callee can answer.
+    let req = test::TestRequest::post().uri("/video-call/respond").set_json(&payload).to_request();
+    req.extensions_mut().insert(AuthenticatedUser { user_id: alice_id, scopes: None });
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+
+    let req = test::TestRequest::post().uri("/video-call/respond").set_json(&payload).to_request();
+    req.extensions_mut().insert(AuthenticatedUser { user_id: bob_id, scopes: None });
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert!(repositories.notifications.find_unhandled(bob_id).await.unwrap().is_empty());
+}
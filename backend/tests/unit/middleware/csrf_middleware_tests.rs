@@ -0,0 +1,81 @@
+use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
+use actix_web::{
+    cookie::Key,
+    get,
+    http::StatusCode,
+    post, test, App, Error, HttpResponse,
+};
+use cphere_backend::{middleware::csrf_middleware::CsrfMiddlewareFactory, services::csrf_service::csrf_token};
+
+#[get("/csrf")]
+async fn issue_token(session: Session) -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::Ok().body(csrf_token(&session)?))
+}
+
+#[post("/login")]
+async fn login() -> HttpResponse {
+    HttpResponse::Ok().finish()
+}
+
+/// The CSRF middleware inside a session middleware, allowing only `https://chat.example.org`.
+macro_rules! csrf_app {
+    () => {
+        test::init_service(
+            App::new()
+                .wrap(CsrfMiddlewareFactory::new(vec!["https://chat.example.org".to_owned()]))
+                .wrap(SessionMiddleware::new(CookieSessionStore::default(), Key::generate()))
+                .service(issue_token)
+                .service(login),
+        )
+        .await
+    };
+}
+
+#[actix_web::test]
+async fn test_anonymous_posts_are_checked_by_origin() {
+    let app = csrf_app!();
+
+    let foreign = test::TestRequest::post()
+        .uri("/login")
+        .insert_header(("Origin", "https://evil.example.org"))
+        .to_request();
+    assert_eq!(test::call_service(&app, foreign).await.status(), StatusCode::FORBIDDEN);
+
+    let foreign_referer = test::TestRequest::post()
+        .uri("/login")
+        .insert_header(("Referer", "https://evil.example.org/form"))
+        .to_request();
+    assert_eq!(test::call_service(&app, foreign_referer).await.status(), StatusCode::FORBIDDEN);
+
+    let own = test::TestRequest::post()
+        .uri("/login")
+        .insert_header(("Origin", "https://chat.example.org"))
+        .to_request();
+    assert_eq!(test::call_service(&app, own).await.status(), StatusCode::OK);
+
+    let outside_browser = test::TestRequest::post().uri("/login").to_request();
+    assert_eq!(test::call_service(&app, outside_browser).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_session_posts_need_the_token() {
+    let app = csrf_app!();
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/csrf").to_request()).await;
+    let cookie = resp.response().cookies().next().expect("a session cookie is set").into_owned();
+    let token = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+    // An allowed origin does not replace the token once the session has state
+    let without_token = test::TestRequest::post()
+        .uri("/login")
+        .cookie(cookie.clone())
+        .insert_header(("Origin", "https://chat.example.org"))
+        .to_request();
+    assert_eq!(test::call_service(&app, without_token).await.status(), StatusCode::FORBIDDEN);
+
+    let with_token = test::TestRequest::post()
+        .uri("/login")
+        .cookie(cookie)
+        .insert_header(("X-CSRF-Token", token))
+        .to_request();
+    assert_eq!(test::call_service(&app, with_token).await.status(), StatusCode::OK);
+}
@@ -0,0 +1,58 @@
+use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
+use actix_web::{
+    cookie::{Cookie, Key},
+    get, test, App, HttpResponse,
+};
+use cphere_backend::middleware::session_key_rotation::SessionKeyRotationFactory;
+
+#[get("/set")]
+async fn set_value(session: Session) -> HttpResponse {
+    session.insert("user_id", "alice").unwrap();
+    HttpResponse::Ok().finish()
+}
+
+#[get("/get")]
+async fn get_value(session: Session) -> HttpResponse {
+    HttpResponse::Ok().body(session.get::<String>("user_id").unwrap().unwrap_or_default())
+}
+
+/// A session cookie issued under `key`.
+async fn cookie_for(key: &Key) -> Cookie<'static> {
+    let app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(CookieSessionStore::default(), key.clone()))
+            .service(set_value),
+    )
+    .await;
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/set").to_request()).await;
+    resp.response().cookies().next().expect("a session cookie is set").into_owned()
+}
+
+/// Read the session with `cookie` from a server using `current`, falling back to `previous`.
+async fn read_with(cookie: Cookie<'static>, current: &Key, previous: Vec<Key>) -> String {
+    let rotation = SessionKeyRotationFactory {
+        cookie_name: cookie.name().to_owned(),
+        current_key: current.clone(),
+        previous_keys: previous,
+    };
+    let app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(CookieSessionStore::default(), current.clone()))
+            .wrap(rotation)
+            .service(get_value),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/get").cookie(cookie).to_request();
+    String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap()
+}
+
+#[actix_web::test]
+async fn test_sessions_survive_a_key_rotation() {
+    let (old_key, new_key) = (Key::generate(), Key::generate());
+    let cookie = cookie_for(&old_key).await;
+
+    assert_eq!(read_with(cookie.clone(), &new_key, vec![old_key.clone()]).await, "alice");
+    assert_eq!(read_with(cookie.clone(), &old_key, Vec::new()).await, "alice");
+    // Without the previous key the session is gone.
+    assert_eq!(read_with(cookie, &new_key, Vec::new()).await, "");
+}
@@ -0,0 +1,49 @@
+use cphere_backend::migrations::mongo_migrations::{mongo_migrations, MigrationStep};
+use std::{collections::HashSet, time::Duration};
+
+#[test]
+fn test_migration_versions_increase() {
+    let migrations = mongo_migrations();
+    let versions: Vec<i64> = migrations.iter().map(|migration| migration.version).collect();
+    assert_eq!(versions[0], 1);
+    assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let names: HashSet<&str> = migrations.iter().map(|migration| migration.name).collect();
+    assert_eq!(names.len(), migrations.len());
+}
+
+#[test]
+fn test_migrations_create_unique_and_ttl_indexes() {
+    let indexes: Vec<_> = mongo_migrations()
+        .into_iter()
+        .flat_map(|migration| migration.steps)
+        .filter_map(|step| match step {
+            MigrationStep::CreateIndexes { collection, indexes } => Some((collection, indexes)),
+            MigrationStep::ConvertToDate { .. } | MigrationStep::Aggregate { .. } => None,
+        })
+        .flat_map(|(collection, indexes)| indexes.into_iter().map(move |index| (collection, index)))
+        .collect();
+
+    let is_unique = |collection: &str, field: &str| {
+        indexes.iter().any(|(name, index)| {
+            *name == collection
+                && index.keys.contains_key(field)
+                && index.options.as_ref().and_then(|options| options.unique) == Some(true)
+        })
+    };
+    assert!(is_unique("users", "username"));
+    assert!(is_unique("users", "email"));
+    assert!(is_unique("messages", "seq"));
+    assert!(is_unique("messages", "client_msg_id"));
+
+    let expires = |collection: &str| {
+        indexes.iter().any(|(name, index)| {
+            *name == collection
+                && index.options.as_ref().and_then(|options| options.expire_after) == Some(Duration::ZERO)
+        })
+    };
+    assert!(expires("sessions"));
+    assert!(expires("api_tokens"));
+    assert!(expires("login_throttles"));
+    assert!(expires("mail_outbox"));
+}
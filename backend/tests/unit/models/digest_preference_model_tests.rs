@@ -0,0 +1,69 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use cphere_backend::{models::digest_preference_model::DigestPreference, types::notification_types::DigestFrequency};
+use mongodb::bson::oid::ObjectId;
+
+fn at(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 5, 1, hour, 30, 0).unwrap()
+}
+
+fn quiet(start: u32, end: u32, utc_offset_minutes: i32) -> DigestPreference {
+    DigestPreference {
+        quiet_hours_start: Some(start),
+        quiet_hours_end: Some(end),
+        utc_offset_minutes,
+        ..DigestPreference::default_for(ObjectId::new())
+    }
+}
+
+#[test]
+fn quiet_hours_within_a_day() {
+    let preference = quiet(9, 17, 0);
+    assert!(!preference.is_quiet_at(at(8)));
+    assert!(preference.is_quiet_at(at(9)));
+    assert!(preference.is_quiet_at(at(16)));
+    assert!(!preference.is_quiet_at(at(17)));
+}
+
+#[test]
+fn quiet_hours_wrap_around_midnight_in_local_time() {
+    let preference = quiet(22, 7, 0);
+    assert!(preference.is_quiet_at(at(23)));
+    assert!(preference.is_quiet_at(at(3)));
+    assert!(!preference.is_quiet_at(at(12)));
+
+    // 20:30 UTC is 22:30 at UTC+2.
+    let preference = quiet(22, 7, 120);
+    assert!(preference.is_quiet_at(at(20)));
+    assert!(!preference.is_quiet_at(at(6)));
+
+    // Equal bounds mean no quiet hours at all.
+    assert!(!quiet(5, 5, 0).is_quiet_at(at(5)));
+}
+
+#[test]
+fn digest_is_due_once_per_interval() {
+    let now = at(12);
+    let mut preference = DigestPreference::default_for(ObjectId::new());
+    assert!(preference.is_due_at(now));
+
+    preference.last_sent_at = Some(now - Duration::hours(23));
+    assert!(!preference.is_due_at(now));
+    preference.last_sent_at = Some(now - Duration::days(1));
+    assert!(preference.is_due_at(now));
+
+    preference.frequency = DigestFrequency::Weekly;
+    assert!(!preference.is_due_at(now));
+    preference.frequency = DigestFrequency::Hourly;
+    assert!(preference.is_due_at(now));
+
+    preference.frequency = DigestFrequency::Never;
+    preference.last_sent_at = None;
+    assert!(!preference.is_due_at(now));
+}
+
+#[test]
+fn digest_waits_for_quiet_hours_to_end() {
+    let preference = quiet(22, 7, 0);
+    assert!(!preference.is_due_at(at(23)));
+    assert!(preference.is_due_at(at(7)));
+}
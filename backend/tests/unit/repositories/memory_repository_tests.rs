@@ -0,0 +1,134 @@
+use chrono::{Duration, Utc};
+use cphere_backend::{
+    models::{
+        report_model::Report,
+        session_model::StoredSession,
+        user_model::{OidcIdentity, User},
+    },
+    repositories::{
+        report_repository::{ReportFilter, ReportSelection},
+        repository::Repositories,
+    },
+    types::moderation_types::{ReportReason, ReportStatus},
+};
+use mongodb::bson::oid::ObjectId;
+use std::collections::HashMap;
+
+fn session(key_hash: &str, user_id: ObjectId, expires_in: Duration) -> StoredSession {
+    let now = Utc::now();
+    StoredSession {
+        id: None,
+        key_hash: key_hash.to_owned(),
+        session_id: Some(format!("{}-id", key_hash)),
+        user_id: Some(user_id),
+        state: HashMap::new(),
+        user_agent: None,
+        ip: None,
+        created_at: now,
+        last_seen_at: now,
+        expires_at: now + expires_in,
+    }
+}
+
+#[actix_web::test]
+async fn test_oidc_identities_only_link_to_verified_emails() {
+    let repositories = Repositories::in_memory();
+    let user_id = repositories
+        .users
+        .insert(&User::new("alice", "alice@example.com", "hash"))
+        .await
+        .unwrap();
+    let identity = OidcIdentity {
+        issuer: "https://idp.example.org".to_owned(),
+        subject: "alice".to_owned(),
+    };
+
+    // Whoever registered the address first may not own it, so the account is left alone.
+    let linked = repositories.users.link_oidc_identity_by_email("alice@example.com", &identity).await.unwrap();
+    assert!(linked.is_none());
+    let user = repositories.users.find_by_id(user_id).await.unwrap().unwrap();
+    assert!(!user.email_verified);
+    assert!(user.oidc_identities.is_empty());
+
+    repositories.users.set_email_verified(user_id).await.unwrap();
+    let linked = repositories.users.link_oidc_identity_by_email("alice@example.com", &identity).await.unwrap();
+    assert_eq!(linked.unwrap().oidc_identities, vec![identity]);
+}
+
+#[actix_web::test]
+async fn test_directory_sync_leaves_local_accounts_alone() {
+    let repositories = Repositories::in_memory();
+    repositories
+        .users
+        .insert(&User::new("alice", "alice@example.com", "hash"))
+        .await
+        .unwrap();
+    let synced = repositories.users.sync_directory_email("alice", "alice@corp.example.org").await.unwrap();
+    assert!(synced.is_none());
+    let alice = repositories.users.find_by_username("alice").await.unwrap().unwrap();
+    assert_eq!(alice.email, "alice@example.com");
+
+    let mut bob = User::new("bob", "bob@corp.example.org", "hash");
+    bob.directory_account = true;
+    repositories.users.insert(&bob).await.unwrap();
+    let synced = repositories.users.sync_directory_email("bob", "robert@corp.example.org").await.unwrap();
+    assert_eq!(synced.unwrap().email, "robert@corp.example.org");
+}
+
+#[actix_web::test]
+async fn test_expired_sessions_are_hidden_and_purged() {
+    let repositories = Repositories::in_memory();
+    let user_id = ObjectId::new();
+    repositories.sessions.insert(&session("live", user_id, Duration::hours(1))).await.unwrap();
+    repositories.sessions.insert(&session("stale", user_id, Duration::hours(-1))).await.unwrap();
+
+    let now = Utc::now();
+    assert!(repositories.sessions.find_by_key_hash("stale", now).await.unwrap().is_none());
+    assert_eq!(repositories.sessions.find_for_user(user_id, now).await.unwrap().len(), 1);
+    assert_eq!(repositories.sessions.count_signed_in(now).await.unwrap(), 1);
+    assert_eq!(repositories.sessions.delete_expired(now).await.unwrap(), 1);
+
+    // A user can only revoke their own sessions.
+    assert!(!repositories.sessions.delete_for_user(ObjectId::new(), "live-id").await.unwrap());
+    assert!(repositories.sessions.delete_for_user(user_id, "live-id").await.unwrap());
+    assert!(repositories.sessions.find_by_key_hash("live", now).await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn test_closing_reports_only_touches_open_ones() {
+    let repositories = Repositories::in_memory();
+    let (reporter_id, reported_user_id, moderator_id) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+    let report = Report::new(reporter_id, reported_user_id, ReportReason::Spam, None);
+    let report_id = repositories.reports.insert(&report).await.unwrap();
+    assert!(repositories.reports.has_open_duplicate(&report).await.unwrap());
+    repositories
+        .reports
+        .insert(&Report::new(ObjectId::new(), reported_user_id, ReportReason::Harassment, None))
+        .await
+        .unwrap();
+
+    let spam = ReportFilter {
+        status: ReportStatus::Open,
+        reason: Some(ReportReason::Spam),
+    };
+    assert_eq!(repositories.reports.count(&spam).await.unwrap(), 1);
+
+    let closed = repositories
+        .reports
+        .close(ReportSelection::Report(report_id), ReportStatus::Dismissed, moderator_id, Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(closed, 1);
+    assert!(!repositories.reports.has_open_duplicate(&report).await.unwrap());
+    let dismissed = repositories.reports.find_by_id(report_id).await.unwrap().unwrap();
+    assert_eq!(dismissed.handled_by, Some(moderator_id));
+
+    let closed = repositories
+        .reports
+        .close(ReportSelection::ReportedUser(reported_user_id), ReportStatus::Resolved, moderator_id, Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(closed, 1);
+    let dismissed = repositories.reports.find_by_id(report_id).await.unwrap().unwrap();
+    assert_eq!(dismissed.status, ReportStatus::Dismissed);
+}
@@ -0,0 +1,301 @@
+use chrono::{Duration, Utc};
+use cphere_backend::{
+    config::database::{init_postgres, Storage},
+    mail::mailer::EmailMessage,
+    migrations::migration_runner::{migration_status, run_migrations, run_postgres_migrations},
+    models::{
+        api_token_model::{ApiToken, ApiTokenKind},
+        audit_event_model::AuditEvent,
+        chat_model::{Chat, ChatPin, LastMessage},
+        digest_preference_model::DigestPreference,
+        message_model::Message,
+        moderation_action_model::ModerationAction,
+        notification_model::Notification,
+        notification_preference_model::NotificationPreference,
+        outbox_model::{OutboxEmail, OutboxStatus},
+        report_model::Report,
+        saved_message_model::SavedMessage,
+        session_model::StoredSession,
+        user_model::{OidcIdentity, User},
+    },
+    repositories::{
+        audit_repository::AuditFilter,
+        message_repository::MessageRange,
+        report_repository::{ReportFilter, ReportSelection},
+        repository::{Repositories, RepositoryError},
+        user_repository::UserFilter,
+    },
+    types::{
+        audit_types::{AuditAction, AuditOutcome},
+        auth_types::TokenScope,
+        moderation_types::{ModerationActionKind, ReportReason, ReportStatus},
+        notification_types::{DigestFrequency, NotificationType},
+    },
+    utils::request_util::RequestContext,
+};
+use mongodb::bson::oid::ObjectId;
+use std::collections::HashMap;
+
+/// Runs against the database in `POSTGRES_TEST_URL`, e.g.
+/// `POSTGRES_TEST_URL=postgres://postgres@localhost/cphere_test cargo test -- --ignored`.
+async fn postgres_repositories() -> Repositories {
+    let url = std::env::var("POSTGRES_TEST_URL").expect("POSTGRES_TEST_URL is not set");
+    let pool = init_postgres(&url).await.expect("PostgreSQL is reachable");
+    run_postgres_migrations(&pool).await.expect("the schema migrates");
+    Repositories::postgres(&pool)
+}
+
+#[actix_web::test]
+#[ignore = "needs a PostgreSQL server in POSTGRES_TEST_URL"]
+async fn test_postgres_repositories_round_trip() {
+    let repositories = postgres_repositories().await;
+    // Unique names keep the test repeatable against the same database.
+    let suffix = ObjectId::new().to_hex();
+
+    let alice_id = repositories
+        .users
+        .insert(&User::new(&format!("alice_{}", suffix), &format!("alice_{}@example.com", suffix), "hash"))
+        .await
+        .unwrap();
+    let bob_id = repositories
+        .users
+        .insert(&User::new(&format!("bob_{}", suffix), &format!("bob_{}@example.com", suffix), "hash"))
+        .await
+        .unwrap();
+    let filter = UserFilter { query: Some(suffix.clone()), ..Default::default() };
+    assert_eq!(repositories.users.count_users(&filter).await.unwrap(), 2);
+    assert!(repositories.users.consume_totp_step(alice_id, 7).await.unwrap());
+    assert!(!repositories.users.consume_totp_step(alice_id, 7).await.unwrap());
+
+    // SSO identities only attach to accounts that verified their email.
+    let alice_email = format!("alice_{}@example.com", suffix);
+    let identity = OidcIdentity { issuer: "https://idp.example.org".to_owned(), subject: suffix.clone() };
+    assert!(repositories.users.link_oidc_identity_by_email(&alice_email, &identity).await.unwrap().is_none());
+    assert!(repositories.users.find_by_oidc_identity(&identity).await.unwrap().is_none());
+    repositories.users.set_email_verified(alice_id).await.unwrap();
+    let linked = repositories.users.link_oidc_identity_by_email(&alice_email, &identity).await.unwrap();
+    assert_eq!(linked.and_then(|user| user.id), Some(alice_id));
+
+    // Only directory accounts follow the directory's email.
+    let directory_email = format!("alice_{}@corp.example.org", suffix);
+    let synced = repositories.users.sync_directory_email(&format!("alice_{}", suffix), &directory_email).await.unwrap();
+    assert!(synced.is_none());
+    let mut carol = User::new(&format!("carol_{}", suffix), &format!("carol_{}@example.com", suffix), "hash");
+    carol.directory_account = true;
+    repositories.users.insert(&carol).await.unwrap();
+    let synced = repositories.users.sync_directory_email(&carol.username, &directory_email).await.unwrap();
+    assert!(synced.is_some_and(|user| user.directory_account && user.email == directory_email));
+
+    let chat_id = repositories
+        .chats
+        .insert(&Chat::new(None, vec![alice_id, bob_id], None))
+        .await
+        .unwrap();
+    let chat = repositories
+        .chats
+        .find_by_participants(&[bob_id, alice_id])
+        .await
+        .unwrap()
+        .expect("the chat is found by its participants");
+    assert_eq!(chat.id, Some(chat_id));
+    assert_eq!(chat.participant_ids, vec![alice_id, bob_id]);
+
+    let since = Utc::now() - Duration::minutes(1);
+    let seq = repositories.chats.next_seq(chat_id).await.unwrap().expect("the chat exists");
+    assert_eq!(seq, 1);
+    let mut message = Message::new(chat_id, bob_id, seq, "hello", None);
+    message.client_msg_id = Some("c-1".to_owned());
+    let message_id = repositories.messages.insert(&message).await.unwrap();
+    repositories
+        .chats
+        .record_message(chat_id, &LastMessage::new(message_id, &message))
+        .await
+        .unwrap();
+    let listed = repositories.chats.list_for_user(alice_id, 0, Some(10)).await.unwrap();
+    let last_message = listed[0].last_message.as_ref().expect("the last message is recorded");
+    assert_eq!(last_message.message_id, message_id);
+    assert_eq!(last_message.seq, seq);
+    let pin = ChatPin { message_id, pinned_by: alice_id, pinned_at: Utc::now() };
+    assert!(repositories.chats.pin(chat_id, &pin, 1).await.unwrap());
+    assert!(!repositories.chats.pin(chat_id, &pin, 2).await.unwrap());
+    let pinned = repositories.chats.find_by_id(chat_id).await.unwrap().unwrap();
+    assert!(pinned.is_pinned(message_id));
+    assert_eq!(repositories.messages.find_by_ids(&[message_id]).await.unwrap().len(), 1);
+    assert!(repositories.chats.unpin(chat_id, message_id).await.unwrap());
+    let saved = SavedMessage::new(alice_id, message_id, chat_id, Some("note".to_owned()), vec!["work".to_owned()]);
+    let first = repositories.saved_messages.upsert(&saved).await.unwrap();
+    let again = repositories
+        .saved_messages
+        .upsert(&SavedMessage { note: None, ..saved })
+        .await
+        .unwrap();
+    assert_eq!(again.id, first.id);
+    assert!(again.note.is_none());
+    let tagged = repositories.saved_messages.find_for_user(alice_id, Some("work"), 0, Some(10)).await.unwrap();
+    assert_eq!(tagged.len(), 1);
+    let retry = Message { id: None, seq: seq + 1, ..message };
+    assert!(matches!(repositories.messages.insert(&retry).await, Err(RepositoryError::Duplicate)));
+    assert!(repositories.messages.find_by_client_msg_id(bob_id, "c-1").await.unwrap().is_some());
+    assert_eq!(repositories.messages.count_since(&[chat_id], alice_id, since).await.unwrap(), 1);
+    assert_eq!(repositories.messages.find_last(chat_id).await.unwrap().unwrap().content, "hello");
+
+    let notification_id = repositories
+        .notifications
+        .insert(&Notification::new(NotificationType::VideoCall, alice_id, bob_id, "call"))
+        .await
+        .unwrap();
+    let before = repositories
+        .notifications
+        .mark_handled(notification_id, alice_id)
+        .await
+        .unwrap()
+        .expect("the notification exists");
+    assert!(!before.is_handled);
+    assert!(repositories.notifications.find_unhandled(alice_id).await.unwrap().is_empty());
+
+    let hidden = repositories
+        .chats
+        .hide(chat_id, alice_id, Utc::now())
+        .await
+        .unwrap()
+        .expect("alice takes part in the chat");
+    assert!(hidden.hidden_at(alice_id).is_some());
+    assert!(hidden.hidden_at(bob_id).is_none());
+    assert_eq!(hidden.hidden_seq(alice_id), Some(1));
+    assert!(hidden.deleted_at.is_none());
+    let hidden = repositories.chats.hide(chat_id, bob_id, Utc::now()).await.unwrap().unwrap();
+    assert!(hidden.deleted_at.is_some(), "the last participant to hide the chat deletes it");
+    assert!(repositories.chats.find_for_participant(chat_id, alice_id).await.unwrap().is_none());
+    let deleted = repositories.chats.find_deleted_before(Utc::now()).await.unwrap();
+    assert!(deleted.iter().any(|chat| chat.id == Some(chat_id)));
+
+    assert!(repositories.chats.delete(chat_id).await.unwrap());
+    assert!(repositories.saved_messages.find_for_user(alice_id, None, 0, None).await.unwrap().is_empty());
+    let range = MessageRange::default();
+    assert!(repositories.messages.find_by_chat(chat_id, &range).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+#[ignore = "needs a PostgreSQL server in POSTGRES_TEST_URL"]
+async fn test_postgres_state_stores_round_trip() {
+    let repositories = postgres_repositories().await;
+    let suffix = ObjectId::new().to_hex();
+    let user_id = ObjectId::new();
+    let now = Utc::now();
+
+    let session = StoredSession {
+        id: None,
+        key_hash: format!("key_{}", suffix),
+        session_id: Some(format!("session_{}", suffix)),
+        user_id: Some(user_id),
+        state: HashMap::from([("user_id".to_owned(), format!("\"{}\"", user_id.to_hex()))]),
+        user_agent: Some("test".to_owned()),
+        ip: None,
+        created_at: now,
+        last_seen_at: now,
+        expires_at: now + Duration::hours(1),
+    };
+    repositories.sessions.insert(&session).await.unwrap();
+    let stored = repositories.sessions.find_by_key_hash(&session.key_hash, now).await.unwrap().unwrap();
+    assert_eq!(stored.state, session.state);
+    assert_eq!(repositories.sessions.find_for_user(user_id, now).await.unwrap().len(), 1);
+    assert!(repositories.sessions.delete_for_user(user_id, session.session_id.as_deref().unwrap()).await.unwrap());
+    assert!(!repositories.sessions.update_state(&session).await.unwrap());
+
+    let token_hash = format!("hash_{}", suffix);
+    let token = ApiToken::new(user_id, ApiTokenKind::Refresh, &token_hash, vec![TokenScope::ChatsRead], None);
+    repositories.api_tokens.insert(&token).await.unwrap();
+    let taken = repositories.api_tokens.take_refresh_token(&token_hash).await.unwrap().unwrap();
+    assert_eq!(taken.scopes, vec![TokenScope::ChatsRead]);
+    assert!(repositories.api_tokens.take_refresh_token(&token_hash).await.unwrap().is_none());
+
+    let throttle_key = format!("login_ip:{}", suffix);
+    let expires_at = now + Duration::hours(1);
+    repositories.throttles.increment(&throttle_key, now, expires_at).await.unwrap();
+    let throttle = repositories.throttles.increment(&throttle_key, now, expires_at).await.unwrap();
+    assert_eq!(throttle.failures, 2);
+    repositories.throttles.lock(&throttle_key, expires_at, expires_at).await.unwrap();
+    let locked = repositories.throttles.find(&throttle_key, now).await.unwrap().unwrap();
+    assert!(locked.locked_until.is_some());
+    repositories.throttles.delete(&throttle_key).await.unwrap();
+    assert!(repositories.throttles.find(&throttle_key, now).await.unwrap().is_none());
+    repositories.throttles.increment(&throttle_key, now, expires_at).await.unwrap();
+    repositories.throttles.delete_with_prefix(&format!("login_ip:{}", &suffix[..8])).await.unwrap();
+    assert!(repositories.throttles.find(&throttle_key, now).await.unwrap().is_none());
+
+    let context = RequestContext { ip: "127.0.0.1".to_owned(), user_agent: "test".to_owned() };
+    let mut event = AuditEvent::new(AuditAction::LoginFailed, AuditOutcome::Failure, &context);
+    event.target_id = Some(user_id);
+    repositories.audit_events.insert(&event).await.unwrap();
+    let filter = AuditFilter { target_id: Some(user_id), ..Default::default() };
+    assert_eq!(repositories.audit_events.count(&filter).await.unwrap(), 1);
+    let events = repositories.audit_events.find(&filter, true, 0, 10).await.unwrap();
+    assert_eq!(events[0].action, AuditAction::LoginFailed);
+
+    let email = EmailMessage {
+        to: format!("{}@example.com", suffix),
+        subject: "subject".to_owned(),
+        text_body: "text".to_owned(),
+        html_body: "html".to_owned(),
+    };
+    let email_id = repositories.outbox.insert(&OutboxEmail::new(email, now)).await.unwrap();
+    repositories.outbox.record_sent(email_id, 1, now, now - Duration::seconds(1)).await.unwrap();
+    let sent = repositories.outbox.find_by_id(email_id).await.unwrap().unwrap();
+    assert_eq!(sent.status, OutboxStatus::Sent);
+    assert!(sent.email.text_body.is_empty());
+    assert!(repositories.outbox.delete_expired(now).await.unwrap() >= 1);
+
+    let mut preference = NotificationPreference::default_for(user_id, NotificationType::VideoCall);
+    preference.muted = true;
+    repositories.notification_preferences.upsert(&preference).await.unwrap();
+    repositories.notification_preferences.upsert(&preference).await.unwrap();
+    assert_eq!(repositories.notification_preferences.find_for_user(user_id).await.unwrap().len(), 1);
+    let stored = repositories
+        .notification_preferences
+        .find(user_id, NotificationType::VideoCall)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(stored.muted);
+
+    repositories.digest_preferences.record_sent(user_id, now).await.unwrap();
+    let digest = DigestPreference { frequency: DigestFrequency::Weekly, ..DigestPreference::default_for(user_id) };
+    repositories.digest_preferences.upsert(&digest).await.unwrap();
+    let stored = repositories.digest_preferences.find(user_id).await.unwrap().unwrap();
+    assert_eq!(stored.frequency, DigestFrequency::Weekly);
+    assert!(stored.last_sent_at.is_some());
+
+    let report = Report::new(ObjectId::new(), user_id, ReportReason::Spam, None);
+    let report_id = repositories.reports.insert(&report).await.unwrap();
+    assert!(repositories.reports.has_open_duplicate(&report).await.unwrap());
+    let moderator_id = ObjectId::new();
+    let closed = repositories
+        .reports
+        .close(ReportSelection::ReportedUser(user_id), ReportStatus::Resolved, moderator_id, now)
+        .await
+        .unwrap();
+    assert_eq!(closed, 1);
+    let resolved = ReportFilter { status: ReportStatus::Resolved, reason: None };
+    let reports = repositories.reports.find(&resolved, 0, 100).await.unwrap();
+    assert!(reports.iter().any(|report| report.id == Some(report_id) && report.handled_by == Some(moderator_id)));
+
+    let action = ModerationAction::new(moderator_id, ModerationActionKind::WarnUser, user_id);
+    repositories.moderation_actions.insert(&action).await.unwrap();
+    let actions = repositories.moderation_actions.find(Some(user_id), 0, 10).await.unwrap();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].action, ModerationActionKind::WarnUser);
+}
+
+#[actix_web::test]
+#[ignore = "needs a PostgreSQL server in POSTGRES_TEST_URL"]
+async fn test_postgres_migration_status_lists_only_sql_migrations() {
+    let url = std::env::var("POSTGRES_TEST_URL").expect("POSTGRES_TEST_URL is not set");
+    let storage = Storage::Postgres(init_postgres(&url).await.expect("PostgreSQL is reachable"));
+
+    run_migrations(&storage).await.unwrap();
+    let status = migration_status(&storage).await.unwrap();
+    assert!(status.pending.is_empty());
+    assert!(status.applied.iter().any(|migration| migration.version == 1));
+    // Nothing more to apply, so a second run reports no migrations
+    assert!(run_migrations(&storage).await.unwrap().is_empty());
+}
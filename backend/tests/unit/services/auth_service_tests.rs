@@ -0,0 +1,29 @@
+use crate::common::{context, test_state};
+use actix_web::web;
+use cphere_backend::{
+    models::user_model::User,
+    services::auth_service::{send_reset_password_email, ResetPasswordRequest},
+    utils::request_util::RequestContext,
+};
+
+#[actix_web::test]
+async fn test_reset_requests_answer_the_same_for_unknown_emails() {
+    let state = web::Data::new(test_state().await);
+    state
+        .repositories
+        .users
+        .insert(&User::new("alice", "alice@example.com", "hash"))
+        .await
+        .unwrap();
+
+    let known = ResetPasswordRequest { email: "alice@example.com".to_owned() };
+    let unknown = ResetPasswordRequest { email: "nobody@example.com".to_owned() };
+    let known_response = send_reset_password_email(&state, &known, &context()).await.unwrap();
+    // Every request counts against the client's IP, so the second one comes from elsewhere.
+    let elsewhere = RequestContext { ip: "198.51.100.1".to_owned(), ..context() };
+    let unknown_response = send_reset_password_email(&state, &unknown, &elsewhere).await.unwrap();
+    assert_eq!(known_response, unknown_response);
+
+    let alice = state.repositories.users.find_by_username("alice").await.unwrap().unwrap();
+    assert!(alice.reset_token_hash.is_some());
+}
@@ -0,0 +1,201 @@
+use crate::common::{context, test_state};
+use actix_web::http::StatusCode;
+use chrono::{Duration, Utc};
+use cphere_backend::{
+    constants::MAX_PINNED_MESSAGES,
+    models::{chat_model::Chat, user_model::User},
+    repositories::message_repository::MessageRange,
+    services::chat_service::{
+        delete_chat, delete_message, get_chat_messages, get_pinned_messages, get_user_chats, hard_delete_chat,
+        pin_message, send_message, unpin_message, ChatListQuery, MessageQuery,
+    },
+};
+use mongodb::bson::oid::ObjectId;
+
+#[actix_web::test]
+async fn test_delete_chat_hides_until_new_activity() {
+    let state = test_state().await;
+    let repositories = &state.repositories;
+    let alice_id = ObjectId::new();
+    let bob_id = ObjectId::new();
+    for (user_id, name) in [(alice_id, "alice"), (bob_id, "bob")] {
+        let mut user = User::new(name, &format!("{}@example.com", name), "hash");
+        user.id = Some(user_id);
+        repositories.users.insert(&user).await.unwrap();
+    }
+    let chat_id = repositories
+        .chats
+        .insert(&Chat::new(None, vec![alice_id, bob_id], None))
+        .await
+        .unwrap();
+    send_message(&state, chat_id, bob_id, "hello", Some(Utc::now() - Duration::minutes(1)), None)
+        .await
+        .unwrap();
+
+    delete_chat(&state, chat_id, alice_id, &context()).await.unwrap();
+    assert!(get_user_chats(&state, alice_id, &ChatListQuery::default()).await.unwrap().is_empty());
+    assert_eq!(get_user_chats(&state, bob_id, &ChatListQuery::default()).await.unwrap().len(), 1);
+
+    // A newer message brings the chat back, without the history alice deleted.
+    send_message(&state, chat_id, bob_id, "still there?", None, None).await.unwrap();
+    assert_eq!(get_user_chats(&state, alice_id, &ChatListQuery::default()).await.unwrap().len(), 1);
+    let messages = get_chat_messages(&state, chat_id, alice_id, &MessageQuery::default()).await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["content"], "still there?");
+    assert_eq!(messages[0]["seq"], 2);
+    let older = MessageQuery { before_seq: Some(2), ..Default::default() };
+    assert!(get_chat_messages(&state, chat_id, alice_id, &older).await.unwrap().is_empty());
+    let hidden = MessageRange { hidden_seq: Some(1), ..Default::default() };
+    assert_eq!(repositories.messages.find_by_chat(chat_id, &hidden).await.unwrap().len(), 1);
+
+    // Once both have deleted it with nothing newer, it is marked for the purge job.
+    delete_chat(&state, chat_id, alice_id, &context()).await.unwrap();
+    delete_chat(&state, chat_id, bob_id, &context()).await.unwrap();
+    let deleted = repositories.chats.find_by_id(chat_id).await.unwrap().unwrap();
+    assert!(deleted.deleted_at.is_some());
+    assert!(get_user_chats(&state, bob_id, &ChatListQuery::default()).await.unwrap().is_empty());
+
+    hard_delete_chat(&state, chat_id).await.unwrap();
+    assert!(repositories.chats.find_by_id(chat_id).await.unwrap().is_none());
+    let range = MessageRange::default();
+    assert!(repositories.messages.find_by_chat(chat_id, &range).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_messages_are_ordered_and_paged_by_sequence() {
+    let state = test_state().await;
+    let alice_id = ObjectId::new();
+    let bob_id = ObjectId::new();
+    let chat_id = state
+        .repositories
+        .chats
+        .insert(&Chat::new(None, vec![alice_id, bob_id], None))
+        .await
+        .unwrap();
+
+    // A skewed client clock does not change where a message lands.
+    let skewed = Utc::now() + Duration::days(1);
+    for (content, client_sent_at) in [("first", Some(skewed)), ("second", None), ("third", None)] {
+        send_message(&state, chat_id, alice_id, content, client_sent_at, None).await.unwrap();
+    }
+
+    let seqs = |messages: Vec<serde_json::Value>| -> Vec<i64> {
+        messages.iter().map(|message| message["seq"].as_i64().unwrap()).collect()
+    };
+    let all = get_chat_messages(&state, chat_id, bob_id, &MessageQuery::default()).await.unwrap();
+    assert_eq!(all[0]["content"], "first");
+    assert_eq!(seqs(all), vec![1, 2, 3]);
+
+    let newest = MessageQuery { limit: Some(2), ..Default::default() };
+    assert_eq!(seqs(get_chat_messages(&state, chat_id, bob_id, &newest).await.unwrap()), vec![2, 3]);
+    let older = MessageQuery { before_seq: Some(2), ..Default::default() };
+    assert_eq!(seqs(get_chat_messages(&state, chat_id, bob_id, &older).await.unwrap()), vec![1]);
+    let catch_up = MessageQuery { after_seq: Some(1), limit: Some(1), ..Default::default() };
+    assert_eq!(seqs(get_chat_messages(&state, chat_id, bob_id, &catch_up).await.unwrap()), vec![2]);
+}
+
+#[actix_web::test]
+async fn test_send_message_is_idempotent_per_client_msg_id() {
+    let state = test_state().await;
+    let alice_id = ObjectId::new();
+    let bob_id = ObjectId::new();
+    let chat_id = state
+        .repositories
+        .chats
+        .insert(&Chat::new(None, vec![alice_id, bob_id], None))
+        .await
+        .unwrap();
+
+    let first = send_message(&state, chat_id, alice_id, "hi", None, Some("c-1")).await.unwrap();
+    let retry = send_message(&state, chat_id, alice_id, "hi", None, Some("c-1")).await.unwrap();
+    assert!(!first.duplicate);
+    assert!(retry.duplicate);
+    assert_eq!(retry.message.id, first.message.id);
+    assert_eq!(retry.message.seq, first.message.seq);
+
+    // The id is only unique per sender.
+    let other = send_message(&state, chat_id, bob_id, "hi", None, Some("c-1")).await.unwrap();
+    assert!(!other.duplicate);
+    assert_eq!(state.repositories.messages.count().await.unwrap(), 2);
+}
+
+#[actix_web::test]
+async fn test_chat_list_is_paged_by_last_activity() {
+    let state = test_state().await;
+    let repositories = &state.repositories;
+    let alice_id = ObjectId::new();
+    let mut chat_ids = Vec::new();
+    for (index, name) in ["bob", "carol", "dave"].into_iter().enumerate() {
+        let mut user = User::new(name, &format!("{}@example.com", name), "hash");
+        let user_id = ObjectId::new();
+        user.id = Some(user_id);
+        repositories.users.insert(&user).await.unwrap();
+        let created_at = Utc::now() - Duration::hours(3 - index as i64);
+        let chat = Chat::new(None, vec![alice_id, user_id], Some(created_at));
+        chat_ids.push(repositories.chats.insert(&chat).await.unwrap());
+    }
+
+    // The oldest chat moves to the top once someone writes in it; the others stay listed without messages.
+    let sent = send_message(&state, chat_ids[0], alice_id, "hi bob", None, None).await.unwrap();
+    let chats = get_user_chats(&state, alice_id, &ChatListQuery::default()).await.unwrap();
+    let usernames: Vec<&str> = chats.iter().map(|chat| chat.participant_username.as_str()).collect();
+    assert_eq!(usernames, vec!["bob", "dave", "carol"]);
+    assert_eq!(chats[0].last_message.as_deref(), Some("hi bob"));
+    assert!(chats[1].last_message.is_none());
+
+    let second_page = ChatListQuery { page: Some(1), per_page: Some(2) };
+    let chats = get_user_chats(&state, alice_id, &second_page).await.unwrap();
+    assert_eq!(chats.len(), 1);
+    assert_eq!(chats[0].participant_username, "carol");
+
+    // Deleting the last message falls back to the one before it.
+    send_message(&state, chat_ids[0], alice_id, "bye bob", None, None).await.unwrap();
+    let newest = send_message(&state, chat_ids[0], alice_id, "oops", None, None).await.unwrap();
+    delete_message(&state, newest.message.id.unwrap()).await.unwrap();
+    let chat = repositories.chats.find_by_id(chat_ids[0]).await.unwrap().unwrap();
+    let last_message = chat.last_message.unwrap();
+    assert_eq!(last_message.content, "bye bob");
+    assert_eq!(last_message.seq, sent.message.seq + 1);
+}
+
+#[actix_web::test]
+async fn test_pinned_messages_are_capped_and_follow_deletes() {
+    let state = test_state().await;
+    let alice_id = ObjectId::new();
+    let bob_id = ObjectId::new();
+    let chat_id = state
+        .repositories
+        .chats
+        .insert(&Chat::new(None, vec![alice_id, bob_id], None))
+        .await
+        .unwrap();
+    let mut message_ids = Vec::new();
+    for index in 0..=MAX_PINNED_MESSAGES {
+        let sent = send_message(&state, chat_id, alice_id, &format!("message {}", index), None, None)
+            .await
+            .unwrap();
+        message_ids.push(sent.message.id.unwrap());
+    }
+
+    for message_id in &message_ids[..MAX_PINNED_MESSAGES] {
+        pin_message(&state, chat_id, bob_id, *message_id).await.unwrap();
+    }
+    // Pinning again is a no-op, but one more pin is over the cap.
+    pin_message(&state, chat_id, alice_id, message_ids[0]).await.unwrap();
+    let over_cap = pin_message(&state, chat_id, alice_id, message_ids[MAX_PINNED_MESSAGES]).await;
+    assert_eq!(over_cap.unwrap_err().as_response_error().status_code(), StatusCode::CONFLICT);
+
+    let pins = get_pinned_messages(&state, chat_id, alice_id).await.unwrap();
+    assert_eq!(pins.len(), MAX_PINNED_MESSAGES);
+    assert_eq!(pins[0]["message"]["id"], message_ids[MAX_PINNED_MESSAGES - 1].to_hex());
+    assert_eq!(pins[0]["pinned_by"], bob_id.to_hex());
+
+    unpin_message(&state, chat_id, alice_id, message_ids[1]).await.unwrap();
+    assert!(unpin_message(&state, chat_id, alice_id, message_ids[1]).await.is_err());
+    delete_message(&state, message_ids[0]).await.unwrap();
+    let pins = get_pinned_messages(&state, chat_id, bob_id).await.unwrap();
+    assert_eq!(pins.len(), MAX_PINNED_MESSAGES - 2);
+    assert!(!state.repositories.chats.find_by_id(chat_id).await.unwrap().unwrap().is_pinned(message_ids[0]));
+
+    assert!(get_pinned_messages(&state, chat_id, ObjectId::new()).await.is_err());
+}
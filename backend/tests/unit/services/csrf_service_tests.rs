@@ -0,0 +1,34 @@
+use cphere_backend::{
+    services::csrf_service::{is_allowed_origin, is_allowed_request_origin},
+    utils::auth_util::constant_time_eq,
+};
+
+#[test]
+fn origin_must_be_listed() {
+    let allowed = vec!["http://localhost".to_owned(), "https://chat.example.org/".to_owned()];
+    assert!(is_allowed_origin(&allowed, "http://localhost"));
+    assert!(is_allowed_origin(&allowed, "https://chat.example.org"));
+    assert!(!is_allowed_origin(&allowed, "http://localhost:3000"));
+    assert!(!is_allowed_origin(&allowed, "https://evil.example.org"));
+    assert!(!is_allowed_origin(&allowed, "null"));
+}
+
+#[test]
+fn anonymous_requests_need_an_allowed_origin_or_referer() {
+    let allowed = vec!["https://chat.example.org".to_owned()];
+    assert!(is_allowed_request_origin(&allowed, Some("https://chat.example.org"), None));
+    assert!(!is_allowed_request_origin(&allowed, Some("https://evil.example.org"), Some("https://chat.example.org/")));
+    assert!(is_allowed_request_origin(&allowed, None, Some("https://chat.example.org/login?next=/")));
+    assert!(!is_allowed_request_origin(&allowed, None, Some("https://chat.example.org.evil.example/login")));
+    assert!(!is_allowed_request_origin(&allowed, None, Some("not a url")));
+    // Clients outside a browser send neither header
+    assert!(is_allowed_request_origin(&allowed, None, None));
+}
+
+#[test]
+fn token_comparison() {
+    assert!(constant_time_eq(b"token", b"token"));
+    assert!(!constant_time_eq(b"token", b"tokem"));
+    assert!(!constant_time_eq(b"token", b"token2"));
+    assert!(!constant_time_eq(b"", b"token"));
+}
@@ -0,0 +1,52 @@
+use crate::common::test_state_with_mailer;
+use chrono::{Duration, Utc};
+use cphere_backend::{
+    constants::OUTBOX_RETENTION_DAYS,
+    mail::{mailer::EmailMessage, memory_mailer::MemoryMailer},
+    models::outbox_model::{OutboxEmail, OutboxStatus},
+    services::mail_service::{deliver_pending_emails, retry_delay},
+};
+
+#[test]
+fn retry_delay_doubles_and_is_capped() {
+    assert_eq!(retry_delay(0), Duration::minutes(1));
+    assert_eq!(retry_delay(1), Duration::minutes(2));
+    assert_eq!(retry_delay(3), Duration::minutes(8));
+    assert_eq!(retry_delay(10), Duration::minutes(1024));
+    assert_eq!(retry_delay(50), Duration::minutes(1024));
+    assert_eq!(retry_delay(-1), Duration::minutes(1));
+}
+
+#[actix_web::test]
+async fn delivered_emails_lose_their_bodies_and_expire() {
+    let mailer = MemoryMailer::default();
+    let state = test_state_with_mailer(mailer.clone());
+    let email = EmailMessage {
+        to: "alice@example.com".to_owned(),
+        subject: "Reset your password".to_owned(),
+        text_body: "https://example.com/reset?token=secret".to_owned(),
+        html_body: "<a href=\"https://example.com/reset?token=secret\">Reset</a>".to_owned(),
+    };
+    let email_id = state
+        .repositories
+        .outbox
+        .insert(&OutboxEmail::new(email, Utc::now() - Duration::minutes(1)))
+        .await
+        .unwrap();
+
+    assert_eq!(deliver_pending_emails(&state).await.unwrap(), 1);
+
+    let sent = mailer.sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].text_body, "https://example.com/reset?token=secret");
+
+    let stored = state.repositories.outbox.find_by_id(email_id).await.unwrap().unwrap();
+    assert_eq!(stored.status, OutboxStatus::Sent);
+    assert_eq!(stored.attempts, 1);
+    assert_eq!(stored.email.text_body, "");
+    assert_eq!(stored.email.html_body, "");
+    let expected = Utc::now() + Duration::days(OUTBOX_RETENTION_DAYS);
+    assert!((stored.expires_at.unwrap() - expected).num_seconds().abs() <= 5);
+
+    assert_eq!(deliver_pending_emails(&state).await.unwrap(), 0);
+}
@@ -0,0 +1,69 @@
+use crate::common::{context, test_state};
+use cphere_backend::{
+    models::{chat_model::Chat, user_model::User},
+    services::{
+        chat_service::{delete_chat, delete_message, hard_delete_chat, send_message},
+        saved_message_service::{
+            list_saved_messages, save_message, unsave_message, SaveMessageRequest, SavedMessageQuery,
+        },
+    },
+};
+use mongodb::bson::oid::ObjectId;
+
+#[actix_web::test]
+async fn test_saved_messages_follow_message_and_chat_deletes() {
+    let state = test_state().await;
+    let repositories = &state.repositories;
+    let alice_id = ObjectId::new();
+    let bob_id = ObjectId::new();
+    let mut user = User::new("bob", "bob@example.com", "hash");
+    user.id = Some(bob_id);
+    repositories.users.insert(&user).await.unwrap();
+    let chat_id = repositories
+        .chats
+        .insert(&Chat::new(None, vec![alice_id, bob_id], None))
+        .await
+        .unwrap();
+    let mut message_ids = Vec::new();
+    for content in ["meeting at 10", "agenda: budget", "lunch?"] {
+        let sent = send_message(&state, chat_id, bob_id, content, None, None).await.unwrap();
+        message_ids.push(sent.message.id.unwrap());
+    }
+
+    let save = |message_id: ObjectId, note: Option<&str>, tags: &[&str]| SaveMessageRequest {
+        message_id,
+        note: note.map(str::to_owned),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+    };
+    save_message(&state, alice_id, &save(message_ids[0], None, &["work"])).await.unwrap();
+    save_message(&state, alice_id, &save(message_ids[1], None, &[])).await.unwrap();
+    // Saving again replaces the note and tags instead of adding an entry.
+    let updated = save_message(&state, alice_id, &save(message_ids[1], Some(" prep "), &["Work", "work"]))
+        .await
+        .unwrap();
+    assert_eq!(updated.note.as_deref(), Some("prep"));
+    assert_eq!(updated.tags, vec!["work"]);
+    assert_eq!(updated.preview.sender_username.as_deref(), Some("bob"));
+    assert!(save_message(&state, ObjectId::new(), &save(message_ids[2], None, &[])).await.is_err());
+
+    let work = SavedMessageQuery { tag: Some("work".to_owned()), ..Default::default() };
+    let saved = list_saved_messages(&state, alice_id, &work).await.unwrap();
+    assert_eq!(saved.len(), 2);
+    assert_eq!(saved[0].preview.content, "agenda: budget");
+
+    delete_message(&state, message_ids[1]).await.unwrap();
+    let saved = list_saved_messages(&state, alice_id, &SavedMessageQuery::default()).await.unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].message_id, message_ids[0].to_hex());
+
+    save_message(&state, bob_id, &save(message_ids[2], None, &[])).await.unwrap();
+    unsave_message(&state, bob_id, message_ids[2]).await.unwrap();
+    save_message(&state, bob_id, &save(message_ids[2], None, &[])).await.unwrap();
+
+    // Deleting the chat for herself drops alice's entries only; removing it for everyone drops the rest.
+    delete_chat(&state, chat_id, alice_id, &context()).await.unwrap();
+    assert!(list_saved_messages(&state, alice_id, &SavedMessageQuery::default()).await.unwrap().is_empty());
+    assert_eq!(list_saved_messages(&state, bob_id, &SavedMessageQuery::default()).await.unwrap().len(), 1);
+    hard_delete_chat(&state, chat_id).await.unwrap();
+    assert!(list_saved_messages(&state, bob_id, &SavedMessageQuery::default()).await.unwrap().is_empty());
+}
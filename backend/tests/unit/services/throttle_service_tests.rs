@@ -0,0 +1,102 @@
+use crate::common::test_state;
+use chrono::{DateTime, Duration, Utc};
+use cphere_backend::{
+    config::app_config::AppConfig,
+    constants::LOGIN_MAX_BACKOFF_SECONDS,
+    models::login_throttle_model::LoginThrottle,
+    services::throttle_service::{
+        backoff, blocked_until, check_throttle, clear_throttle, login_keys, record_failure, record_login_failure,
+        unlock_account, FailureOutcome, ThrottleScope,
+    },
+};
+
+fn throttle(failures: i32, last_failure_at: DateTime<Utc>) -> LoginThrottle {
+    LoginThrottle {
+        id: None,
+        key: ThrottleScope::LoginAccount.key("alice"),
+        failures,
+        last_failure_at,
+        locked_until: None,
+        expires_at: None,
+    }
+}
+
+#[test]
+fn backoff_doubles_up_to_the_cap() {
+    assert_eq!(backoff(0), Duration::zero());
+    assert_eq!(backoff(1), Duration::seconds(1));
+    assert_eq!(backoff(2), Duration::seconds(2));
+    assert_eq!(backoff(4), Duration::seconds(8));
+    assert_eq!(backoff(30), Duration::seconds(LOGIN_MAX_BACKOFF_SECONDS));
+    assert_eq!(backoff(i32::MAX), Duration::seconds(LOGIN_MAX_BACKOFF_SECONDS));
+}
+
+#[test]
+fn backoff_holds_retries_back_until_it_passes() {
+    let now = Utc::now();
+    assert!(blocked_until(&throttle(0, now), now).is_none());
+    // Three failures mean a four second wait.
+    let last_failure_at = now - Duration::seconds(2);
+    assert_eq!(blocked_until(&throttle(3, last_failure_at), now), Some(last_failure_at + Duration::seconds(4)));
+    assert!(blocked_until(&throttle(3, now - Duration::seconds(5)), now).is_none());
+}
+
+#[test]
+fn lockout_outlasts_backoff() {
+    let now = Utc::now();
+    let mut locked = throttle(0, now - Duration::minutes(1));
+    locked.locked_until = Some(now + Duration::minutes(15));
+    assert_eq!(blocked_until(&locked, now), locked.locked_until);
+
+    locked.locked_until = Some(now - Duration::seconds(1));
+    assert!(blocked_until(&locked, now).is_none());
+}
+
+#[test]
+fn keys_are_scoped_and_case_insensitive() {
+    assert_eq!(ThrottleScope::LoginAccount.key("Alice"), "login_account:alice");
+    assert_ne!(ThrottleScope::LoginAccount.key("alice"), ThrottleScope::LoginIp.key("alice"));
+}
+
+#[actix_web::test]
+async fn failures_lock_the_key_until_cleared() {
+    let state = test_state().await;
+    let key = ThrottleScope::LoginIp.key("203.0.113.7");
+    let keys = [key.clone()];
+    check_throttle(&state, &keys).await.unwrap();
+
+    let lockout = Duration::minutes(15);
+    assert_eq!(record_failure(&state, &key, 2, lockout).await.unwrap(), FailureOutcome::Counted);
+    assert_eq!(record_failure(&state, &key, 2, lockout).await.unwrap(), FailureOutcome::LockedOut);
+    let error = check_throttle(&state, &keys).await.unwrap_err();
+    assert_eq!(error.as_response_error().status_code().as_u16(), 429);
+
+    clear_throttle(&state, &key).await.unwrap();
+    check_throttle(&state, &keys).await.unwrap();
+}
+
+#[actix_web::test]
+async fn only_existing_accounts_are_locked_and_only_for_one_ip() {
+    let state = test_state().await;
+    let mut config = AppConfig::new().unwrap();
+    config.login_max_account_failures = 1;
+    config.login_max_ip_failures = 100;
+
+    let outcome = record_login_failure(&state, &config, "nobody", "203.0.113.7", false).await.unwrap();
+    assert_eq!(outcome, FailureOutcome::Counted);
+    let outcome = record_login_failure(&state, &config, "alice", "203.0.113.7", true).await.unwrap();
+    assert_eq!(outcome, FailureOutcome::LockedOut);
+
+    let now = Utc::now();
+    let throttles = &state.repositories.throttles;
+    let [account_key, account_ip_key, _] = login_keys("alice", "203.0.113.7");
+    assert!(throttles.find(&account_ip_key, now).await.unwrap().unwrap().locked_until.is_some());
+    // The account as a whole only backs off, so the owner can still sign in from elsewhere.
+    assert!(throttles.find(&account_key, now).await.unwrap().unwrap().locked_until.is_none());
+    let [_, elsewhere_key, _] = login_keys("alice", "198.51.100.1");
+    assert!(throttles.find(&elsewhere_key, now).await.unwrap().is_none());
+
+    unlock_account(&state, "alice").await.unwrap();
+    assert!(throttles.find(&account_key, now).await.unwrap().is_none());
+    assert!(throttles.find(&account_ip_key, now).await.unwrap().is_none());
+}
@@ -0,0 +1,27 @@
+use crate::common::test_state;
+use cphere_backend::{
+    models::user_model::User,
+    services::two_factor_service::{disable_two_factor, DisableTwoFactorRequest},
+    utils::{auth_util::hash_password, totp_util::generate_totp_secret},
+};
+
+#[actix_web::test]
+async fn test_disabling_two_factor_is_throttled() {
+    let state = test_state().await;
+    let mut user = User::new("alice", "alice@example.com", &hash_password("Quiet-Harbor-Lantern-42").unwrap());
+    user.totp_enabled = true;
+    user.totp_secret = Some(generate_totp_secret());
+    let user_id = state.repositories.users.insert(&user).await.unwrap();
+
+    let guess = DisableTwoFactorRequest {
+        password: "Quiet-Harbor-Lantern-42".to_owned(),
+        code: "000000".to_owned(),
+    };
+    let error = disable_two_factor(&state, user_id, &guess).await.unwrap_err();
+    assert_eq!(error.as_response_error().status_code().as_u16(), 401);
+
+    // The next guess has to wait out the backoff.
+    let error = disable_two_factor(&state, user_id, &guess).await.unwrap_err();
+    assert_eq!(error.as_response_error().status_code().as_u16(), 429);
+    assert!(state.repositories.users.find_by_id(user_id).await.unwrap().unwrap().totp_enabled);
+}
@@ -0,0 +1,30 @@
+use actix_session::storage::SessionStore;
+use actix_web::cookie::time::Duration;
+use chrono::Utc;
+use cphere_backend::{
+    repositories::repository::Repositories,
+    session::repository_session_store::{RepositorySessionStore, SESSION_ID, SESSION_USER_ID},
+};
+use mongodb::bson::oid::ObjectId;
+use std::collections::HashMap;
+
+#[actix_web::test]
+async fn test_updating_a_revoked_session_does_not_bring_it_back() {
+    let repositories = Repositories::in_memory();
+    let store = RepositorySessionStore::new(repositories.sessions.clone());
+    let user_id = ObjectId::new();
+    let state = HashMap::from([
+        (SESSION_USER_ID.to_owned(), format!("\"{}\"", user_id.to_hex())),
+        (SESSION_ID.to_owned(), "\"laptop\"".to_owned()),
+    ]);
+    let ttl = Duration::hours(1);
+
+    let session_key = store.save(state.clone(), &ttl).await.unwrap();
+    assert_eq!(store.load(&session_key).await.unwrap(), Some(state.clone()));
+
+    assert!(repositories.sessions.delete_for_user(user_id, "laptop").await.unwrap());
+    let session_key = store.update(session_key, state, &ttl).await.unwrap();
+
+    assert!(store.load(&session_key).await.unwrap().is_none());
+    assert!(repositories.sessions.find_for_user(user_id, Utc::now()).await.unwrap().is_empty());
+}
@@ -0,0 +1,8 @@
+use cphere_backend::types::audit_types::{AuditAction, AuditOutcome};
+
+#[test]
+fn audit_actions_use_snake_case() {
+    assert_eq!(serde_json::to_string(&AuditAction::PasswordResetRequested).unwrap(), r#""password_reset_requested""#);
+    let outcome: AuditOutcome = serde_json::from_str(r#""failure""#).unwrap();
+    assert_eq!(outcome, AuditOutcome::Failure);
+}
@@ -0,0 +1,51 @@
+use actix_web::http::Method;
+use cphere_backend::{
+    models::user_model::UserRole,
+    types::auth_types::{ApiResource, AuthenticatedUser, TokenScope},
+};
+use mongodb::bson::oid::ObjectId;
+
+fn token_user(scopes: &[TokenScope]) -> AuthenticatedUser {
+    AuthenticatedUser {
+        user_id: ObjectId::new(),
+        scopes: Some(scopes.to_vec()),
+    }
+}
+
+#[test]
+fn read_only_chat_token_cannot_write() {
+    let user = token_user(&[TokenScope::ChatsRead]);
+    assert!(user.has_scope(ApiResource::Chats.required_scope(&Method::GET)));
+    assert!(!user.has_scope(ApiResource::Chats.required_scope(&Method::POST)));
+    assert!(!user.has_scope(ApiResource::Notifications.required_scope(&Method::GET)));
+}
+
+#[test]
+fn write_scope_includes_read() {
+    let user = token_user(&[TokenScope::NotificationsWrite]);
+    assert!(user.has_scope(ApiResource::Notifications.required_scope(&Method::GET)));
+    assert!(user.has_scope(ApiResource::Notifications.required_scope(&Method::POST)));
+}
+
+#[test]
+fn cookie_sessions_are_unscoped() {
+    let user = AuthenticatedUser {
+        user_id: ObjectId::new(),
+        scopes: None,
+    };
+    assert!(TokenScope::ALL.iter().all(|scope| user.has_scope(*scope)));
+}
+
+#[test]
+fn scopes_use_resource_names() {
+    let scopes: Vec<TokenScope> = serde_json::from_str(r#"["chats:read", "websocket"]"#).unwrap();
+    assert_eq!(scopes, vec![TokenScope::ChatsRead, TokenScope::Websocket]);
+}
+
+#[test]
+fn admin_routes_need_admin_scope_and_role_order() {
+    let user = token_user(&[TokenScope::UsersRead]);
+    assert!(!user.has_scope(ApiResource::Admin.required_scope(&Method::GET)));
+    assert!(UserRole::Admin > UserRole::Moderator && UserRole::Moderator > UserRole::User);
+    assert_eq!(serde_json::to_string(&UserRole::Moderator).unwrap(), r#""moderator""#);
+}
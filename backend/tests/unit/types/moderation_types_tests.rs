@@ -0,0 +1,19 @@
+use actix_web::http::Method;
+use cphere_backend::types::{
+    auth_types::{ApiResource, TokenScope},
+    moderation_types::{ReportReason, ReportStatus},
+};
+
+#[test]
+fn report_reasons_use_snake_case() {
+    let reason: ReportReason = serde_json::from_str(r#""hate_speech""#).unwrap();
+    assert_eq!(reason, ReportReason::HateSpeech);
+    assert_eq!(serde_json::to_string(&ReportStatus::Open).unwrap(), r#""open""#);
+    assert!(serde_json::from_str::<ReportReason>(r#""rude""#).is_err());
+}
+
+#[test]
+fn reporting_needs_report_scope() {
+    assert_eq!(ApiResource::Reports.required_scope(&Method::POST), TokenScope::ReportsWrite);
+    assert!(!TokenScope::ChatsWrite.grants(TokenScope::ReportsWrite));
+}
@@ -0,0 +1,41 @@
+use cphere_backend::{
+    models::notification_preference_model::NotificationPreference, types::notification_types::NotificationType,
+};
+use mongodb::bson::oid::ObjectId;
+
+fn preference_for(notification_type: NotificationType, in_app: bool, email: bool, muted: bool) -> NotificationPreference {
+    NotificationPreference {
+        in_app,
+        email,
+        muted,
+        ..NotificationPreference::default_for(ObjectId::new(), notification_type)
+    }
+}
+
+#[test]
+fn default_preference_pushes_without_email() {
+    let preference = NotificationPreference::default_for(ObjectId::new(), NotificationType::FriendRequest);
+    assert!(preference.pushes_live());
+    assert!(!preference.sends_email());
+}
+
+#[test]
+fn channels_follow_preferences_and_muting() {
+    let email_only = preference_for(NotificationType::ChatMessage, false, true, false);
+    assert!(!email_only.pushes_live());
+    assert!(email_only.sends_email());
+
+    let muted = preference_for(NotificationType::ChatMessage, true, true, true);
+    assert!(!muted.pushes_live());
+    assert!(!muted.sends_email());
+}
+
+#[test]
+fn calls_always_ring() {
+    for (in_app, muted) in [(false, false), (true, true), (false, true)] {
+        let preference = preference_for(NotificationType::VideoCall, in_app, true, muted);
+        assert!(preference.pushes_live());
+        assert_eq!(preference.sends_email(), !muted);
+    }
+    assert!(!NotificationType::MissedCall.is_live_signal());
+}
@@ -0,0 +1,50 @@
+use cphere_backend::utils::auth_util::{
+    generate_pkce_verifier, generate_reset_token, hash_token, pkce_challenge, sign_token, verify_signed_token,
+};
+
+#[test]
+fn pkce_challenge_matches_rfc_example() {
+    // RFC 7636 appendix B.
+    assert_eq!(
+        pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+        "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+    );
+}
+
+#[test]
+fn pkce_verifier_is_valid() {
+    let verifier = generate_pkce_verifier();
+    assert!((43..=128).contains(&verifier.len()));
+    assert!(verifier
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c)));
+    assert_ne!(verifier, generate_pkce_verifier());
+}
+
+#[test]
+fn token_hash_is_sha256_hex() {
+    assert_eq!(
+        hash_token("abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    let token = generate_reset_token();
+    assert_eq!(hash_token(&token), hash_token(&token));
+    assert_ne!(hash_token(&token), token);
+    assert_ne!(hash_token(&token), hash_token(&generate_reset_token()));
+}
+
+#[test]
+fn signed_tokens_only_verify_for_their_secret_and_purpose() {
+    let token = sign_token("secret", "email_verification", "user:1700000000");
+    assert_eq!(
+        verify_signed_token("secret", "email_verification", &token).as_deref(),
+        Some("user:1700000000")
+    );
+    assert!(verify_signed_token("other-secret", "email_verification", &token).is_none());
+    assert!(verify_signed_token("secret", "password_reset", &token).is_none());
+
+    let tampered = token.replacen("user", "admin", 1);
+    assert!(verify_signed_token("secret", "email_verification", &tampered).is_none());
+    assert!(verify_signed_token("secret", "email_verification", "user:1700000000").is_none());
+    assert!(verify_signed_token("secret", "email_verification", "user.zz").is_none());
+}
@@ -0,0 +1,47 @@
+use cphere_backend::utils::{
+    auth_util::hash_password,
+    password_util::{estimate_strength, PasswordPolicy},
+};
+use std::{collections::HashSet, sync::Arc};
+
+fn policy() -> PasswordPolicy {
+    PasswordPolicy {
+        min_length: 8,
+        banned_passwords: Arc::new(HashSet::from(["password123".to_string()])),
+        history_size: 2,
+        min_strength_score: 2,
+    }
+}
+
+fn rules(violations: &[cphere_backend::utils::password_util::PasswordRuleViolation]) -> Vec<&'static str> {
+    violations.iter().map(|violation| violation.rule).collect()
+}
+
+#[test]
+fn test_strength_grows_with_length_and_variety() {
+    assert_eq!(estimate_strength("").score, 0);
+    assert_eq!(estimate_strength("aaaaaaaa").score, 0);
+    assert!(estimate_strength("Tr0ub4dor&3").score >= 2);
+    assert_eq!(estimate_strength("correct horse battery staple, Mr. Jones!").score, 4);
+}
+
+#[test]
+fn test_policy_names_failed_rules() {
+    let policy = policy();
+    assert!(policy.check("Xk9#mQ2v!p", Some("alice"), &[]).is_empty());
+    assert_eq!(rules(&policy.check("", None, &[])), vec!["min_length", "strength"]);
+    assert!(rules(&policy.check("PASSWORD123", None, &[])).contains(&"common_password"));
+    assert!(rules(&policy.check("alice-Xk9#mQ2v", Some("Alice"), &[])).contains(&"contains_username"));
+}
+
+#[test]
+fn test_policy_rejects_recent_passwords_only() {
+    let policy = policy();
+    let history = vec![
+        hash_password("Newest#Pass1").unwrap(),
+        hash_password("Middle#Pass2").unwrap(),
+        hash_password("Oldest#Pass3").unwrap(),
+    ];
+    assert_eq!(rules(&policy.check("Middle#Pass2", None, &history)), vec!["reused"]);
+    assert!(policy.check("Oldest#Pass3", None, &history).is_empty());
+}
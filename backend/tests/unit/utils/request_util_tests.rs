@@ -0,0 +1,66 @@
+use actix_web::{http::header::USER_AGENT, test::TestRequest, web};
+use cphere_backend::utils::request_util::{client_ip, describe_device, RequestContext, TrustedProxies};
+use std::net::SocketAddr;
+
+fn from_peer(peer: &str) -> TestRequest {
+    TestRequest::default()
+        .peer_addr(peer.parse::<SocketAddr>().unwrap())
+        .app_data(web::Data::new(TrustedProxies(vec!["10.0.0.2".parse().unwrap(), "10.0.0.3".parse().unwrap()])))
+}
+
+#[test]
+fn request_context_reads_peer_ip_and_user_agent() {
+    let req = from_peer("198.51.100.4:52000")
+        .insert_header((USER_AGENT, "Mozilla/5.0 Firefox/128.0"))
+        .to_http_request();
+    let context = RequestContext::from_request(&req);
+    assert_eq!(context.ip, "198.51.100.4");
+    assert_eq!(context.user_agent, "Mozilla/5.0 Firefox/128.0");
+
+    let context = RequestContext::from_request(&TestRequest::default().to_http_request());
+    assert_eq!(context.ip, "unknown");
+    assert_eq!(context.user_agent, "unknown");
+}
+
+#[test]
+fn forwarded_ip_is_only_believed_from_trusted_proxies() {
+    // A client talking to the server directly cannot pick its own address.
+    let req = from_peer("198.51.100.4:52000")
+        .insert_header(("X-Forwarded-For", "203.0.113.7"))
+        .to_http_request();
+    assert_eq!(client_ip(&req), "198.51.100.4");
+
+    // Without a configured list no proxy is trusted.
+    let req = TestRequest::default()
+        .peer_addr("10.0.0.2:52000".parse().unwrap())
+        .insert_header(("X-Forwarded-For", "203.0.113.7"))
+        .to_http_request();
+    assert_eq!(client_ip(&req), "10.0.0.2");
+
+    let req = from_peer("10.0.0.2:52000")
+        .insert_header(("X-Forwarded-For", "203.0.113.7"))
+        .to_http_request();
+    assert_eq!(client_ip(&req), "203.0.113.7");
+
+    // Entries the client prepended are skipped, as are the trusted proxies in the chain.
+    let req = from_peer("10.0.0.2:52000")
+        .insert_header(("X-Forwarded-For", "192.0.2.1, 203.0.113.7, 10.0.0.3"))
+        .to_http_request();
+    assert_eq!(client_ip(&req), "203.0.113.7");
+
+    let req = from_peer("10.0.0.2:52000").to_http_request();
+    assert_eq!(client_ip(&req), "10.0.0.2");
+}
+
+#[test]
+fn devices_are_described_by_browser_and_os() {
+    let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+    assert_eq!(describe_device(firefox), "Firefox on Linux");
+    let edge = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+        Chrome/126.0.0.0 Safari/537.36 Edg/126.0.0.0";
+    assert_eq!(describe_device(edge), "Edge on Windows");
+    let safari = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 \
+        (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1";
+    assert_eq!(describe_device(safari), "Safari on iOS");
+    assert_eq!(describe_device("curl/8.5.0"), "Unknown device");
+}
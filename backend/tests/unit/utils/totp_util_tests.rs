@@ -0,0 +1,46 @@
+use cphere_backend::utils::totp_util::{
+    base32_decode, base32_encode, generate_recovery_codes, hotp, is_recovery_code, normalize_recovery_code,
+    verify_totp,
+};
+
+// The RFC 6238 SHA-1 test secret.
+const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+#[test]
+fn base32_round_trips() {
+    let encoded = base32_encode(RFC_SECRET);
+    assert_eq!(encoded, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+    assert_eq!(base32_decode(&encoded.to_lowercase()).unwrap(), RFC_SECRET);
+}
+
+#[test]
+fn hotp_matches_rfc_test_vectors() {
+    // RFC 6238 appendix B, truncated to six digits.
+    assert_eq!(hotp(RFC_SECRET, 59 / 30), "287082");
+    assert_eq!(hotp(RFC_SECRET, 1111111109 / 30), "081804");
+    assert_eq!(hotp(RFC_SECRET, 1234567890 / 30), "005924");
+}
+
+#[test]
+fn verify_totp_allows_drift_and_rejects_replay() {
+    let secret = base32_encode(RFC_SECRET);
+    let step = verify_totp(&secret, "081804", 1111111109 + 30, None).unwrap();
+    assert_eq!(step, 1111111109 / 30);
+    assert_eq!(verify_totp(&secret, "081804", 1111111109, Some(step)), None);
+    assert_eq!(verify_totp(&secret, "081804", 1111111109 + 120, None), None);
+    assert_eq!(verify_totp(&secret, "81804", 1111111109, None), None);
+}
+
+#[test]
+fn recovery_codes_are_normalized() {
+    assert_eq!(normalize_recovery_code(" ABCDE-fghij "), "abcdefghij");
+}
+
+#[test]
+fn only_recovery_shaped_codes_are_hashed() {
+    assert!(generate_recovery_codes(3).iter().all(|code| is_recovery_code(code)));
+    assert!(is_recovery_code("ABCDE-FGH23"));
+    assert!(!is_recovery_code("287082"));
+    assert!(!is_recovery_code(" 287 082 "));
+    assert!(!is_recovery_code(""));
+}
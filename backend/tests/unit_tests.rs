@@ -1,5 +1,63 @@
+// helpers shared by the unit tests
+#[path = "unit/common.rs"]
+mod common;
+// auth related unit tests
+#[path = "unit/auth/ldap_provider_tests.rs"]
+mod ldap_provider_tests;
 // handlers related unit tests
 #[path = "unit/handlers/auth_handler_tests.rs"]
 mod auth_handler_tests;
 #[path = "unit/handlers/video_call_handler_tests.rs"]
-mod video_call_handler_tests;
\ No newline at end of file
+mod video_call_handler_tests;
+// middleware related unit tests
+#[path = "unit/middleware/csrf_middleware_tests.rs"]
+mod csrf_middleware_tests;
+#[path = "unit/middleware/session_key_rotation_tests.rs"]
+mod session_key_rotation_tests;
+// migrations related unit tests
+#[path = "unit/migrations/mongo_migrations_tests.rs"]
+mod mongo_migrations_tests;
+// models related unit tests
+#[path = "unit/models/digest_preference_model_tests.rs"]
+mod digest_preference_model_tests;
+// repositories related unit tests
+#[path = "unit/repositories/memory_repository_tests.rs"]
+mod memory_repository_tests;
+#[path = "unit/repositories/postgres_repository_tests.rs"]
+mod postgres_repository_tests;
+// session related unit tests
+#[path = "unit/session/repository_session_store_tests.rs"]
+mod repository_session_store_tests;
+// services related unit tests
+#[path = "unit/services/auth_service_tests.rs"]
+mod auth_service_tests;
+#[path = "unit/services/csrf_service_tests.rs"]
+mod csrf_service_tests;
+#[path = "unit/services/chat_service_tests.rs"]
+mod chat_service_tests;
+#[path = "unit/services/mail_service_tests.rs"]
+mod mail_service_tests;
+#[path = "unit/services/saved_message_service_tests.rs"]
+mod saved_message_service_tests;
+#[path = "unit/services/throttle_service_tests.rs"]
+mod throttle_service_tests;
+#[path = "unit/services/two_factor_service_tests.rs"]
+mod two_factor_service_tests;
+// utils related unit tests
+#[path = "unit/utils/password_util_tests.rs"]
+mod password_util_tests;
+#[path = "unit/utils/auth_util_tests.rs"]
+mod auth_util_tests;
+#[path = "unit/utils/totp_util_tests.rs"]
+mod totp_util_tests;
+#[path = "unit/utils/request_util_tests.rs"]
+mod request_util_tests;
+// types related unit tests
+#[path = "unit/types/audit_types_tests.rs"]
+mod audit_types_tests;
+#[path = "unit/types/auth_types_tests.rs"]
+mod auth_types_tests;
+#[path = "unit/types/moderation_types_tests.rs"]
+mod moderation_types_tests;
+#[path = "unit/types/notification_types_tests.rs"]
+mod notification_types_tests;
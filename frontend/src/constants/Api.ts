@@ -27,6 +27,10 @@ export const ENDPOINTS = {
       uri: '/auth/change_password',
       method: 'post',
     },
+    CSRF: {
+      uri: '/auth/csrf',
+      method: 'get',
+    },
   },
   USERS: {
     IS_ONLINE: {
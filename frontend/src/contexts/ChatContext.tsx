@@ -12,8 +12,9 @@ type ChatSummaryBackendType = {
     id: string
     participant_username: string
     participant_user_id: string
-    last_message: string
-    last_message_timestamp: Date
+    last_message: string | null
+    last_message_timestamp: Date | null
+    last_activity_at: Date
 }
 
 type ChatContextType = {
@@ -58,8 +59,8 @@ export const ChatProvider: React.FC<{ children: React.ReactNode }> = ({ children
         id: chat.id,
         participantUsername: chat.participant_username,
         participantUserId: chat.participant_user_id,
-        lastMessage: chat.last_message,
-        lastMessageTimestamp: new Date(chat.last_message_timestamp)
+        lastMessage: chat.last_message ?? '',
+        lastMessageTimestamp: new Date(chat.last_message_timestamp ?? chat.last_activity_at)
     })
 
     return (
@@ -4,7 +4,7 @@ import { ArrowLeftIcon, VideoCameraIcon, TrashIcon } from '@heroicons/react/24/o
 import { UserAvatar } from '../../components/chat/UserAvatar'
 import { MessageCard } from '../../components/chat/MessageCard'
 import chatBackendApiService, { ChatsDeletePayload } from '../../services/chat/ChatBackendApiService'
-import { ChatMessage, UserOnline, UserOffline, DeleteChat } from '../../types/WsMessageTypes'
+import { ChatMessage, MessageDeleted, UserOnline, UserOffline, DeleteChat } from '../../types/WsMessageTypes'
 import wsService from '../../services/ws/WsService'
 import { useAuthentication } from '../../contexts/AuthenticationContext'
 import videoBackendApiService, { VideoIntiatePayload } from '../../services/video/VideoBackendApiService'
@@ -61,7 +61,9 @@ export const ChatBoard = () => {
         content: messageContent.trim(),
         sender_id: authState.userId,
         sender_username: authState.username,
-        created_at: new Date()
+        created_at: new Date(),
+        // Lets the server store the message once if the send is retried
+        client_msg_id: crypto.randomUUID()
       }
       wsService.sendMessage(newChatMessage)
       setMessageContent('')
@@ -131,6 +133,12 @@ export const ChatBoard = () => {
       }
     };
 
+    const messageDeletedListener = (message: MessageDeleted) => {
+      if (message.chat_id === chatId) {
+        setMessages(prevMessages => prevMessages.filter(m => m.id !== message.message_id));
+      }
+    };
+
     const deleteChatListener = (message: DeleteChat) => {
       if (message.chat_id === chatId) {
         navigate(-1);
@@ -138,11 +146,13 @@ export const ChatBoard = () => {
     }
 
     wsService.addEventListener('chat_message', chatMessageListener);
+    wsService.addEventListener('message_deleted', messageDeletedListener);
     wsService.addEventListener('delete_chat', deleteChatListener);
 
     // Cleanup listeners on unmount
     return () => {
       wsService.removeEventListener('chat_message', chatMessageListener);
+      wsService.removeEventListener('message_deleted', messageDeletedListener);
       wsService.removeEventListener('delete_chat', deleteChatListener);
     };
   }, []);
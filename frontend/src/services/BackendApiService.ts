@@ -1,5 +1,24 @@
-import axios from 'axios';
-import { API_BASE_URL } from '../constants/Api';
+import axios, { AxiosError, AxiosInstance, InternalAxiosRequestConfig } from 'axios';
+import { API_BASE_URL, ENDPOINTS } from '../constants/Api';
+
+const CSRF_HEADER = 'X-CSRF-Token';
+const SAFE_METHODS = ['get', 'head', 'options'];
+
+// CSRF token of the current session, shared by every service instance
+let csrfToken: Promise<string> | null = null;
+
+const fetchCsrfToken = (instance: AxiosInstance): Promise<string> => {
+  if (!csrfToken) {
+    csrfToken = instance
+      .get(ENDPOINTS.AUTH.CSRF.uri)
+      .then((response) => response.data.csrf_token)
+      .catch((error) => {
+        csrfToken = null;
+        throw error;
+      });
+  }
+  return csrfToken;
+};
 
 // Backend API service
 class BackendApiService {
@@ -8,6 +27,27 @@ class BackendApiService {
     baseURL: API_BASE_URL, // adjust as needed
     withCredentials: true, // ensures cookies (session tokens) are sent
   });
+
+  constructor() {
+    // Attach the session's CSRF token to state-changing requests
+    this.axiosInstance.interceptors.request.use(async (config) => {
+      if (!SAFE_METHODS.includes((config.method ?? 'get').toLowerCase())) {
+        config.headers.set(CSRF_HEADER, await fetchCsrfToken(this.axiosInstance));
+      }
+      return config;
+    });
+
+    // The session may have changed (e.g. after logout); fetch a fresh token and retry once
+    this.axiosInstance.interceptors.response.use(undefined, async (error: AxiosError) => {
+      const config = error.config as (InternalAxiosRequestConfig & { csrfRetried?: boolean }) | undefined;
+      if (config && !config.csrfRetried && error.response?.status === 403 && error.response.data === 'Invalid CSRF token') {
+        config.csrfRetried = true;
+        csrfToken = null;
+        return this.axiosInstance.request(config);
+      }
+      return Promise.reject(error);
+    });
+  }
 }
 
 // Export a singleton instance for ease of use
@@ -3,6 +3,10 @@ import { NotificationSummaryBackendType } from "../contexts/NotificationContext"
 export type WsMessage =
   DeleteChat
   | ChatMessage
+  | MessageAck
+  | MessageDeleted
+  | MessagePinned
+  | MessageUnpinned
   | UserOnline
   | UserOffline
   | WebrtcOffer
@@ -28,6 +32,49 @@ export interface ChatMessage {
   sender_id: string;
   sender_username: string;
   created_at: Date | null;
+  client_msg_id?: string;
+}
+
+export interface MessageAck {
+  type: "message_ack";
+  chat_id: string;
+  message_id: string;
+  client_msg_id: string | null;
+  seq: number;
+  created_at: string;
+}
+
+export interface MessageDeleted {
+  type: "message_deleted";
+  chat_id: string;
+  message_id: string;
+}
+
+export interface PinnedMessage {
+  message: {
+    id: string;
+    chat_id: string;
+    sender_id: string;
+    seq: number;
+    content: string;
+    created_at: string;
+  };
+  pinned_by: string;
+  pinned_at: string;
+}
+
+export interface MessagePinned {
+  type: "message_pinned";
+  chat_id: string;
+  message_id: string;
+  pin: PinnedMessage;
+}
+
+export interface MessageUnpinned {
+  type: "message_unpinned";
+  chat_id: string;
+  message_id: string;
+  unpinned_by: string;
 }
 
 export interface UserOffline {